@@ -1,11 +1,13 @@
 import type {
   Application,
+  AuthConfig,
   ApplicationFilterParams,
   ApplicationWithRelations,
   CreateApplication,
   CreateDomain,
   CreateHealthcheck,
   CreateInfra,
+  CreateMaintenance,
   CreateNetworkShare,
   CreateNote,
   CreatePerson,
@@ -19,16 +21,19 @@ import type {
   HealthcheckExecuteResult,
   HealthcheckFilterParams,
   HealthcheckWithRelations,
+  Identity,
   Infra,
   InfraFilterParams,
   InfraWithRelations,
   KumaEndpoint,
   KumaMonitor,
+  KumaSyncItem,
   LinkDomain,
   LinkInfra,
   LinkNetworkShare,
   LinkPerson,
   LinkService,
+  Maintenance,
   NetworkShare,
   NetworkShareWithRelations,
   Note,
@@ -48,6 +53,7 @@ import type {
   UpdateDomain,
   UpdateHealthcheck,
   UpdateInfra,
+  UpdateMaintenance,
   UpdateNetworkShare,
   UpdateNote,
   UpdatePerson,
@@ -66,6 +72,14 @@ async function request<T>(url: string, options: RequestInit = {}): Promise<T> {
     ...options,
   });
 
+  // Not logged in: send the user to the login page, keeping where they were
+  if (response.status === 401 && !url.startsWith('/auth/session')) {
+    const current = window.location.pathname + window.location.search;
+    if (window.location.pathname !== '/login') {
+      window.location.assign(`/login?redirect=${encodeURIComponent(current)}`);
+    }
+  }
+
   if (!response.ok) {
     const error = await response
       .json()
@@ -73,7 +87,7 @@ async function request<T>(url: string, options: RequestInit = {}): Promise<T> {
     throw new Error(error.message || 'Request failed');
   }
 
-  if (response.status === 204) {
+  if (response.status === 204 || response.status === 202) {
     return undefined as T;
   }
 
@@ -245,6 +259,29 @@ export const infraApi = {
   delete: (id: string) => request<void>(`/infra/${id}`, { method: 'DELETE' }),
 };
 
+// Maintenance API
+export const maintenanceApi = {
+  list: (params: { infra_id?: string; upcoming?: boolean } = {}) =>
+    request<Maintenance[]>(`/maintenance${buildQueryString(params)}`),
+
+  get: (id: string) => request<Maintenance>(`/maintenance/${id}`),
+
+  create: (data: CreateMaintenance) =>
+    request<Maintenance>('/maintenance', {
+      method: 'POST',
+      body: JSON.stringify(data),
+    }),
+
+  update: (id: string, data: UpdateMaintenance) =>
+    request<Maintenance>(`/maintenance/${id}`, {
+      method: 'PUT',
+      body: JSON.stringify(data),
+    }),
+
+  delete: (id: string) =>
+    request<void>(`/maintenance/${id}`, { method: 'DELETE' }),
+};
+
 // Domains API
 export const domainsApi = {
   list: async (params: DomainFilterParams = {}) => {
@@ -281,6 +318,14 @@ export const domainsApi = {
     }),
 
   delete: (id: string) => request<void>(`/domains/${id}`, { method: 'DELETE' }),
+
+  refresh: (id: string) =>
+    request<DomainWithRelations>(`/domains/${id}/refresh`, { method: 'POST' }),
+
+  checkCertificates: (id: string) =>
+    request<DomainWithRelations>(`/domains/${id}/certificates/check`, {
+      method: 'POST',
+    }),
 };
 
 // People API
@@ -422,6 +467,11 @@ export const healthchecksApi = {
 
   syncKumaOne: (id: string) =>
     request<void>(`/healthchecks/sync/kuma/${id}`, { method: 'POST' }),
+
+  kumaQueue: (healthcheck_id?: string) =>
+    request<KumaSyncItem[]>(
+      `/healthchecks/kuma/queue${buildQueryString({ healthcheck_id })}`
+    ),
 };
 
 // Resolve API
@@ -432,6 +482,21 @@ export const resolveApi = {
     ),
 };
 
+// Auth API
+export const authApi = {
+  config: () => request<AuthConfig>('/auth/config'),
+
+  login: (token: string) =>
+    request<Identity>('/auth/session', {
+      method: 'POST',
+      body: JSON.stringify({ token }),
+    }),
+
+  logout: () => request<void>('/auth/session', { method: 'DELETE' }),
+
+  me: () => request<Identity>('/auth/me'),
+};
+
 // Version API
 export const versionApi = {
   get: () => request<{ version: string }>('/version'),
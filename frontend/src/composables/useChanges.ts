@@ -0,0 +1,111 @@
+import { onUnmounted, watch, type Ref } from 'vue';
+import { toast } from 'vue-sonner';
+import { authApi } from '@/api';
+import type { ChangeEvent } from '@/types';
+
+// Called with a change, or without one when too much changed to catch up
+type Listener = (change?: ChangeEvent) => void;
+
+// Module-level singleton: one stream shared by all consumers
+const listeners = new Set<Listener>();
+let eventSource: EventSource | null = null;
+let me: Promise<string | null> | null = null;
+
+function openConnection() {
+  if (eventSource) return;
+  eventSource = new EventSource('/api/events');
+
+  eventSource.onmessage = (e: MessageEvent) => {
+    try {
+      const change: ChangeEvent = JSON.parse(e.data);
+      listeners.forEach((listener) => listener(change));
+    } catch {
+      // Silently ignore malformed events
+    }
+  };
+
+  // EventSource reconnects by itself and sends the id of the last change,
+  // the backend replays what was missed, or asks to reload if that's too much.
+  eventSource.addEventListener('reload', () => {
+    listeners.forEach((listener) => listener());
+  });
+}
+
+function closeConnection() {
+  if (eventSource) {
+    eventSource.close();
+    eventSource = null;
+  }
+}
+
+function myName(): Promise<string | null> {
+  me ??= authApi
+    .me()
+    .then((identity) => identity.name)
+    .catch(() => null);
+  return me;
+}
+
+// Short description of a change, e.g. "alice updated App"
+function describe(change: ChangeEvent): string {
+  const actor = change.actor ?? 'Auto';
+  const name = change.entity_name ?? change.entity_type;
+  const verb = change.action.endsWith('e')
+    ? `${change.action}d`
+    : `${change.action}ed`;
+  return `${actor} ${verb} ${name}`;
+}
+
+// Reloads a detail view when someone else changes its entity, links
+// something to it or unlinks something from it. While `editing`, the form
+// is left alone and a warning is shown instead: saving is refused as it's
+// based on the old version, the view reloads once the form is closed.
+export function useEntityChanges(
+  entityType: string,
+  entityId: string,
+  reload: () => void,
+  editing?: Ref<boolean>
+) {
+  let stale = false;
+  const listener: Listener = async (change) => {
+    if (!change) {
+      if (editing?.value) stale = true;
+      else reload();
+      return;
+    }
+
+    const matches =
+      (change.entity_type === entityType && change.entity_id === entityId) ||
+      (change.related_type === entityType && change.related_id === entityId);
+    if (!matches || change.actor === (await myName())) return;
+
+    if (editing?.value) {
+      stale = true;
+      toast.warning(`${describe(change)} while you were editing`, {
+        description: 'Close the form to load their change.',
+      });
+      return;
+    }
+    toast.info(describe(change));
+    reload();
+  };
+
+  if (editing) {
+    watch(editing, (open) => {
+      if (!open && stale) {
+        stale = false;
+        reload();
+      }
+    });
+  }
+
+  listeners.add(listener);
+  openConnection();
+
+  onUnmounted(() => {
+    listeners.delete(listener);
+    if (listeners.size === 0) {
+      closeConnection();
+    }
+  });
+}
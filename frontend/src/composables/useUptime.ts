@@ -7,20 +7,45 @@ export interface HeartbeatEntry {
   msg: string | null;
 }
 
+// Compact live state of a healthcheck, all heartbeats of the last hour are at
+// /api/healthchecks/uptime/{id}
 export interface MonitorData {
-  kuma_id: number;
-  heartbeats: HeartbeatEntry[];
+  healthcheck_id: string;
+  kuma_id: number | null; // null when run by the native scheduler
+  status: number | null;
+  up: number;
+  down: number;
+  uptime_percent: number | null;
+  ping: number | null;
+  heartbeats: HeartbeatEntry[]; // the last SUMMARY_BEATS
 }
 
 type UptimeEvent =
-  | { type: 'snapshot'; monitors: Record<number, MonitorData> }
-  | { type: 'update'; kuma_id: number; entry: HeartbeatEntry };
+  | { type: 'snapshot'; monitors: Record<string, MonitorData> }
+  | {
+      type: 'update';
+      healthcheck_id: string;
+      kuma_id: number | null;
+      entry: HeartbeatEntry;
+    };
 
-// Module-level singleton state shared by all consumers
-const monitors = ref<Map<number, MonitorData>>(new Map());
+// Module-level singleton state shared by all consumers, keyed by healthcheck id
+const monitors = ref<Map<string, MonitorData>>(new Map());
 let eventSource: EventSource | null = null;
 let consumerCount = 0;
-const HEARTBEAT_WINDOW_SECS = 3600 * 2;
+const SUMMARY_BEATS = 45;
+
+function applyUpdate(monitor: MonitorData, entry: HeartbeatEntry) {
+  // The stream may repeat a heartbeat that was already in the snapshot
+  if (monitor.heartbeats.some((h) => h.time === entry.time)) return;
+  monitor.heartbeats = [...monitor.heartbeats, entry].slice(-SUMMARY_BEATS);
+  monitor.status = entry.status;
+  monitor.ping = entry.ping;
+  if (entry.status === 1) monitor.up++;
+  if (entry.status === 0) monitor.down++;
+  const total = monitor.up + monitor.down;
+  monitor.uptime_percent = total > 0 ? (monitor.up * 100) / total : null;
+}
 
 function openConnection() {
   if (eventSource) return;
@@ -30,29 +55,22 @@ function openConnection() {
     try {
       const event: UptimeEvent = JSON.parse(e.data);
       if (event.type === 'snapshot') {
-        const newMap = new Map<number, MonitorData>();
-        for (const [id, monitor] of Object.entries(event.monitors)) {
-          newMap.set(Number(id), monitor as MonitorData);
-        }
-        monitors.value = newMap;
+        monitors.value = new Map(Object.entries(event.monitors));
       } else if (event.type === 'update') {
-        const existing = monitors.value.get(event.kuma_id);
-        const cutoff = Date.now() - HEARTBEAT_WINDOW_SECS * 1000;
-
-        if (existing) {
-          existing.heartbeats.push(event.entry);
-          existing.heartbeats = existing.heartbeats.filter((h) => {
-            return new Date(h.time).getTime() > cutoff;
-          });
-          // Trigger Vue reactivity for Map mutation
-          monitors.value = new Map(monitors.value);
-        } else {
-          monitors.value.set(event.kuma_id, {
-            kuma_id: event.kuma_id,
-            heartbeats: [event.entry],
-          });
-          monitors.value = new Map(monitors.value);
-        }
+        const existing = monitors.value.get(event.healthcheck_id) ?? {
+          healthcheck_id: event.healthcheck_id,
+          kuma_id: event.kuma_id,
+          status: null,
+          up: 0,
+          down: 0,
+          uptime_percent: null,
+          ping: null,
+          heartbeats: [],
+        };
+        applyUpdate(existing, event.entry);
+        monitors.value.set(event.healthcheck_id, existing);
+        // Trigger Vue reactivity for Map mutation
+        monitors.value = new Map(monitors.value);
       }
     } catch {
       // Silently ignore malformed events
@@ -60,8 +78,8 @@ function openConnection() {
   };
 
   eventSource.onerror = () => {
-    // EventSource handles reconnect automatically.
-    // On reconnect, the backend sends a fresh snapshot.
+    // EventSource handles reconnect automatically and sends the id of the
+    // last event, the backend replays what was missed or sends a snapshot.
   };
 
   window.addEventListener('beforeunload', () => {
@@ -89,12 +107,12 @@ export function useUptime() {
     }
   });
 
-  function getMonitorData(kumaId: number): MonitorData | undefined {
-    return monitors.value.get(kumaId);
+  function getMonitorData(healthcheckId: string): MonitorData | undefined {
+    return monitors.value.get(healthcheckId);
   }
 
   return {
-    monitors: monitors as Ref<Map<number, MonitorData>>,
+    monitors: monitors as Ref<Map<string, MonitorData>>,
     getMonitorData,
   };
 }
@@ -77,6 +77,11 @@ const routes = [
     path: '/healthchecks/:id',
     component: () => import('./views/healthchecks/HealthcheckDetail.vue'),
   },
+  // Login
+  {
+    path: '/login',
+    component: () => import('./views/LoginView.vue'),
+  },
   // Search
   {
     path: '/search',
@@ -58,6 +58,7 @@ export interface Application {
   outline_url: string | null;
   created_at: string;
   updated_at: string;
+  version: number; // bumped by every update
   created_by: string | null;
 }
 
@@ -81,6 +82,7 @@ export interface UpdateApplication {
   status?: string;
   image_refs?: string;
   outline_url?: string;
+  version?: number; // the version the change is based on
 }
 
 export interface ApplicationWithRelations extends Application {
@@ -106,6 +108,7 @@ export interface Service {
   outline_url: string | null;
   created_at: string;
   updated_at: string;
+  version: number;
   created_by: string | null;
 }
 
@@ -127,6 +130,7 @@ export interface UpdateService {
   status?: string;
   image_refs?: string;
   outline_url?: string;
+  version?: number;
 }
 
 export interface ServiceRelation {
@@ -162,6 +166,7 @@ export interface Infra {
   type: string;
   created_at: string;
   updated_at: string;
+  version: number;
   created_by: string | null;
 }
 
@@ -175,6 +180,7 @@ export interface UpdateInfra {
   name?: string;
   description?: string;
   type?: string;
+  version?: number;
 }
 
 export interface InfraRelation {
@@ -221,6 +227,7 @@ export interface Domain {
   target_service_name: string | null;
   created_at: string;
   updated_at: string;
+  version: number;
   created_by: string | null;
 }
 
@@ -247,6 +254,7 @@ export interface UpdateDomain {
   target_service_id?: string;
   status?: string;
   notes?: string;
+  version?: number;
 }
 
 export interface DomainRelation {
@@ -261,6 +269,34 @@ export interface DomainRelation {
 
 export interface DomainWithRelations extends Domain {
   applications: ApplicationDomainRelation[];
+  lookup: DomainLookup | null;
+  certificates: Certificate[];
+}
+
+export interface DomainLookup {
+  name: string;
+  source: string | null;
+  registrar: string | null;
+  dns_provider: string | null;
+  expires_at: string | null;
+  error: string | null;
+  checked_at: string;
+  mismatches: string[];
+}
+
+export interface Certificate {
+  domain_id: string;
+  port: number;
+  subject: string | null;
+  issuer: string | null;
+  sans: string[];
+  serial: string | null;
+  not_before: string | null;
+  not_after: string | null;
+  chain_valid: boolean | null;
+  chain_error: string | null;
+  error: string | null;
+  checked_at: string;
 }
 
 export interface DomainNamedWithRelations extends DomainWithRelations {
@@ -286,8 +322,10 @@ export interface Person {
   phone: string | null;
   is_active: boolean;
   notes: string | null;
+  access_role: AccessRole;
   created_at: string;
   updated_at: string;
+  version: number;
   created_by: string | null;
 }
 
@@ -299,6 +337,7 @@ export interface CreatePerson {
   phone?: string;
   is_active?: boolean;
   notes?: string;
+  access_role?: AccessRole;
 }
 
 export interface UpdatePerson {
@@ -309,6 +348,8 @@ export interface UpdatePerson {
   phone?: string;
   is_active?: boolean;
   notes?: string;
+  access_role?: AccessRole;
+  version?: number;
 }
 
 export interface PersonRelation {
@@ -353,6 +394,7 @@ export interface NetworkShare {
   notes: string | null;
   created_at: string;
   updated_at: string;
+  version: number;
   created_by: string | null;
 }
 
@@ -374,6 +416,7 @@ export interface UpdateNetworkShare {
   purpose?: string;
   status?: string;
   notes?: string;
+  version?: number;
 }
 
 export interface NetworkShareRelation {
@@ -420,6 +463,7 @@ export interface Note {
   is_pinned: boolean;
   created_at: string;
   updated_at: string;
+  version: number;
   created_by: string | null;
 }
 
@@ -439,6 +483,7 @@ export interface UpdateNote {
   note_type?: string;
   url?: string;
   is_pinned?: boolean;
+  version?: number;
 }
 
 // Dashboard types
@@ -451,6 +496,7 @@ export interface DashboardStats {
   network_shares: EntityStats;
   notes: number;
   expiring_domains: ExpiringDomain[];
+  expiring_certificates: ExpiringCertificate[];
   healthchecks: HealthcheckStats;
   recent_activity: RecentActivity[];
 }
@@ -466,6 +512,8 @@ export interface RecentActivity {
   name: string;
   entity_type: string;
   updated_at: string;
+  action: string;
+  actor: string | null;
 }
 
 export interface EntityStats {
@@ -479,6 +527,14 @@ export interface ExpiringDomain {
   expires_at: string | null;
 }
 
+export interface ExpiringCertificate {
+  domain_id: string;
+  fqdn: string;
+  port: number;
+  issuer: string | null;
+  not_after: string;
+}
+
 // Search types
 export interface SearchResults {
   applications: SearchResult[];
@@ -505,6 +561,7 @@ export interface Stack {
   notes: string | null;
   created_at: string;
   updated_at: string;
+  version: number;
 }
 
 export interface CreateStack {
@@ -515,6 +572,7 @@ export interface CreateStack {
 export interface UpdateStack {
   name?: string;
   notes?: string;
+  version?: number;
 }
 
 export interface StackRelation {
@@ -533,6 +591,8 @@ export interface ApplicationStackRelation {
 }
 
 // Healthcheck types
+export type HealthcheckKind = 'http' | 'tcp' | 'dns' | 'tls';
+
 export interface Healthcheck {
   id: string;
   name: string;
@@ -556,9 +616,17 @@ export interface Healthcheck {
   request_body: string | null;
   http_auth_user: string | null;
   http_auth_pass: string | null;
+  kind: HealthcheckKind;
+  port: number | null;
+  dns_record_type: string | null;
+  dns_resolver: string | null;
+  tls_expiry_days: number | null;
+  json_path: string | null;
+  json_expected_value: string | null;
   kuma_dirty: boolean;
   created_at: string;
   updated_at: string;
+  version: number;
   created_by: string | null;
 }
 
@@ -584,6 +652,13 @@ export interface CreateHealthcheck {
   request_body?: string;
   http_auth_user?: string;
   http_auth_pass?: string;
+  kind?: HealthcheckKind;
+  port?: number;
+  dns_record_type?: string;
+  dns_resolver?: string;
+  tls_expiry_days?: number;
+  json_path?: string;
+  json_expected_value?: string;
 }
 
 export interface UpdateHealthcheck {
@@ -608,6 +683,14 @@ export interface UpdateHealthcheck {
   request_body?: string;
   http_auth_user?: string;
   http_auth_pass?: string;
+  kind?: HealthcheckKind;
+  port?: number;
+  dns_record_type?: string;
+  dns_resolver?: string;
+  tls_expiry_days?: number;
+  json_path?: string;
+  json_expected_value?: string;
+  version?: number;
 }
 
 export interface HealthcheckWithRelations extends Healthcheck {
@@ -636,6 +719,7 @@ export interface HealthcheckExecuteResult {
   status_code: number | null;
   response_time_ms: number;
   body_match: boolean | null;
+  detail: string | null;
   error: string | null;
   executed_at: string;
 }
@@ -661,6 +745,54 @@ export interface KumaEndpoint {
   url: string;
 }
 
+export type KumaSyncOperation = 'push' | 'pause' | 'delete';
+
+export interface KumaSyncItem {
+  healthcheck_id: string;
+  operation: KumaSyncOperation;
+  kuma_id: number | null;
+  name: string;
+  attempts: number;
+  last_error: string | null;
+  next_attempt_at: string;
+  created_at: string;
+  updated_at: string;
+  kuma_version: number;
+}
+
+// Planned downtime of an infra item, synced to a Kuma maintenance window
+export interface Maintenance {
+  id: string;
+  infra_id: string;
+  infra_name: string | null;
+  title: string;
+  description: string | null;
+  starts_at: string;
+  ends_at: string;
+  kuma_id: number | null;
+  kuma_monitors: number[];
+  kuma_dirty: boolean;
+  attempts: number;
+  last_error: string | null;
+  next_attempt_at: string;
+  created_at: string;
+  updated_at: string;
+  version: number;
+  created_by: string | null;
+}
+
+export interface CreateMaintenance {
+  infra_id: string;
+  title: string;
+  description?: string;
+  starts_at: string;
+  ends_at: string;
+}
+
+export type UpdateMaintenance = Partial<Omit<CreateMaintenance, 'infra_id'>> & {
+  version?: number;
+};
+
 // Kuma import types (for importing from Uptime Kuma export)
 export interface KumaMonitorImport {
   kuma_id: number;
@@ -698,3 +830,30 @@ export interface ImportMapping {
   domain_target_name: string;
   skip: boolean;
 }
+
+// Auth
+export type AccessRole = 'viewer' | 'editor' | 'admin';
+
+export interface AuthConfig {
+  oidc: boolean;
+}
+
+export interface Identity {
+  person_id: string | null;
+  name: string;
+  role: AccessRole;
+}
+
+// Live changes, from /api/events
+export interface ChangeEvent {
+  id: number; // id of the audit event
+  actor: string | null; // null for changes made by Auto itself
+  entity_type: string;
+  entity_id: string;
+  entity_name: string | null;
+  action: string;
+  related_type: string | null;
+  related_id: string | null;
+  fields: string[]; // fields changed by an update
+  created_at: string;
+}
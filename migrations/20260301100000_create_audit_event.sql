@@ -0,0 +1,19 @@
+-- Persistent audit log of every mutation across all entities
+CREATE TABLE audit_event (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    actor TEXT, -- NULL for changes made by Auto itself (e.g. Kuma sync)
+    entity_type TEXT NOT NULL, -- application, service, infra, domain, person, network_share, note, stack, healthcheck
+    entity_id TEXT NOT NULL,
+    entity_name TEXT, -- name at the time of the event, kept for deleted entities
+    action TEXT NOT NULL, -- create, update, delete, link, unlink
+    related_type TEXT, -- for link/unlink: the other side of the relation
+    related_id TEXT,
+    before TEXT, -- JSON
+    after TEXT, -- JSON
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX idx_audit_event_entity ON audit_event(entity_type, entity_id);
+CREATE INDEX idx_audit_event_related ON audit_event(related_type, related_id);
+CREATE INDEX idx_audit_event_actor ON audit_event(actor);
+CREATE INDEX idx_audit_event_created_at ON audit_event(created_at);
@@ -0,0 +1,25 @@
+-- Personal API tokens, sent as `Authorization: Bearer <token>`.
+-- Only a SHA-256 hash of the token is stored; the token itself is shown once on creation.
+CREATE TABLE api_token (
+    id TEXT PRIMARY KEY,
+    person_id TEXT NOT NULL REFERENCES person(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    prefix TEXT NOT NULL, -- first characters of the token, to recognise it in listings
+    expires_at TEXT,
+    last_used_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX idx_api_token_person ON api_token(person_id);
+
+-- Cookie sessions for the web UI, keyed by a SHA-256 hash of the cookie value
+CREATE TABLE session (
+    id_hash TEXT PRIMARY KEY,
+    person_id TEXT REFERENCES person(id) ON DELETE CASCADE, -- NULL for the bootstrap admin token
+    name TEXT NOT NULL, -- identity recorded as actor / created_by
+    expires_at TEXT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX idx_session_expires_at ON session(expires_at);
@@ -0,0 +1,3 @@
+-- Access level of a person when logged in: viewer (read only), editor (create/update/link), admin (delete, Kuma sync, roles)
+ALTER TABLE person ADD COLUMN access_role TEXT NOT NULL DEFAULT 'viewer'
+    CHECK (access_role IN ('viewer', 'editor', 'admin'));
@@ -0,0 +1,8 @@
+-- Pending OIDC logins between redirecting to the provider and its callback
+CREATE TABLE oidc_login (
+    state TEXT PRIMARY KEY,
+    nonce TEXT NOT NULL,
+    code_verifier TEXT NOT NULL, -- PKCE verifier, the provider only gets its hash
+    redirect_to TEXT NOT NULL, -- path in the web UI to return to after logging in
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,27 @@
+-- Soft delete: deleted rows stay in the table (with all their links) until
+-- they are restored or purged from the trash.
+ALTER TABLE application ADD COLUMN deleted_at TEXT;
+ALTER TABLE application ADD COLUMN deleted_by TEXT;
+ALTER TABLE service ADD COLUMN deleted_at TEXT;
+ALTER TABLE service ADD COLUMN deleted_by TEXT;
+ALTER TABLE infra ADD COLUMN deleted_at TEXT;
+ALTER TABLE infra ADD COLUMN deleted_by TEXT;
+ALTER TABLE domain ADD COLUMN deleted_at TEXT;
+ALTER TABLE domain ADD COLUMN deleted_by TEXT;
+ALTER TABLE person ADD COLUMN deleted_at TEXT;
+ALTER TABLE person ADD COLUMN deleted_by TEXT;
+ALTER TABLE network_share ADD COLUMN deleted_at TEXT;
+ALTER TABLE network_share ADD COLUMN deleted_by TEXT;
+ALTER TABLE stack ADD COLUMN deleted_at TEXT;
+ALTER TABLE stack ADD COLUMN deleted_by TEXT;
+ALTER TABLE healthcheck ADD COLUMN deleted_at TEXT;
+ALTER TABLE healthcheck ADD COLUMN deleted_by TEXT;
+
+-- Domains and healthchecks are deleted along with the entity they belong to
+-- (what ON DELETE CASCADE did before), and remember which one that was so
+-- they come back when it is restored.
+ALTER TABLE domain ADD COLUMN deleted_with TEXT;
+ALTER TABLE healthcheck ADD COLUMN deleted_with TEXT;
+
+CREATE INDEX idx_domain_deleted_with ON domain(deleted_with);
+CREATE INDEX idx_healthcheck_deleted_with ON healthcheck(deleted_with);
@@ -0,0 +1,12 @@
+-- Full snapshots of application, service, infra, domain and healthcheck
+-- after every create and update, numbered per entity
+CREATE TABLE revision (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    revision INTEGER NOT NULL,
+    actor TEXT,
+    snapshot TEXT NOT NULL, -- JSON
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (entity_type, entity_id, revision)
+);
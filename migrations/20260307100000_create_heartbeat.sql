@@ -0,0 +1,13 @@
+-- Kuma heartbeats of the monitors of our healthchecks, kept for
+-- HEARTBEAT_RETENTION_DAYS for long-term uptime statistics
+CREATE TABLE heartbeat (
+    healthcheck_id TEXT NOT NULL REFERENCES healthcheck(id) ON DELETE CASCADE,
+    time TEXT NOT NULL, -- UTC, YYYY-MM-DD HH:MM:SS
+    kuma_id INTEGER NOT NULL,
+    status INTEGER NOT NULL, -- 0 = down, 1 = up, 2 = pending, 3 = maintenance
+    ping INTEGER, -- milliseconds
+    msg TEXT,
+    PRIMARY KEY (healthcheck_id, time)
+);
+
+CREATE INDEX idx_heartbeat_time ON heartbeat(time);
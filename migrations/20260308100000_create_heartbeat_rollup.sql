@@ -0,0 +1,18 @@
+-- Hourly and daily aggregates of the heartbeats, kept longer than the raw
+-- heartbeats
+CREATE TABLE heartbeat_rollup (
+    healthcheck_id TEXT NOT NULL REFERENCES healthcheck(id) ON DELETE CASCADE,
+    resolution TEXT NOT NULL, -- hour, day
+    bucket TEXT NOT NULL, -- UTC start of the hour or day, YYYY-MM-DD HH:MM:SS
+    up INTEGER NOT NULL,
+    down INTEGER NOT NULL,
+    pending INTEGER NOT NULL,
+    maintenance INTEGER NOT NULL,
+    ping_count INTEGER NOT NULL, -- heartbeats with a ping, to average over
+    ping_sum INTEGER,
+    ping_min INTEGER,
+    ping_max INTEGER,
+    PRIMARY KEY (healthcheck_id, resolution, bucket)
+);
+
+CREATE INDEX idx_heartbeat_rollup_bucket ON heartbeat_rollup(resolution, bucket);
@@ -0,0 +1,19 @@
+-- Periods in which a healthcheck was down, detected from its heartbeats
+CREATE TABLE incident (
+    id TEXT PRIMARY KEY,
+    healthcheck_id TEXT NOT NULL REFERENCES healthcheck(id) ON DELETE CASCADE,
+    -- Owner of the healthcheck when the incident started
+    application_id TEXT REFERENCES application(id) ON DELETE SET NULL,
+    service_id TEXT REFERENCES service(id) ON DELETE SET NULL,
+    started_at TEXT NOT NULL, -- UTC time of the first down heartbeat
+    resolved_at TEXT, -- UTC time of the first up heartbeat after it
+    msg TEXT, -- message of the first down heartbeat
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (healthcheck_id, started_at)
+);
+
+-- At most one open incident per healthcheck
+CREATE UNIQUE INDEX idx_incident_open ON incident(healthcheck_id) WHERE resolved_at IS NULL;
+CREATE INDEX idx_incident_application ON incident(application_id);
+CREATE INDEX idx_incident_service ON incident(service_id);
+CREATE INDEX idx_incident_started_at ON incident(started_at);
@@ -0,0 +1,31 @@
+-- Where notifications about healthchecks going down or up and about
+-- expiring domains are sent
+CREATE TABLE notification_channel (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    kind TEXT NOT NULL CHECK (kind IN ('webhook', 'email', 'slack')),
+    url TEXT, -- webhook and slack: where to POST to
+    email TEXT, -- email: also sent here, next to the owners and maintainers
+    events TEXT NOT NULL DEFAULT '["down","up","domain_expiring"]', -- JSON array
+    is_enabled INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT
+);
+
+-- Every notification sent, or attempted, through a channel
+CREATE TABLE notification (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    channel_id TEXT NOT NULL REFERENCES notification_channel(id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    -- What the notification is about, e.g. an incident. A channel gets each
+    -- event about the same thing only once.
+    subject_key TEXT NOT NULL,
+    title TEXT NOT NULL,
+    recipients TEXT NOT NULL DEFAULT '[]', -- JSON array of email addresses
+    error TEXT, -- why it could not be delivered
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (channel_id, event, subject_key)
+);
+
+CREATE INDEX idx_notification_created_at ON notification(created_at);
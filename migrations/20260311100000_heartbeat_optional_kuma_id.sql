@@ -0,0 +1,20 @@
+-- Heartbeats of the native scheduler don't come from a Kuma monitor
+CREATE TABLE heartbeat_new (
+    healthcheck_id TEXT NOT NULL REFERENCES healthcheck(id) ON DELETE CASCADE,
+    time TEXT NOT NULL, -- UTC, YYYY-MM-DD HH:MM:SS
+    kuma_id INTEGER, -- NULL when run by the native scheduler
+    status INTEGER NOT NULL, -- 0 = down, 1 = up, 2 = pending, 3 = maintenance
+    ping INTEGER, -- milliseconds
+    msg TEXT,
+    PRIMARY KEY (healthcheck_id, time)
+);
+
+INSERT INTO heartbeat_new (healthcheck_id, time, kuma_id, status, ping, msg)
+SELECT healthcheck_id, time, kuma_id, status, ping, msg
+FROM heartbeat;
+
+DROP TABLE heartbeat;
+
+ALTER TABLE heartbeat_new RENAME TO heartbeat;
+
+CREATE INDEX idx_heartbeat_time ON heartbeat(time);
@@ -0,0 +1,16 @@
+-- Healthchecks other than HTTP requests
+ALTER TABLE healthcheck ADD COLUMN kind TEXT NOT NULL DEFAULT 'http' CHECK (kind IN ('http', 'tcp', 'dns', 'tls'));
+
+-- tcp and tls: port to connect to, tls defaults to 443
+ALTER TABLE healthcheck ADD COLUMN port INTEGER;
+
+-- dns: record type to resolve (defaults to A) and server to ask (defaults to the system resolver)
+ALTER TABLE healthcheck ADD COLUMN dns_record_type TEXT;
+ALTER TABLE healthcheck ADD COLUMN dns_resolver TEXT;
+
+-- tls: fail when the certificate expires within this many days (defaults to 14)
+ALTER TABLE healthcheck ADD COLUMN tls_expiry_days INTEGER;
+
+-- http: JSONPath into the response body and the value it should have
+ALTER TABLE healthcheck ADD COLUMN json_path TEXT;
+ALTER TABLE healthcheck ADD COLUMN json_expected_value TEXT;
@@ -0,0 +1,21 @@
+-- Kuma monitors of purged healthchecks, deleted from Kuma on the next sync.
+-- A row stays until Kuma confirms the deletion, so failures are retried.
+CREATE TABLE kuma_tombstone (
+    kuma_id INTEGER PRIMARY KEY,
+    healthcheck_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- A trigger rather than code, so healthchecks removed by ON DELETE CASCADE
+-- leave a tombstone too. Monitors still used by another healthcheck stay.
+CREATE TRIGGER healthcheck_kuma_tombstone
+AFTER DELETE ON healthcheck
+WHEN OLD.kuma_id IS NOT NULL
+ AND NOT EXISTS (SELECT 1 FROM healthcheck WHERE kuma_id = OLD.kuma_id)
+BEGIN
+    INSERT OR REPLACE INTO kuma_tombstone (kuma_id, healthcheck_id, name)
+    VALUES (OLD.kuma_id, OLD.id, OLD.name);
+END;
@@ -0,0 +1,35 @@
+-- Pending changes to Kuma monitors, one per healthcheck, worked off by the
+-- background sync worker. Rows stay until Kuma accepted the change, so
+-- failures are retried. No foreign key: purged healthchecks keep their delete.
+CREATE TABLE kuma_sync_queue (
+    healthcheck_id TEXT PRIMARY KEY,
+    operation TEXT NOT NULL CHECK (operation IN ('push', 'pause', 'delete')),
+    kuma_id INTEGER,
+    name TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX idx_kuma_sync_queue_next_attempt ON kuma_sync_queue(next_attempt_at);
+
+-- Tombstones become queued deletes
+INSERT INTO kuma_sync_queue (healthcheck_id, operation, kuma_id, name, attempts, last_error, created_at)
+SELECT healthcheck_id, 'delete', kuma_id, name, attempts, last_error, created_at
+FROM kuma_tombstone;
+
+DROP TRIGGER healthcheck_kuma_tombstone;
+DROP TABLE kuma_tombstone;
+
+-- A trigger rather than code, so healthchecks removed by ON DELETE CASCADE
+-- queue a delete too. Monitors still used by another healthcheck stay.
+CREATE TRIGGER healthcheck_kuma_delete
+AFTER DELETE ON healthcheck
+WHEN OLD.kuma_id IS NOT NULL
+ AND NOT EXISTS (SELECT 1 FROM healthcheck WHERE kuma_id = OLD.kuma_id)
+BEGIN
+    INSERT OR REPLACE INTO kuma_sync_queue (healthcheck_id, operation, kuma_id, name)
+    VALUES (OLD.id, 'delete', OLD.kuma_id, OLD.name);
+END;
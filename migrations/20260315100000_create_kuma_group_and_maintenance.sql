@@ -0,0 +1,44 @@
+-- Kuma group monitor of each application and service with healthchecks.
+-- No foreign key, ids are unique across applications and services.
+CREATE TABLE kuma_group (
+    target_id TEXT PRIMARY KEY,
+    target_type TEXT NOT NULL CHECK (target_type IN ('application', 'service')),
+    kuma_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+-- Planned downtime of an infra item, synced to a Kuma maintenance window
+-- covering the monitors of everything that depends on it. Deleted windows
+-- stay flagged until the sync worker removed them from Kuma.
+CREATE TABLE maintenance (
+    id TEXT PRIMARY KEY,
+    infra_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    starts_at TEXT NOT NULL,
+    ends_at TEXT NOT NULL,
+    kuma_id INTEGER,
+    -- Monitors last added to the Kuma maintenance, as a JSON array
+    kuma_monitors TEXT NOT NULL DEFAULT '[]',
+    kuma_dirty INTEGER NOT NULL DEFAULT 1,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TEXT NOT NULL DEFAULT (datetime('now')),
+    deleted_at TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT
+);
+
+CREATE INDEX idx_maintenance_infra ON maintenance(infra_id);
+
+-- Purging an infra item removes its windows from Kuma too
+CREATE TRIGGER infra_maintenance_delete
+AFTER DELETE ON infra
+BEGIN
+    UPDATE maintenance
+    SET deleted_at = COALESCE(deleted_at, datetime('now')),
+        kuma_dirty = 1,
+        next_attempt_at = datetime('now')
+    WHERE infra_id = OLD.id;
+END;
@@ -0,0 +1,14 @@
+-- Version of every editable entity, bumped by each update. Clients send the
+-- version they based a change on (`If-Match` or `version`) and get a 412
+-- when someone else changed the entity meanwhile.
+ALTER TABLE application ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE service ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE infra ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE domain ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE person ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE network_share ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE note ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE stack ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE healthcheck ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE maintenance ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE notification_channel ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,15 @@
+-- Last RDAP lookup of the registrable parent of each domain. Domain fields
+-- that are empty or still hold the previous lookup's value follow the
+-- lookup, values entered by hand are kept and flagged when they differ.
+CREATE TABLE domain_lookup (
+    domain_id TEXT PRIMARY KEY REFERENCES domain(id) ON DELETE CASCADE,
+    -- Registrable domain that was looked up, e.g. example.org for www.example.org
+    name TEXT NOT NULL,
+    -- RDAP URL that answered
+    source TEXT,
+    registrar TEXT,
+    dns_provider TEXT,
+    expires_at TEXT,
+    error TEXT,
+    checked_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
@@ -0,0 +1,21 @@
+-- TLS certificates served for each domain, on 443 or the ports of its TLS
+-- healthchecks. A failed check keeps the previous certificate, with the error.
+CREATE TABLE certificate (
+    domain_id TEXT NOT NULL REFERENCES domain(id) ON DELETE CASCADE,
+    port INTEGER NOT NULL,
+    subject TEXT,
+    issuer TEXT,
+    -- JSON array of the DNS names and IP addresses it is valid for
+    sans TEXT NOT NULL DEFAULT '[]',
+    serial TEXT,
+    not_before TEXT,
+    not_after TEXT,
+    -- Whether the chain up to a trusted root is valid for the domain
+    chain_valid INTEGER,
+    chain_error TEXT,
+    error TEXT,
+    checked_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (domain_id, port)
+);
+
+CREATE INDEX idx_certificate_not_after ON certificate(not_after);
@@ -0,0 +1,11 @@
+-- Webhook URLs of notification channels hold their secret, and the audit log
+-- is readable by viewers: scrub the URLs recorded so far.
+UPDATE audit_event
+SET before = json_set(before, '$.url', '[redacted]')
+WHERE entity_type = 'notification_channel'
+  AND json_type(before, '$.url') = 'text';
+
+UPDATE audit_event
+SET after = json_set(after, '$.url', '[redacted]')
+WHERE entity_type = 'notification_channel'
+  AND json_type(after, '$.url') = 'text';
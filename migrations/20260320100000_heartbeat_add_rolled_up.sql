@@ -0,0 +1,8 @@
+-- Heartbeats are added to the rollups once, when they are new, so late ones
+-- (backfilled history, clock skew between monitors) are counted too. Those
+-- stored so far have been rolled up already.
+ALTER TABLE heartbeat ADD COLUMN rolled_up INTEGER NOT NULL DEFAULT 0;
+
+UPDATE heartbeat SET rolled_up = 1;
+
+CREATE INDEX idx_heartbeat_not_rolled_up ON heartbeat(healthcheck_id) WHERE rolled_up = 0;
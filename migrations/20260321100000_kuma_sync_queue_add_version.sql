@@ -0,0 +1,19 @@
+-- Counter of the changes of a healthcheck to sync to Kuma, bumped by its
+-- edits and by changes of its application, service or stacks. It's apart
+-- from `version`, which only real edits bump. A queued item keeps the
+-- counter it was queued for, so the sync worker only removes the item, and
+-- clears `kuma_dirty`, when the healthcheck didn't change while it was
+-- being pushed.
+ALTER TABLE healthcheck ADD COLUMN kuma_version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE kuma_sync_queue ADD COLUMN kuma_version INTEGER NOT NULL DEFAULT 0;
+
+DROP TRIGGER healthcheck_kuma_delete;
+
+CREATE TRIGGER healthcheck_kuma_delete
+AFTER DELETE ON healthcheck
+WHEN OLD.kuma_id IS NOT NULL
+ AND NOT EXISTS (SELECT 1 FROM healthcheck WHERE kuma_id = OLD.kuma_id)
+BEGIN
+    INSERT OR REPLACE INTO kuma_sync_queue (healthcheck_id, operation, kuma_id, name, kuma_version)
+    VALUES (OLD.id, 'delete', OLD.kuma_id, OLD.name, OLD.kuma_version);
+END;
@@ -0,0 +1,212 @@
+-- Names, fqdns and emails only have to be unique outside the trash, so a
+-- trashed entity doesn't block creating a new one with the same name. The
+-- UNIQUE constraints become partial unique indexes, which takes rebuilding
+-- the tables. Migrations run with foreign keys off (see `migrate`), so
+-- dropping the old tables doesn't cascade to everything linked to them.
+
+CREATE TABLE application_new (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    url TEXT,
+    description TEXT,
+    repository_url TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    environment TEXT NOT NULL DEFAULT 'prd',
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT,
+    image_refs TEXT,
+    outline_url TEXT,
+    deleted_at TEXT,
+    deleted_by TEXT,
+    version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO application_new (id, name, url, description, repository_url, status, environment,
+                             created_at, updated_at, created_by, image_refs, outline_url,
+                             deleted_at, deleted_by, version)
+SELECT id, name, url, description, repository_url, status, environment,
+       created_at, updated_at, created_by, image_refs, outline_url,
+       deleted_at, deleted_by, version
+FROM application;
+DROP TABLE application;
+ALTER TABLE application_new RENAME TO application;
+CREATE INDEX idx_application_name ON application(name);
+CREATE INDEX idx_application_environment ON application(environment);
+CREATE UNIQUE INDEX idx_application_name_environment_active
+    ON application(name, environment) WHERE deleted_at IS NULL;
+
+CREATE TABLE service_new (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    repository_url TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    environment TEXT NOT NULL DEFAULT 'prd', -- 'dev', 'prd', ...
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT,
+    image_refs TEXT,
+    outline_url TEXT,
+    deleted_at TEXT,
+    deleted_by TEXT,
+    version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO service_new (id, name, description, repository_url, status, environment,
+                         created_at, updated_at, created_by, image_refs, outline_url,
+                         deleted_at, deleted_by, version)
+SELECT id, name, description, repository_url, status, environment,
+       created_at, updated_at, created_by, image_refs, outline_url,
+       deleted_at, deleted_by, version
+FROM service;
+DROP TABLE service;
+ALTER TABLE service_new RENAME TO service;
+CREATE INDEX idx_service_name ON service(name);
+CREATE INDEX idx_service_environment ON service(environment);
+CREATE UNIQUE INDEX idx_service_name_environment_active
+    ON service(name, environment) WHERE deleted_at IS NULL;
+
+CREATE TABLE infra_new (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    type TEXT NOT NULL, -- nomad cluster, server, ...
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT,
+    deleted_at TEXT,
+    deleted_by TEXT,
+    version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO infra_new (id, name, description, type, created_at, updated_at, created_by,
+                       deleted_at, deleted_by, version)
+SELECT id, name, description, type, created_at, updated_at, created_by,
+       deleted_at, deleted_by, version
+FROM infra;
+DROP TABLE infra;
+ALTER TABLE infra_new RENAME TO infra;
+CREATE INDEX idx_infra_name ON infra(name);
+CREATE UNIQUE INDEX idx_infra_name_active ON infra(name) WHERE deleted_at IS NULL;
+
+-- Purging an infra item removes its windows from Kuma too
+CREATE TRIGGER infra_maintenance_delete
+AFTER DELETE ON infra
+BEGIN
+    UPDATE maintenance
+    SET deleted_at = COALESCE(deleted_at, datetime('now')),
+        kuma_dirty = 1,
+        next_attempt_at = datetime('now')
+    WHERE infra_id = OLD.id;
+END;
+
+CREATE TABLE domain_new (
+    id TEXT PRIMARY KEY,
+    fqdn TEXT NOT NULL,
+    registrar TEXT,
+    dns_provider TEXT,
+    expires_at TEXT,
+    notes TEXT,
+    target_application_id TEXT REFERENCES application(id) ON DELETE CASCADE,
+    target_service_id TEXT REFERENCES service(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT,
+    deleted_at TEXT,
+    deleted_by TEXT,
+    deleted_with TEXT,
+    version INTEGER NOT NULL DEFAULT 1,
+    CHECK (
+        (target_application_id IS NOT NULL AND target_service_id IS NULL) OR
+        (target_application_id IS NULL AND target_service_id IS NOT NULL)
+    )
+);
+INSERT INTO domain_new (id, fqdn, registrar, dns_provider, expires_at, notes,
+                        target_application_id, target_service_id, created_at, updated_at,
+                        created_by, deleted_at, deleted_by, deleted_with, version)
+SELECT id, fqdn, registrar, dns_provider, expires_at, notes,
+       target_application_id, target_service_id, created_at, updated_at,
+       created_by, deleted_at, deleted_by, deleted_with, version
+FROM domain;
+DROP TABLE domain;
+ALTER TABLE domain_new RENAME TO domain;
+CREATE INDEX idx_domain_application ON domain(target_application_id);
+CREATE INDEX idx_domain_service ON domain(target_service_id);
+CREATE INDEX idx_domain_fqdn ON domain(fqdn);
+CREATE INDEX idx_domain_deleted_with ON domain(deleted_with);
+CREATE UNIQUE INDEX idx_domain_fqdn_active ON domain(fqdn COLLATE NOCASE) WHERE deleted_at IS NULL;
+
+CREATE TABLE person_new (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    email TEXT,
+    role TEXT,
+    department TEXT,
+    phone TEXT,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT,
+    access_role TEXT NOT NULL DEFAULT 'viewer'
+        CHECK (access_role IN ('viewer', 'editor', 'admin')),
+    deleted_at TEXT,
+    deleted_by TEXT,
+    version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO person_new (id, name, email, role, department, phone, is_active, notes,
+                        created_at, updated_at, created_by, access_role,
+                        deleted_at, deleted_by, version)
+SELECT id, name, email, role, department, phone, is_active, notes,
+       created_at, updated_at, created_by, access_role,
+       deleted_at, deleted_by, version
+FROM person;
+DROP TABLE person;
+ALTER TABLE person_new RENAME TO person;
+CREATE INDEX idx_person_name ON person(name);
+CREATE UNIQUE INDEX idx_person_email_active ON person(email) WHERE deleted_at IS NULL;
+
+CREATE TABLE network_share_new (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    share_type TEXT NOT NULL DEFAULT 'smb', -- smb, nfs, cifs
+    server TEXT,
+    purpose TEXT,
+    status TEXT NOT NULL DEFAULT 'active',
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    created_by TEXT,
+    deleted_at TEXT,
+    deleted_by TEXT,
+    version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO network_share_new (id, name, path, share_type, server, purpose, status, notes,
+                               created_at, updated_at, created_by,
+                               deleted_at, deleted_by, version)
+SELECT id, name, path, share_type, server, purpose, status, notes,
+       created_at, updated_at, created_by,
+       deleted_at, deleted_by, version
+FROM network_share;
+DROP TABLE network_share;
+ALTER TABLE network_share_new RENAME TO network_share;
+CREATE INDEX idx_network_share_name ON network_share(name);
+CREATE UNIQUE INDEX idx_network_share_name_active
+    ON network_share(name) WHERE deleted_at IS NULL;
+
+CREATE TABLE stack_new (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    notes TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+    deleted_at TEXT,
+    deleted_by TEXT,
+    version INTEGER NOT NULL DEFAULT 1
+);
+INSERT INTO stack_new (id, name, notes, created_at, updated_at, deleted_at, deleted_by, version)
+SELECT id, name, notes, created_at, updated_at, deleted_at, deleted_by, version
+FROM stack;
+DROP TABLE stack;
+ALTER TABLE stack_new RENAME TO stack;
+CREATE INDEX idx_stack_name ON stack(name);
+CREATE UNIQUE INDEX idx_stack_name_active ON stack(name) WHERE deleted_at IS NULL;
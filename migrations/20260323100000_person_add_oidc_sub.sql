@@ -0,0 +1,9 @@
+-- Subject identifier of the OIDC account a person logs in with, stored on
+-- first login, so a later change of the email address can't hand the
+-- account to someone else.
+ALTER TABLE person ADD COLUMN oidc_sub TEXT;
+CREATE UNIQUE INDEX idx_person_oidc_sub ON person(oidc_sub);
+
+-- Email addresses are matched ignoring case, so keep them unique that way too
+DROP INDEX idx_person_email_active;
+CREATE UNIQUE INDEX idx_person_email_active ON person(email COLLATE NOCASE) WHERE deleted_at IS NULL;
@@ -1,17 +1,27 @@
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, post, put},
 };
 use serde::Deserialize;
 use tracing::instrument;
 
+use crate::api::auth::require_role;
+use crate::api::{Actor, CsvUpload, IfMatch, etag};
 use crate::models::{
-    Application, ApplicationWithRelations, CreateApplication, LinkDomain, LinkInfra,
-    LinkNetworkShare, LinkPerson, LinkService, PaginationParams, UpdateApplication,
+    Application, ApplicationWithRelations, AuditEvent, CreateApplication, CsvImportForm,
+    CsvImportParams, CsvImportReport, Incident, IncidentFilters, LinkDomain, LinkInfra,
+    LinkNetworkShare, LinkPerson, LinkService, PaginationParams, Revision, RevisionDiff,
+    RevisionDiffParams, Role, UpdateApplication, UptimeParams, UptimeStats,
 };
 use crate::overview::Overview as _;
 use crate::service::application;
+use crate::service::audit;
+use crate::service::csv_import::{self, CsvEntity};
+use crate::service::heartbeat;
+use crate::service::incident;
+use crate::service::revision;
 use crate::{AppState, Result};
 
 #[derive(Debug, Deserialize, Default)]
@@ -24,10 +34,22 @@ pub struct ApplicationFilters {
 }
 
 pub fn routes() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list).post(create))
-        .route("/{id}", get(get_one).put(update).delete(delete_one))
-        .route("/{id}/overview.md", get(get_overview_md))
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history))
+        .route("/{id}/uptime", get(uptime))
+        .route("/{id}/incidents", get(incidents))
+        .route("/{id}/revisions", get(revisions))
+        .route("/{id}/revisions/{revision}/diff", get(revision_diff))
+        .route("/{id}/overview.md", get(get_overview_md));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/import", post(import_csv))
+        .route("/{id}", put(update))
+        .route("/{id}/revisions/{revision}/rollback", post(rollback))
         .route("/{id}/sync-outline", post(sync_outline))
         // Relationship management
         .route(
@@ -54,6 +76,13 @@ pub fn routes() -> Router<AppState> {
             "/{id}/stacks/{stack_id}",
             post(link_stack).delete(unlink_stack),
         )
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
 }
 
 #[utoipa::path(
@@ -99,7 +128,7 @@ async fn list(
         ("id" = String, Path, description = "Application ID")
     ),
     responses(
-        (status = 200, description = "Application found", body = ApplicationWithRelations),
+        (status = 200, description = "Application found", body = ApplicationWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
         (status = 404, description = "Application not found"),
         (status = 500, description = "Internal server error")
     )
@@ -109,7 +138,7 @@ async fn get_one(
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
     let result = application::get_with_relations(&state.pool, &id).await?;
-    Ok(Json(result))
+    Ok((etag(result.application.version), Json(result)))
 }
 
 #[utoipa::path(
@@ -138,6 +167,7 @@ async fn get_overview_md(
     post,
     path = "/api/applications",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     request_body = CreateApplication,
     responses(
         (status = 201, description = "Application created", body = Application),
@@ -147,63 +177,139 @@ async fn get_overview_md(
 )]
 async fn create(
     State(state): State<AppState>,
+    actor: Actor,
     Json(input): Json<CreateApplication>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = application::create(&state.pool, input).await?;
+    let result = application::create(&state.pool, actor.as_deref(), input).await?;
     Ok((axum::http::StatusCode::CREATED, Json(result)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/applications/import",
+    tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("partial" = Option<bool>, Query, description = "Import the valid rows even if other rows have errors"),
+    ),
+    request_body(content = CsvImportForm, content_type = "multipart/form-data", description = "CSV with a header row. Fields: name, description, repository_url, environment, url, status, image_refs, outline_url; relation columns: infra, services, domains, people, shares, stacks. Several names in one relation column are separated by `;`."),
+    responses(
+        (status = 200, description = "Rows imported", body = CsvImportReport),
+        (status = 400, description = "Unreadable file or unknown columns"),
+        (status = 422, description = "Some rows have errors, nothing was imported", body = CsvImportReport),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn import_csv(
+    State(state): State<AppState>,
+    actor: Actor,
+    Query(params): Query<CsvImportParams>,
+    upload: CsvUpload,
+) -> Result<impl axum::response::IntoResponse> {
+    let report = csv_import::import(
+        &state.pool,
+        actor.as_deref(),
+        CsvEntity::Application,
+        &upload.data,
+        &upload.mapping,
+        params.partial,
+    )
+    .await?;
+
+    let status = if report.committed {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::UNPROCESSABLE_ENTITY
+    };
+    Ok((status, Json(report)))
+}
+
 #[utoipa::path(
     put,
     path = "/api/applications/{id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
-        ("id" = String, Path, description = "Application ID")
+        ("id" = String, Path, description = "Application ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     request_body = UpdateApplication,
     responses(
-        (status = 200, description = "Application updated", body = Application),
+        (status = 200, description = "Application updated", body = Application, headers(("ETag" = String, description = "New version"))),
         (status = 404, description = "Application not found"),
         (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
-    Json(input): Json<UpdateApplication>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateApplication>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = application::update(&state.pool, &id, input).await?;
-    Ok(Json(result))
+    input.version = if_match.0.or(input.version);
+    let result = application::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/applications/{id}",
     tag = "applications",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
-        ("id" = String, Path, description = "Application ID")
+        ("id" = String, Path, description = "Application ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     responses(
-        (status = 204, description = "Application deleted"),
+        (status = 204, description = "Application moved to the trash"),
         (status = 404, description = "Application not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn delete_one(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
+    if_match: IfMatch,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::delete(&state.pool, &id).await?;
+    application::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/applications/{id}/history",
+    tag = "applications",
+    params(
+        ("id" = String, Path, description = "Application ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "application", &id, &params).await?;
+    Ok(Json(result))
+}
+
 // Relationship handlers
 
 #[utoipa::path(
     post,
     path = "/api/applications/{id}/infra/{infra_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("infra_id" = String, Path, description = "Infrastructure ID")
@@ -217,10 +323,18 @@ async fn delete_one(
 )]
 async fn link_infra(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, infra_id)): Path<(String, String)>,
     Json(input): Json<LinkInfra>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::link_infra(&state.pool, &app_id, &infra_id, input.notes.as_deref()).await?;
+    application::link_infra(
+        &state.pool,
+        actor.as_deref(),
+        &app_id,
+        &infra_id,
+        input.notes.as_deref(),
+    )
+    .await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -228,6 +342,7 @@ async fn link_infra(
     delete,
     path = "/api/applications/{id}/infra/{infra_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("infra_id" = String, Path, description = "Infrastructure ID")
@@ -240,9 +355,10 @@ async fn link_infra(
 )]
 async fn unlink_infra(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, infra_id)): Path<(String, String)>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::unlink_infra(&state.pool, &app_id, &infra_id).await?;
+    application::unlink_infra(&state.pool, actor.as_deref(), &app_id, &infra_id).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -250,6 +366,7 @@ async fn unlink_infra(
     post,
     path = "/api/applications/{id}/services/{service_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("service_id" = String, Path, description = "Service ID")
@@ -263,10 +380,18 @@ async fn unlink_infra(
 )]
 async fn link_service(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, service_id)): Path<(String, String)>,
     Json(input): Json<LinkService>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::link_service(&state.pool, &app_id, &service_id, input.notes.as_deref()).await?;
+    application::link_service(
+        &state.pool,
+        actor.as_deref(),
+        &app_id,
+        &service_id,
+        input.notes.as_deref(),
+    )
+    .await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -274,6 +399,7 @@ async fn link_service(
     delete,
     path = "/api/applications/{id}/services/{service_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("service_id" = String, Path, description = "Service ID")
@@ -286,9 +412,10 @@ async fn link_service(
 )]
 async fn unlink_service(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, service_id)): Path<(String, String)>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::unlink_service(&state.pool, &app_id, &service_id).await?;
+    application::unlink_service(&state.pool, actor.as_deref(), &app_id, &service_id).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -296,6 +423,7 @@ async fn unlink_service(
     post,
     path = "/api/applications/{id}/domains/{domain_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("domain_id" = String, Path, description = "Domain ID")
@@ -310,10 +438,18 @@ async fn unlink_service(
 #[instrument(skip(state))]
 async fn link_domain(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, domain_id)): Path<(String, String)>,
     Json(input): Json<LinkDomain>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::link_domain(&state.pool, &app_id, &domain_id, input.notes.as_deref()).await?;
+    application::link_domain(
+        &state.pool,
+        actor.as_deref(),
+        &app_id,
+        &domain_id,
+        input.notes.as_deref(),
+    )
+    .await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -321,6 +457,7 @@ async fn link_domain(
     delete,
     path = "/api/applications/{id}/domains/{domain_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("domain_id" = String, Path, description = "Domain ID")
@@ -333,9 +470,10 @@ async fn link_domain(
 )]
 async fn unlink_domain(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, domain_id)): Path<(String, String)>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::unlink_domain(&state.pool, &app_id, &domain_id).await?;
+    application::unlink_domain(&state.pool, actor.as_deref(), &app_id, &domain_id).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -343,6 +481,7 @@ async fn unlink_domain(
     post,
     path = "/api/applications/{id}/people/{person_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("person_id" = String, Path, description = "Person ID")
@@ -356,11 +495,13 @@ async fn unlink_domain(
 )]
 async fn link_person(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, person_id)): Path<(String, String)>,
     Json(input): Json<LinkPerson>,
 ) -> Result<impl axum::response::IntoResponse> {
     application::link_person(
         &state.pool,
+        actor.as_deref(),
         &app_id,
         &person_id,
         &input.contribution_type,
@@ -376,6 +517,7 @@ async fn link_person(
     delete,
     path = "/api/applications/{id}/people/{person_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("person_id" = String, Path, description = "Person ID")
@@ -388,9 +530,10 @@ async fn link_person(
 )]
 async fn unlink_person(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, person_id)): Path<(String, String)>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::unlink_person(&state.pool, &app_id, &person_id).await?;
+    application::unlink_person(&state.pool, actor.as_deref(), &app_id, &person_id).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -398,6 +541,7 @@ async fn unlink_person(
     post,
     path = "/api/applications/{id}/shares/{share_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("share_id" = String, Path, description = "Network share ID")
@@ -411,11 +555,13 @@ async fn unlink_person(
 )]
 async fn link_share(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, share_id)): Path<(String, String)>,
     Json(input): Json<LinkNetworkShare>,
 ) -> Result<impl axum::response::IntoResponse> {
     application::link_network_share(
         &state.pool,
+        actor.as_deref(),
         &app_id,
         &share_id,
         input.usage.as_deref(),
@@ -431,6 +577,7 @@ async fn link_share(
     delete,
     path = "/api/applications/{id}/shares/{share_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("share_id" = String, Path, description = "Network share ID")
@@ -443,9 +590,10 @@ async fn link_share(
 )]
 async fn unlink_share(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, share_id)): Path<(String, String)>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::unlink_network_share(&state.pool, &app_id, &share_id).await?;
+    application::unlink_network_share(&state.pool, actor.as_deref(), &app_id, &share_id).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -453,6 +601,7 @@ async fn unlink_share(
     post,
     path = "/api/applications/{id}/stacks/{stack_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("stack_id" = String, Path, description = "Stack ID")
@@ -465,9 +614,10 @@ async fn unlink_share(
 )]
 async fn link_stack(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, stack_id)): Path<(String, String)>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::link_stack(&state.pool, &app_id, &stack_id).await?;
+    application::link_stack(&state.pool, actor.as_deref(), &app_id, &stack_id).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -475,6 +625,7 @@ async fn link_stack(
     delete,
     path = "/api/applications/{id}/stacks/{stack_id}",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID"),
         ("stack_id" = String, Path, description = "Stack ID")
@@ -487,9 +638,10 @@ async fn link_stack(
 )]
 async fn unlink_stack(
     State(state): State<AppState>,
+    actor: Actor,
     Path((app_id, stack_id)): Path<(String, String)>,
 ) -> Result<impl axum::response::IntoResponse> {
-    application::unlink_stack(&state.pool, &app_id, &stack_id).await?;
+    application::unlink_stack(&state.pool, actor.as_deref(), &app_id, &stack_id).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -497,6 +649,7 @@ async fn unlink_stack(
     post,
     path = "/api/applications/{id}/sync-outline",
     tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Application ID")
     ),
@@ -534,3 +687,138 @@ async fn sync_outline(
 
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/applications/{id}/revisions",
+    tag = "applications",
+    params(
+        ("id" = String, Path, description = "Application ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Full snapshots after every create and update, newest first", body = inline(crate::models::PaginatedResponse<Revision>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revisions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::list(&state.pool, "application", &id, &params).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/applications/{id}/revisions/{revision}/diff",
+    tag = "applications",
+    params(
+        ("id" = String, Path, description = "Application ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("against" = Option<i64>, Query, description = "Revision to compare with, defaults to the one before"),
+    ),
+    responses(
+        (status = 200, description = "Fields changed in the revision", body = RevisionDiff),
+        (status = 404, description = "Revision not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revision_diff(
+    State(state): State<AppState>,
+    Path((id, rev)): Path<(String, i64)>,
+    Query(params): Query<RevisionDiffParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::diff(&state.pool, "application", &id, rev, params.against).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/applications/{id}/revisions/{revision}/rollback",
+    tag = "applications",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("id" = String, Path, description = "Application ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
+    ),
+    responses(
+        (status = 200, description = "Application updated to the revision", body = Application, headers(("ETag" = String, description = "New version"))),
+        (status = 404, description = "Application or revision not found"),
+        (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match is outdated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn rollback(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path((id, rev)): Path<(String, i64)>,
+    if_match: IfMatch,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = application::rollback(&state.pool, actor.as_deref(), &id, rev, if_match.0).await?;
+    Ok((etag(result.version), Json(result)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/applications/{id}/incidents",
+    tag = "applications",
+    params(
+        ("id" = String, Path, description = "Application ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Incidents of the healthchecks of the application, most recent first", body = inline(crate::models::PaginatedResponse<Incident>)),
+        (status = 404, description = "Application not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn incidents(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    application::get(&state.pool, &id).await?;
+    let filters = IncidentFilters {
+        page: params.page,
+        per_page: params.per_page,
+        application_id: Some(id),
+        ..Default::default()
+    };
+    let result = incident::list(&state.pool, &filters).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/applications/{id}/uptime",
+    tag = "applications",
+    params(
+        ("id" = String, Path, description = "Application ID"),
+        ("range" = Option<String>, Query, description = "24h (default), 7d, 30d or 90d"),
+    ),
+    responses(
+        (status = 200, description = "Uptime statistics of every healthcheck of the application", body = Vec<UptimeStats>),
+        (status = 404, description = "Application not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn uptime(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<UptimeParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = heartbeat::application_stats(
+        &state.pool,
+        &id,
+        params.range,
+        state.config.uptime_retention,
+    )
+    .await?;
+    Ok(Json(result))
+}
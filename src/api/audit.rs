@@ -0,0 +1,40 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    routing::get,
+};
+
+use crate::models::{AuditEvent, AuditFilters};
+use crate::service::audit;
+use crate::{AppState, Result};
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/", get(list))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/audit",
+    tag = "audit",
+    params(
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+        ("actor" = Option<String>, Query, description = "Filter by actor"),
+        ("entity_type" = Option<String>, Query, description = "Filter by entity type (either side of a link)"),
+        ("entity_id" = Option<String>, Query, description = "Filter by entity ID (either side of a link)"),
+        ("action" = Option<String>, Query, description = "Filter by action: create, update, delete, link, unlink"),
+        ("since" = Option<String>, Query, description = "Only events at or after this timestamp"),
+        ("until" = Option<String>, Query, description = "Only events before this timestamp"),
+    ),
+    responses(
+        (status = 200, description = "List of audit events, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn list(
+    State(state): State<AppState>,
+    Query(filters): Query<AuditFilters>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::list(&state.pool, &filters).await?;
+    Ok(Json(result))
+}
@@ -0,0 +1,493 @@
+use axum::{
+    Extension, Json, Router,
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    routing::{delete, get, post},
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use axum_extra::headers::{Authorization, HeaderMapExt, authorization::Bearer};
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::api::Actor;
+use crate::models::{
+    ApiToken, AuthConfig, CreateApiToken, CreateSession, Identity, NewApiToken, OidcLogin, Role,
+};
+use crate::oidc::{self, OidcClient};
+use crate::service::auth;
+use crate::{AppState, Error, Result};
+
+/// Name of the cookie holding the session secret for the web UI.
+pub const SESSION_COOKIE: &str = "auto_session";
+
+/// Name of the cookie tying a pending OIDC login to the browser that
+/// started it, so a callback can't be replayed in another browser.
+const OIDC_STATE_COOKIE: &str = "auto_oidc_state";
+
+/// Routes reachable without being authenticated (logging in and out).
+pub fn public_routes() -> Router<AppState> {
+    Router::new()
+        .route("/config", get(config))
+        .route("/session", post(login).delete(logout))
+        // Single sign-on
+        .route("/login", get(oidc_login))
+        .route("/callback", get(oidc_callback))
+        .route("/logout", get(oidc_logout))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LoginParams {
+    /// Path in the web UI to return to after logging in
+    pub redirect: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/me", get(me))
+        .route("/tokens", get(list_tokens).post(create_token))
+        .route("/tokens/{id}", delete(delete_token))
+}
+
+/// Middleware rejecting requests without a valid Bearer token or session
+/// cookie. The resolved [`Identity`] is stored in the request extensions.
+pub async fn require_identity(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response> {
+    let identity = resolve_identity(&state, req.headers())
+        .await?
+        .ok_or_else(|| Error::Unauthorized("Authentication required".to_string()))?;
+
+    req.extensions_mut().insert(identity);
+    Ok(next.run(req).await)
+}
+
+/// Middleware rejecting callers below the minimum [`Role`] of a route group.
+///
+/// Must run after [`require_identity`]; route groups declare their minimum
+/// role with `.route_layer(middleware::from_fn_with_state(Role::Editor, require_role))`.
+pub async fn require_role(
+    State(min_role): State<Role>,
+    Extension(identity): Extension<Identity>,
+    req: Request,
+    next: Next,
+) -> Result<Response> {
+    ensure_role(&identity, min_role)?;
+    Ok(next.run(req).await)
+}
+
+/// Fail with [`Error::Forbidden`] unless the caller has at least `min_role`.
+pub fn ensure_role(identity: &Identity, min_role: Role) -> Result<()> {
+    if identity.role < min_role {
+        return Err(Error::Forbidden(format!(
+            "This requires the {} role",
+            min_role.as_str()
+        )));
+    }
+    Ok(())
+}
+
+/// Resolve the caller from the `Authorization: Bearer` header, falling back
+/// to the session cookie.
+async fn resolve_identity(state: &AppState, headers: &HeaderMap) -> Result<Option<Identity>> {
+    if let Some(Authorization(bearer)) = headers.typed_get::<Authorization<Bearer>>() {
+        return authenticate_secret(state, bearer.token()).await;
+    }
+
+    let jar = CookieJar::from_headers(headers);
+    match jar.get(SESSION_COOKIE) {
+        Some(cookie) => auth::authenticate_session(&state.pool, cookie.value()).await,
+        None => Ok(None),
+    }
+}
+
+/// Resolve an API token or the bootstrap admin token.
+async fn authenticate_secret(state: &AppState, secret: &str) -> Result<Option<Identity>> {
+    if auth::is_admin_token(secret, state.config.auth_admin_token.as_deref()) {
+        return Ok(Some(Identity {
+            person_id: None,
+            name: auth::ADMIN_NAME.to_string(),
+            role: Role::Admin,
+        }));
+    }
+
+    auth::authenticate_token(&state.pool, secret).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/session",
+    tag = "auth",
+    request_body = CreateSession,
+    responses(
+        (status = 200, description = "Session started, the session cookie is set", body = Identity),
+        (status = 401, description = "Invalid token"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(())
+)]
+async fn login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(input): Json<CreateSession>,
+) -> Result<impl IntoResponse> {
+    let identity = authenticate_secret(&state, input.token.trim())
+        .await?
+        .ok_or_else(|| Error::Unauthorized("Invalid token".to_string()))?;
+
+    let secret = auth::create_session(&state.pool, &identity).await?;
+    Ok((jar.add(session_cookie(&state, secret)), Json(identity)))
+}
+
+/// The cookie carrying a newly created session.
+fn session_cookie(state: &AppState, secret: String) -> Cookie<'static> {
+    Cookie::build((SESSION_COOKIE, secret))
+        .path("/")
+        .http_only(true)
+        // Lax rather than Strict so the redirect back from the OIDC provider
+        // already carries the cookie
+        .same_site(SameSite::Lax)
+        .secure(state.config.base_url.starts_with("https://"))
+        .max_age(time::Duration::days(auth::SESSION_TTL_DAYS))
+        .build()
+}
+
+fn oidc_state_cookie(state: &AppState, login_state: String) -> Cookie<'static> {
+    Cookie::build((OIDC_STATE_COOKIE, login_state))
+        .path("/api/auth")
+        .http_only(true)
+        // Lax so the top-level redirect back from the provider carries it
+        .same_site(SameSite::Lax)
+        .secure(state.config.base_url.starts_with("https://"))
+        .max_age(time::Duration::minutes(auth::OIDC_LOGIN_TTL_MINUTES))
+        .build()
+}
+
+/// Only allow redirects to paths on this server, never to other hosts.
+fn local_path(redirect: Option<&str>) -> String {
+    match redirect {
+        Some(path) if path.starts_with('/') && !path.starts_with("//") && !path.contains('\\') => {
+            path.to_string()
+        }
+        _ => "/".to_string(),
+    }
+}
+
+fn oidc_client(state: &AppState) -> Result<OidcClient> {
+    state
+        .config
+        .oidc
+        .as_ref()
+        .map(OidcClient::new)
+        .ok_or_else(|| Error::NotFound("Single sign-on is not configured".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/config",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Available login methods", body = AuthConfig)
+    ),
+    security(())
+)]
+async fn config(State(state): State<AppState>) -> Json<AuthConfig> {
+    Json(AuthConfig {
+        oidc: state.config.oidc.is_some(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/login",
+    tag = "auth",
+    params(
+        ("redirect" = Option<String>, Query, description = "Path in the web UI to return to after logging in")
+    ),
+    responses(
+        (status = 303, description = "Redirect to the OIDC provider"),
+        (status = 404, description = "Single sign-on is not configured"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(())
+)]
+async fn oidc_login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(params): Query<LoginParams>,
+) -> Result<impl IntoResponse> {
+    let client = oidc_client(&state)?;
+    let metadata = client.discover().await?;
+
+    let login = OidcLogin {
+        state: oidc::random_token(),
+        nonce: oidc::random_token(),
+        code_verifier: oidc::random_token(),
+        redirect_to: local_path(params.redirect.as_deref()),
+    };
+    auth::create_oidc_login(&state.pool, &login).await?;
+
+    let url = client.authorization_url(
+        &metadata,
+        &login.state,
+        &login.nonce,
+        &oidc::pkce_challenge(&login.code_verifier),
+    );
+    Ok((
+        jar.add(oidc_state_cookie(&state, login.state)),
+        Redirect::to(url.as_str()),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/callback",
+    tag = "auth",
+    params(
+        ("code" = Option<String>, Query, description = "Authorization code"),
+        ("state" = Option<String>, Query, description = "State passed to the provider by /api/auth/login"),
+    ),
+    responses(
+        (status = 303, description = "Session started, redirect into the web UI (or to the login page on failure)"),
+        (status = 404, description = "Single sign-on is not configured"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(())
+)]
+async fn oidc_callback(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Query(params): Query<CallbackParams>,
+) -> Response {
+    let browser_state = jar.get(OIDC_STATE_COOKIE).map(|c| c.value().to_string());
+    let jar = jar.remove(Cookie::build(OIDC_STATE_COOKIE).path("/api/auth"));
+
+    match complete_oidc_login(&state, params, browser_state.as_deref()).await {
+        Ok((identity, redirect_to)) => match auth::create_session(&state.pool, &identity).await {
+            Ok(secret) => (
+                jar.add(session_cookie(&state, secret)),
+                Redirect::to(&redirect_to),
+            )
+                .into_response(),
+            Err(e) => e.into_response(),
+        },
+        // Send the user back to the login page with the reason instead of a bare JSON error
+        Err(Error::Unauthorized(msg)) => {
+            warn!("OIDC login failed: {msg}");
+            let query = url::form_urlencoded::Serializer::new(String::new())
+                .append_pair("error", &msg)
+                .finish();
+            (jar, Redirect::to(&format!("/login?{query}"))).into_response()
+        }
+        Err(e) => (jar, e).into_response(),
+    }
+}
+
+async fn complete_oidc_login(
+    state: &AppState,
+    params: CallbackParams,
+    browser_state: Option<&str>,
+) -> Result<(Identity, String)> {
+    let client = oidc_client(state)?;
+
+    if let Some(error) = params.error {
+        let description = params.error_description.unwrap_or_default();
+        return Err(Error::Unauthorized(
+            format!("Login refused by the identity provider: {error} {description}")
+                .trim_end()
+                .to_string(),
+        ));
+    }
+    let (Some(code), Some(login_state)) = (params.code, params.state) else {
+        return Err(Error::Unauthorized(
+            "Missing code or state in callback".to_string(),
+        ));
+    };
+
+    // Only the browser that started the login may finish it, otherwise an
+    // attacker could log a victim in to the attacker's account
+    if browser_state != Some(login_state.as_str()) {
+        return Err(Error::Unauthorized(
+            "Login was started in another browser".to_string(),
+        ));
+    }
+
+    let login = auth::take_oidc_login(&state.pool, &login_state).await?;
+    let metadata = client.discover().await?;
+    let id_token = client
+        .exchange_code(&metadata, &code, &login.code_verifier)
+        .await?;
+    let claims = client
+        .verify_id_token(&metadata, &id_token, &login.nonce)
+        .await?;
+    let identity = auth::identity_from_oidc(&state.pool, &claims).await?;
+
+    Ok((identity, login.redirect_to))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/logout",
+    tag = "auth",
+    responses(
+        (status = 303, description = "Session ended, redirect to the provider's logout page or the web UI"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(())
+)]
+async fn oidc_logout(State(state): State<AppState>, jar: CookieJar) -> Result<impl IntoResponse> {
+    if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        auth::delete_session(&state.pool, cookie.value()).await?;
+    }
+    let jar = jar.remove(Cookie::build(SESSION_COOKIE).path("/"));
+
+    // Also end the session at the provider, otherwise the next login
+    // silently logs the same user in again
+    let mut redirect_to = "/".to_string();
+    if let Ok(client) = oidc_client(&state) {
+        match client.discover().await {
+            Ok(metadata) => {
+                if let Some(url) = client.logout_url(&metadata, &state.config.base_url) {
+                    redirect_to = url.to_string();
+                }
+            }
+            Err(e) => warn!("Skipping OIDC logout at the provider: {e}"),
+        }
+    }
+
+    Ok((jar, Redirect::to(&redirect_to)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/auth/session",
+    tag = "auth",
+    responses(
+        (status = 204, description = "Session ended, the session cookie is cleared"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(())
+)]
+async fn logout(State(state): State<AppState>, jar: CookieJar) -> Result<impl IntoResponse> {
+    if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        auth::delete_session(&state.pool, cookie.value()).await?;
+    }
+
+    let jar = jar.remove(Cookie::build(SESSION_COOKIE).path("/"));
+    Ok((jar, StatusCode::NO_CONTENT))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/me",
+    tag = "auth",
+    responses(
+        (status = 200, description = "The authenticated identity", body = Identity),
+        (status = 401, description = "Not authenticated")
+    )
+)]
+async fn me(Extension(identity): Extension<Identity>) -> Json<Identity> {
+    Json(identity)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/tokens",
+    tag = "auth",
+    responses(
+        (status = 200, description = "API tokens of the caller (all tokens for the admin token)", body = Vec<ApiToken>),
+        (status = 401, description = "Not authenticated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn list_tokens(
+    State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+) -> Result<impl IntoResponse> {
+    let result = auth::list_tokens(&state.pool, identity.person_id.as_deref()).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/tokens",
+    tag = "auth",
+    request_body = CreateApiToken,
+    responses(
+        (status = 201, description = "API token created, the secret is only returned once", body = NewApiToken),
+        (status = 400, description = "Invalid input"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Only admins can create tokens for someone else"),
+        (status = 404, description = "Person not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn create_token(
+    State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    Json(input): Json<CreateApiToken>,
+) -> Result<impl IntoResponse> {
+    let person_id = match (&identity.person_id, &input.person_id) {
+        (Some(own), Some(requested)) if own != requested => {
+            ensure_role(&identity, Role::Admin)?;
+            requested.clone()
+        }
+        (Some(own), _) => own.clone(),
+        (None, Some(requested)) => requested.clone(),
+        (None, None) => {
+            return Err(Error::ValidationError("person_id is required".to_string()));
+        }
+    };
+
+    let result = auth::create_token(&state.pool, actor.as_deref(), &person_id, input).await?;
+    Ok((StatusCode::CREATED, Json(result)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/auth/tokens/{id}",
+    tag = "auth",
+    params(
+        ("id" = String, Path, description = "API token ID")
+    ),
+    responses(
+        (status = 204, description = "API token revoked"),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "API token not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn delete_token(
+    State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let token = auth::get_token(&state.pool, &id).await?;
+    if identity.role < Role::Admin
+        && identity
+            .person_id
+            .as_ref()
+            .is_some_and(|own| *own != token.person_id)
+    {
+        return Err(Error::NotFound(format!(
+            "API token with id '{}' not found",
+            id
+        )));
+    }
+
+    auth::delete_token(&state.pool, actor.as_deref(), &id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,13 +1,24 @@
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::get,
+    middleware,
+    routing::{delete, get, post, put},
 };
 use serde::Deserialize;
 
-use crate::models::{CreateDomain, PaginationParams, UpdateDomain, DomainWithRelations, Domain};
+use crate::api::auth::require_role;
+use crate::api::{Actor, CsvUpload, IfMatch, etag};
+use crate::models::{
+    AuditEvent, CreateDomain, CsvImportForm, CsvImportParams, CsvImportReport, Domain,
+    DomainWithRelations, PaginationParams, Revision, RevisionDiff, RevisionDiffParams, Role,
+    UpdateDomain,
+};
+use crate::service::audit;
+use crate::service::certificate;
+use crate::service::csv_import::{self, CsvEntity};
 use crate::service::domain;
-use crate::{AppState, Result};
+use crate::service::revision;
+use crate::{AppState, Result, rdap};
 
 #[derive(Debug, Deserialize, Default)]
 pub struct DomainFilters {
@@ -17,9 +28,28 @@ pub struct DomainFilters {
 }
 
 pub fn routes() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list).post(create))
-        .route("/{id}", get(get_one).put(update).delete(delete_one))
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history))
+        .route("/{id}/revisions", get(revisions))
+        .route("/{id}/revisions/{revision}/diff", get(revision_diff));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/import", post(import_csv))
+        .route("/{id}", put(update))
+        .route("/{id}/refresh", post(refresh))
+        .route("/{id}/certificates/check", post(check_certificates))
+        .route("/{id}/revisions/{revision}/rollback", post(rollback))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
 }
 
 #[utoipa::path(
@@ -57,7 +87,7 @@ async fn list(
         ("id" = String, Path, description = "Domain ID")
     ),
     responses(
-        (status = 200, description = "Domain found", body = DomainWithRelations),
+        (status = 200, description = "Domain found", body = DomainWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
         (status = 404, description = "Domain not found"),
         (status = 500, description = "Internal server error")
     )
@@ -67,13 +97,14 @@ async fn get_one(
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
     let result = domain::get_with_relations(&state.pool, &id).await?;
-    Ok(Json(result))
+    Ok((etag(result.domain.version), Json(result)))
 }
 
 #[utoipa::path(
     post,
     path = "/api/domains",
     tag = "domains",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     request_body = CreateDomain,
     responses(
         (status = 201, description = "Domain created", body = Domain),
@@ -83,53 +114,255 @@ async fn get_one(
 )]
 async fn create(
     State(state): State<AppState>,
+    actor: Actor,
     Json(input): Json<CreateDomain>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = domain::create(&state.pool, input).await?;
+    let result = domain::create(&state.pool, actor.as_deref(), input).await?;
     Ok((axum::http::StatusCode::CREATED, Json(result)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/domains/import",
+    tag = "domains",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("partial" = Option<bool>, Query, description = "Import the valid rows even if other rows have errors"),
+    ),
+    request_body(content = CsvImportForm, content_type = "multipart/form-data", description = "CSV with a header row. Fields: fqdn, registrar, dns_provider, expires_at, notes; relation columns: target_application, target_service, applications. Several names in one relation column are separated by `;`."),
+    responses(
+        (status = 200, description = "Rows imported", body = CsvImportReport),
+        (status = 400, description = "Unreadable file or unknown columns"),
+        (status = 422, description = "Some rows have errors, nothing was imported", body = CsvImportReport),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn import_csv(
+    State(state): State<AppState>,
+    actor: Actor,
+    Query(params): Query<CsvImportParams>,
+    upload: CsvUpload,
+) -> Result<impl axum::response::IntoResponse> {
+    let report = csv_import::import(
+        &state.pool,
+        actor.as_deref(),
+        CsvEntity::Domain,
+        &upload.data,
+        &upload.mapping,
+        params.partial,
+    )
+    .await?;
+
+    let status = if report.committed {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::UNPROCESSABLE_ENTITY
+    };
+    Ok((status, Json(report)))
+}
+
 #[utoipa::path(
     put,
     path = "/api/domains/{id}",
     tag = "domains",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
-        ("id" = String, Path, description = "Domain ID")
+        ("id" = String, Path, description = "Domain ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     request_body = UpdateDomain,
     responses(
-        (status = 200, description = "Domain updated", body = Domain),
+        (status = 200, description = "Domain updated", body = Domain, headers(("ETag" = String, description = "New version"))),
         (status = 404, description = "Domain not found"),
         (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
-    Json(input): Json<UpdateDomain>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateDomain>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = domain::update(&state.pool, &id, input).await?;
-    Ok(Json(result))
+    input.version = if_match.0.or(input.version);
+    let result = domain::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/domains/{id}",
     tag = "domains",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
-        ("id" = String, Path, description = "Domain ID")
+        ("id" = String, Path, description = "Domain ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     responses(
-        (status = 204, description = "Domain deleted"),
+        (status = 204, description = "Domain moved to the trash"),
         (status = 404, description = "Domain not found"),
+        (status = 409, description = "Domain is still used by healthchecks"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn delete_one(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
+    if_match: IfMatch,
 ) -> Result<impl axum::response::IntoResponse> {
-    domain::delete(&state.pool, &id).await?;
+    domain::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    post,
+    path = "/api/domains/{id}/refresh",
+    tag = "domains",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("id" = String, Path, description = "Domain ID")
+    ),
+    responses(
+        (status = 200, description = "Domain looked up with RDAP, a failed lookup is in `lookup.error`", body = DomainWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
+        (status = 404, description = "Domain not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn refresh(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let existing = domain::get(&state.pool, &id).await?;
+    let client = rdap::RdapClient::new(&state.config.rdap_url)?;
+    rdap::refresh(&state.pool, &client, &[existing]).await?;
+
+    let result = domain::get_with_relations(&state.pool, &id).await?;
+    Ok((etag(result.domain.version), Json(result)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/domains/{id}/certificates/check",
+    tag = "domains",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("id" = String, Path, description = "Domain ID")
+    ),
+    responses(
+        (status = 200, description = "Certificates checked, a failed connection is in their `error`", body = DomainWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
+        (status = 404, description = "Domain not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn check_certificates(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let existing = domain::get(&state.pool, &id).await?;
+    certificate::check(&state.pool, &existing).await?;
+
+    let result = domain::get_with_relations(&state.pool, &id).await?;
+    Ok((etag(result.domain.version), Json(result)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/domains/{id}/history",
+    tag = "domains",
+    params(
+        ("id" = String, Path, description = "Domain ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "domain", &id, &params).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/domains/{id}/revisions",
+    tag = "domains",
+    params(
+        ("id" = String, Path, description = "Domain ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Full snapshots after every create and update, newest first", body = inline(crate::models::PaginatedResponse<Revision>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revisions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::list(&state.pool, "domain", &id, &params).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/domains/{id}/revisions/{revision}/diff",
+    tag = "domains",
+    params(
+        ("id" = String, Path, description = "Domain ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("against" = Option<i64>, Query, description = "Revision to compare with, defaults to the one before"),
+    ),
+    responses(
+        (status = 200, description = "Fields changed in the revision", body = RevisionDiff),
+        (status = 404, description = "Revision not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revision_diff(
+    State(state): State<AppState>,
+    Path((id, rev)): Path<(String, i64)>,
+    Query(params): Query<RevisionDiffParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::diff(&state.pool, "domain", &id, rev, params.against).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/domains/{id}/revisions/{revision}/rollback",
+    tag = "domains",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("id" = String, Path, description = "Domain ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
+    ),
+    responses(
+        (status = 200, description = "Domain updated to the revision", body = Domain, headers(("ETag" = String, description = "New version"))),
+        (status = 404, description = "Domain or revision not found"),
+        (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match is outdated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn rollback(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path((id, rev)): Path<(String, i64)>,
+    if_match: IfMatch,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = domain::rollback(&state.pool, actor.as_deref(), &id, rev, if_match.0).await?;
+    Ok((etag(result.version), Json(result)))
+}
@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+
+use axum::{
+    Router,
+    extract::{Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::get,
+};
+use futures::stream::{self, Stream, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, warn};
+
+use crate::changes::{self, ChangeFilter};
+use crate::models::{ChangeEvent, ChangeStreamFilters};
+use crate::service::audit;
+use crate::{AppState, Result};
+
+pub fn routes() -> Router<AppState> {
+    // Any authenticated user
+    Router::new().route("/", get(stream_changes))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/events",
+    tag = "events",
+    params(
+        ("entity_types" = Option<String>, Query, description = "Only changes to these comma separated entity types, or links to them"),
+        ("entity_id" = Option<String>, Query, description = "Only changes to this entity, or links to it"),
+        ("Last-Event-ID" = Option<i64>, Header, description = "Resume after this change, the id of its audit event. Sends a `reload` event instead if it is too old"),
+    ),
+    responses(
+        (status = 200, description = "Server-sent changes as they are made", body = ChangeEvent, content_type = "text/event-stream"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn stream_changes(
+    State(state): State<AppState>,
+    Query(filters): Query<ChangeStreamFilters>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>>> {
+    let filter = ChangeFilter::from(filters);
+
+    // Subscribe FIRST, then look up where to start — prevents missed
+    // changes in the gap. Changes seen twice are skipped by id.
+    let rx = state.change_tx.subscribe();
+
+    let resume_from = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok());
+    let missed = match resume_from {
+        Some(id) => changes::missed(&state.pool, id).await?,
+        None => None,
+    };
+    let (last_id, initial) = match (resume_from, missed) {
+        (Some(id), Some(missed)) => {
+            debug!("Resuming change stream after event {id}");
+            let initial = missed
+                .iter()
+                .filter(|change| filter.matches(change))
+                .filter_map(change_sse_event)
+                .collect();
+            (missed.last().map_or(id, |c| c.id), initial)
+        }
+        (Some(id), None) => {
+            debug!("Change stream can't resume after event {id}, reloading");
+            let last_id = audit::last_id(&state.pool).await?;
+            (last_id, vec![reload_sse_event(last_id)])
+        }
+        (None, _) => (audit::last_id(&state.pool).await?, Vec::new()),
+    };
+    let initial: Vec<_> = initial.into_iter().map(Ok).collect();
+
+    let live = futures::stream::unfold(
+        (rx, last_id, state, filter, VecDeque::new()),
+        |(mut rx, mut last_id, state, filter, mut pending)| async move {
+            loop {
+                let change = match pending.pop_front() {
+                    Some(change) => change,
+                    None => match rx.recv().await {
+                        Ok(change) if change.id <= last_id => continue,
+                        Ok(change) => change,
+                        Err(RecvError::Lagged(n)) => {
+                            // Changes were missed, read them from the audit log
+                            warn!("SSE client lagged by {n} changes, catching up");
+                            match changes::missed(&state.pool, last_id).await {
+                                Ok(Some(missed)) => pending = missed.into(),
+                                Ok(None) => {
+                                    last_id = audit::last_id(&state.pool)
+                                        .await
+                                        .map_err(|e| error!("Failed to read changes: {e}"))
+                                        .ok()?;
+                                    let event = reload_sse_event(last_id);
+                                    let next = (rx, last_id, state, filter, pending);
+                                    return Some((Ok(event), next));
+                                }
+                                Err(e) => {
+                                    error!("Failed to read missed changes: {e}");
+                                    return None;
+                                }
+                            }
+                            continue;
+                        }
+                        Err(RecvError::Closed) => return None,
+                    },
+                };
+                last_id = change.id;
+                if !filter.matches(&change) {
+                    continue;
+                }
+                if let Some(event) = change_sse_event(&change) {
+                    return Some((Ok(event), (rx, last_id, state, filter, pending)));
+                }
+            }
+        },
+    );
+
+    Ok(Sse::new(stream::iter(initial).chain(live)).keep_alive(KeepAlive::default()))
+}
+
+fn change_sse_event(change: &ChangeEvent) -> Option<Event> {
+    let json = serde_json::to_string(change).ok()?;
+    Some(Event::default().id(change.id.to_string()).data(json))
+}
+
+/// Tells a client that too much changed to catch up, it should reload what it
+/// shows. Resumes after change `last_id`.
+fn reload_sse_event(last_id: i64) -> Event {
+    Event::default()
+        .event("reload")
+        .id(last_id.to_string())
+        .data("{}")
+}
@@ -0,0 +1,53 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::header,
+    response::IntoResponse,
+    routing::get,
+};
+
+use crate::models::{Graph, GraphFormat, GraphParams};
+use crate::service::graph;
+use crate::{AppState, Result};
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/", get(get_graph))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/graph",
+    tag = "graph",
+    params(
+        ("root" = Option<String>, Query, description = "Only the part of the graph connected to this application, service, infra or domain"),
+        ("depth" = Option<u32>, Query, description = "Maximum number of edges between the root and any node"),
+        ("format" = Option<String>, Query, description = "json (default), dot or mermaid"),
+    ),
+    responses(
+        (status = 200, description = "Dependency graph", body = Graph),
+        (status = 200, description = "Graphviz DOT", content_type = "text/vnd.graphviz", body = String),
+        (status = 200, description = "Mermaid flowchart", content_type = "text/plain", body = String),
+        (status = 404, description = "Root entity not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn get_graph(
+    State(state): State<AppState>,
+    Query(params): Query<GraphParams>,
+) -> Result<impl IntoResponse> {
+    let result = graph::get(&state.pool, params.root.as_deref(), params.depth).await?;
+
+    Ok(match params.format {
+        GraphFormat::Json => Json(result).into_response(),
+        GraphFormat::Dot => (
+            [(header::CONTENT_TYPE, "text/vnd.graphviz")],
+            graph::to_dot(&result),
+        )
+            .into_response(),
+        GraphFormat::Mermaid => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            graph::to_mermaid(&result),
+        )
+            .into_response(),
+    })
+}
@@ -1,18 +1,31 @@
+use std::collections::HashSet;
+
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
+    http::HeaderMap,
+    middleware,
     response::sse::{Event, KeepAlive, Sse},
-    routing::{get, post},
+    routing::{delete, get, post, put},
 };
 use futures::stream::{self, Stream, StreamExt};
 use serde::Deserialize;
 use tracing::debug;
 
+use crate::api::auth::require_role;
+use crate::api::{Actor, IfMatch, etag};
 use crate::models::{
-    CreateHealthcheck, Healthcheck, HealthcheckExecuteResult, HealthcheckWithRelations,
-    KumaEndpoint, KumaMonitor, PaginationParams, UpdateHealthcheck, UptimeEvent,
+    AuditEvent, CreateHealthcheck, Healthcheck, HealthcheckExecuteResult, HealthcheckWithRelations,
+    KumaDiff, KumaEndpoint, KumaImport, KumaImportResult, KumaMonitor, KumaSyncItem,
+    MonitorSummary, MonitorUptime, PaginationParams, Revision, RevisionDiff, RevisionDiffParams,
+    Role, UpdateHealthcheck, UptimeEvent, UptimeHistory, UptimeParams, UptimeStats,
+    UptimeStreamFilters,
 };
+use crate::service::audit;
 use crate::service::healthcheck;
+use crate::service::heartbeat;
+use crate::service::kuma_sync;
+use crate::service::revision;
 use crate::{AppState, Result, kuma};
 
 #[derive(Debug, Deserialize, Default)]
@@ -25,16 +38,43 @@ pub struct HealthcheckFilters {
     pub is_enabled: Option<bool>,
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct KumaQueueFilters {
+    pub healthcheck_id: Option<String>,
+}
+
 pub fn routes() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list).post(create))
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
         .route("/export/kuma", get(export_kuma))
-        .route("/sync/kuma", post(sync_kuma_all))
         .route("/kuma-endpoint", get(kuma_endpoint))
-        .route("/sync/kuma/{id}", post(sync_kuma_one))
+        .route("/kuma/queue", get(kuma_queue))
         .route("/uptime/stream", get(uptime_stream))
-        .route("/{id}", get(get_one).put(update).delete(delete_one))
-        .route("/{id}/execute", get(execute))
+        .route("/uptime/{id}", get(uptime_live))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history))
+        .route("/{id}/uptime", get(uptime))
+        .route("/{id}/uptime/history", get(uptime_history))
+        .route("/{id}/revisions", get(revisions))
+        .route("/{id}/revisions/{revision}/diff", get(revision_diff))
+        .route("/{id}/execute", get(execute));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/{id}", put(update))
+        .route("/{id}/revisions/{revision}/rollback", post(rollback))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route("/sync/kuma", post(sync_kuma_all))
+        .route("/sync/kuma/{id}", post(sync_kuma_one))
+        .route("/kuma/diff", get(kuma_diff))
+        .route("/kuma/import", post(kuma_import))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
 }
 
 #[utoipa::path(
@@ -82,7 +122,7 @@ async fn list(
         ("id" = String, Path, description = "Healthcheck ID")
     ),
     responses(
-        (status = 200, description = "Healthcheck found", body = HealthcheckWithRelations),
+        (status = 200, description = "Healthcheck found", body = HealthcheckWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
         (status = 404, description = "Healthcheck not found"),
         (status = 500, description = "Internal server error")
     )
@@ -92,13 +132,14 @@ async fn get_one(
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
     let result = healthcheck::get_with_relations(&state.pool, &id).await?;
-    Ok(Json(result))
+    Ok((etag(result.healthcheck.version), Json(result)))
 }
 
 #[utoipa::path(
     post,
     path = "/api/healthchecks",
     tag = "healthchecks",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     request_body = CreateHealthcheck,
     responses(
         (status = 201, description = "Healthcheck created", body = Healthcheck),
@@ -108,9 +149,10 @@ async fn get_one(
 )]
 async fn create(
     State(state): State<AppState>,
+    actor: Actor,
     Json(input): Json<CreateHealthcheck>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = healthcheck::create(&state.pool, input).await?;
+    let result = healthcheck::create(&state.pool, actor.as_deref(), input).await?;
     Ok((axum::http::StatusCode::CREATED, Json(result)))
 }
 
@@ -118,47 +160,85 @@ async fn create(
     put,
     path = "/api/healthchecks/{id}",
     tag = "healthchecks",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
-        ("id" = String, Path, description = "Healthcheck ID")
+        ("id" = String, Path, description = "Healthcheck ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     request_body = UpdateHealthcheck,
     responses(
-        (status = 200, description = "Healthcheck updated", body = Healthcheck),
+        (status = 200, description = "Healthcheck updated", body = Healthcheck, headers(("ETag" = String, description = "New version"))),
         (status = 404, description = "Healthcheck not found"),
         (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
-    Json(input): Json<UpdateHealthcheck>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateHealthcheck>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = healthcheck::update(&state.pool, &id, input).await?;
-    Ok(Json(result))
+    input.version = if_match.0.or(input.version);
+    let result = healthcheck::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/healthchecks/{id}",
     tag = "healthchecks",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
-        ("id" = String, Path, description = "Healthcheck ID")
+        ("id" = String, Path, description = "Healthcheck ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     responses(
-        (status = 204, description = "Healthcheck deleted"),
+        (status = 204, description = "Healthcheck moved to the trash"),
         (status = 404, description = "Healthcheck not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn delete_one(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
+    if_match: IfMatch,
 ) -> Result<impl axum::response::IntoResponse> {
-    healthcheck::delete(&state.pool, &id).await?;
+    healthcheck::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
+
+    // Pause its Kuma monitor right away
+    let _ = state.kuma_sync_tx.send(());
+
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/healthchecks/{id}/history",
+    tag = "healthchecks",
+    params(
+        ("id" = String, Path, description = "Healthcheck ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "healthcheck", &id, &params).await?;
+    Ok(Json(result))
+}
+
 #[utoipa::path(
     get,
     path = "/api/healthchecks/{id}/execute",
@@ -212,11 +292,12 @@ async fn export_kuma(State(state): State<AppState>) -> Result<impl axum::respons
     post,
     path = "/api/healthchecks/sync/kuma/{id}",
     tag = "healthchecks",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
         ("id" = String, Path, description = "Healthcheck ID to sync")
     ),
     responses(
-        (status = 204, description = "Healthcheck synced to Kuma"),
+        (status = 202, description = "Healthcheck queued to be synced to Kuma"),
         (status = 404, description = "Healthcheck not found"),
         (status = 500, description = "Internal server error")
     )
@@ -225,98 +306,420 @@ async fn sync_kuma_one(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
-    // rust_socketio::Client is !Send, so we run the sync on a dedicated
-    // blocking thread that owns its own async context.
-    let handle = tokio::runtime::Handle::current();
-    let state_clone = state.clone();
-    tokio::task::spawn_blocking(move || {
-        handle.block_on(kuma::sync_healthcheck_to_kuma(state_clone, &id))
-    })
-    .await
-    .map_err(|e| crate::Error::InternalError(e.to_string()))??;
-
-    // Notify the persistent poller to reconnect and pick up new kuma_ids
-    let _ = state.kuma_refresh_tx.send(());
-
-    Ok(axum::http::StatusCode::NO_CONTENT)
+    healthcheck::get(&state.pool, &id).await?;
+    kuma_sync::enqueue_push(&state.pool, Some(&id)).await?;
+    let _ = state.kuma_sync_tx.send(());
+    Ok(axum::http::StatusCode::ACCEPTED)
 }
 
 #[utoipa::path(
     post,
     path = "/api/healthchecks/sync/kuma",
     tag = "healthchecks",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     responses(
-        (status = 204, description = "All healthchecks synced to Kuma"),
+        (status = 202, description = "All healthchecks queued to be synced to Kuma"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn sync_kuma_all(State(state): State<AppState>) -> Result<impl axum::response::IntoResponse> {
-    // rust_socketio::Client is !Send, so we run the sync on a dedicated
-    // blocking thread that owns its own async context.
-    let handle = tokio::runtime::Handle::current();
-    let state_clone = state.clone();
-    tokio::task::spawn_blocking(move || {
-        handle.block_on(kuma::sync_healthchecks_to_kuma(state_clone))
-    })
-    .await
-    .map_err(|e| crate::Error::InternalError(e.to_string()))??;
+    kuma_sync::enqueue_push(&state.pool, None).await?;
+    let _ = state.kuma_sync_tx.send(());
+    Ok(axum::http::StatusCode::ACCEPTED)
+}
 
-    // Notify the persistent poller to reconnect and pick up new kuma_ids
-    let _ = state.kuma_refresh_tx.send(());
+#[utoipa::path(
+    get,
+    path = "/api/healthchecks/kuma/queue",
+    tag = "healthchecks",
+    params(
+        ("healthcheck_id" = Option<String>, Query, description = "Only the item of this healthcheck"),
+    ),
+    responses(
+        (status = 200, description = "Changes waiting to be synced to Kuma, with their attempts and last error", body = Vec<KumaSyncItem>),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn kuma_queue(
+    State(state): State<AppState>,
+    Query(filters): Query<KumaQueueFilters>,
+) -> Result<Json<Vec<KumaSyncItem>>> {
+    let items = kuma_sync::list(&state.pool, filters.healthcheck_id.as_deref()).await?;
+    Ok(Json(items))
+}
 
-    Ok(axum::http::StatusCode::NO_CONTENT)
+#[utoipa::path(
+    get,
+    path = "/api/healthchecks/kuma/diff",
+    tag = "healthchecks",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    responses(
+        (status = 200, description = "Kuma monitors missing in Auto, drifted monitors and orphaned kuma_ids", body = KumaDiff),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn kuma_diff(State(state): State<AppState>) -> Result<Json<KumaDiff>> {
+    let monitors = fetch_kuma_monitors(&state).await?;
+    Ok(Json(kuma::diff(&state.pool, &monitors).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/healthchecks/kuma/import",
+    tag = "healthchecks",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    request_body = KumaImport,
+    responses(
+        (status = 200, description = "Healthchecks created from Kuma monitors, and the monitors skipped", body = KumaImportResult),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn kuma_import(
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(input): Json<KumaImport>,
+) -> Result<Json<KumaImportResult>> {
+    let monitors = fetch_kuma_monitors(&state).await?;
+    let result = kuma::import(&state.pool, actor.as_deref(), &monitors, input).await?;
+
+    // Let the poller pick up the heartbeats of the imported monitors
+    if !result.created.is_empty() {
+        let _ = state.kuma_refresh_tx.send(());
+    }
+
+    Ok(Json(result))
+}
+
+async fn fetch_kuma_monitors(state: &AppState) -> Result<Vec<serde_json::Value>> {
+    // rust_socketio::Client is !Send, so we talk to Kuma on a dedicated
+    // blocking thread that owns its own async context.
+    let handle = tokio::runtime::Handle::current();
+    let config = state.config.clone();
+    tokio::task::spawn_blocking(move || handle.block_on(kuma::fetch_monitors(&config)))
+        .await
+        .map_err(|e| crate::Error::InternalError(e.to_string()))?
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/healthchecks/uptime/stream",
+    tag = "healthchecks",
+    params(
+        ("kuma_ids" = Option<String>, Query, description = "Only these comma separated Kuma monitor ids"),
+        ("application_id" = Option<String>, Query, description = "Only healthchecks of this application"),
+        ("service_id" = Option<String>, Query, description = "Only healthchecks of this service"),
+        ("Last-Event-ID" = Option<u64>, Header, description = "Resume after this event, sends a snapshot instead if it is too old"),
+    ),
+    responses(
+        (status = 200, description = "Server-sent uptime events, each with a numbered id", body = UptimeEvent, content_type = "text/event-stream"),
+        (status = 400, description = "Invalid kuma_ids"),
+        (status = 500, description = "Internal server error")
+    )
+)]
 async fn uptime_stream(
     State(state): State<AppState>,
-) -> Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>> {
-    // Subscribe FIRST, then read snapshot — prevents missed events in the gap.
+    Query(filters): Query<UptimeStreamFilters>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>>> {
+    let filter = UptimeFilter::new(&state.pool, filters).await?;
+
+    // Subscribe FIRST, then read the snapshot or missed events — prevents
+    // missed events in the gap. Events seen twice are skipped by id.
     let rx = state.uptime_tx.subscribe();
 
-    // Build the initial snapshot
-    // NOTE: this snapshot is too large. It is about 1.8MB of data and seems to take quite a while
-    // to get to the client
-    debug!("Getting uptime state snapshot");
-    let snapshot = {
-        let read = state.uptime_state.read().await;
-        UptimeEvent::Snapshot {
-            monitors: read.clone(),
+    let resume_from = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let initial = match resume_from.and_then(|id| Some((id, state.uptime_tx.since(id)?))) {
+        Some((id, missed)) => {
+            debug!("Resuming uptime stream after event {id}");
+            missed
+        }
+        None => {
+            let id = state.uptime_tx.last_id();
+            vec![(id, uptime_snapshot(&state, &filter).await)]
         }
     };
+    let last_id = initial
+        .last()
+        .map(|(id, _)| *id)
+        .or(resume_from)
+        .unwrap_or_default();
+    let initial: Vec<_> = initial
+        .into_iter()
+        .filter_map(|(id, event)| uptime_sse_event(id, &filter.apply(event)?))
+        .map(Ok)
+        .collect();
 
-    let snapshot_json = serde_json::to_string(&snapshot)
-        .unwrap_or_else(|_| r#"{"type":"snapshot","monitors":{}}"#.to_string());
-    let snapshot_event = Ok::<Event, axum::Error>(Event::default().data(snapshot_json));
-
-    // Chain: one snapshot event, then the live broadcast stream
-    let initial = stream::once(async move { snapshot_event });
-    let live = broadcast_to_stream(rx);
-    let combined = initial.chain(live);
+    let live = futures::stream::unfold(
+        (rx, last_id, state, filter),
+        |(mut rx, mut last_id, state, filter)| async move {
+            loop {
+                let (id, event) = match rx.recv().await {
+                    Ok((id, _)) if id <= last_id => continue,
+                    Ok(numbered) => numbered,
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
+                        // Events were missed, start over from the current state
+                        tracing::warn!("SSE client lagged by {n} events, sending a snapshot");
+                        let id = state.uptime_tx.last_id();
+                        (id, uptime_snapshot(&state, &filter).await)
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+                };
+                last_id = id;
+                if let Some(event) = filter.apply(event).and_then(|e| uptime_sse_event(id, &e)) {
+                    return Some((Ok(event), (rx, last_id, state, filter)));
+                }
+            }
+        },
+    );
 
-    debug!("Start sending stream");
+    Ok(Sse::new(stream::iter(initial).chain(live)).keep_alive(KeepAlive::default()))
+}
 
-    Sse::new(combined).keep_alive(KeepAlive::default())
+/// Healthchecks an uptime stream is limited to
+struct UptimeFilter {
+    kuma_ids: Option<HashSet<i32>>,
+    healthcheck_ids: Option<HashSet<String>>,
 }
 
-/// Converts a `broadcast::Receiver` into a `Stream` of SSE events.
-fn broadcast_to_stream(
-    rx: tokio::sync::broadcast::Receiver<UptimeEvent>,
-) -> impl Stream<Item = std::result::Result<Event, axum::Error>> {
-    futures::stream::unfold(rx, |mut rx| async move {
-        loop {
-            match rx.recv().await {
-                Ok(event) => {
-                    if let Ok(json) = serde_json::to_string(&event) {
-                        return Some((Ok(Event::default().data(json)), rx));
-                    }
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
-                    tracing::warn!("SSE client lagged by {n} events, continuing");
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    return None;
-                }
+impl UptimeFilter {
+    async fn new(pool: &sqlx::SqlitePool, filters: UptimeStreamFilters) -> Result<Self> {
+        let kuma_ids = match filters.kuma_ids.as_deref() {
+            Some(ids) => Some(
+                ids.split(',')
+                    .filter(|id| !id.trim().is_empty())
+                    .map(|id| {
+                        id.trim().parse().map_err(|_| {
+                            crate::Error::ValidationError(format!("Invalid kuma id '{id}'"))
+                        })
+                    })
+                    .collect::<Result<HashSet<i32>>>()?,
+            ),
+            None => None,
+        };
+
+        // Healthchecks added later are left out until the client reconnects
+        let mut healthcheck_ids: Option<HashSet<String>> = None;
+        if let Some(application_id) = &filters.application_id {
+            let ids = healthcheck::get_for_application(pool, application_id).await?;
+            healthcheck_ids = Some(ids.into_iter().map(|h| h.id).collect());
+        }
+        if let Some(service_id) = &filters.service_id {
+            let ids = healthcheck::get_for_service(pool, service_id).await?;
+            healthcheck_ids
+                .get_or_insert_default()
+                .extend(ids.into_iter().map(|h| h.id));
+        }
+
+        Ok(Self {
+            kuma_ids,
+            healthcheck_ids,
+        })
+    }
+
+    fn matches(&self, healthcheck_id: &str, kuma_id: Option<i32>) -> bool {
+        let kuma = self
+            .kuma_ids
+            .as_ref()
+            .is_none_or(|ids| kuma_id.is_some_and(|id| ids.contains(&id)));
+        let healthcheck = self
+            .healthcheck_ids
+            .as_ref()
+            .is_none_or(|ids| ids.contains(healthcheck_id));
+        kuma && healthcheck
+    }
+
+    /// The part of an event the client asked for, if any. Snapshots are
+    /// always sent, as they replace what the client has.
+    fn apply(&self, event: UptimeEvent) -> Option<UptimeEvent> {
+        match event {
+            UptimeEvent::Snapshot { mut monitors } => {
+                monitors.retain(|id, monitor| self.matches(id, monitor.kuma_id));
+                Some(UptimeEvent::Snapshot { monitors })
             }
+            UptimeEvent::Update {
+                ref healthcheck_id,
+                kuma_id,
+                ..
+            } => self.matches(healthcheck_id, kuma_id).then_some(event),
         }
-    })
+    }
+}
+
+/// Summaries of the current uptime of the healthchecks a client asked for
+async fn uptime_snapshot(state: &AppState, filter: &UptimeFilter) -> UptimeEvent {
+    let read = state.uptime_state.read().await;
+    UptimeEvent::Snapshot {
+        monitors: read
+            .iter()
+            .filter(|(id, monitor)| filter.matches(id, monitor.kuma_id))
+            .map(|(id, monitor)| (id.clone(), MonitorSummary::from(monitor)))
+            .collect(),
+    }
+}
+
+fn uptime_sse_event(id: u64, event: &UptimeEvent) -> Option<Event> {
+    let json = serde_json::to_string(event).ok()?;
+    Some(Event::default().id(id.to_string()).data(json))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/healthchecks/uptime/{id}",
+    tag = "healthchecks",
+    params(
+        ("id" = String, Path, description = "Healthcheck ID")
+    ),
+    responses(
+        (status = 200, description = "All heartbeats of the last hour, which the uptime stream only summarizes", body = MonitorUptime),
+        (status = 404, description = "Healthcheck not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn uptime_live(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<MonitorUptime>> {
+    let hc = healthcheck::get(&state.pool, &id).await?;
+    let monitor = state.uptime_state.read().await.get(&id).cloned();
+    Ok(Json(monitor.unwrap_or(MonitorUptime {
+        healthcheck_id: hc.id,
+        kuma_id: hc.kuma_id,
+        heartbeats: Vec::new(),
+    })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/healthchecks/{id}/revisions",
+    tag = "healthchecks",
+    params(
+        ("id" = String, Path, description = "Healthcheck ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Full snapshots after every create and update, newest first", body = inline(crate::models::PaginatedResponse<Revision>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revisions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::list(&state.pool, "healthcheck", &id, &params).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/healthchecks/{id}/revisions/{revision}/diff",
+    tag = "healthchecks",
+    params(
+        ("id" = String, Path, description = "Healthcheck ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("against" = Option<i64>, Query, description = "Revision to compare with, defaults to the one before"),
+    ),
+    responses(
+        (status = 200, description = "Fields changed in the revision", body = RevisionDiff),
+        (status = 404, description = "Revision not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revision_diff(
+    State(state): State<AppState>,
+    Path((id, rev)): Path<(String, i64)>,
+    Query(params): Query<RevisionDiffParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::diff(&state.pool, "healthcheck", &id, rev, params.against).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/healthchecks/{id}/revisions/{revision}/rollback",
+    tag = "healthchecks",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("id" = String, Path, description = "Healthcheck ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
+    ),
+    responses(
+        (status = 200, description = "Healthcheck updated to the revision", body = Healthcheck, headers(("ETag" = String, description = "New version"))),
+        (status = 404, description = "Healthcheck or revision not found"),
+        (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match is outdated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn rollback(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path((id, rev)): Path<(String, i64)>,
+    if_match: IfMatch,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = healthcheck::rollback(&state.pool, actor.as_deref(), &id, rev, if_match.0).await?;
+    Ok((etag(result.version), Json(result)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/healthchecks/{id}/uptime",
+    tag = "healthchecks",
+    params(
+        ("id" = String, Path, description = "Healthcheck ID"),
+        ("range" = Option<String>, Query, description = "24h (default), 7d, 30d or 90d"),
+    ),
+    responses(
+        (status = 200, description = "Uptime statistics of the healthcheck", body = UptimeStats),
+        (status = 404, description = "Healthcheck not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn uptime(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<UptimeParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = heartbeat::stats(
+        &state.pool,
+        &id,
+        params.range,
+        state.config.uptime_retention,
+    )
+    .await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/healthchecks/{id}/uptime/history",
+    tag = "healthchecks",
+    params(
+        ("id" = String, Path, description = "Healthcheck ID"),
+        ("range" = Option<String>, Query, description = "24h (default), 7d, 30d or 90d"),
+    ),
+    responses(
+        (status = 200, description = "Hourly or daily uptime buckets of the healthcheck", body = UptimeHistory),
+        (status = 404, description = "Healthcheck not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn uptime_history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<UptimeParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = heartbeat::history(
+        &state.pool,
+        &id,
+        params.range,
+        state.config.uptime_retention,
+    )
+    .await?;
+    Ok(Json(result))
 }
@@ -0,0 +1,63 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::get,
+};
+
+use crate::models::{Incident, IncidentFilters};
+use crate::service::incident;
+use crate::{AppState, Result};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/incidents",
+    tag = "incidents",
+    params(
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+        ("status" = Option<String>, Query, description = "open or resolved"),
+        ("healthcheck_id" = Option<String>, Query, description = "Filter by healthcheck ID"),
+        ("application_id" = Option<String>, Query, description = "Filter by application ID"),
+        ("service_id" = Option<String>, Query, description = "Filter by service ID"),
+        ("since" = Option<String>, Query, description = "Only incidents that started at or after this timestamp"),
+        ("until" = Option<String>, Query, description = "Only incidents that started before this timestamp"),
+    ),
+    responses(
+        (status = 200, description = "Incidents, most recent first", body = inline(crate::models::PaginatedResponse<Incident>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn list(
+    State(state): State<AppState>,
+    Query(filters): Query<IncidentFilters>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = incident::list(&state.pool, &filters).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/incidents/{id}",
+    tag = "incidents",
+    params(
+        ("id" = String, Path, description = "Incident ID")
+    ),
+    responses(
+        (status = 200, description = "Incident found", body = Incident),
+        (status = 404, description = "Incident not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn get_one(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = incident::get(&state.pool, &id).await?;
+    Ok(Json(result))
+}
@@ -1,12 +1,21 @@
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::get,
+    middleware,
+    routing::{delete, get, post, put},
 };
 use serde::Deserialize;
 
-use crate::models::{CreateInfra, PaginationParams, UpdateInfra, InfraWithRelations, Infra};
+use crate::api::auth::require_role;
+use crate::api::{Actor, IfMatch, etag};
+use crate::models::{
+    AuditEvent, CreateInfra, ImpactReport, Infra, InfraWithRelations, PaginationParams, Revision,
+    RevisionDiff, RevisionDiffParams, Role, UpdateInfra,
+};
+use crate::service::audit;
+use crate::service::impact;
 use crate::service::infra;
+use crate::service::revision;
 use crate::{AppState, Result};
 
 #[derive(Debug, Deserialize, Default)]
@@ -19,9 +28,27 @@ pub struct InfraFilters {
 }
 
 pub fn routes() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list).post(create))
-        .route("/{id}", get(get_one).put(update).delete(delete_one))
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history))
+        .route("/{id}/impact", get(impact))
+        .route("/{id}/impact.md", get(impact_md))
+        .route("/{id}/revisions", get(revisions))
+        .route("/{id}/revisions/{revision}/diff", get(revision_diff));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/{id}", put(update))
+        .route("/{id}/revisions/{revision}/rollback", post(rollback))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
 }
 
 #[utoipa::path(
@@ -60,7 +87,7 @@ async fn list(
         ("id" = String, Path, description = "Infrastructure ID")
     ),
     responses(
-        (status = 200, description = "Infrastructure found", body = InfraWithRelations),
+        (status = 200, description = "Infrastructure found", body = InfraWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
         (status = 404, description = "Infrastructure not found"),
         (status = 500, description = "Internal server error")
     )
@@ -70,13 +97,14 @@ async fn get_one(
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
     let result = infra::get_with_relations(&state.pool, &id).await?;
-    Ok(Json(result))
+    Ok((etag(result.infra.version), Json(result)))
 }
 
 #[utoipa::path(
     post,
     path = "/api/infra",
     tag = "infra",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     request_body = CreateInfra,
     responses(
         (status = 201, description = "Infrastructure created", body = Infra),
@@ -86,9 +114,10 @@ async fn get_one(
 )]
 async fn create(
     State(state): State<AppState>,
+    actor: Actor,
     Json(input): Json<CreateInfra>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = infra::create(&state.pool, input).await?;
+    let result = infra::create(&state.pool, actor.as_deref(), input).await?;
     Ok((axum::http::StatusCode::CREATED, Json(result)))
 }
 
@@ -96,43 +125,195 @@ async fn create(
     put,
     path = "/api/infra/{id}",
     tag = "infra",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
-        ("id" = String, Path, description = "Infrastructure ID")
+        ("id" = String, Path, description = "Infrastructure ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     request_body = UpdateInfra,
     responses(
-        (status = 200, description = "Infrastructure updated", body = Infra),
+        (status = 200, description = "Infrastructure updated", body = Infra, headers(("ETag" = String, description = "New version"))),
         (status = 404, description = "Infrastructure not found"),
         (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
-    Json(input): Json<UpdateInfra>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateInfra>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = infra::update(&state.pool, &id, input).await?;
-    Ok(Json(result))
+    input.version = if_match.0.or(input.version);
+    let result = infra::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/infra/{id}",
     tag = "infra",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
-        ("id" = String, Path, description = "Infrastructure ID")
+        ("id" = String, Path, description = "Infrastructure ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     responses(
-        (status = 204, description = "Infrastructure deleted"),
+        (status = 204, description = "Infrastructure moved to the trash"),
         (status = 404, description = "Infrastructure not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn delete_one(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
+    if_match: IfMatch,
 ) -> Result<impl axum::response::IntoResponse> {
-    infra::delete(&state.pool, &id).await?;
+    infra::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/infra/{id}/history",
+    tag = "infra",
+    params(
+        ("id" = String, Path, description = "Infrastructure ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "infra", &id, &params).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/infra/{id}/revisions",
+    tag = "infra",
+    params(
+        ("id" = String, Path, description = "Infrastructure ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Full snapshots after every create and update, newest first", body = inline(crate::models::PaginatedResponse<Revision>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revisions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::list(&state.pool, "infra", &id, &params).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/infra/{id}/revisions/{revision}/diff",
+    tag = "infra",
+    params(
+        ("id" = String, Path, description = "Infrastructure ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("against" = Option<i64>, Query, description = "Revision to compare with, defaults to the one before"),
+    ),
+    responses(
+        (status = 200, description = "Fields changed in the revision", body = RevisionDiff),
+        (status = 404, description = "Revision not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revision_diff(
+    State(state): State<AppState>,
+    Path((id, rev)): Path<(String, i64)>,
+    Query(params): Query<RevisionDiffParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::diff(&state.pool, "infra", &id, rev, params.against).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/infra/{id}/revisions/{revision}/rollback",
+    tag = "infra",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("id" = String, Path, description = "Infrastructure ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
+    ),
+    responses(
+        (status = 200, description = "Infrastructure updated to the revision", body = Infra, headers(("ETag" = String, description = "New version"))),
+        (status = 404, description = "Infrastructure or revision not found"),
+        (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match is outdated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn rollback(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path((id, rev)): Path<(String, i64)>,
+    if_match: IfMatch,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = infra::rollback(&state.pool, actor.as_deref(), &id, rev, if_match.0).await?;
+    Ok((etag(result.version), Json(result)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/infra/{id}/impact",
+    tag = "infra",
+    params(
+        ("id" = String, Path, description = "Infrastructure ID")
+    ),
+    responses(
+        (status = 200, description = "Everything affected when this infra goes down", body = ImpactReport),
+        (status = 404, description = "Infrastructure not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn impact(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = impact::analyze(&state.pool, "infra", &id).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/infra/{id}/impact.md",
+    tag = "infra",
+    params(
+        ("id" = String, Path, description = "Infrastructure ID")
+    ),
+    responses(
+        (status = 200, description = "Impact report as markdown", content_type = "text/markdown", body = String),
+        (status = 404, description = "Infrastructure not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn impact_md(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let report = impact::analyze(&state.pool, "infra", &id).await?;
+    let md = impact::to_md(&report);
+    Ok(([(axum::http::header::CONTENT_TYPE, "text/markdown")], md))
+}
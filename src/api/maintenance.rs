@@ -0,0 +1,180 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    middleware,
+    routing::{delete, get, post, put},
+};
+
+use crate::api::auth::require_role;
+use crate::api::{Actor, IfMatch, etag};
+use crate::models::{
+    AuditEvent, CreateMaintenance, Maintenance, MaintenanceFilters, PaginationParams, Role,
+    UpdateMaintenance,
+};
+use crate::service::{audit, maintenance};
+use crate::{AppState, Result};
+
+pub fn routes() -> Router<AppState> {
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/{id}", put(update))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/maintenance",
+    tag = "maintenance",
+    params(
+        ("infra_id" = Option<String>, Query, description = "Only maintenance of this infra item"),
+        ("upcoming" = Option<bool>, Query, description = "Only maintenance that hasn't ended yet"),
+    ),
+    responses(
+        (status = 200, description = "Maintenance, latest start first", body = Vec<Maintenance>),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn list(
+    State(state): State<AppState>,
+    Query(filters): Query<MaintenanceFilters>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = maintenance::list(&state.pool, &filters).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/maintenance/{id}",
+    tag = "maintenance",
+    params(
+        ("id" = String, Path, description = "Maintenance ID")
+    ),
+    responses(
+        (status = 200, description = "Maintenance found", body = Maintenance, headers(("ETag" = String, description = "Current version, for If-Match"))),
+        (status = 404, description = "Maintenance not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn get_one(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = maintenance::get(&state.pool, &id).await?;
+    Ok((etag(result.version), Json(result)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/maintenance",
+    tag = "maintenance",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    request_body = CreateMaintenance,
+    responses(
+        (status = 201, description = "Maintenance scheduled, synced to Kuma in the background", body = Maintenance),
+        (status = 400, description = "Invalid input"),
+        (status = 404, description = "Infra not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn create(
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(input): Json<CreateMaintenance>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = maintenance::create(&state.pool, actor.as_deref(), input).await?;
+    let _ = state.kuma_sync_tx.send(());
+    Ok((axum::http::StatusCode::CREATED, Json(result)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/maintenance/{id}",
+    tag = "maintenance",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("id" = String, Path, description = "Maintenance ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
+    ),
+    request_body = UpdateMaintenance,
+    responses(
+        (status = 200, description = "Maintenance updated", body = Maintenance, headers(("ETag" = String, description = "New version"))),
+        (status = 404, description = "Maintenance not found"),
+        (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn update(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path(id): Path<String>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateMaintenance>,
+) -> Result<impl axum::response::IntoResponse> {
+    input.version = if_match.0.or(input.version);
+    let result = maintenance::update(&state.pool, actor.as_deref(), &id, input).await?;
+    let _ = state.kuma_sync_tx.send(());
+    Ok((etag(result.version), Json(result)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/maintenance/{id}",
+    tag = "maintenance",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    params(
+        ("id" = String, Path, description = "Maintenance ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
+    ),
+    responses(
+        (status = 204, description = "Maintenance deleted, removed from Kuma in the background"),
+        (status = 404, description = "Maintenance not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn delete_one(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path(id): Path<String>,
+    if_match: IfMatch,
+) -> Result<impl axum::response::IntoResponse> {
+    maintenance::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
+    let _ = state.kuma_sync_tx.send(());
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/maintenance/{id}/history",
+    tag = "maintenance",
+    params(
+        ("id" = String, Path, description = "Maintenance ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "maintenance", &id, &params).await?;
+    Ok(Json(result))
+}
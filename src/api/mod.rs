@@ -1,41 +1,74 @@
-use axum::extract::{Path, Query, Request, State};
-use axum::http::header;
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use axum::extract::{FromRequest, FromRequestParts, Multipart, Path, Query, Request, State};
+use axum::http::{header, request::Parts};
 use axum::response::IntoResponse;
 use axum::routing::get;
-use axum::{Form, Json, Router};
+use axum::{Form, Json, Router, middleware};
 
 use crate::AppState;
+use crate::models::Identity;
 
 pub mod applications;
+pub mod audit;
+pub mod auth;
 pub mod dashboard;
 pub mod domains;
+pub mod events;
+pub mod graph;
 pub mod healthchecks;
+pub mod incidents;
 pub mod infra;
+pub mod maintenance;
 pub mod notes;
+pub mod notifications;
 pub mod outline;
 pub mod people;
 pub mod search;
 pub mod services;
 pub mod shares;
 pub mod stacks;
+#[cfg(test)]
+mod tests;
+pub mod transfer;
+pub mod trash;
 
 pub fn api_routes(state: AppState) -> Router<AppState> {
-    Router::new()
-        .route("/health", get(healthcheck))
-        .route("/version", get(version))
+    // Everything except health, version and logging in requires authentication
+    let protected = Router::new()
+        .nest("/auth", auth::routes())
         .nest("/applications", applications::routes())
+        .nest("/audit", audit::routes())
+        .nest("/events", events::routes())
         .nest("/services", services::routes())
         .nest("/infra", infra::routes())
+        .nest("/maintenance", maintenance::routes())
         .nest("/domains", domains::routes())
         .nest("/people", people::routes())
         .nest("/shares", shares::routes())
         .nest("/notes", notes::routes())
         .nest("/stacks", stacks::routes())
         .nest("/healthchecks", healthchecks::routes())
+        .nest("/incidents", incidents::routes())
+        .nest("/notifications", notifications::routes())
         .nest("/dashboard", dashboard::routes())
+        .nest("/graph", graph::routes())
         .nest("/search", search::routes())
         .nest("/outline", outline::routes())
+        .nest("/trash", trash::routes())
         .route("/resolve/{id}", get(resolve_id))
+        .merge(transfer::routes())
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_identity,
+        ));
+
+    Router::new()
+        .route("/health", get(healthcheck))
+        .route("/version", get(version))
+        .nest("/auth", auth::public_routes())
+        .merge(protected)
         .with_state(state)
 }
 
@@ -45,7 +78,8 @@ pub fn api_routes(state: AppState) -> Router<AppState> {
     tag = "health",
     responses(
         (status = 200, description = "Service is healthy", body = String)
-    )
+    ),
+    security(())
 )]
 async fn healthcheck() -> &'static str {
     "ok"
@@ -57,7 +91,8 @@ async fn healthcheck() -> &'static str {
     tag = "health",
     responses(
         (status = 200, description = "API version information", body = serde_json::Value)
-    )
+    ),
+    security(())
 )]
 async fn version() -> Json<serde_json::Value> {
     Json(serde_json::json!({
@@ -86,6 +121,78 @@ async fn resolve_id(
     Ok(Json(resolved))
 }
 
+/// The user performing a request, recorded in the audit log and as `created_by`.
+///
+/// Taken from the [`Identity`] that [`auth::require_identity`] stores in the
+/// request extensions; empty on routes that don't require authentication.
+#[derive(Debug, Clone, Default)]
+pub struct Actor(pub Option<String>);
+
+impl Actor {
+    pub fn as_deref(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+impl<S> FromRequestParts<S> for Actor
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let name = parts
+            .extensions
+            .get::<Identity>()
+            .map(|identity| identity.name.clone());
+
+        Ok(Self(name))
+    }
+}
+
+/// `ETag` header of an entity version, e.g. `"3"`.
+pub fn etag(version: i64) -> [(header::HeaderName, String); 1] {
+    [(header::ETAG, format!("\"{version}\""))]
+}
+
+/// The version from an `If-Match` header, as sent by [`etag`]. Empty
+/// without the header or for `*`, which matches any version. `If-Match`
+/// compares strongly, so a weak ETag (`W/"3"`) never matches.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IfMatch(pub Option<i64>);
+
+impl<S> FromRequestParts<S> for IfMatch
+where
+    S: Send + Sync,
+{
+    type Rejection = crate::Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(header::IF_MATCH) else {
+            return Ok(Self(None));
+        };
+        let value = value.to_str().unwrap_or_default().trim();
+        if value == "*" {
+            return Ok(Self(None));
+        }
+        if value.starts_with("W/") {
+            return Err(crate::Error::PreconditionFailed(format!(
+                "Weak If-Match '{value}' never matches, send the ETag as it is, like \"3\""
+            )));
+        }
+
+        value
+            .trim_matches('"')
+            .parse()
+            .map(|version| Self(Some(version)))
+            .map_err(|_| {
+                crate::Error::ValidationError(format!(
+                    "Invalid If-Match '{value}', expected an ETag like \"3\""
+                ))
+            })
+    }
+}
+
 #[allow(unused)]
 pub struct FlexibleInput<T>(pub T);
 
@@ -123,3 +230,50 @@ where
         Ok(Self(data))
     }
 }
+
+/// A CSV file uploaded as `multipart/form-data`: the file in a `file` field
+/// and an optional JSON column mapping in a `mapping` field.
+pub struct CsvUpload {
+    pub data: Vec<u8>,
+    pub mapping: HashMap<String, String>,
+}
+
+impl<S> FromRequest<S> for CsvUpload
+where
+    S: Send + Sync,
+{
+    type Rejection = crate::Error;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let invalid = |e: axum::extract::multipart::MultipartError| {
+            crate::Error::ValidationError(e.body_text())
+        };
+
+        let mut multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(|e| crate::Error::ValidationError(e.body_text()))?;
+
+        let mut data = None;
+        let mut mapping = HashMap::new();
+        while let Some(field) = multipart.next_field().await.map_err(invalid)? {
+            match field.name() {
+                Some("file") => data = Some(field.bytes().await.map_err(invalid)?.to_vec()),
+                Some("mapping") => {
+                    let text = field.text().await.map_err(invalid)?;
+                    if !text.trim().is_empty() {
+                        mapping = serde_json::from_str(&text).map_err(|e| {
+                            crate::Error::ValidationError(format!("Invalid column mapping: {e}"))
+                        })?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let data = data.ok_or_else(|| {
+            crate::Error::ValidationError("Missing 'file' field with the CSV file".to_string())
+        })?;
+
+        Ok(Self { data, mapping })
+    }
+}
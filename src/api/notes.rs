@@ -1,18 +1,35 @@
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::get,
+    middleware,
+    routing::{delete, get, post, put},
 };
 use serde::Deserialize;
 
-use crate::models::{CreateNote, PaginationParams, UpdateNote, Note};
+use crate::api::auth::require_role;
+use crate::api::{Actor, IfMatch, etag};
+use crate::models::{AuditEvent, CreateNote, Note, PaginationParams, Role, UpdateNote};
+use crate::service::audit;
 use crate::service::note;
 use crate::{AppState, Result};
 
 pub fn routes() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list).post(create))
-        .route("/{id}", get(get_one).put(update).delete(delete_one))
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/{id}", put(update))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
 }
 
 #[derive(Debug, Deserialize)]
@@ -61,7 +78,7 @@ async fn list(
         ("id" = String, Path, description = "Note ID")
     ),
     responses(
-        (status = 200, description = "Note found", body = Note),
+        (status = 200, description = "Note found", body = Note, headers(("ETag" = String, description = "Current version, for If-Match"))),
         (status = 404, description = "Note not found"),
         (status = 500, description = "Internal server error")
     )
@@ -71,13 +88,14 @@ async fn get_one(
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
     let result = note::get(&state.pool, &id).await?;
-    Ok(Json(result))
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     post,
     path = "/api/notes",
     tag = "notes",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     request_body = CreateNote,
     responses(
         (status = 201, description = "Note created", body = Note),
@@ -87,9 +105,10 @@ async fn get_one(
 )]
 async fn create(
     State(state): State<AppState>,
+    actor: Actor,
     Json(input): Json<CreateNote>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = note::create(&state.pool, input).await?;
+    let result = note::create(&state.pool, actor.as_deref(), input).await?;
     Ok((axum::http::StatusCode::CREATED, Json(result)))
 }
 
@@ -97,43 +116,77 @@ async fn create(
     put,
     path = "/api/notes/{id}",
     tag = "notes",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
-        ("id" = String, Path, description = "Note ID")
+        ("id" = String, Path, description = "Note ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     request_body = UpdateNote,
     responses(
-        (status = 200, description = "Note updated", body = Note),
+        (status = 200, description = "Note updated", body = Note, headers(("ETag" = String, description = "New version"))),
         (status = 404, description = "Note not found"),
         (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
-    Json(input): Json<UpdateNote>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateNote>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = note::update(&state.pool, &id, input).await?;
-    Ok(Json(result))
+    input.version = if_match.0.or(input.version);
+    let result = note::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/notes/{id}",
     tag = "notes",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
-        ("id" = String, Path, description = "Note ID")
+        ("id" = String, Path, description = "Note ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     responses(
         (status = 204, description = "Note deleted"),
         (status = 404, description = "Note not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn delete_one(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
+    if_match: IfMatch,
 ) -> Result<impl axum::response::IntoResponse> {
-    note::delete(&state.pool, &id).await?;
+    note::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/notes/{id}/history",
+    tag = "notes",
+    params(
+        ("id" = String, Path, description = "Note ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "note", &id, &params).await?;
+    Ok(Json(result))
+}
@@ -0,0 +1,189 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    middleware,
+    routing::{get, post},
+};
+
+use crate::api::auth::require_role;
+use crate::api::{Actor, IfMatch, etag};
+use crate::models::{
+    CreateNotificationChannel, Notification, NotificationChannel, NotificationFilters, Role,
+    UpdateNotificationChannel,
+};
+use crate::service::notification;
+use crate::{AppState, Result};
+
+pub fn routes() -> Router<AppState> {
+    // Channels hold webhook URLs, which are secrets
+    Router::new()
+        .route("/", get(list_sent))
+        .route("/channels", get(list).post(create))
+        .route(
+            "/channels/{id}",
+            get(get_one).put(update).delete(delete_one),
+        )
+        .route("/channels/{id}/test", post(test))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/notifications",
+    tag = "notifications",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    params(
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+        ("channel_id" = Option<String>, Query, description = "Filter by channel ID"),
+        ("event" = Option<String>, Query, description = "Filter by event: down, up, domain_expiring or test"),
+    ),
+    responses(
+        (status = 200, description = "Sent notifications, newest first", body = inline(crate::models::PaginatedResponse<Notification>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn list_sent(
+    State(state): State<AppState>,
+    Query(filters): Query<NotificationFilters>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = notification::list_sent(&state.pool, &filters).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/notifications/channels",
+    tag = "notifications",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    responses(
+        (status = 200, description = "List of notification channels", body = Vec<NotificationChannel>),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn list(State(state): State<AppState>) -> Result<impl axum::response::IntoResponse> {
+    let result = notification::list(&state.pool).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/notifications/channels/{id}",
+    tag = "notifications",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    params(
+        ("id" = String, Path, description = "Notification channel ID")
+    ),
+    responses(
+        (status = 200, description = "Notification channel found", body = NotificationChannel, headers(("ETag" = String, description = "Current version, for If-Match"))),
+        (status = 404, description = "Notification channel not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn get_one(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = notification::get(&state.pool, &id).await?;
+    Ok((etag(result.version), Json(result)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/notifications/channels",
+    tag = "notifications",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    request_body = CreateNotificationChannel,
+    responses(
+        (status = 201, description = "Notification channel created", body = NotificationChannel),
+        (status = 400, description = "Invalid input"),
+        (status = 409, description = "A channel with this name already exists"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn create(
+    State(state): State<AppState>,
+    actor: Actor,
+    Json(input): Json<CreateNotificationChannel>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = notification::create(&state.pool, actor.as_deref(), input).await?;
+    Ok((axum::http::StatusCode::CREATED, Json(result)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/notifications/channels/{id}",
+    tag = "notifications",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    params(
+        ("id" = String, Path, description = "Notification channel ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
+    ),
+    request_body = UpdateNotificationChannel,
+    responses(
+        (status = 200, description = "Notification channel updated", body = NotificationChannel, headers(("ETag" = String, description = "New version"))),
+        (status = 404, description = "Notification channel not found"),
+        (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn update(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path(id): Path<String>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateNotificationChannel>,
+) -> Result<impl axum::response::IntoResponse> {
+    input.version = if_match.0.or(input.version);
+    let result = notification::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/notifications/channels/{id}",
+    tag = "notifications",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    params(
+        ("id" = String, Path, description = "Notification channel ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
+    ),
+    responses(
+        (status = 204, description = "Notification channel deleted, with its sent notifications"),
+        (status = 404, description = "Notification channel not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn delete_one(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path(id): Path<String>,
+    if_match: IfMatch,
+) -> Result<impl axum::response::IntoResponse> {
+    notification::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/notifications/channels/{id}/test",
+    tag = "notifications",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    params(
+        ("id" = String, Path, description = "Notification channel ID")
+    ),
+    responses(
+        (status = 200, description = "Test notification, with the error if it could not be delivered", body = Notification),
+        (status = 404, description = "Notification channel not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn test(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = notification::test(&state.pool, &state.config, &id).await?;
+    Ok(Json(result))
+}
@@ -1,12 +1,16 @@
+use crate::api::auth::require_role;
+use crate::models::Role;
 use crate::{AppState, Result, outline};
 
-use axum::{Router, extract::State, response::IntoResponse, routing::get};
+use axum::{Router, extract::State, middleware, response::IntoResponse, routing::get};
 use futures::{StreamExt as _, stream};
 use sqlx::prelude::FromRow;
 use tracing::info;
 
 pub fn routes() -> Router<AppState> {
-    Router::new().route("/sync", get(sync_all))
+    Router::new()
+        .route("/sync", get(sync_all))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role))
 }
 
 #[derive(Debug, FromRow)]
@@ -16,10 +20,10 @@ struct Id {
 
 async fn sync_all(State(state): State<AppState>) -> Result<impl IntoResponse> {
     // sync all applications
-    let app_ids = sqlx::query_as::<_, Id>("SELECT id FROM application;")
+    let app_ids = sqlx::query_as::<_, Id>("SELECT id FROM application WHERE deleted_at IS NULL;")
         .fetch_all(&state.pool)
         .await?;
-    let service_ids = sqlx::query_as::<_, Id>("SELECT id FROM service;")
+    let service_ids = sqlx::query_as::<_, Id>("SELECT id FROM service WHERE deleted_at IS NULL;")
         .fetch_all(&state.pool)
         .await?;
 
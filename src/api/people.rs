@@ -1,11 +1,19 @@
 use axum::{
-    Json, Router,
+    Extension, Json, Router,
     extract::{Path, Query, State},
-    routing::get,
+    middleware,
+    routing::{delete, get, post, put},
 };
 use serde::Deserialize;
 
-use crate::models::{CreatePerson, PaginationParams, UpdatePerson, PersonWithRelations, Person};
+use crate::api::auth::{ensure_role, require_role};
+use crate::api::{Actor, CsvUpload, IfMatch, etag};
+use crate::models::{
+    AuditEvent, CreatePerson, CsvImportForm, CsvImportParams, CsvImportReport, Identity,
+    PaginationParams, Person, PersonWithRelations, Role, UpdatePerson,
+};
+use crate::service::audit;
+use crate::service::csv_import::{self, CsvEntity};
 use crate::service::person;
 use crate::{AppState, Result};
 
@@ -18,9 +26,23 @@ pub struct PersonFilters {
 }
 
 pub fn routes() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list).post(create))
-        .route("/{id}", get(get_one).put(update).delete(delete_one))
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/import", post(import_csv))
+        .route("/{id}", put(update))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
 }
 
 #[utoipa::path(
@@ -59,7 +81,7 @@ async fn list(
         ("id" = String, Path, description = "Person ID")
     ),
     responses(
-        (status = 200, description = "Person found", body = PersonWithRelations),
+        (status = 200, description = "Person found", body = PersonWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
         (status = 404, description = "Person not found"),
         (status = 500, description = "Internal server error")
     )
@@ -69,69 +91,172 @@ async fn get_one(
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
     let result = person::get_with_relations(&state.pool, &id).await?;
-    Ok(Json(result))
+    Ok((etag(result.person.version), Json(result)))
 }
 
 #[utoipa::path(
     post,
     path = "/api/people",
     tag = "people",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     request_body = CreatePerson,
     responses(
         (status = 201, description = "Person created", body = Person),
         (status = 400, description = "Invalid input"),
+        (status = 403, description = "Only admins can assign an access role"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn create(
     State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
     Json(input): Json<CreatePerson>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = person::create(&state.pool, input).await?;
+    if input.access_role.is_some() {
+        ensure_role(&identity, Role::Admin)?;
+    }
+    let result = person::create(&state.pool, actor.as_deref(), input).await?;
     Ok((axum::http::StatusCode::CREATED, Json(result)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/people/import",
+    tag = "people",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("partial" = Option<bool>, Query, description = "Import the valid rows even if other rows have errors"),
+    ),
+    request_body(content = CsvImportForm, content_type = "multipart/form-data", description = "CSV with a header row. Fields: name, email, role, department, phone, is_active, notes; relation column: applications. Several names in one relation column are separated by `;`."),
+    responses(
+        (status = 200, description = "Rows imported", body = CsvImportReport),
+        (status = 400, description = "Unreadable file or unknown columns"),
+        (status = 422, description = "Some rows have errors, nothing was imported", body = CsvImportReport),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn import_csv(
+    State(state): State<AppState>,
+    actor: Actor,
+    Query(params): Query<CsvImportParams>,
+    upload: CsvUpload,
+) -> Result<impl axum::response::IntoResponse> {
+    let report = csv_import::import(
+        &state.pool,
+        actor.as_deref(),
+        CsvEntity::Person,
+        &upload.data,
+        &upload.mapping,
+        params.partial,
+    )
+    .await?;
+
+    let status = if report.committed {
+        axum::http::StatusCode::OK
+    } else {
+        axum::http::StatusCode::UNPROCESSABLE_ENTITY
+    };
+    Ok((status, Json(report)))
+}
+
 #[utoipa::path(
     put,
     path = "/api/people/{id}",
     tag = "people",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
-        ("id" = String, Path, description = "Person ID")
+        ("id" = String, Path, description = "Person ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     request_body = UpdatePerson,
     responses(
-        (status = 200, description = "Person updated", body = Person),
+        (status = 200, description = "Person updated", body = Person, headers(("ETag" = String, description = "New version"))),
         (status = 404, description = "Person not found"),
         (status = 400, description = "Invalid input"),
+        (status = 403, description = "Only admins can assign an access role, or change the email or active state of someone with at least the caller's role"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update(
     State(state): State<AppState>,
+    Extension(identity): Extension<Identity>,
+    actor: Actor,
     Path(id): Path<String>,
-    Json(input): Json<UpdatePerson>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdatePerson>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = person::update(&state.pool, &id, input).await?;
-    Ok(Json(result))
+    input.version = if_match.0.or(input.version);
+    if input.access_role.is_some() {
+        ensure_role(&identity, Role::Admin)?;
+    }
+
+    // Whoever controls the email address of an account can log in with it
+    // (on first login), so only admins change that of an equal or higher role.
+    let target = person::get(&state.pool, &id).await?;
+    let changes_login = input
+        .email
+        .as_ref()
+        .is_some_and(|email| target.email.as_ref() != Some(email))
+        || input
+            .is_active
+            .is_some_and(|is_active| is_active != target.is_active);
+    if changes_login && target.access_role >= identity.role {
+        ensure_role(&identity, Role::Admin)?;
+        // Checked against this version
+        input.version = input.version.or(Some(target.version));
+    }
+
+    let result = person::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/people/{id}",
     tag = "people",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
-        ("id" = String, Path, description = "Person ID")
+        ("id" = String, Path, description = "Person ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     responses(
-        (status = 204, description = "Person deleted"),
+        (status = 204, description = "Person moved to the trash"),
         (status = 404, description = "Person not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn delete_one(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
+    if_match: IfMatch,
 ) -> Result<impl axum::response::IntoResponse> {
-    person::delete(&state.pool, &id).await?;
+    person::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/people/{id}/history",
+    tag = "people",
+    params(
+        ("id" = String, Path, description = "Person ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "person", &id, &params).await?;
+    Ok(Json(result))
+}
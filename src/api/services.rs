@@ -1,15 +1,24 @@
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, post, put},
 };
 use serde::Deserialize;
 use tracing::info;
 
+use crate::api::auth::require_role;
+use crate::api::{Actor, IfMatch, etag};
 use crate::models::{
-    CreateService, LinkInfra, PaginationParams, Service, ServiceWithRelations, UpdateService,
+    AuditEvent, CreateService, ImpactReport, Incident, IncidentFilters, LinkInfra,
+    PaginationParams, Revision, RevisionDiff, RevisionDiffParams, Role, Service,
+    ServiceWithRelations, UpdateService,
 };
 use crate::overview::Overview as _;
+use crate::service::audit;
+use crate::service::impact;
+use crate::service::incident;
+use crate::service::revision;
 use crate::service::service;
 use crate::{AppState, Result};
 
@@ -23,15 +32,34 @@ pub struct ServiceFilters {
 }
 
 pub fn routes() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list).post(create))
-        .route("/{id}", get(get_one).put(update).delete(delete_one))
-        .route("/{id}/overview.md", get(get_overview_md))
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history))
+        .route("/{id}/incidents", get(incidents))
+        .route("/{id}/impact", get(impact))
+        .route("/{id}/impact.md", get(impact_md))
+        .route("/{id}/revisions", get(revisions))
+        .route("/{id}/revisions/{revision}/diff", get(revision_diff))
+        .route("/{id}/overview.md", get(get_overview_md));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/{id}", put(update))
+        .route("/{id}/revisions/{revision}/rollback", post(rollback))
         .route("/{id}/sync-outline", post(sync_outline))
         .route(
             "/{id}/infra/{infra_id}",
             post(link_infra).delete(unlink_infra),
         )
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
 }
 
 #[utoipa::path(
@@ -78,7 +106,7 @@ async fn list(
         ("id" = String, Path, description = "Service ID")
     ),
     responses(
-        (status = 200, description = "Service found", body = ServiceWithRelations),
+        (status = 200, description = "Service found", body = ServiceWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
         (status = 404, description = "Service not found"),
         (status = 500, description = "Internal server error")
     )
@@ -88,7 +116,7 @@ async fn get_one(
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
     let result = service::get_with_relations(&state.pool, &id).await?;
-    Ok(Json(result))
+    Ok((etag(result.service.version), Json(result)))
 }
 
 #[utoipa::path(
@@ -117,6 +145,7 @@ async fn get_overview_md(
     post,
     path = "/api/services",
     tag = "services",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     request_body = CreateService,
     responses(
         (status = 201, description = "Service created", body = Service),
@@ -126,9 +155,10 @@ async fn get_overview_md(
 )]
 async fn create(
     State(state): State<AppState>,
+    actor: Actor,
     Json(input): Json<CreateService>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = service::create(&state.pool, input).await?;
+    let result = service::create(&state.pool, actor.as_deref(), input).await?;
     Ok((axum::http::StatusCode::CREATED, Json(result)))
 }
 
@@ -136,53 +166,119 @@ async fn create(
     put,
     path = "/api/services/{id}",
     tag = "services",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
-        ("id" = String, Path, description = "Service ID")
+        ("id" = String, Path, description = "Service ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     request_body = UpdateService,
     responses(
-        (status = 200, description = "Service updated", body = Service),
+        (status = 200, description = "Service updated", body = Service, headers(("ETag" = String, description = "New version"))),
         (status = 404, description = "Service not found"),
         (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
-    Json(input): Json<UpdateService>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateService>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = service::update(&state.pool, &id, input).await?;
-    Ok(Json(result))
+    input.version = if_match.0.or(input.version);
+    let result = service::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/services/{id}",
     tag = "services",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
-        ("id" = String, Path, description = "Service ID")
+        ("id" = String, Path, description = "Service ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     responses(
-        (status = 204, description = "Service deleted"),
+        (status = 204, description = "Service moved to the trash"),
         (status = 404, description = "Service not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn delete_one(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
+    if_match: IfMatch,
 ) -> Result<impl axum::response::IntoResponse> {
-    service::delete(&state.pool, &id).await?;
+    service::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/services/{id}/history",
+    tag = "services",
+    params(
+        ("id" = String, Path, description = "Service ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "service", &id, &params).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/services/{id}/incidents",
+    tag = "services",
+    params(
+        ("id" = String, Path, description = "Service ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Incidents of the healthchecks of the service, most recent first", body = inline(crate::models::PaginatedResponse<Incident>)),
+        (status = 404, description = "Service not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn incidents(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    service::get(&state.pool, &id).await?;
+    let filters = IncidentFilters {
+        page: params.page,
+        per_page: params.per_page,
+        service_id: Some(id),
+        ..Default::default()
+    };
+    let result = incident::list(&state.pool, &filters).await?;
+    Ok(Json(result))
+}
+
 // Relationship handlers
 
 #[utoipa::path(
     post,
     path = "/api/services/{id}/infra/{infra_id}",
     tag = "services",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Service ID"),
         ("infra_id" = String, Path, description = "Infrastructure ID")
@@ -196,10 +292,18 @@ async fn delete_one(
 )]
 async fn link_infra(
     State(state): State<AppState>,
+    actor: Actor,
     Path((service_id, infra_id)): Path<(String, String)>,
     Json(input): Json<LinkInfra>,
 ) -> Result<impl axum::response::IntoResponse> {
-    service::link_infra(&state.pool, &service_id, &infra_id, input.notes.as_deref()).await?;
+    service::link_infra(
+        &state.pool,
+        actor.as_deref(),
+        &service_id,
+        &infra_id,
+        input.notes.as_deref(),
+    )
+    .await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -207,6 +311,7 @@ async fn link_infra(
     delete,
     path = "/api/services/{id}/infra/{infra_id}",
     tag = "services",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Service ID"),
         ("infra_id" = String, Path, description = "Infrastructure ID")
@@ -219,9 +324,10 @@ async fn link_infra(
 )]
 async fn unlink_infra(
     State(state): State<AppState>,
+    actor: Actor,
     Path((service_id, infra_id)): Path<(String, String)>,
 ) -> Result<impl axum::response::IntoResponse> {
-    service::unlink_infra(&state.pool, &service_id, &infra_id).await?;
+    service::unlink_infra(&state.pool, actor.as_deref(), &service_id, &infra_id).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
 
@@ -229,6 +335,7 @@ async fn unlink_infra(
     post,
     path = "/api/services/{id}/sync-outline",
     tag = "services",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
         ("id" = String, Path, description = "Service ID")
     ),
@@ -266,3 +373,121 @@ async fn sync_outline(
 
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/services/{id}/revisions",
+    tag = "services",
+    params(
+        ("id" = String, Path, description = "Service ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Full snapshots after every create and update, newest first", body = inline(crate::models::PaginatedResponse<Revision>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revisions(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::list(&state.pool, "service", &id, &params).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/services/{id}/revisions/{revision}/diff",
+    tag = "services",
+    params(
+        ("id" = String, Path, description = "Service ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("against" = Option<i64>, Query, description = "Revision to compare with, defaults to the one before"),
+    ),
+    responses(
+        (status = 200, description = "Fields changed in the revision", body = RevisionDiff),
+        (status = 404, description = "Revision not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn revision_diff(
+    State(state): State<AppState>,
+    Path((id, rev)): Path<(String, i64)>,
+    Query(params): Query<RevisionDiffParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = revision::diff(&state.pool, "service", &id, rev, params.against).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/services/{id}/revisions/{revision}/rollback",
+    tag = "services",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("id" = String, Path, description = "Service ID"),
+        ("revision" = i64, Path, description = "Revision number"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
+    ),
+    responses(
+        (status = 200, description = "Service updated to the revision", body = Service, headers(("ETag" = String, description = "New version"))),
+        (status = 404, description = "Service or revision not found"),
+        (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match is outdated"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn rollback(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path((id, rev)): Path<(String, i64)>,
+    if_match: IfMatch,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = service::rollback(&state.pool, actor.as_deref(), &id, rev, if_match.0).await?;
+    Ok((etag(result.version), Json(result)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/services/{id}/impact",
+    tag = "services",
+    params(
+        ("id" = String, Path, description = "Service ID")
+    ),
+    responses(
+        (status = 200, description = "Everything affected when this service goes down", body = ImpactReport),
+        (status = 404, description = "Service not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn impact(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = impact::analyze(&state.pool, "service", &id).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/services/{id}/impact.md",
+    tag = "services",
+    params(
+        ("id" = String, Path, description = "Service ID")
+    ),
+    responses(
+        (status = 200, description = "Impact report as markdown", content_type = "text/markdown", body = String),
+        (status = 404, description = "Service not found"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn impact_md(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl axum::response::IntoResponse> {
+    let report = impact::analyze(&state.pool, "service", &id).await?;
+    let md = impact::to_md(&report);
+    Ok(([(axum::http::header::CONTENT_TYPE, "text/markdown")], md))
+}
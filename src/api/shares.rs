@@ -1,11 +1,18 @@
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::get,
+    middleware,
+    routing::{delete, get, post, put},
 };
 use serde::Deserialize;
 
-use crate::models::{CreateNetworkShare, PaginationParams, UpdateNetworkShare, NetworkShareWithRelations, NetworkShare};
+use crate::api::auth::require_role;
+use crate::api::{Actor, IfMatch, etag};
+use crate::models::{
+    AuditEvent, CreateNetworkShare, NetworkShare, NetworkShareWithRelations, PaginationParams,
+    Role, UpdateNetworkShare,
+};
+use crate::service::audit;
 use crate::service::network_share;
 use crate::{AppState, Result};
 
@@ -19,9 +26,22 @@ pub struct ShareFilters {
 }
 
 pub fn routes() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list).post(create))
-        .route("/{id}", get(get_one).put(update).delete(delete_one))
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/{id}", put(update))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
 }
 
 #[utoipa::path(
@@ -67,7 +87,7 @@ async fn list(
         ("id" = String, Path, description = "Network share ID")
     ),
     responses(
-        (status = 200, description = "Network share found", body = NetworkShareWithRelations),
+        (status = 200, description = "Network share found", body = NetworkShareWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
         (status = 404, description = "Network share not found"),
         (status = 500, description = "Internal server error")
     )
@@ -77,13 +97,14 @@ async fn get_one(
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
     let result = network_share::get_with_relations(&state.pool, &id).await?;
-    Ok(Json(result))
+    Ok((etag(result.network_share.version), Json(result)))
 }
 
 #[utoipa::path(
     post,
     path = "/api/shares",
     tag = "shares",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     request_body = CreateNetworkShare,
     responses(
         (status = 201, description = "Network share created", body = NetworkShare),
@@ -93,9 +114,10 @@ async fn get_one(
 )]
 async fn create(
     State(state): State<AppState>,
+    actor: Actor,
     Json(input): Json<CreateNetworkShare>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = network_share::create(&state.pool, input).await?;
+    let result = network_share::create(&state.pool, actor.as_deref(), input).await?;
     Ok((axum::http::StatusCode::CREATED, Json(result)))
 }
 
@@ -103,43 +125,77 @@ async fn create(
     put,
     path = "/api/shares/{id}",
     tag = "shares",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
-        ("id" = String, Path, description = "Network share ID")
+        ("id" = String, Path, description = "Network share ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     request_body = UpdateNetworkShare,
     responses(
-        (status = 200, description = "Network share updated", body = NetworkShare),
+        (status = 200, description = "Network share updated", body = NetworkShare, headers(("ETag" = String, description = "New version"))),
         (status = 404, description = "Network share not found"),
         (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
-    Json(input): Json<UpdateNetworkShare>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateNetworkShare>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = network_share::update(&state.pool, &id, input).await?;
-    Ok(Json(result))
+    input.version = if_match.0.or(input.version);
+    let result = network_share::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/shares/{id}",
     tag = "shares",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
-        ("id" = String, Path, description = "Network share ID")
+        ("id" = String, Path, description = "Network share ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     responses(
-        (status = 204, description = "Network share deleted"),
+        (status = 204, description = "Network share moved to the trash"),
         (status = 404, description = "Network share not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn delete_one(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
+    if_match: IfMatch,
 ) -> Result<impl axum::response::IntoResponse> {
-    network_share::delete(&state.pool, &id).await?;
+    network_share::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/shares/{id}/history",
+    tag = "shares",
+    params(
+        ("id" = String, Path, description = "Network share ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "network_share", &id, &params).await?;
+    Ok(Json(result))
+}
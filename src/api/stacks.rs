@@ -1,17 +1,36 @@
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::get,
+    middleware,
+    routing::{delete, get, post, put},
 };
 
-use crate::models::{CreateStack, PaginationParams, UpdateStack, StackWithRelations, Stack};
+use crate::api::auth::require_role;
+use crate::api::{Actor, IfMatch, etag};
+use crate::models::{
+    AuditEvent, CreateStack, PaginationParams, Role, Stack, StackWithRelations, UpdateStack,
+};
+use crate::service::audit;
 use crate::service::stack;
 use crate::{AppState, Result};
 
 pub fn routes() -> Router<AppState> {
-    Router::new()
-        .route("/", get(list).post(create))
-        .route("/{id}", get(get_one).put(update).delete(delete_one))
+    // Any authenticated user
+    let viewer = Router::new()
+        .route("/", get(list))
+        .route("/{id}", get(get_one))
+        .route("/{id}/history", get(history));
+
+    let editor = Router::new()
+        .route("/", post(create))
+        .route("/{id}", put(update))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{id}", delete(delete_one))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
 }
 
 #[utoipa::path(
@@ -44,7 +63,7 @@ async fn list(
         ("id" = String, Path, description = "Stack ID")
     ),
     responses(
-        (status = 200, description = "Stack found", body = StackWithRelations),
+        (status = 200, description = "Stack found", body = StackWithRelations, headers(("ETag" = String, description = "Current version, for If-Match"))),
         (status = 404, description = "Stack not found"),
         (status = 500, description = "Internal server error")
     )
@@ -54,13 +73,14 @@ async fn get_one(
     Path(id): Path<String>,
 ) -> Result<impl axum::response::IntoResponse> {
     let result = stack::get_with_relations(&state.pool, &id).await?;
-    Ok(Json(result))
+    Ok((etag(result.stack.version), Json(result)))
 }
 
 #[utoipa::path(
     post,
     path = "/api/stacks",
     tag = "stacks",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     request_body = CreateStack,
     responses(
         (status = 201, description = "Stack created", body = Stack),
@@ -70,9 +90,10 @@ async fn get_one(
 )]
 async fn create(
     State(state): State<AppState>,
+    actor: Actor,
     Json(input): Json<CreateStack>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = stack::create(&state.pool, input).await?;
+    let result = stack::create(&state.pool, actor.as_deref(), input).await?;
     Ok((axum::http::StatusCode::CREATED, Json(result)))
 }
 
@@ -80,43 +101,77 @@ async fn create(
     put,
     path = "/api/stacks/{id}",
     tag = "stacks",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
     params(
-        ("id" = String, Path, description = "Stack ID")
+        ("id" = String, Path, description = "Stack ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     request_body = UpdateStack,
     responses(
-        (status = 200, description = "Stack updated", body = Stack),
+        (status = 200, description = "Stack updated", body = Stack, headers(("ETag" = String, description = "New version"))),
         (status = 404, description = "Stack not found"),
         (status = 400, description = "Invalid input"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn update(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
-    Json(input): Json<UpdateStack>,
+    if_match: IfMatch,
+    Json(mut input): Json<UpdateStack>,
 ) -> Result<impl axum::response::IntoResponse> {
-    let result = stack::update(&state.pool, &id, input).await?;
-    Ok(Json(result))
+    input.version = if_match.0.or(input.version);
+    let result = stack::update(&state.pool, actor.as_deref(), &id, input).await?;
+    Ok((etag(result.version), Json(result)))
 }
 
 #[utoipa::path(
     delete,
     path = "/api/stacks/{id}",
     tag = "stacks",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
     params(
-        ("id" = String, Path, description = "Stack ID")
+        ("id" = String, Path, description = "Stack ID"),
+        ("If-Match" = Option<String>, Header, description = "Only if this is still the current version, as in the ETag"),
     ),
     responses(
-        (status = 204, description = "Stack deleted"),
+        (status = 204, description = "Stack moved to the trash"),
         (status = 404, description = "Stack not found"),
+        (status = 412, description = "Changed meanwhile, the If-Match or version is outdated"),
         (status = 500, description = "Internal server error")
     )
 )]
 async fn delete_one(
     State(state): State<AppState>,
+    actor: Actor,
     Path(id): Path<String>,
+    if_match: IfMatch,
 ) -> Result<impl axum::response::IntoResponse> {
-    stack::delete(&state.pool, &id).await?;
+    stack::delete(&state.pool, actor.as_deref(), &id, if_match.0).await?;
     Ok(axum::http::StatusCode::NO_CONTENT)
 }
+
+#[utoipa::path(
+    get,
+    path = "/api/stacks/{id}/history",
+    tag = "stacks",
+    params(
+        ("id" = String, Path, description = "Stack ID"),
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+    ),
+    responses(
+        (status = 200, description = "Audit history, newest first", body = inline(crate::models::PaginatedResponse<AuditEvent>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<PaginationParams>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = audit::history(&state.pool, "stack", &id, &params).await?;
+    Ok(Json(result))
+}
@@ -0,0 +1,354 @@
+//! Access control of the routes, through a running server.
+
+use reqwest::StatusCode;
+use serde_json::{Value, json};
+use url::Url;
+
+use crate::OidcConfig;
+use crate::models::{OidcLogin, Role};
+use crate::service::auth;
+use crate::testing;
+
+fn client() -> reqwest::Client {
+    reqwest::Client::new()
+}
+
+#[tokio::test]
+async fn viewer_cannot_edit_and_editor_cannot_delete() {
+    let state = testing::state().await;
+    let (_, viewer) = testing::person_with_token(&state.pool, "Viewer", Role::Viewer).await;
+    let (_, editor) = testing::person_with_token(&state.pool, "Editor", Role::Editor).await;
+    let url = testing::serve(state).await;
+
+    let list = client()
+        .get(format!("{url}/api/applications"))
+        .bearer_auth(&viewer)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(list.status(), StatusCode::OK);
+
+    let create = |token: &str| {
+        client()
+            .post(format!("{url}/api/applications"))
+            .bearer_auth(token)
+            .json(&json!({ "name": "app" }))
+            .send()
+    };
+    assert_eq!(
+        create(&viewer).await.unwrap().status(),
+        StatusCode::FORBIDDEN
+    );
+    let created = create(&editor).await.unwrap();
+    assert_eq!(created.status(), StatusCode::CREATED);
+    let id = created.json::<Value>().await.unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let delete = client()
+        .delete(format!("{url}/api/applications/{id}"))
+        .bearer_auth(&editor)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(delete.status(), StatusCode::FORBIDDEN);
+    let delete = client()
+        .delete(format!("{url}/api/applications/{id}"))
+        .bearer_auth(testing::ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(delete.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn editor_cannot_raise_own_access_role() {
+    let state = testing::state().await;
+    let pool = state.pool.clone();
+    let (id, editor) = testing::person_with_token(&pool, "Editor", Role::Editor).await;
+    let url = testing::serve(state).await;
+
+    let update = client()
+        .put(format!("{url}/api/people/{id}"))
+        .bearer_auth(&editor)
+        .json(&json!({ "access_role": "admin" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(update.status(), StatusCode::FORBIDDEN);
+
+    let (role,) = sqlx::query_as::<_, (Role,)>("SELECT access_role FROM person WHERE id = ?1")
+        .bind(&id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(role, Role::Editor);
+
+    // Other fields are still theirs to edit
+    let update = client()
+        .put(format!("{url}/api/people/{id}"))
+        .bearer_auth(&editor)
+        .json(&json!({ "phone": "123" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(update.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn editor_cannot_take_over_an_admin_account() {
+    let state = testing::state().await;
+    let pool = state.pool.clone();
+    let (admin, _) = testing::person_with_token(&pool, "Admin", Role::Admin).await;
+    let (viewer, _) = testing::person_with_token(&pool, "Viewer", Role::Viewer).await;
+    let (_, editor) = testing::person_with_token(&pool, "Editor", Role::Editor).await;
+    let url = testing::serve(state).await;
+
+    let update = |id: &str, body: Value| {
+        client()
+            .put(format!("{url}/api/people/{id}"))
+            .bearer_auth(&editor)
+            .json(&body)
+            .send()
+    };
+    let email = json!({ "email": "editor@example.com" });
+    assert_eq!(
+        update(&admin, email.clone()).await.unwrap().status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        update(&admin, json!({ "is_active": false }))
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::FORBIDDEN
+    );
+    let (email_of_admin,) =
+        sqlx::query_as::<_, (Option<String>,)>("SELECT email FROM person WHERE id = ?1")
+            .bind(&admin)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(email_of_admin, None);
+
+    // Other fields, and the email of a lower role, are still theirs to edit
+    assert_eq!(
+        update(&admin, json!({ "phone": "123" }))
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        update(&viewer, email).await.unwrap().status(),
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn if_match_needs_a_strong_etag() {
+    let state = testing::state().await;
+    let url = testing::serve(state).await;
+
+    let created = client()
+        .post(format!("{url}/api/applications"))
+        .bearer_auth(testing::ADMIN_TOKEN)
+        .json(&json!({ "name": "app" }))
+        .send()
+        .await
+        .unwrap();
+    let id = created.json::<Value>().await.unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let fetched = client()
+        .get(format!("{url}/api/applications/{id}"))
+        .bearer_auth(testing::ADMIN_TOKEN)
+        .send()
+        .await
+        .unwrap();
+    let etag = fetched.headers()[reqwest::header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let update = |if_match: String| {
+        client()
+            .put(format!("{url}/api/applications/{id}"))
+            .bearer_auth(testing::ADMIN_TOKEN)
+            .header(reqwest::header::IF_MATCH, if_match)
+            .json(&json!({ "description": "Website" }))
+            .send()
+    };
+    assert_eq!(
+        update(format!("W/{etag}")).await.unwrap().status(),
+        StatusCode::PRECONDITION_FAILED
+    );
+    assert_eq!(update(etag).await.unwrap().status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn oidc_callback_needs_the_browser_that_started_the_login() {
+    let mut state = testing::state().await;
+    state.config.oidc = Some(OidcConfig {
+        issuer_url: Url::parse("http://127.0.0.1:1").unwrap(),
+        client_id: "auto".to_string(),
+        client_secret: None,
+        redirect_url: Url::parse("http://localhost/api/auth/callback").unwrap(),
+        scopes: "openid".to_string(),
+    });
+    let pool = state.pool.clone();
+    let login = OidcLogin {
+        state: "the-state".to_string(),
+        nonce: "nonce".to_string(),
+        code_verifier: "verifier".to_string(),
+        redirect_to: "/".to_string(),
+    };
+    auth::create_oidc_login(&pool, &login).await.unwrap();
+    let url = testing::serve(state).await;
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    for cookie in [None, Some("auto_oidc_state=other-state")] {
+        let mut request = client.get(format!(
+            "{url}/api/auth/callback?code=the-code&state=the-state"
+        ));
+        if let Some(cookie) = cookie {
+            request = request.header(reqwest::header::COOKIE, cookie);
+        }
+        let response = request.send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::SEE_OTHER);
+        let location = response.headers()[reqwest::header::LOCATION]
+            .to_str()
+            .unwrap();
+        assert!(location.starts_with("/login?error="));
+    }
+
+    let pending = || async {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM oidc_login")
+            .fetch_one(&pool)
+            .await
+            .unwrap()
+    };
+    assert_eq!(pending().await, 1);
+
+    // The browser that started it gets on to the provider, which is down here
+    client
+        .get(format!(
+            "{url}/api/auth/callback?code=the-code&state=the-state"
+        ))
+        .header(reqwest::header::COOKIE, "auto_oidc_state=the-state")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(pending().await, 0);
+}
+
+#[tokio::test]
+async fn viewer_cannot_read_notification_channel_urls_from_the_audit_log() {
+    let state = testing::state().await;
+    let (_, viewer) = testing::person_with_token(&state.pool, "Viewer", Role::Viewer).await;
+    let url = testing::serve(state).await;
+
+    let created = client()
+        .post(format!("{url}/api/notifications/channels"))
+        .bearer_auth(testing::ADMIN_TOKEN)
+        .json(&json!({
+            "name": "Ops",
+            "kind": "slack",
+            "url": "https://hooks.slack.com/services/SECRET",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(created.status(), StatusCode::CREATED);
+
+    let audit = client()
+        .get(format!("{url}/api/audit?entity_type=notification_channel"))
+        .bearer_auth(&viewer)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(audit.status(), StatusCode::OK);
+    let audit = audit.text().await.unwrap();
+    assert!(audit.contains("https://hooks.slack.com/[redacted]"));
+    assert!(!audit.contains("SECRET"));
+}
+
+#[tokio::test]
+async fn names_in_the_trash_can_be_reused_but_block_the_restore() {
+    let state = testing::state().await;
+    let url = testing::serve(state).await;
+    let send = |request: reqwest::RequestBuilder| async move {
+        let response = request
+            .bearer_auth(testing::ADMIN_TOKEN)
+            .send()
+            .await
+            .unwrap();
+        let status = response.status();
+        (
+            status,
+            response.json::<Value>().await.unwrap_or(Value::Null),
+        )
+    };
+    let create = |fqdn: &'static str| {
+        let url = url.clone();
+        async move {
+            let (status, application) = send(
+                client()
+                    .post(format!("{url}/api/applications"))
+                    .json(&json!({"name": "web"})),
+            )
+            .await;
+            assert_eq!(status, StatusCode::CREATED);
+            let id = application["id"].as_str().unwrap().to_string();
+            let (status, _) = send(
+                client()
+                    .post(format!("{url}/api/domains"))
+                    .json(&json!({"fqdn": fqdn, "target_application_id": id})),
+            )
+            .await;
+            assert_eq!(status, StatusCode::CREATED);
+            id
+        }
+    };
+
+    let old = create("web.example.org").await;
+    let (status, _) = send(client().delete(format!("{url}/api/applications/{old}"))).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    // The name and the fqdn are free again while it's in the trash
+    let new = create("web.example.org").await;
+
+    let restore = || send(client().post(format!("{url}/api/trash/application/{old}/restore")));
+    let (status, error) = restore().await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        error["message"],
+        "Another application with the name and environment of 'web' exists, rename or delete it first"
+    );
+
+    let (status, _) = send(
+        client()
+            .put(format!("{url}/api/applications/{new}"))
+            .json(&json!({"name": "www"})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    // The domain deleted along with it is taken too
+    let (status, error) = restore().await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(
+        error["message"]
+            .as_str()
+            .unwrap()
+            .contains("'web.example.org'")
+    );
+
+    let (status, _) = send(client().delete(format!("{url}/api/applications/{new}"))).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = restore().await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,74 @@
+use axum::{
+    Json, Router,
+    extract::{DefaultBodyLimit, Query, State},
+    http::header,
+    middleware,
+    routing::{get, post},
+};
+
+use crate::api::Actor;
+use crate::api::auth::require_role;
+use crate::models::{ExportDocument, ImportParams, ImportReport, Role};
+use crate::service::transfer;
+use crate::{AppState, Result};
+
+/// Largest export document accepted by the import, well above axum's 2 MB default.
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/export", get(export))
+        .route(
+            "/import",
+            post(import).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/export",
+    tag = "export",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    responses(
+        (status = 200, description = "Every entity and junction table as JSON", body = ExportDocument),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn export(State(state): State<AppState>) -> Result<impl axum::response::IntoResponse> {
+    let document = transfer::export(&state.pool).await?;
+    let date = document
+        .exported_at
+        .as_deref()
+        .and_then(|at| at.split(' ').next())
+        .unwrap_or_default();
+    let disposition = format!("attachment; filename=\"auto-export-{date}.json\"");
+
+    Ok(([(header::CONTENT_DISPOSITION, disposition)], Json(document)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/import",
+    tag = "export",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    params(
+        ("dry_run" = Option<bool>, Query, description = "Only report what would change"),
+    ),
+    request_body = ExportDocument,
+    responses(
+        (status = 200, description = "Rows created, updated and unchanged per table", body = ImportReport),
+        (status = 400, description = "Invalid document or a row violates a constraint"),
+        (status = 409, description = "A row conflicts with an existing row with another ID"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn import(
+    State(state): State<AppState>,
+    actor: Actor,
+    Query(params): Query<ImportParams>,
+    Json(document): Json<ExportDocument>,
+) -> Result<impl axum::response::IntoResponse> {
+    let report = transfer::import(&state.pool, actor.as_deref(), document, params.dry_run).await?;
+    Ok(Json(report))
+}
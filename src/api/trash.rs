@@ -0,0 +1,103 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    middleware,
+    routing::{delete, get, post},
+};
+
+use crate::api::Actor;
+use crate::api::auth::require_role;
+use crate::models::{Role, TrashFilters, TrashItem};
+use crate::service::trash;
+use crate::{AppState, Result};
+
+pub fn routes() -> Router<AppState> {
+    // Any authenticated user
+    let viewer = Router::new().route("/", get(list));
+
+    let editor = Router::new()
+        .route("/{entity_type}/{id}/restore", post(restore))
+        .route_layer(middleware::from_fn_with_state(Role::Editor, require_role));
+
+    let admin = Router::new()
+        .route("/{entity_type}/{id}", delete(purge))
+        .route_layer(middleware::from_fn_with_state(Role::Admin, require_role));
+
+    viewer.merge(editor).merge(admin)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/trash",
+    tag = "trash",
+    params(
+        ("page" = Option<u32>, Query, description = "Page number"),
+        ("per_page" = Option<u32>, Query, description = "Items per page (max 100)"),
+        ("entity_type" = Option<String>, Query, description = "Only items of this entity type"),
+    ),
+    responses(
+        (status = 200, description = "Deleted items, most recently deleted first", body = inline(crate::models::PaginatedResponse<TrashItem>)),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn list(
+    State(state): State<AppState>,
+    Query(filters): Query<TrashFilters>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = trash::list(&state.pool, &filters).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/trash/{entity_type}/{id}/restore",
+    tag = "trash",
+    security(("bearer_token" = ["editor"]), ("session_cookie" = ["editor"])),
+    params(
+        ("entity_type" = String, Path, description = "Entity type, e.g. application"),
+        ("id" = String, Path, description = "Entity ID"),
+    ),
+    responses(
+        (status = 200, description = "Item restored together with its links and dependents", body = TrashItem),
+        (status = 404, description = "Item not in the trash"),
+        (status = 409, description = "Item was deleted along with another item, a parent is still in the trash, or another item took its name"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn restore(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path((entity_type, id)): Path<(String, String)>,
+) -> Result<impl axum::response::IntoResponse> {
+    let result = trash::restore(&state.pool, actor.as_deref(), &entity_type, &id).await?;
+    Ok(Json(result))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/trash/{entity_type}/{id}",
+    tag = "trash",
+    security(("bearer_token" = ["admin"]), ("session_cookie" = ["admin"])),
+    params(
+        ("entity_type" = String, Path, description = "Entity type, e.g. application"),
+        ("id" = String, Path, description = "Entity ID"),
+    ),
+    responses(
+        (status = 204, description = "Item and everything deleted along with it permanently deleted"),
+        (status = 404, description = "Item not in the trash"),
+        (status = 409, description = "Item was deleted along with another item"),
+        (status = 500, description = "Internal server error")
+    )
+)]
+async fn purge(
+    State(state): State<AppState>,
+    actor: Actor,
+    Path((entity_type, id)): Path<(String, String)>,
+) -> Result<impl axum::response::IntoResponse> {
+    trash::purge(&state.pool, actor.as_deref(), &entity_type, &id).await?;
+
+    // Delete the Kuma monitors of purged healthchecks right away
+    let _ = state.kuma_sync_tx.send(());
+
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
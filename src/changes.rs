@@ -0,0 +1,179 @@
+/*!
+ * Live feed of changes to entities, for `/api/events`.
+ *
+ * Every mutating service function records an audit event, so the feed tails
+ * the audit log and broadcasts what was added since the last look. That
+ * includes changes made by the Kuma sync worker, imports and other instances
+ * sharing the database.
+ */
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use sqlx::SqlitePool;
+use tokio::sync::broadcast;
+use tracing::error;
+
+use crate::models::{ChangeEvent, ChangeStreamFilters};
+use crate::{Result, service};
+
+/// Broadcast channel for the change feed.
+pub type ChangeTx = broadcast::Sender<ChangeEvent>;
+
+/// Most changes read from the audit log at once.
+pub const CHANGE_BATCH: i64 = 500;
+
+/// Broadcasts new audit events every second.
+pub fn spawn_change_feed(pool: SqlitePool, tx: ChangeTx) {
+    tokio::spawn(async move {
+        // Only changes made from now on, clients resume older ones themselves
+        let mut last_id = loop {
+            match service::audit::last_id(&pool).await {
+                Ok(id) => break id,
+                Err(e) => {
+                    error!("Failed to read the latest audit event: {e}");
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        };
+
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            let changes = match service::audit::changes_since(&pool, last_id, CHANGE_BATCH).await {
+                Ok(changes) => changes,
+                Err(e) => {
+                    error!("Failed to read changes: {e}");
+                    continue;
+                }
+            };
+            for change in changes {
+                last_id = change.id;
+                // No receivers is fine
+                let _ = tx.send(change);
+            }
+        }
+    });
+}
+
+/// Changes after audit event `after_id`, e.g. those a client missed, or
+/// `None` when there are more than [`CHANGE_BATCH`] of them or the id is
+/// unknown. The client should reload what it shows then.
+pub async fn missed(pool: &SqlitePool, after_id: i64) -> Result<Option<Vec<ChangeEvent>>> {
+    if after_id > service::audit::last_id(pool).await? {
+        return Ok(None);
+    }
+    let changes = service::audit::changes_since(pool, after_id, CHANGE_BATCH + 1).await?;
+    if changes.len() as i64 > CHANGE_BATCH {
+        return Ok(None);
+    }
+    Ok(Some(changes))
+}
+
+/// Changes a client of the feed asked for
+#[derive(Debug, Default)]
+pub struct ChangeFilter {
+    entity_types: Option<HashSet<String>>,
+    entity_id: Option<String>,
+}
+
+impl From<ChangeStreamFilters> for ChangeFilter {
+    fn from(filters: ChangeStreamFilters) -> Self {
+        let entity_types = filters.entity_types.map(|types| {
+            types
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect()
+        });
+        Self {
+            entity_types,
+            entity_id: filters.entity_id,
+        }
+    }
+}
+
+impl ChangeFilter {
+    /// Links match on either side, so linking a share to an application
+    /// shows up for both.
+    pub fn matches(&self, change: &ChangeEvent) -> bool {
+        let sides = [
+            Some((change.entity_type.as_str(), change.entity_id.as_str())),
+            change
+                .related_type
+                .as_deref()
+                .zip(change.related_id.as_deref()),
+        ];
+        sides.into_iter().flatten().any(|(entity_type, entity_id)| {
+            self.entity_types
+                .as_ref()
+                .is_none_or(|types| types.contains(entity_type))
+                && self.entity_id.as_deref().is_none_or(|id| id == entity_id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::Json;
+
+    use super::*;
+
+    fn link(entity: (&str, &str), related: (&str, &str)) -> ChangeEvent {
+        ChangeEvent {
+            id: 1,
+            actor: Some("alice".into()),
+            entity_type: entity.0.into(),
+            entity_id: entity.1.into(),
+            entity_name: None,
+            action: "link".into(),
+            related_type: Some(related.0.into()),
+            related_id: Some(related.1.into()),
+            fields: Json(Vec::new()),
+            created_at: "2026-03-01 10:00:00".into(),
+        }
+    }
+
+    fn filter(entity_types: Option<&str>, entity_id: Option<&str>) -> ChangeFilter {
+        ChangeFilter::from(ChangeStreamFilters {
+            entity_types: entity_types.map(str::to_string),
+            entity_id: entity_id.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn filter_matches_either_side_of_a_link() {
+        let change = link(("application", "a1"), ("network_share", "s1"));
+
+        assert!(filter(None, None).matches(&change));
+        assert!(filter(Some("service, application"), None).matches(&change));
+        assert!(filter(Some("network_share"), Some("s1")).matches(&change));
+        assert!(!filter(Some("service,infra"), None).matches(&change));
+        // Type and id have to match the same side
+        assert!(!filter(Some("application"), Some("s1")).matches(&change));
+    }
+
+    #[tokio::test]
+    async fn missed_gives_up_on_old_and_unknown_ids() {
+        let pool = crate::testing::pool().await;
+        assert_eq!(missed(&pool, 0).await.unwrap().map(|c| c.len()), Some(0));
+        assert!(missed(&pool, 1).await.unwrap().is_none());
+
+        sqlx::query(
+            r#"
+            WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i <= ?1)
+            INSERT INTO audit_event (entity_type, entity_id, action)
+            SELECT 'application', 'a' || i, 'create' FROM n
+            "#,
+        )
+        .bind(CHANGE_BATCH)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert!(missed(&pool, 0).await.unwrap().is_none());
+        let changes = missed(&pool, 1).await.unwrap().unwrap();
+        assert_eq!(changes.len() as i64, CHANGE_BATCH);
+    }
+}
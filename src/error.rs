@@ -12,6 +12,9 @@ pub enum Error {
     AxumError(#[from] axum::Error),
     #[error("KumaError: {0}")]
     KumaError(String),
+    /// The connection to Kuma failed, unlike a request Kuma turned down
+    #[error("KumaError: {0}")]
+    KumaConnectionError(String),
     #[error("IOError: {0}")]
     IOError(#[from] std::io::Error),
     #[error("SqlxError: {0}")]
@@ -24,6 +27,13 @@ pub enum Error {
     ValidationError(String),
     #[error("{0}")]
     Conflict(String),
+    /// The entity changed since the version the client based its change on
+    #[error("{0}")]
+    PreconditionFailed(String),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
     #[error("{0}")]
     InternalError(String),
     #[error("Kuma convert error")]
@@ -45,6 +55,13 @@ impl IntoResponse for Error {
                 (StatusCode::BAD_REQUEST, "validation_error", msg.clone())
             }
             Error::Conflict(msg) => (StatusCode::CONFLICT, "conflict", msg.clone()),
+            Error::PreconditionFailed(msg) => (
+                StatusCode::PRECONDITION_FAILED,
+                "precondition_failed",
+                msg.clone(),
+            ),
+            Error::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, "unauthorized", msg.clone()),
+            Error::Forbidden(msg) => (StatusCode::FORBIDDEN, "forbidden", msg.clone()),
             Error::InternalError(msg) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "internal_error",
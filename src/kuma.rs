@@ -2,16 +2,19 @@
  * Custom Uptime Kuma client using Socket.IO.
  *
  * Replaces the `kuma-client` crate which panicked on float deserialization.
- * Only implements the subset needed: connect, login, edit/add monitors.
+ * Only implements the subset needed: connect, login, list, edit/add,
+ * pause/resume and delete monitors, their tags and maintenance windows.
  *
- * The database of Auto is the single source of truth — this module
- * pushes healthcheck state to Kuma.
+ * The database of Auto is the single source of truth — the sync worker in
+ * this module pushes healthcheck state to Kuma from a queue, and compares the monitors in Kuma with
+ * it to report drift or import monitors Auto doesn't know about.
  *
  * Also contains the persistent Kuma poller that reads heartbeat data
- * from Kuma and broadcasts it via SSE to connected clients.
+ * from Kuma, stores it and broadcasts it via SSE to connected clients.
  */
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -22,27 +25,105 @@ use rust_socketio::{
     asynchronous::{Client as SocketClient, ClientBuilder},
 };
 use serde_json::{Value, json};
+use sqlx::SqlitePool;
 use tokio::sync::{RwLock, broadcast, mpsc, watch};
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
     AppState, Error, Result,
     models::{
-        HEARTBEAT_WINDOW_SECS, HealthcheckWithRelations, HeartbeatEntry, MonitorUptime,
-        UpdateHealthcheck, UptimeEvent,
+        CreateHealthcheck, FieldChange, HEARTBEAT_WINDOW_SECS, Healthcheck,
+        HealthcheckWithRelations, HeartbeatEntry, KumaDiff, KumaDrift, KumaImport,
+        KumaImportResult, KumaImportSkipped, KumaMissingMonitor, KumaOrphan, KumaSyncItem,
+        Maintenance, MonitorSummary, MonitorUptime, UptimeEvent, UptimeRetention,
     },
-    service,
+    service::{self, kuma_sync::KumaTarget},
 };
 
 // ── Public type aliases ────────────────────────────────────────────
 
-pub type UptimeState = Arc<RwLock<HashMap<i32, MonitorUptime>>>;
-pub type UptimeTx = broadcast::Sender<UptimeEvent>;
+pub type UptimeState = Arc<RwLock<HashMap<String, MonitorUptime>>>;
 
-// ── KumaClient (unchanged) ────────────────────────────────────────
+/// Uptime events that are kept to replay to clients resuming a stream.
+const UPTIME_REPLAY_EVENTS: usize = 512;
+
+/// Broadcasts uptime events to SSE clients, numbered so that a client that
+/// reconnects with `Last-Event-ID` gets the events it missed from the most
+/// recent ones.
+///
+/// Numbers start at the startup time in milliseconds, so ids from before a
+/// restart are too old to resume from.
+#[derive(Clone)]
+pub struct UptimeTx {
+    tx: broadcast::Sender<(u64, UptimeEvent)>,
+    log: Arc<std::sync::Mutex<UptimeLog>>,
+}
+
+struct UptimeLog {
+    next_id: u64,
+    recent: VecDeque<(u64, UptimeEvent)>,
+}
+
+impl UptimeTx {
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity);
+        let log = UptimeLog {
+            next_id: Utc::now().timestamp_millis() as u64,
+            recent: VecDeque::with_capacity(UPTIME_REPLAY_EVENTS),
+        };
+        Self {
+            tx,
+            log: Arc::new(std::sync::Mutex::new(log)),
+        }
+    }
+
+    /// Numbers and broadcasts an event, returns its id.
+    pub fn send(&self, event: UptimeEvent) -> u64 {
+        let mut log = self.log.lock().unwrap();
+        let id = log.next_id;
+        log.next_id += 1;
+        if log.recent.len() == UPTIME_REPLAY_EVENTS {
+            log.recent.pop_front();
+        }
+        log.recent.push_back((id, event.clone()));
+        // Sent while holding the lock, so events go out in order
+        let _ = self.tx.send((id, event));
+        id
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<(u64, UptimeEvent)> {
+        self.tx.subscribe()
+    }
+
+    /// Id of the latest event, or the one before the first.
+    pub fn last_id(&self) -> u64 {
+        self.log.lock().unwrap().next_id - 1
+    }
+
+    /// Events after `id`, or `None` when some of them were dropped already
+    /// or `id` is unknown.
+    pub fn since(&self, id: u64) -> Option<Vec<(u64, UptimeEvent)>> {
+        let log = self.log.lock().unwrap();
+        let oldest = log.recent.front().map_or(log.next_id, |(i, _)| *i);
+        if id >= log.next_id || id + 1 < oldest {
+            return None;
+        }
+        Some(
+            log.recent
+                .iter()
+                .filter(|(i, _)| *i > id)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+// ── KumaClient ─────────────────────────────────────────────────────
 
 struct KumaClient {
     socket: SocketClient,
+    /// Latest `monitorList` pushed by Kuma, it sends one after login
+    monitor_list: watch::Receiver<Option<Value>>,
 }
 
 impl KumaClient {
@@ -56,6 +137,7 @@ impl KumaClient {
     async fn connect(url: &url::Url, username: &str, password: &str) -> Result<Self> {
         let ready = Arc::new(tokio::sync::Notify::new());
         let ready_signal = ready.clone();
+        let (monitor_list_tx, monitor_list) = watch::channel(None);
 
         let socket = ClientBuilder::new(url.as_str())
             .on_any(move |event, _payload, _client| {
@@ -66,31 +148,49 @@ impl KumaClient {
                 }
                 .boxed()
             })
+            .on("monitorList", move |payload: Payload, _client| {
+                // Payload shape: `[{ "1": {id, name, type, ...}, "2": {...} }]`
+                if let Payload::Text(values) = payload {
+                    let _ = monitor_list_tx.send(values.into_iter().next());
+                }
+                async {}.boxed()
+            })
             .connect()
             .await
-            .map_err(|e| Error::KumaError(format!("Connection failed: {e}")))?;
+            .map_err(|e| Error::KumaConnectionError(format!("Connection failed: {e}")))?;
 
         // Wait until the server pushes its first event (usually "info"),
         // which confirms the Socket.IO connection is fully established.
         tokio::time::timeout(Duration::from_secs(10), ready.notified())
             .await
-            .map_err(|_| Error::KumaError("Timed out waiting for Kuma to be ready".into()))?;
+            .map_err(|_| {
+                Error::KumaConnectionError("Timed out waiting for Kuma to be ready".into())
+            })?;
 
         debug!("Socket.IO connection established");
 
-        let client = Self { socket };
+        let client = Self {
+            socket,
+            monitor_list,
+        };
         client.login(username, password).await?;
         Ok(client)
     }
 
     /// Emit a Socket.IO event and wait for the ack response.
     async fn call(&self, event: &str, payload: Value) -> Result<Value> {
+        self.call_args(event, vec![payload]).await
+    }
+
+    /// Emit a Socket.IO event with any number of arguments and wait for the
+    /// ack response.
+    async fn call_args(&self, event: &str, args: Vec<Value>) -> Result<Value> {
         let (tx, mut rx) = mpsc::channel::<Value>(1);
 
         self.socket
             .emit_with_ack(
                 event,
-                Payload::Text(vec![payload]),
+                Payload::Text(args),
                 Duration::from_secs(30),
                 move |message: Payload, _| {
                     let tx = tx.clone();
@@ -110,11 +210,11 @@ impl KumaClient {
                 },
             )
             .await
-            .map_err(|e| Error::KumaError(format!("Emit '{event}' failed: {e}")))?;
+            .map_err(|e| Error::KumaConnectionError(format!("Emit '{event}' failed: {e}")))?;
 
         rx.recv()
             .await
-            .ok_or_else(|| Error::KumaError(format!("No ack response for '{event}'")))
+            .ok_or_else(|| Error::KumaConnectionError(format!("No ack response for '{event}'")))
     }
 
     async fn login(&self, username: &str, password: &str) -> Result<()> {
@@ -153,6 +253,121 @@ impl KumaClient {
         extract_monitor_id(&response, "add")
     }
 
+    async fn delete_monitor(&self, kuma_id: i32) -> Result<()> {
+        let response = self.call("deleteMonitor", json!(kuma_id)).await?;
+        check_ok(&response, "deleteMonitor")
+    }
+
+    /// Pauses or resumes a monitor, `editMonitor` leaves that alone.
+    async fn set_active(&self, kuma_id: i32, active: bool) -> Result<()> {
+        let event = if active {
+            "resumeMonitor"
+        } else {
+            "pauseMonitor"
+        };
+        let response = self.call(event, json!(kuma_id)).await?;
+        check_ok(&response, event)
+    }
+
+    /// All tags, with their id, name and color.
+    async fn tags(&self) -> Result<Vec<Value>> {
+        let response = self.call_args("getTags", vec![]).await?;
+        check_ok(&response, "getTags")?;
+        Ok(response
+            .get("tags")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn add_tag(&self, name: &str, color: &str) -> Result<i32> {
+        let response = self
+            .call(
+                "addTag",
+                json!({ "name": name, "color": color, "new": true }),
+            )
+            .await?;
+        check_ok(&response, "addTag")?;
+        response
+            .pointer("/tag/id")
+            .and_then(Value::as_i64)
+            .map(|v| v as i32)
+            .ok_or_else(|| Error::KumaError("Missing tag id in addTag response".into()))
+    }
+
+    async fn add_monitor_tag(&self, tag_id: i32, kuma_id: i32, value: &str) -> Result<()> {
+        let response = self
+            .call_args(
+                "addMonitorTag",
+                vec![json!(tag_id), json!(kuma_id), json!(value)],
+            )
+            .await?;
+        check_ok(&response, "addMonitorTag")
+    }
+
+    async fn delete_monitor_tag(&self, tag_id: i32, kuma_id: i32, value: &str) -> Result<()> {
+        let response = self
+            .call_args(
+                "deleteMonitorTag",
+                vec![json!(tag_id), json!(kuma_id), json!(value)],
+            )
+            .await?;
+        check_ok(&response, "deleteMonitorTag")
+    }
+
+    /// Adds a maintenance window, or edits it when it has an id.
+    async fn save_maintenance(&self, maintenance: Value) -> Result<i32> {
+        let event = if maintenance.get("id").is_some() {
+            "editMaintenance"
+        } else {
+            "addMaintenance"
+        };
+        let response = self.call(event, maintenance).await?;
+        check_ok(&response, event)?;
+        response
+            .get("maintenanceID")
+            .and_then(Value::as_i64)
+            .map(|v| v as i32)
+            .ok_or_else(|| Error::KumaError(format!("Missing maintenanceID in {event} response")))
+    }
+
+    /// Replaces the monitors covered by a maintenance window.
+    async fn set_maintenance_monitors(&self, kuma_id: i32, monitors: &[i32]) -> Result<()> {
+        let monitors = monitors.iter().map(|id| json!({ "id": id })).collect();
+        let response = self
+            .call_args(
+                "addMonitorMaintenance",
+                vec![json!(kuma_id), Value::Array(monitors)],
+            )
+            .await?;
+        check_ok(&response, "addMonitorMaintenance")
+    }
+
+    async fn delete_maintenance(&self, kuma_id: i32) -> Result<()> {
+        let response = self.call("deleteMaintenance", json!(kuma_id)).await?;
+        check_ok(&response, "deleteMaintenance")
+    }
+
+    /// All monitors, as Kuma sends them to its own frontend.
+    async fn monitors(&mut self) -> Result<Vec<Value>> {
+        let list = tokio::time::timeout(
+            Duration::from_secs(30),
+            self.monitor_list.wait_for(Option::is_some),
+        )
+        .await
+        .map_err(|_| Error::KumaConnectionError("Timed out waiting for the monitor list".into()))?
+        .map_err(|_| {
+            Error::KumaConnectionError("Connection closed before the monitor list".into())
+        })?
+        .clone();
+
+        Ok(list
+            .as_ref()
+            .and_then(Value::as_object)
+            .map(|monitors| monitors.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
     async fn disconnect(self) -> Result<()> {
         self.socket
             .disconnect()
@@ -162,7 +377,7 @@ impl KumaClient {
     }
 }
 
-fn extract_monitor_id(response: &Value, event: &str) -> Result<i32> {
+fn check_ok(response: &Value, event: &str) -> Result<()> {
     if response.get("ok").and_then(|v| v.as_bool()) != Some(true) {
         let msg = response
             .get("msg")
@@ -170,6 +385,11 @@ fn extract_monitor_id(response: &Value, event: &str) -> Result<i32> {
             .unwrap_or("Unknown error");
         return Err(Error::KumaError(format!("{event} failed: {msg}")));
     }
+    Ok(())
+}
+
+fn extract_monitor_id(response: &Value, event: &str) -> Result<i32> {
+    check_ok(response, event)?;
     response
         .get("monitorID")
         .and_then(|v| v.as_i64())
@@ -179,7 +399,8 @@ fn extract_monitor_id(response: &Value, event: &str) -> Result<i32> {
 
 // ── Monitor JSON builder ────────────────────────────────────────────
 
-fn build_monitor_json(hc_with_relations: &HealthcheckWithRelations) -> Value {
+/// Monitor of a healthcheck, in the Kuma group monitor `parent`
+fn build_monitor_json(hc_with_relations: &HealthcheckWithRelations, parent: Option<i32>) -> Value {
     let url = hc_with_relations.url();
     let hc = &hc_with_relations.healthcheck;
 
@@ -201,7 +422,8 @@ fn build_monitor_json(hc_with_relations: &HealthcheckWithRelations) -> Value {
         "method": hc.method,
         "httpBodyEncoding": encoding,
         "accepted_statuscodes": [hc.expected_status.to_string()],
-        "conditions": "[]"
+        "conditions": "[]",
+        "parent": parent
     });
 
     let obj = monitor.as_object_mut().unwrap();
@@ -228,102 +450,969 @@ fn build_monitor_json(hc_with_relations: &HealthcheckWithRelations) -> Value {
         }
     }
 
+    // Kuma monitor type and its own fields
+    match hc.kind.as_str() {
+        "tcp" => {
+            obj.insert("type".into(), json!("port"));
+            obj.insert("hostname".into(), json!(hc_with_relations.domain_fqdn));
+            obj.insert("port".into(), json!(hc_with_relations.port()));
+        }
+        "dns" => {
+            obj.insert("type".into(), json!("dns"));
+            obj.insert("hostname".into(), json!(hc_with_relations.domain_fqdn));
+            obj.insert(
+                "dns_resolve_type".into(),
+                json!(hc.dns_record_type.as_deref().unwrap_or("A")),
+            );
+            let resolver = hc
+                .dns_resolver
+                .as_deref()
+                .and_then(service::healthcheck::parse_resolver)
+                .unwrap_or(SocketAddr::from(([1, 1, 1, 1], 53)));
+            obj.insert(
+                "dns_resolve_server".into(),
+                json!(resolver.ip().to_string()),
+            );
+            obj.insert("port".into(), json!(resolver.port()));
+        }
+        "tls" => {
+            // Kuma checks certificates as part of an HTTP monitor
+            obj.insert(
+                "url".into(),
+                json!(format!(
+                    "https://{}:{}",
+                    hc_with_relations.domain_fqdn,
+                    hc_with_relations.port()
+                )),
+            );
+            obj.insert("method".into(), json!("HEAD"));
+            obj.insert("accepted_statuscodes".into(), json!(["100-599"]));
+            obj.insert("expiryNotification".into(), json!(true));
+            obj.insert("ignoreTls".into(), json!(false));
+        }
+        _ => {
+            if let Some(ref path) = hc.json_path {
+                obj.insert("type".into(), json!("json-query"));
+                obj.insert("jsonPath".into(), json!(path));
+                obj.insert("jsonPathOperator".into(), json!("=="));
+                obj.insert(
+                    "expectedValue".into(),
+                    json!(hc.json_expected_value.as_deref().unwrap_or_default()),
+                );
+            } else if let Some(ref keyword) = hc.expected_body {
+                obj.insert("type".into(), json!("keyword"));
+                obj.insert("keyword".into(), json!(keyword));
+                obj.insert("invertKeyword".into(), json!(false));
+            }
+        }
+    }
+
     monitor
 }
 
-pub async fn sync_healthcheck_to_kuma(state: AppState, id: &str) -> Result<()> {
-    debug!("Connecting to Kuma at {}", state.config.kuma_url);
-    let client = KumaClient::connect(
-        &state.config.kuma_url,
-        &state.config.kuma_username,
-        &state.config.kuma_password,
-    )
-    .await?;
+/// Group monitor of the application or service of healthchecks
+fn build_group_json(target: &KumaTarget) -> Value {
+    json!({
+        "type": "group",
+        "name": format!("{} ({})", target.name, target.environment),
+        "interval": 60,
+        "active": true,
+        "maxretries": 0,
+        "retryInterval": 60,
+        "accepted_statuscodes": ["200-299"],
+        "conditions": "[]"
+    })
+}
+
+/// Name and value of a tag on a monitor
+type MonitorTag<'a> = (&'a str, String);
+
+/// Tags of the monitors of an application or service: its environment and
+/// the stacks of an application
+fn monitor_tags(target: &KumaTarget) -> Vec<MonitorTag<'static>> {
+    std::iter::once(("environment", target.environment.clone()))
+        .chain(target.stacks.iter().map(|stack| ("stack", stack.clone())))
+        .collect()
+}
+
+/// One-off Kuma maintenance window, dates in UTC
+fn build_maintenance_json(maintenance: &Maintenance) -> Value {
+    let midnight = json!({ "hours": 0, "minutes": 0 });
+    let mut window = json!({
+        "title": maintenance.title,
+        "description": maintenance.description.as_deref().unwrap_or_default(),
+        "strategy": "single",
+        "active": true,
+        "intervalDay": 1,
+        "dateRange": [maintenance.starts_at, maintenance.ends_at],
+        "timeRange": [midnight, midnight],
+        "weekdays": [],
+        "daysOfMonth": [],
+        "cron": "30 3 * * *",
+        "durationMinutes": 60,
+        "timezoneOption": "UTC"
+    });
+    if let Some(kuma_id) = maintenance.kuma_id {
+        window
+            .as_object_mut()
+            .unwrap()
+            .insert("id".into(), json!(kuma_id));
+    }
+    window
+}
+
+// ── Sync worker ─────────────────────────────────────────────────────
 
-    let hc = service::healthcheck::get_with_relations(&state.pool, id).await?;
+/// How often the sync worker looks for dirty healthchecks and due items
+/// when it isn't woken up.
+const SYNC_INTERVAL: Duration = Duration::from_secs(10);
 
-    let name = hc.healthcheck.name.clone();
-    let kuma_id = hc.healthcheck.kuma_id;
-    let mut monitor = build_monitor_json(&hc);
+/// Monitor tags managed by Auto, others are left alone
+const MANAGED_TAGS: &[&str] = &["environment", "stack"];
 
-    if let Some(kuma_id) = kuma_id {
+const ENVIRONMENT_TAG_COLOR: &str = "#2563EB";
+const STACK_TAG_COLOR: &str = "#059669";
+
+/// Spawns the Kuma sync worker on a dedicated OS thread, like the poller.
+///
+/// Every few seconds, or right away when woken up through `sync_rx`, it
+/// queues the healthchecks flagged `kuma_dirty` and works off the queue
+/// items that are due over one authenticated connection, which is kept
+/// between rounds. A failed item, or a failed connection, is recorded on the
+/// items and retried with backoff without holding up the others.
+pub fn spawn_kuma_sync_worker(state: AppState, sync_rx: watch::Receiver<()>) {
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Kuma sync worker runtime");
+        rt.block_on(run_sync_worker(state, sync_rx));
+    });
+}
+
+async fn run_sync_worker(state: AppState, mut sync_rx: watch::Receiver<()>) {
+    // With both sources, healthchecks without monitor are run natively and
+    // only get one when synced explicitly
+    let new_monitors = state.config.uptime_source == crate::UptimeSource::Kuma;
+    let mut client: Option<KumaClient> = None;
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(SYNC_INTERVAL) => {}
+            changed = sync_rx.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+
+        if let Err(e) = service::kuma_sync::enqueue_dirty(&state.pool, new_monitors).await {
+            error!("Kuma sync: failed to queue dirty healthchecks: {e}");
+            continue;
+        }
+        if let Err(e) = service::maintenance::mark_changed_dirty(&state.pool).await {
+            error!("Kuma sync: failed to check maintenance monitors: {e}");
+        }
+        let items = match service::kuma_sync::due(&state.pool).await {
+            Ok(items) => items,
+            Err(e) => {
+                error!("Kuma sync: failed to get queued items: {e}");
+                continue;
+            }
+        };
+        let windows = match service::maintenance::due(&state.pool).await {
+            Ok(windows) => windows,
+            Err(e) => {
+                error!("Kuma sync: failed to get maintenance to sync: {e}");
+                continue;
+            }
+        };
+        if items.is_empty() && windows.is_empty() {
+            continue;
+        }
+
+        if client.is_none() {
+            match KumaClient::connect(
+                &state.config.kuma_url,
+                &state.config.kuma_username,
+                &state.config.kuma_password,
+            )
+            .await
+            {
+                Ok(connected) => client = Some(connected),
+                Err(e) => {
+                    // Recorded on the items, so they back off until Kuma is back
+                    error!("Kuma sync: {e}");
+                    let error = e.to_string();
+                    for item in &items {
+                        if let Err(e) = service::kuma_sync::failed(&state.pool, item, &error).await
+                        {
+                            error!("Kuma sync: failed to record error: {e}");
+                        }
+                    }
+                    for window in &windows {
+                        if let Err(e) =
+                            service::maintenance::failed(&state.pool, window, &error).await
+                        {
+                            error!("Kuma sync: failed to record error: {e}");
+                        }
+                    }
+                    continue;
+                }
+            }
+        }
+
+        let connected = client.as_mut().expect("connected above");
+        if let Err(e) = sync_round(&state, connected, items, windows).await {
+            // The connection may be gone, start over with a fresh one
+            warn!("Kuma sync: {e}, reconnecting on the next round");
+            if let Some(old) = client.take() {
+                let _ = old.disconnect().await;
+            }
+        }
+    }
+}
+
+/// What a sync round knows about Kuma, kept up to date along the way
+struct SyncRound {
+    /// Monitors by kuma_id
+    monitors: HashMap<i32, Value>,
+    /// Tag ids by name, fetched when first needed
+    tags: Option<HashMap<String, i32>>,
+}
+
+impl SyncRound {
+    fn is_active(&self, kuma_id: i32) -> Option<bool> {
+        monitor_flag(self.monitors.get(&kuma_id)?, "active")
+    }
+}
+
+/// Applies due queue items in order, then syncs due maintenance windows. A
+/// failed item is recorded and the round goes on with the next one, only a
+/// failed connection ends the round.
+async fn sync_round(
+    state: &AppState,
+    client: &mut KumaClient,
+    items: Vec<KumaSyncItem>,
+    windows: Vec<Maintenance>,
+) -> Result<()> {
+    let mut round = SyncRound {
+        monitors: client
+            .monitors()
+            .await?
+            .into_iter()
+            .filter_map(|m| Some((monitor_id(&m)?, m)))
+            .collect(),
+        tags: None,
+    };
+    let mut added = false;
+    let mut lost = None;
+
+    for item in items {
+        let recorded = match sync_item(client, &state.pool, &item, &mut round).await {
+            Ok(created) => {
+                added |= created;
+                service::kuma_sync::done(&state.pool, &item).await
+            }
+            Err(e) => {
+                let message = format!(
+                    "{} of '{}' failed (attempt {}): {e}",
+                    item.operation,
+                    item.name,
+                    item.attempts + 1
+                );
+                if matches!(e, Error::KumaConnectionError(_)) {
+                    lost = Some(Error::KumaConnectionError(message));
+                } else {
+                    warn!("Kuma sync: {message}");
+                }
+                service::kuma_sync::failed(&state.pool, &item, &e.to_string()).await
+            }
+        };
+        if let Err(e) = recorded {
+            error!("Kuma sync: failed to record '{}': {e}", item.name);
+        }
+        if lost.is_some() {
+            break;
+        }
+    }
+
+    // Let the poller pick up the heartbeats of new monitors
+    if added {
+        let _ = state.kuma_refresh_tx.send(());
+    }
+    if let Some(e) = lost {
+        return Err(e);
+    }
+
+    for window in windows {
+        if let Err(e) = sync_maintenance(client, &state.pool, &window).await {
+            let message = format!(
+                "Maintenance '{}' failed (attempt {}): {e}",
+                window.title,
+                window.attempts + 1
+            );
+            if let Err(e) = service::maintenance::failed(&state.pool, &window, &e.to_string()).await
+            {
+                error!("Kuma sync: failed to record '{}': {e}", window.title);
+            }
+            if matches!(e, Error::KumaConnectionError(_)) {
+                return Err(Error::KumaConnectionError(message));
+            }
+            warn!("Kuma sync: {message}");
+        }
+    }
+    Ok(())
+}
+
+/// Applies one queue item, returns whether a monitor was added.
+async fn sync_item(
+    client: &KumaClient,
+    pool: &SqlitePool,
+    item: &KumaSyncItem,
+    round: &mut SyncRound,
+) -> Result<bool> {
+    match (item.operation.as_str(), item.kuma_id) {
+        ("push", _) => {
+            let hc =
+                match service::healthcheck::get_with_relations(pool, &item.healthcheck_id).await {
+                    Ok(hc) => hc,
+                    // Deleted meanwhile, a pause or delete is queued instead
+                    Err(Error::NotFound(_)) => return Ok(false),
+                    Err(e) => return Err(e),
+                };
+            let added = hc.healthcheck.kuma_id.is_none();
+            push_healthcheck(client, pool, &hc, item.kuma_version, round).await?;
+            Ok(added)
+        }
+        ("pause", Some(kuma_id)) => {
+            if round.is_active(kuma_id) == Some(true) {
+                debug!("Pausing monitor '{}' (kuma_id: {kuma_id})", item.name);
+                client.set_active(kuma_id, false).await?;
+            }
+            service::healthcheck::clear_kuma_dirty(pool, &item.healthcheck_id, item.kuma_version)
+                .await?;
+            Ok(false)
+        }
+        ("delete", Some(kuma_id)) => {
+            debug!("Deleting monitor '{}' (kuma_id: {kuma_id})", item.name);
+            client.delete_monitor(kuma_id).await?;
+            Ok(false)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Adds or edits the monitor of a healthcheck in the group of its
+/// application or service, pauses or resumes it when `is_enabled` differs
+/// from whether it's active in Kuma, and updates its tags. `kuma_version` is
+/// the one the push was queued for.
+async fn push_healthcheck(
+    client: &KumaClient,
+    pool: &SqlitePool,
+    hc: &HealthcheckWithRelations,
+    kuma_version: i64,
+    round: &mut SyncRound,
+) -> Result<()> {
+    let name = &hc.healthcheck.name;
+    let target = service::kuma_sync::target(pool, &hc.healthcheck).await?;
+    let parent = match &target {
+        Some(target) => Some(ensure_group(client, pool, target, round).await?),
+        None => None,
+    };
+    let mut monitor = build_monitor_json(hc, parent);
+
+    let (kuma_id, is_active) = if let Some(kuma_id) = hc.healthcheck.kuma_id {
         monitor
             .as_object_mut()
             .unwrap()
             .insert("id".into(), json!(kuma_id));
         debug!("Editing monitor '{name}' (kuma_id: {kuma_id})");
         client.edit_monitor(monitor).await?;
+        (kuma_id, round.is_active(kuma_id))
     } else {
         debug!("Adding monitor '{name}'");
         let new_id = client.add_monitor(monitor).await?;
         debug!("Created monitor '{name}' with kuma_id: {new_id}");
-        service::healthcheck::update(
-            &state.pool,
-            &hc.healthcheck.id,
-            UpdateHealthcheck {
-                kuma_id: Some(new_id),
-                ..Default::default()
-            },
-        )
-        .await?;
+        service::healthcheck::set_kuma_id(pool, &hc.healthcheck.id, new_id).await?;
         debug!("Updated Auto healthcheck's kuma_id");
+        // Kuma starts new monitors
+        (new_id, Some(true))
+    };
+
+    if is_active != Some(hc.healthcheck.is_enabled) {
+        debug!(
+            "Setting monitor '{name}' (kuma_id: {kuma_id}) active: {}",
+            hc.healthcheck.is_enabled
+        );
+        client
+            .set_active(kuma_id, hc.healthcheck.is_enabled)
+            .await?;
     }
 
-    // Clear dirty flag — this healthcheck is now in sync with Kuma
-    service::healthcheck::clear_kuma_dirty(&state.pool, &hc.healthcheck.id).await?;
+    if let Some(target) = &target {
+        sync_tags(client, kuma_id, &monitor_tags(target), round).await?;
+    }
 
-    client.disconnect().await?;
-    debug!("Kuma sync complete");
+    // Clear dirty flag — this healthcheck is now in sync with Kuma, unless
+    // it changed since it was queued
+    service::healthcheck::clear_kuma_dirty(pool, &hc.healthcheck.id, kuma_version).await?;
+    Ok(())
+}
+
+/// The group monitor of an application or service, added when it has none
+/// in Kuma and renamed when its name or environment changed.
+async fn ensure_group(
+    client: &KumaClient,
+    pool: &SqlitePool,
+    target: &KumaTarget,
+    round: &mut SyncRound,
+) -> Result<i32> {
+    let mut group = build_group_json(target);
+
+    if let Some(kuma_id) = target.group_kuma_id
+        && let Some(existing) = round.monitors.get(&kuma_id)
+    {
+        if existing.get("name") != group.get("name") {
+            debug!("Renaming group monitor {kuma_id} to {}", group["name"]);
+            group
+                .as_object_mut()
+                .unwrap()
+                .insert("id".into(), json!(kuma_id));
+            client.edit_monitor(group.clone()).await?;
+            round.monitors.insert(kuma_id, group);
+        }
+        return Ok(kuma_id);
+    }
+
+    debug!("Adding group monitor {}", group["name"]);
+    let kuma_id = client.add_monitor(group.clone()).await?;
+    service::kuma_sync::set_group(pool, target, kuma_id).await?;
+    round.monitors.insert(kuma_id, group);
+    Ok(kuma_id)
+}
+
+/// Adds and removes the managed tags of a monitor to match `desired`.
+async fn sync_tags(
+    client: &KumaClient,
+    kuma_id: i32,
+    desired: &[MonitorTag<'_>],
+    round: &mut SyncRound,
+) -> Result<()> {
+    let (add, remove) = tag_changes(round.monitors.get(&kuma_id), desired);
+
+    for (tag_id, value) in remove {
+        client.delete_monitor_tag(tag_id, kuma_id, &value).await?;
+    }
+    for (name, value) in add {
+        let tag_id = tag_id(client, round, name).await?;
+        client.add_monitor_tag(tag_id, kuma_id, &value).await?;
+    }
     Ok(())
 }
 
-pub async fn sync_healthchecks_to_kuma(state: AppState) -> Result<()> {
-    debug!("Connecting to Kuma at {}", state.config.kuma_url);
-    let client = KumaClient::connect(
-        &state.config.kuma_url,
-        &state.config.kuma_username,
-        &state.config.kuma_password,
+/// Id of the tag with the given name, added when Kuma doesn't have it yet.
+async fn tag_id(client: &KumaClient, round: &mut SyncRound, name: &str) -> Result<i32> {
+    if round.tags.is_none() {
+        let tags = client.tags().await?;
+        round.tags = Some(
+            tags.iter()
+                .filter_map(|t| Some((monitor_str(t, "name")?, monitor_id(t)?)))
+                .collect(),
+        );
+    }
+    let tags = round.tags.as_mut().expect("fetched above");
+    if let Some(id) = tags.get(name) {
+        return Ok(*id);
+    }
+
+    let color = if name == "environment" {
+        ENVIRONMENT_TAG_COLOR
+    } else {
+        STACK_TAG_COLOR
+    };
+    let id = client.add_tag(name, color).await?;
+    tags.insert(name.to_string(), id);
+    Ok(id)
+}
+
+/// Managed tags to add to a monitor, by name, and to remove from it, by
+/// tag id, to go from its current tags to `desired`. Other tags are left
+/// alone.
+fn tag_changes<'a>(
+    monitor: Option<&Value>,
+    desired: &'a [MonitorTag<'a>],
+) -> (Vec<MonitorTag<'a>>, Vec<(i32, String)>) {
+    let current: Vec<(i32, String, String)> = monitor
+        .and_then(|m| m.get("tags"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| {
+            let name = monitor_str(tag, "name")?;
+            MANAGED_TAGS.contains(&name.as_str()).then_some(())?;
+            Some((
+                monitor_int(tag, "tag_id")?,
+                name,
+                monitor_str(tag, "value").unwrap_or_default(),
+            ))
+        })
+        .collect();
+
+    let add = desired
+        .iter()
+        .filter(|(name, value)| !current.iter().any(|(_, n, v)| n == name && v == value))
+        .cloned()
+        .collect();
+    let remove = current
+        .into_iter()
+        .filter(|(_, name, value)| !desired.iter().any(|(n, v)| n == name && v == value))
+        .map(|(tag_id, _, value)| (tag_id, value))
+        .collect();
+    (add, remove)
+}
+
+/// Adds or edits the Kuma maintenance window of planned maintenance and
+/// sets the monitors it covers, or deletes it from Kuma when deleted.
+async fn sync_maintenance(
+    client: &KumaClient,
+    pool: &SqlitePool,
+    maintenance: &Maintenance,
+) -> Result<()> {
+    if maintenance.deleted_at.is_some() {
+        if let Some(kuma_id) = maintenance.kuma_id {
+            debug!(
+                "Deleting maintenance '{}' (kuma_id: {kuma_id})",
+                maintenance.title
+            );
+            client.delete_maintenance(kuma_id).await?;
+        }
+        return service::maintenance::remove(pool, &maintenance.id).await;
+    }
+
+    let monitors = service::maintenance::monitor_ids(pool, &maintenance.infra_id).await?;
+    debug!(
+        "Saving maintenance '{}' covering {} monitors",
+        maintenance.title,
+        monitors.len()
+    );
+    let kuma_id = client
+        .save_maintenance(build_maintenance_json(maintenance))
+        .await?;
+    if maintenance.kuma_id.is_none() {
+        // Kept right away, so a retry doesn't add the window again
+        service::maintenance::set_kuma_id(pool, &maintenance.id, kuma_id).await?;
+    }
+    client.set_maintenance_monitors(kuma_id, &monitors).await?;
+    service::maintenance::synced(pool, maintenance, kuma_id, &monitors).await
+}
+
+// ── Reconciliation ──────────────────────────────────────────────────
+
+/// Monitor fields that only some Kuma versions know about
+const IGNORED_MONITOR_FIELDS: &[&str] = &["conditions"];
+
+/// Fetches all monitors from Kuma.
+pub async fn fetch_monitors(config: &crate::Config) -> Result<Vec<Value>> {
+    debug!("Connecting to Kuma at {}", config.kuma_url);
+    let mut client = KumaClient::connect(
+        &config.kuma_url,
+        &config.kuma_username,
+        &config.kuma_password,
     )
     .await?;
+    let monitors = client.monitors().await;
+    client.disconnect().await?;
+    monitors
+}
 
-    let healthchecks = service::healthcheck::get_all_with_relations(&state.pool).await?;
+/// Compares the healthchecks with the monitors fetched from Kuma.
+///
+/// Monitors referred to by a healthcheck in the trash are not missing, and
+/// Kuma groups are left out.
+pub async fn diff(pool: &SqlitePool, monitors: &[Value]) -> Result<KumaDiff> {
+    let by_id: HashMap<i32, &Value> = monitors
+        .iter()
+        .filter_map(|m| Some((monitor_id(m)?, m)))
+        .collect();
+
+    let groups = service::kuma_sync::groups(pool).await?;
+    let mut drifted = Vec::new();
+    let mut orphaned = Vec::new();
+    for hc in service::healthcheck::get_all_with_relations(pool).await? {
+        let Some(kuma_id) = hc.healthcheck.kuma_id else {
+            continue;
+        };
+        match by_id.get(&kuma_id) {
+            Some(monitor) => {
+                let target = hc
+                    .healthcheck
+                    .application_id
+                    .as_ref()
+                    .or(hc.healthcheck.service_id.as_ref());
+                let parent = target.and_then(|id| groups.get(id)).copied();
+                let changes = monitor_drift(&build_monitor_json(&hc, parent), monitor);
+                if !changes.is_empty() {
+                    drifted.push(KumaDrift {
+                        healthcheck_id: hc.healthcheck.id,
+                        name: hc.healthcheck.name,
+                        kuma_id,
+                        changes,
+                    });
+                }
+            }
+            None => orphaned.push(KumaOrphan {
+                healthcheck_id: hc.healthcheck.id,
+                name: hc.healthcheck.name,
+                kuma_id,
+            }),
+        }
+    }
 
-    for hc in healthchecks {
-        let name = hc.healthcheck.name.clone();
-        let hc_id = hc.healthcheck.id.clone();
-        let kuma_id = hc.healthcheck.kuma_id;
-        let mut monitor = build_monitor_json(&hc);
+    let known = service::healthcheck::kuma_ids(pool).await?;
+    let mut missing = Vec::new();
+    for monitor in monitors {
+        let Some(kuma_id) = monitor_id(monitor) else {
+            continue;
+        };
+        let monitor_type = monitor_str(monitor, "type").unwrap_or_default();
+        if known.contains(&kuma_id) || monitor_type == "group" {
+            continue;
+        }
 
-        if let Some(kuma_id) = kuma_id {
-            monitor
-                .as_object_mut()
-                .unwrap()
-                .insert("id".into(), json!(kuma_id));
-            debug!("Editing monitor '{name}' (kuma_id: {kuma_id})");
-            client.edit_monitor(monitor).await?;
-        } else {
-            debug!("Adding monitor '{name}'");
-            let new_id = client.add_monitor(monitor).await?;
-            debug!("Created monitor '{name}' with kuma_id: {new_id}");
-            service::healthcheck::update(
-                &state.pool,
-                &hc_id,
-                UpdateHealthcheck {
-                    kuma_id: Some(new_id),
-                    ..Default::default()
-                },
-            )
-            .await?;
-            debug!("Updated Auto healthcheck's kuma_id");
+        let (hostname, mut import_error) = match healthcheck_from_monitor(monitor) {
+            Ok((host, _)) => (Some(host), None),
+            Err(e) => (monitor_host(monitor), Some(e)),
+        };
+        let domain = match &hostname {
+            Some(host) => service::domain::find_by_fqdn(pool, host).await?,
+            None => None,
+        };
+        if let (None, None, Some(host)) = (&domain, &import_error, &hostname) {
+            import_error = Some(format!("No domain with fqdn {host}"));
         }
 
-        // Clear dirty flag — this healthcheck is now in sync with Kuma
-        service::healthcheck::clear_kuma_dirty(&state.pool, &hc_id).await?;
+        missing.push(KumaMissingMonitor {
+            kuma_id,
+            name: monitor_str(monitor, "name").unwrap_or_default(),
+            monitor_type,
+            hostname,
+            domain_id: domain.map(|d| d.id),
+            import_error,
+        });
     }
 
-    client.disconnect().await?;
-    debug!("Kuma sync complete");
-    Ok(())
+    missing.sort_by_key(|m| m.name.to_lowercase());
+    drifted.sort_by_key(|d| d.name.to_lowercase());
+    orphaned.sort_by_key(|o| o.name.to_lowercase());
+    Ok(KumaDiff {
+        missing,
+        drifted,
+        orphaned,
+    })
+}
+
+/// Creates healthchecks for Kuma monitors that no healthcheck refers to.
+///
+/// A monitor is added to the application or service of the domain whose
+/// fqdn is the host it checks, and keeps its `kuma_id`. Monitors that can't
+/// be imported are skipped with the reason.
+pub async fn import(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    monitors: &[Value],
+    input: KumaImport,
+) -> Result<KumaImportResult> {
+    let known = service::healthcheck::kuma_ids(pool).await?;
+    let mut created = Vec::new();
+    let mut skipped = Vec::new();
+
+    for monitor in monitors {
+        let Some(kuma_id) = monitor_id(monitor) else {
+            continue;
+        };
+        let requested = input.kuma_ids.as_ref().map(|ids| ids.contains(&kuma_id));
+        if requested == Some(false) || monitor_str(monitor, "type").as_deref() == Some("group") {
+            continue;
+        }
+        if known.contains(&kuma_id) {
+            if requested == Some(true) {
+                skipped.push(KumaImportSkipped {
+                    kuma_id,
+                    name: monitor_str(monitor, "name"),
+                    reason: "Already monitored by a healthcheck".into(),
+                });
+            }
+            continue;
+        }
+
+        match import_monitor(pool, actor, monitor).await? {
+            Ok(healthcheck) => created.push(healthcheck),
+            Err(reason) => skipped.push(KumaImportSkipped {
+                kuma_id,
+                name: monitor_str(monitor, "name"),
+                reason,
+            }),
+        }
+    }
+
+    for &kuma_id in input.kuma_ids.iter().flatten() {
+        if !monitors.iter().any(|m| monitor_id(m) == Some(kuma_id)) {
+            skipped.push(KumaImportSkipped {
+                kuma_id,
+                name: None,
+                reason: "Monitor not found in Kuma".into(),
+            });
+        }
+    }
+
+    Ok(KumaImportResult { created, skipped })
+}
+
+/// Creates the healthcheck of one monitor, or tells why it can't.
+async fn import_monitor(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    monitor: &Value,
+) -> Result<std::result::Result<Healthcheck, String>> {
+    let (host, mut input) = match healthcheck_from_monitor(monitor) {
+        Ok(converted) => converted,
+        Err(reason) => return Ok(Err(reason)),
+    };
+    let Some(domain) = service::domain::find_by_fqdn(pool, &host).await? else {
+        return Ok(Err(format!("No domain with fqdn {host}")));
+    };
+    match (domain.target_application_id, domain.target_service_id) {
+        (Some(application_id), _) => input.application_id = Some(application_id),
+        (None, Some(service_id)) => input.service_id = Some(service_id),
+        (None, None) => {
+            return Ok(Err(format!("Domain {host} has no application or service")));
+        }
+    }
+    input.domain_id = domain.id;
+
+    let healthcheck = match service::healthcheck::create(pool, actor, input).await {
+        Ok(healthcheck) => healthcheck,
+        Err(Error::ValidationError(reason) | Error::NotFound(reason) | Error::Conflict(reason)) => {
+            return Ok(Err(reason));
+        }
+        Err(e) => return Err(e),
+    };
+    // The healthcheck is what Kuma has, nothing to push. A new healthcheck
+    // has no counted changes yet.
+    service::healthcheck::clear_kuma_dirty(pool, &healthcheck.id, 0).await?;
+
+    Ok(Ok(Healthcheck {
+        kuma_dirty: false,
+        ..healthcheck
+    }))
+}
+
+/// Fields of `expected` (as built by `build_monitor_json`) that differ from
+/// the monitor in Kuma.
+fn monitor_drift(expected: &Value, monitor: &Value) -> Vec<FieldChange> {
+    let Some(expected) = expected.as_object() else {
+        return Vec::new();
+    };
+    expected
+        .iter()
+        .filter(|(field, _)| !IGNORED_MONITOR_FIELDS.contains(&field.as_str()))
+        .filter_map(|(field, after)| {
+            let before = monitor.get(field).unwrap_or(&Value::Null);
+            (!same_monitor_value(after, before)).then(|| FieldChange {
+                field: field.clone(),
+                before: before.clone(),
+                after: after.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Compares monitor fields the way Kuma stores them: booleans as 0 or 1,
+/// empty values as `null` and headers as JSON text.
+fn same_monitor_value(auto: &Value, kuma: &Value) -> bool {
+    match (auto, kuma) {
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_monitor_value(a, b))
+        }
+        (Value::Bool(b), Value::Number(n)) | (Value::Number(n), Value::Bool(b)) => {
+            n.as_i64() == Some(*b as i64)
+        }
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::String(a), Value::String(b)) if a != b => {
+            match (
+                serde_json::from_str::<Value>(a),
+                serde_json::from_str::<Value>(b),
+            ) {
+                (Ok(a @ Value::Object(_)), Ok(b @ Value::Object(_))) => a == b,
+                _ => false,
+            }
+        }
+        _ => monitor_text(auto) == monitor_text(kuma),
+    }
+}
+
+fn monitor_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) if s.is_empty() || s == "null" => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+fn monitor_id(monitor: &Value) -> Option<i32> {
+    monitor.get("id")?.as_i64().map(|id| id as i32)
+}
+
+fn monitor_str(monitor: &Value, field: &str) -> Option<String> {
+    monitor
+        .get(field)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+fn monitor_int(monitor: &Value, field: &str) -> Option<i32> {
+    monitor
+        .get(field)
+        .and_then(Value::as_f64)
+        .map(|v| v.round() as i32)
+}
+
+fn monitor_flag(monitor: &Value, field: &str) -> Option<bool> {
+    let value = monitor.get(field)?;
+    value.as_bool().or_else(|| value.as_i64().map(|n| n != 0))
+}
+
+/// Host a monitor checks, lowercased
+fn monitor_host(monitor: &Value) -> Option<String> {
+    monitor_str(monitor, "hostname")
+        .or_else(|| {
+            let url = url::Url::parse(&monitor_str(monitor, "url")?).ok()?;
+            url.host_str().map(str::to_string)
+        })
+        .map(|host| host.to_lowercase())
+}
+
+/// The reverse of `build_monitor_json`: the host a monitor checks and the
+/// healthcheck for it, without domain, application or service.
+fn healthcheck_from_monitor(
+    monitor: &Value,
+) -> std::result::Result<(String, CreateHealthcheck), String> {
+    let monitor_type = monitor_str(monitor, "type").unwrap_or_default();
+    let mut hc = CreateHealthcheck {
+        name: monitor_str(monitor, "name").ok_or("Monitor has no name")?,
+        application_id: None,
+        service_id: None,
+        kuma_id: monitor_id(monitor),
+        domain_id: String::new(),
+        protocol: "https".into(),
+        path: "/".into(),
+        method: "GET".into(),
+        headers: None,
+        expected_status: 200,
+        expected_body: None,
+        timeout_seconds: monitor_int(monitor, "timeout").unwrap_or(30).max(1),
+        interval: monitor_int(monitor, "interval").unwrap_or(60),
+        is_enabled: monitor_flag(monitor, "active").unwrap_or(true),
+        notes: monitor_str(monitor, "description"),
+        retry: monitor_int(monitor, "maxretries").unwrap_or(0),
+        retry_interval: monitor_int(monitor, "retryInterval").unwrap_or(60),
+        request_body_encoding: "JSON".into(),
+        request_body: None,
+        http_auth_user: None,
+        http_auth_pass: None,
+        kind: "http".into(),
+        port: None,
+        dns_record_type: None,
+        dns_resolver: None,
+        tls_expiry_days: None,
+        json_path: None,
+        json_expected_value: None,
+    };
+
+    match monitor_type.as_str() {
+        "http" | "keyword" | "json-query" => {
+            let raw = monitor_str(monitor, "url").ok_or("Monitor has no URL")?;
+            let url = url::Url::parse(&raw).map_err(|e| format!("Invalid URL {raw}: {e}"))?;
+            if !matches!(url.scheme(), "http" | "https") {
+                return Err(format!("Unsupported URL scheme in {raw}"));
+            }
+            if url.port().is_some() {
+                return Err(format!(
+                    "HTTP healthchecks can't have a port, {raw} has one"
+                ));
+            }
+            hc.protocol = url.scheme().into();
+            hc.path = match url.query() {
+                Some(query) => format!("{}?{query}", url.path()),
+                None => url.path().into(),
+            };
+            hc.method = monitor_str(monitor, "method")
+                .unwrap_or_else(|| "GET".into())
+                .to_uppercase();
+            hc.expected_status = monitor
+                .pointer("/accepted_statuscodes/0")
+                .and_then(Value::as_str)
+                .and_then(|codes| codes.split('-').next()?.parse().ok())
+                .unwrap_or(200);
+            hc.headers = monitor_str(monitor, "headers")
+                .filter(|h| serde_json::from_str::<HashMap<String, String>>(h).is_ok());
+            hc.request_body = monitor_str(monitor, "body");
+            hc.request_body_encoding = match monitor_str(monitor, "httpBodyEncoding").as_deref() {
+                Some("form") => "x-www-form-urlencoded",
+                Some("xml") => "XML",
+                _ => "JSON",
+            }
+            .into();
+            if monitor_str(monitor, "authMethod").as_deref() == Some("basic") {
+                hc.http_auth_user = monitor_str(monitor, "basic_auth_user");
+                hc.http_auth_pass = monitor_str(monitor, "basic_auth_pass");
+            }
+
+            if monitor_type == "keyword" {
+                if monitor_flag(monitor, "invertKeyword") == Some(true) {
+                    return Err("Inverted keyword monitors can't be imported".into());
+                }
+                hc.expected_body = monitor_str(monitor, "keyword");
+            } else if monitor_type == "json-query" {
+                let operator = monitor_str(monitor, "jsonPathOperator");
+                if operator.as_deref().is_some_and(|op| op != "==") {
+                    return Err(format!(
+                        "JSON query monitors with operator {} can't be imported",
+                        operator.unwrap_or_default()
+                    ));
+                }
+                hc.json_path = monitor_str(monitor, "jsonPath");
+                hc.json_expected_value = monitor_str(monitor, "expectedValue");
+            }
+
+            let host = url.host_str().ok_or_else(|| format!("No host in {raw}"))?;
+            Ok((host.to_lowercase(), hc))
+        }
+        "port" => {
+            hc.kind = "tcp".into();
+            hc.port = monitor_int(monitor, "port");
+            let host = monitor_host(monitor).ok_or("Monitor has no hostname")?;
+            Ok((host, hc))
+        }
+        "dns" => {
+            hc.kind = "dns".into();
+            hc.dns_record_type = monitor_str(monitor, "dns_resolve_type");
+            hc.dns_resolver = monitor_str(monitor, "dns_resolve_server").map(|server| {
+                match monitor_int(monitor, "port") {
+                    None | Some(53) => server,
+                    Some(port) if server.contains(':') => format!("[{server}]:{port}"),
+                    Some(port) => format!("{server}:{port}"),
+                }
+            });
+            let host = monitor_host(monitor).ok_or("Monitor has no hostname")?;
+            Ok((host, hc))
+        }
+        "" => Err("Monitor has no type".into()),
+        other => Err(format!("{other} monitors can't be imported")),
+    }
 }
 
 // ── Persistent Kuma Poller ──────────────────────────────────────────
@@ -331,11 +1420,13 @@ pub async fn sync_healthchecks_to_kuma(state: AppState) -> Result<()> {
 /// Spawns the persistent Kuma poller on a dedicated OS thread.
 ///
 /// The poller maintains a Socket.IO connection to Kuma, listens for
-/// heartbeat events, and broadcasts them to SSE clients. It reconnects
+/// heartbeat events, stores them in the database and broadcasts them to
+/// SSE clients. It reconnects
 /// automatically on disconnect with exponential backoff, and reconnects
 /// immediately when notified of a sync (to pick up new kuma_ids).
 pub fn spawn_kuma_poller(
     config: crate::Config,
+    pool: SqlitePool,
     uptime_state: UptimeState,
     uptime_tx: UptimeTx,
     refresh_rx: watch::Receiver<()>,
@@ -345,7 +1436,13 @@ pub fn spawn_kuma_poller(
             .enable_all()
             .build()
             .expect("Kuma poller runtime");
-        rt.block_on(run_poller_loop(config, uptime_state, uptime_tx, refresh_rx));
+        rt.block_on(run_poller_loop(
+            config,
+            pool,
+            uptime_state,
+            uptime_tx,
+            refresh_rx,
+        ));
     });
 }
 
@@ -377,6 +1474,7 @@ impl ReconnectPolicy {
 
 async fn run_poller_loop(
     config: crate::Config,
+    pool: SqlitePool,
     uptime_state: UptimeState,
     uptime_tx: UptimeTx,
     mut refresh_rx: watch::Receiver<()>,
@@ -384,8 +1482,14 @@ async fn run_poller_loop(
     let mut policy = ReconnectPolicy::new();
 
     loop {
-        match connect_and_poll(&config, uptime_state.clone(), uptime_tx.clone(), &mut refresh_rx)
-            .await
+        match connect_and_poll(
+            &config,
+            &pool,
+            uptime_state.clone(),
+            uptime_tx.clone(),
+            &mut refresh_rx,
+        )
+        .await
         {
             Ok(()) => {
                 // Clean disconnect triggered by refresh signal
@@ -406,6 +1510,7 @@ async fn run_poller_loop(
 
 async fn connect_and_poll(
     config: &crate::Config,
+    pool: &SqlitePool,
     uptime_state: UptimeState,
     uptime_tx: UptimeTx,
     refresh_rx: &mut watch::Receiver<()>,
@@ -418,8 +1523,12 @@ async fn connect_and_poll(
     let ready_signal = ready.clone();
 
     // Clone state/tx for each handler closure (they need 'static + Send)
+    let pool_for_hb_list = pool.clone();
+    let config_for_hb_list = config.clone();
     let state_for_hb_list = uptime_state.clone();
     let tx_for_hb_list = uptime_tx.clone();
+    let pool_for_hb = pool.clone();
+    let config_for_hb = config.clone();
     let state_for_hb = uptime_state.clone();
     let tx_for_hb = uptime_tx.clone();
 
@@ -433,21 +1542,25 @@ async fn connect_and_poll(
             .boxed()
         })
         .on("heartbeatList", move |payload: Payload, _client| {
+            let pool = pool_for_hb_list.clone();
+            let config = config_for_hb_list.clone();
             let state = state_for_hb_list.clone();
             let tx = tx_for_hb_list.clone();
             async move {
                 if let Payload::Text(values) = payload {
-                    handle_heartbeat_list(values, state, tx).await;
+                    handle_heartbeat_list(values, pool, config, state, tx).await;
                 }
             }
             .boxed()
         })
         .on("heartbeat", move |payload: Payload, _client| {
+            let pool = pool_for_hb.clone();
+            let config = config_for_hb.clone();
             let state = state_for_hb.clone();
             let tx = tx_for_hb.clone();
             async move {
                 if let Payload::Text(values) = payload {
-                    handle_heartbeat(values, state, tx).await;
+                    handle_heartbeat(values, pool, config, state, tx).await;
                 }
             }
             .boxed()
@@ -527,7 +1640,13 @@ async fn connect_and_poll(
 ///
 /// Payload shape: `[{ "1": [{status, time, ping, msg}, ...], "2": [...] }]`
 /// Keys are monitor IDs as strings (JS object keys are always strings).
-async fn handle_heartbeat_list(values: Vec<Value>, state: UptimeState, tx: UptimeTx) {
+async fn handle_heartbeat_list(
+    values: Vec<Value>,
+    pool: SqlitePool,
+    config: crate::Config,
+    state: UptimeState,
+    tx: UptimeTx,
+) {
     let data = match values.into_iter().next() {
         Some(v) => v,
         None => return,
@@ -550,9 +1669,20 @@ async fn handle_heartbeat_list(values: Vec<Value>, state: UptimeState, tx: Uptim
             None => continue,
         };
 
-        let mut heartbeats: Vec<HeartbeatEntry> = beats_arr
-            .iter()
-            .filter_map(parse_heartbeat_entry)
+        // The full list goes to the database, only the last hour stays in memory
+        let all: Vec<HeartbeatEntry> = beats_arr.iter().filter_map(parse_heartbeat_entry).collect();
+        store_heartbeats(&pool, &config, kuma_id, &all).await;
+
+        let healthcheck_ids = match service::healthcheck::ids_for_kuma(&pool, kuma_id).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                error!("Kuma poller: failed to get healthchecks of monitor {kuma_id}: {e}");
+                continue;
+            }
+        };
+
+        let mut heartbeats: Vec<HeartbeatEntry> = all
+            .into_iter()
             .filter(|h| {
                 DateTime::parse_from_rfc3339(&h.time)
                     .map(|t| t.with_timezone(&Utc) > cutoff)
@@ -562,22 +1692,27 @@ async fn handle_heartbeat_list(values: Vec<Value>, state: UptimeState, tx: Uptim
 
         heartbeats.sort_by(|a, b| a.time.cmp(&b.time));
 
-        write.insert(
-            kuma_id,
-            MonitorUptime {
-                kuma_id,
-                heartbeats,
-            },
-        );
+        for healthcheck_id in healthcheck_ids {
+            write.insert(
+                healthcheck_id.clone(),
+                MonitorUptime {
+                    healthcheck_id,
+                    kuma_id: Some(kuma_id),
+                    heartbeats: heartbeats.clone(),
+                },
+            );
+        }
     }
     drop(write);
 
-    // Broadcast snapshot to all connected SSE clients
+    // Broadcast a snapshot to all connected SSE clients
     let snapshot = {
         let read = state.read().await;
-        read.clone()
+        read.iter()
+            .map(|(id, monitor)| (id.clone(), MonitorSummary::from(monitor)))
+            .collect()
     };
-    let _ = tx.send(UptimeEvent::Snapshot { monitors: snapshot });
+    tx.send(UptimeEvent::Snapshot { monitors: snapshot });
 
     info!(
         "Kuma poller: received heartbeat list for {} monitors",
@@ -589,7 +1724,13 @@ async fn handle_heartbeat_list(values: Vec<Value>, state: UptimeState, tx: Uptim
 ///
 /// Payload shape: `[{ monitorID: 42, status: 1, time: "...", ping: 120, msg: "" }]`
 /// Note: Kuma uses `monitorID` (capital D) here, not `monitorId`.
-async fn handle_heartbeat(values: Vec<Value>, state: UptimeState, tx: UptimeTx) {
+async fn handle_heartbeat(
+    values: Vec<Value>,
+    pool: SqlitePool,
+    config: crate::Config,
+    state: UptimeState,
+    tx: UptimeTx,
+) {
     let data = match values.into_iter().next() {
         Some(v) => v,
         None => return,
@@ -604,36 +1745,113 @@ async fn handle_heartbeat(values: Vec<Value>, state: UptimeState, tx: UptimeTx)
         None => return,
     };
 
+    let healthcheck_ids = match service::healthcheck::ids_for_kuma(&pool, kuma_id).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            error!("Kuma poller: failed to get healthchecks of monitor {kuma_id}: {e}");
+            return;
+        }
+    };
+
+    let mut is_new = false;
+    for healthcheck_id in healthcheck_ids {
+        if push_heartbeat(&state, &healthcheck_id, Some(kuma_id), &entry).await {
+            is_new = true;
+            tx.send(UptimeEvent::Update {
+                healthcheck_id,
+                kuma_id: Some(kuma_id),
+                entry: entry.clone(),
+            });
+        }
+    }
+
+    if is_new {
+        store_heartbeats(&pool, &config, kuma_id, std::slice::from_ref(&entry)).await;
+    }
+}
+
+/// Adds a heartbeat to the in-memory state of a healthcheck and drops the
+/// ones that fell out of the window. Returns whether it is new.
+pub(crate) async fn push_heartbeat(
+    state: &UptimeState,
+    healthcheck_id: &str,
+    kuma_id: Option<i32>,
+    entry: &HeartbeatEntry,
+) -> bool {
     let cutoff = Utc::now() - chrono::Duration::seconds(HEARTBEAT_WINDOW_SECS);
 
-    let is_new = {
-        let mut write = state.write().await;
-        let monitor = write.entry(kuma_id).or_insert(MonitorUptime {
+    let mut write = state.write().await;
+    let monitor = write
+        .entry(healthcheck_id.to_string())
+        .or_insert_with(|| MonitorUptime {
+            healthcheck_id: healthcheck_id.to_string(),
             kuma_id,
             heartbeats: Vec::new(),
         });
 
-        // Deduplicate: skip if we already have an entry with this timestamp.
-        // Zombie connections from incomplete disconnects can deliver the same
-        // heartbeat multiple times.
-        let is_new = !monitor.heartbeats.iter().any(|h| h.time == entry.time);
-        if is_new {
-            monitor.heartbeats.push(entry.clone());
-        }
+    // Deduplicate: skip if we already have an entry with this timestamp.
+    // Zombie connections from incomplete disconnects can deliver the same
+    // heartbeat multiple times.
+    let is_new = !monitor.heartbeats.iter().any(|h| h.time == entry.time);
+    if is_new {
+        monitor.heartbeats.push(entry.clone());
+    }
 
-        // Prune entries older than the window
-        monitor.heartbeats.retain(|h| {
-            DateTime::parse_from_rfc3339(&h.time)
-                .map(|t| t.with_timezone(&Utc) > cutoff)
-                .unwrap_or(true)
-        });
+    // Prune entries older than the window
+    monitor.heartbeats.retain(|h| {
+        DateTime::parse_from_rfc3339(&h.time)
+            .map(|t| t.with_timezone(&Utc) > cutoff)
+            .unwrap_or(true)
+    });
 
-        is_new
-    };
+    is_new
+}
 
-    if is_new {
-        let _ = tx.send(UptimeEvent::Update { kuma_id, entry });
+/// Stores heartbeats, updates the incidents of the monitor and notifies
+/// about the ones that were opened or resolved.
+async fn store_heartbeats(
+    pool: &SqlitePool,
+    config: &crate::Config,
+    kuma_id: i32,
+    entries: &[HeartbeatEntry],
+) {
+    if let Err(e) = service::heartbeat::store(pool, kuma_id, entries).await {
+        error!("Kuma poller: failed to store heartbeats of monitor {kuma_id}: {e}");
+        return;
     }
+
+    let incidents = match service::incident::detect(pool, kuma_id).await {
+        Ok(incidents) => incidents,
+        Err(e) => {
+            error!("Kuma poller: failed to update incidents of monitor {kuma_id}: {e}");
+            return;
+        }
+    };
+
+    crate::notify::spawn_incident_notifications(pool, config, incidents);
+}
+
+// ── Uptime rollups ──────────────────────────────────────────────────
+
+/// Spawns a task that rolls stored heartbeats up into hourly and daily
+/// buckets and deletes whatever is past its retention, once at startup and
+/// then every ten minutes.
+pub fn spawn_uptime_rollup(pool: SqlitePool, retention: UptimeRetention) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = service::heartbeat::rollup(&pool).await {
+                error!("Failed to roll up heartbeats: {e}");
+                continue;
+            }
+            match service::heartbeat::prune(&pool, retention).await {
+                Ok(0) => {}
+                Ok(n) => info!("Deleted {n} heartbeats and rollups past their retention"),
+                Err(e) => error!("Failed to delete old heartbeats: {e}"),
+            }
+        }
+    });
 }
 
 /// Parses a JSON value into a HeartbeatEntry.
@@ -663,3 +1881,150 @@ fn parse_heartbeat_entry(v: &Value) -> Option<HeartbeatEntry> {
         msg,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn healthcheck_from_monitor_converts_kuma_monitors() {
+        let (host, hc) = healthcheck_from_monitor(&json!({
+            "id": 7,
+            "name": "Website",
+            "type": "keyword",
+            "url": "https://WWW.example.org/health?full=1",
+            "method": "get",
+            "accepted_statuscodes": ["200-299"],
+            "keyword": "ok",
+            "invertKeyword": 0,
+            "active": 1,
+            "timeout": 48,
+            "authMethod": null
+        }))
+        .unwrap();
+        assert_eq!(host, "www.example.org");
+        assert_eq!(hc.kuma_id, Some(7));
+        assert_eq!((hc.kind.as_str(), hc.protocol.as_str()), ("http", "https"));
+        assert_eq!(
+            (hc.path.as_str(), hc.method.as_str()),
+            ("/health?full=1", "GET")
+        );
+        assert_eq!((hc.expected_status, hc.timeout_seconds), (200, 48));
+        assert_eq!(hc.expected_body.as_deref(), Some("ok"));
+        assert!(hc.is_enabled);
+
+        let (host, hc) = healthcheck_from_monitor(&json!({
+            "id": 8,
+            "name": "Resolves",
+            "type": "dns",
+            "hostname": "example.org",
+            "dns_resolve_type": "AAAA",
+            "dns_resolve_server": "9.9.9.9",
+            "port": 5353
+        }))
+        .unwrap();
+        assert_eq!(host, "example.org");
+        assert_eq!(hc.kind, "dns");
+        assert_eq!(hc.dns_record_type.as_deref(), Some("AAAA"));
+        assert_eq!(hc.dns_resolver.as_deref(), Some("9.9.9.9:5353"));
+
+        let push = healthcheck_from_monitor(&json!({"id": 9, "name": "Cron", "type": "push"}));
+        assert_eq!(push.unwrap_err(), "push monitors can't be imported");
+    }
+
+    #[test]
+    fn monitor_drift_ignores_how_kuma_stores_values() {
+        let expected = json!({
+            "active": true,
+            "authMethod": "null",
+            "headers": "{\"Accept\": \"text/html\"}",
+            "accepted_statuscodes": ["200"],
+            "timeout": 30,
+            "conditions": "[]"
+        });
+        let same = json!({
+            "active": 1,
+            "authMethod": null,
+            "headers": "{\n  \"Accept\": \"text/html\"\n}",
+            "accepted_statuscodes": ["200"],
+            "timeout": 30.0,
+            "conditions": []
+        });
+        assert!(monitor_drift(&expected, &same).is_empty());
+
+        let drifted = json!({
+            "active": 0,
+            "authMethod": null,
+            "headers": null,
+            "accepted_statuscodes": ["200-299"],
+            "timeout": 30
+        });
+        let fields: Vec<_> = monitor_drift(&expected, &drifted)
+            .into_iter()
+            .map(|c| c.field)
+            .collect();
+        assert_eq!(fields, ["accepted_statuscodes", "active", "headers"]);
+    }
+
+    #[test]
+    fn tag_changes_only_touches_managed_tags() {
+        let monitor = json!({
+            "id": 3,
+            "tags": [
+                {"tag_id": 1, "name": "environment", "value": "staging"},
+                {"tag_id": 2, "name": "stack", "value": "Rust"},
+                {"tag_id": 5, "name": "team", "value": "web"}
+            ]
+        });
+        let desired = [
+            ("environment", "production".to_string()),
+            ("stack", "Rust".to_string()),
+            ("stack", "Vue".to_string()),
+        ];
+
+        let (add, remove) = tag_changes(Some(&monitor), &desired);
+        assert_eq!(
+            add,
+            [
+                ("environment", "production".to_string()),
+                ("stack", "Vue".to_string())
+            ]
+        );
+        assert_eq!(remove, [(1, "staging".to_string())]);
+
+        let (add, remove) = tag_changes(None, &desired);
+        assert_eq!(add.len(), 3);
+        assert!(remove.is_empty());
+    }
+
+    #[test]
+    fn uptime_tx_replays_recent_events() {
+        let tx = UptimeTx::new(4);
+        let start = tx.last_id();
+        let update = |time: &str| UptimeEvent::Update {
+            healthcheck_id: "hc".into(),
+            kuma_id: None,
+            entry: HeartbeatEntry {
+                status: 1,
+                time: time.into(),
+                ping: None,
+                msg: None,
+            },
+        };
+
+        let first = tx.send(update("1"));
+        assert_eq!(first, start + 1);
+        assert_eq!(tx.since(start).unwrap().len(), 1);
+        // Unknown ids can't be resumed from
+        assert!(tx.since(first + 1).is_none());
+
+        for time in 2..=UPTIME_REPLAY_EVENTS + 1 {
+            tx.send(update(&time.to_string()));
+        }
+        // The first event was dropped
+        assert!(tx.since(start).is_none());
+        let missed = tx.since(first).unwrap();
+        assert_eq!(missed.len(), UPTIME_REPLAY_EVENTS);
+        assert_eq!(missed.last().unwrap().0, tx.last_id());
+    }
+}
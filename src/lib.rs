@@ -6,25 +6,33 @@ use tokio::sync::{RwLock, broadcast, watch};
 use tracing::info;
 
 mod api;
+pub mod changes;
 mod config;
 mod error;
 pub mod kuma;
 pub mod models;
+pub mod notify;
+pub mod oidc;
 mod openapi;
 pub mod outline;
 pub mod overview;
+pub mod rdap;
 mod routes;
+pub mod scheduler;
 mod service;
+#[cfg(test)]
+mod testing;
+pub mod tls;
 
-pub use config::Config;
+pub use config::{Config, OidcConfig, SmtpConfig, UptimeSource};
 pub use error::Error;
 pub use openapi::ApiDoc;
 pub use routes::router;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+use changes::ChangeTx;
 use kuma::{UptimeState, UptimeTx};
-use models::UptimeEvent;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -34,8 +42,12 @@ pub struct AppState {
     pub uptime_state: UptimeState,
     /// Broadcast channel for SSE fan-out.
     pub uptime_tx: UptimeTx,
+    /// Broadcast channel for the change feed.
+    pub change_tx: ChangeTx,
     /// Notifies the Kuma poller to reconnect after a sync.
     pub kuma_refresh_tx: watch::Sender<()>,
+    /// Wakes up the Kuma sync worker to work off its queue.
+    pub kuma_sync_tx: watch::Sender<()>,
 }
 
 impl AppState {
@@ -48,21 +60,40 @@ impl AppState {
 
         info!("Running migrations");
 
-        // Run migrations
-        sqlx::migrate!("./migrations").run(&pool).await?;
+        migrate(&pool).await?;
 
-        let (uptime_tx, _) = broadcast::channel::<UptimeEvent>(64);
+        let uptime_tx = UptimeTx::new(64);
+        let (change_tx, _) = broadcast::channel(256);
         let uptime_state: UptimeState = Arc::new(RwLock::new(HashMap::new()));
         let (kuma_refresh_tx, _) = watch::channel(());
+        let (kuma_sync_tx, _) = watch::channel(());
 
         let state = Self {
             pool,
             config,
             uptime_state,
             uptime_tx,
+            change_tx,
             kuma_refresh_tx,
+            kuma_sync_tx,
         };
 
         Ok(state)
     }
 }
+
+/// Runs the migrations with foreign keys off, so rebuilding a table doesn't
+/// cascade to the rows referring to it. A migration can't turn them off
+/// itself, SQLite ignores the pragma in the transaction it runs in.
+pub(crate) async fn migrate(pool: &SqlitePool) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    sqlx::query("PRAGMA foreign_keys = OFF")
+        .execute(&mut *conn)
+        .await?;
+    let migrated = sqlx::migrate!("./migrations").run(&mut *conn).await;
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&mut *conn)
+        .await?;
+    migrated?;
+    Ok(())
+}
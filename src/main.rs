@@ -14,13 +14,35 @@ async fn main() -> auto::Result<()> {
 
     let state = auto::AppState::new().await?;
 
-    info!("Starting Kuma uptime poller");
-    auto::kuma::spawn_kuma_poller(
-        state.config.clone(),
-        state.uptime_state.clone(),
-        state.uptime_tx.clone(),
-        state.kuma_refresh_tx.subscribe(),
-    );
+    if state.config.uptime_source.kuma() {
+        info!("Starting Kuma uptime poller");
+        auto::kuma::spawn_kuma_poller(
+            state.config.clone(),
+            state.pool.clone(),
+            state.uptime_state.clone(),
+            state.uptime_tx.clone(),
+            state.kuma_refresh_tx.subscribe(),
+        );
+
+        info!("Starting Kuma sync worker");
+        auto::kuma::spawn_kuma_sync_worker(state.clone(), state.kuma_sync_tx.subscribe());
+    }
+
+    if state.config.uptime_source.native() {
+        info!("Starting native healthcheck scheduler");
+        auto::scheduler::spawn_healthcheck_scheduler(
+            state.config.clone(),
+            state.pool.clone(),
+            state.uptime_state.clone(),
+            state.uptime_tx.clone(),
+        );
+    }
+
+    auto::changes::spawn_change_feed(state.pool.clone(), state.change_tx.clone());
+    auto::kuma::spawn_uptime_rollup(state.pool.clone(), state.config.uptime_retention);
+    auto::notify::spawn_domain_expiry_scan(state.pool.clone(), state.config.clone());
+    auto::rdap::spawn_domain_lookup_scan(state.pool.clone(), state.config.clone());
+    auto::tls::spawn_certificate_scan(state.pool.clone());
 
     info!("Starting server");
 
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, types::Json};
+use utoipa::ToSchema;
+
+/// Audit event - one create/update/delete/link/unlink performed on an entity
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub actor: Option<String>,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub entity_name: Option<String>,
+    pub action: String,
+    pub related_type: Option<String>,
+    pub related_id: Option<String>,
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<Json<Value>>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<Json<Value>>,
+    pub created_at: String,
+}
+
+/// The kind of mutation an audit event records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Link,
+    Unlink,
+    /// Brought back from the trash
+    Restore,
+    /// Permanently removed from the trash
+    Purge,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Link => "link",
+            AuditAction::Unlink => "unlink",
+            AuditAction::Restore => "restore",
+            AuditAction::Purge => "purge",
+        }
+    }
+}
+
+/// Filters for listing audit events
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct AuditFilters {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub actor: Option<String>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub action: Option<String>,
+    /// Only events at or after this timestamp (`YYYY-MM-DD HH:MM:SS`)
+    pub since: Option<String>,
+    /// Only events before this timestamp (`YYYY-MM-DD HH:MM:SS`)
+    pub until: Option<String>,
+}
+
+/// A change to an entity as it happens, streamed at `/api/events`. The id is
+/// the id of its audit event, without the before and after values.
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct ChangeEvent {
+    pub id: i64,
+    pub actor: Option<String>,
+    pub entity_type: String,
+    pub entity_id: String,
+    pub entity_name: Option<String>,
+    pub action: String,
+    pub related_type: Option<String>,
+    pub related_id: Option<String>,
+    /// Fields changed by an update
+    #[schema(value_type = Vec<String>)]
+    pub fields: Json<Vec<String>>,
+    pub created_at: String,
+}
+
+/// Filters for the change stream
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct ChangeStreamFilters {
+    /// Comma separated entity types, matching either side of a link
+    pub entity_types: Option<String>,
+    /// Only changes to this entity, or links to it
+    pub entity_id: Option<String>,
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// Access level of a person, ordered from least to most privileged
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    sqlx::Type,
+    ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Role {
+    /// Can browse everything
+    #[default]
+    Viewer,
+    /// Can also create, update, link and unlink entities
+    Editor,
+    /// Can also delete entities, trigger Kuma syncs and assign roles
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+/// The authenticated caller of a request
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct Identity {
+    /// The person this identity belongs to, `None` for the bootstrap admin token
+    pub person_id: Option<String>,
+    /// Name recorded as actor in the audit log and as `created_by`
+    pub name: String,
+    pub role: Role,
+}
+
+/// Personal API token - the secret itself is never stored or returned again
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiToken {
+    pub id: String,
+    pub person_id: String,
+    pub name: String,
+    pub prefix: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub created_at: String,
+}
+
+/// DTO for creating a new API token
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateApiToken {
+    pub name: String,
+    /// Person the token belongs to, defaults to the caller
+    pub person_id: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+/// A newly created API token, including the secret which is only shown once
+#[derive(Debug, Serialize, ToSchema)]
+pub struct NewApiToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    pub secret: String,
+}
+
+/// DTO for starting a cookie session with an API token
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateSession {
+    pub token: String,
+}
+
+/// A login started at the OIDC provider that hasn't come back yet
+#[derive(Debug, Clone, FromRow)]
+pub struct OidcLogin {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub redirect_to: String,
+}
+
+/// Which login methods the server offers
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuthConfig {
+    /// Single sign-on via `/api/auth/login` is available
+    pub oidc: bool,
+}
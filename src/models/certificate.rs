@@ -0,0 +1,28 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::types::Json;
+use utoipa::ToSchema;
+
+/// TLS certificate a domain serves on a port
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct Certificate {
+    pub domain_id: String,
+    pub port: i64,
+    pub subject: Option<String>,
+    pub issuer: Option<String>,
+    /// DNS names and IP addresses the certificate is valid for
+    #[schema(value_type = Vec<String>)]
+    pub sans: Json<Vec<String>>,
+    /// Serial number, as colon separated hex
+    pub serial: Option<String>,
+    pub not_before: Option<String>,
+    pub not_after: Option<String>,
+    /// Whether the chain up to a trusted root is valid for the domain,
+    /// unknown until a check succeeds
+    pub chain_valid: Option<bool>,
+    /// Why the chain is invalid, e.g. an unknown issuer or another name
+    pub chain_error: Option<String>,
+    /// Why the last check failed to connect
+    pub error: Option<String>,
+    pub checked_at: String,
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Query parameters for a CSV import
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct CsvImportParams {
+    /// Import the valid rows even if other rows have errors
+    #[serde(default)]
+    pub partial: bool,
+}
+
+/// Multipart body of a CSV import
+#[derive(Debug, ToSchema)]
+#[allow(unused)]
+pub struct CsvImportForm {
+    /// The CSV file, with a header row
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+    /// JSON object mapping CSV headers to field names, e.g.
+    /// `{"Application": "name", "Cluster": "infra"}`. Map a header to `""`
+    /// to ignore that column. Headers that aren't mapped must match a field
+    /// name themselves.
+    pub mapping: Option<String>,
+}
+
+/// Outcome of one CSV row
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CsvRowReport {
+    /// Line in the CSV file, the header being line 1
+    pub line: usize,
+    /// ID of the created entity
+    pub id: Option<String>,
+    pub errors: Vec<String>,
+}
+
+/// Result of a CSV import
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CsvImportReport {
+    /// `false` if nothing was written because some rows have errors or one
+    /// could not be created
+    pub committed: bool,
+    pub total: usize,
+    pub imported: usize,
+    pub failed: usize,
+    pub rows: Vec<CsvRowReport>,
+}
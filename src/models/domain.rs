@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
 
+use super::Certificate;
+
 /// Domain entity - DNS records
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Domain {
@@ -15,6 +17,7 @@ pub struct Domain {
     pub target_service_id: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub version: i64,
     pub created_by: Option<String>,
 }
 
@@ -40,6 +43,8 @@ pub struct UpdateDomain {
     pub notes: Option<String>,
     pub target_application_id: Option<String>,
     pub target_service_id: Option<String>,
+    /// Version the change is based on, like an `If-Match` header
+    pub version: Option<i64>,
 }
 
 /// Domain relation for application detail view
@@ -81,6 +86,31 @@ pub struct DomainWithRelations {
     pub target_application_name: Option<String>,
     pub target_service_name: Option<String>,
     pub applications: Vec<ApplicationDomainRelation>,
+    /// Last RDAP lookup, if any
+    pub lookup: Option<DomainLookup>,
+    /// TLS certificates served for the domain, by port
+    pub certificates: Vec<Certificate>,
+}
+
+/// Registration data of a domain's registrable parent, from RDAP
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct DomainLookup {
+    /// Registrable domain that was looked up, e.g. `example.org` for
+    /// `www.example.org`
+    pub name: String,
+    /// RDAP URL that answered
+    pub source: Option<String>,
+    pub registrar: Option<String>,
+    /// Registrable domain of the nameservers, e.g. `cloudflare.com`
+    pub dns_provider: Option<String>,
+    /// Expiry date (`YYYY-MM-DD`)
+    pub expires_at: Option<String>,
+    /// Why the last lookup failed
+    pub error: Option<String>,
+    pub checked_at: String,
+    /// Fields entered by hand that differ from the lookup
+    #[sqlx(skip)]
+    pub mismatches: Vec<String>,
 }
 
 /// Application relation for domain detail view
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// Dependency graph of applications, services, infra and domains
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct Graph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// An entity in the dependency graph
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct GraphNode {
+    pub id: String,
+    /// application, service, infra or domain
+    pub entity_type: String,
+    pub name: String,
+    /// Environment of applications and services
+    pub environment: Option<String>,
+}
+
+/// A dependency between two entities, pointing from the dependent entity to
+/// what it depends on
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    /// `uses` (application → service), `runs_on` (application or service →
+    /// infra), `has_domain` (application → domain) or `targets` (domain →
+    /// application or service)
+    pub relation: String,
+}
+
+/// Output format of the graph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    #[default]
+    Json,
+    /// Graphviz DOT
+    Dot,
+    Mermaid,
+}
+
+/// Query parameters for the graph
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct GraphParams {
+    /// Only the part of the graph connected to this entity
+    pub root: Option<String>,
+    /// Maximum number of edges between the root and any node
+    pub depth: Option<u32>,
+    #[serde(default)]
+    pub format: GraphFormat,
+}
@@ -3,7 +3,15 @@ use sqlx::FromRow;
 use std::collections::HashMap;
 use utoipa::ToSchema;
 
-/// Healthcheck entity - HTTP checks for monitoring endpoints
+use super::FieldChange;
+
+/// Kinds of healthchecks
+pub const HEALTHCHECK_KINDS: &[&str] = &["http", "tcp", "dns", "tls"];
+
+/// Record types a DNS healthcheck can resolve
+pub const DNS_RECORD_TYPES: &[&str] = &["A", "AAAA", "CNAME", "MX", "NS", "TXT"];
+
+/// Healthcheck entity - HTTP, TCP, DNS and TLS checks for monitoring endpoints
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Healthcheck {
     pub id: String,
@@ -28,9 +36,25 @@ pub struct Healthcheck {
     pub request_body: Option<String>,
     pub http_auth_user: Option<String>,
     pub http_auth_pass: Option<String>,
+    /// http, tcp, dns or tls
+    pub kind: String,
+    /// Port of a TCP or TLS check, TLS defaults to 443
+    pub port: Option<i32>,
+    /// Record type of a DNS check, defaults to A
+    pub dns_record_type: Option<String>,
+    /// DNS server to ask instead of the system resolver
+    pub dns_resolver: Option<String>,
+    /// A TLS check fails when the certificate expires within this many days,
+    /// defaults to 14
+    pub tls_expiry_days: Option<i32>,
+    /// JSONPath into the response body of an HTTP check, e.g. `$.status`
+    pub json_path: Option<String>,
+    /// Value at `json_path`, any value will do if not set
+    pub json_expected_value: Option<String>,
     pub kuma_dirty: bool,
     pub created_at: String,
     pub updated_at: String,
+    pub version: i64,
     pub created_by: Option<String>,
 }
 
@@ -68,6 +92,14 @@ pub struct CreateHealthcheck {
     pub request_body: Option<String>,
     pub http_auth_user: Option<String>,
     pub http_auth_pass: Option<String>,
+    #[serde(default = "default_kind")]
+    pub kind: String,
+    pub port: Option<i32>,
+    pub dns_record_type: Option<String>,
+    pub dns_resolver: Option<String>,
+    pub tls_expiry_days: Option<i32>,
+    pub json_path: Option<String>,
+    pub json_expected_value: Option<String>,
 }
 
 /// DTO for updating a healthcheck
@@ -94,6 +126,19 @@ pub struct UpdateHealthcheck {
     pub request_body: Option<String>,
     pub http_auth_user: Option<String>,
     pub http_auth_pass: Option<String>,
+    pub kind: Option<String>,
+    pub port: Option<i32>,
+    pub dns_record_type: Option<String>,
+    pub dns_resolver: Option<String>,
+    pub tls_expiry_days: Option<i32>,
+    pub json_path: Option<String>,
+    pub json_expected_value: Option<String>,
+    /// Version the change is based on, like an `If-Match` header
+    pub version: Option<i64>,
+}
+
+fn default_kind() -> String {
+    "http".to_string()
 }
 
 fn default_protocol() -> String {
@@ -172,6 +217,9 @@ pub struct HealthcheckExecuteResult {
     pub status_code: Option<u16>,
     pub response_time_ms: u64,
     pub body_match: Option<bool>,
+    /// What a check found: the JSONPath value, the resolved records or the
+    /// certificate expiry
+    pub detail: Option<String>,
     pub error: Option<String>,
     pub executed_at: String,
 }
@@ -189,11 +237,112 @@ pub struct KumaMonitor {
     pub target_name: String,
 }
 
+/// Differences between the healthchecks in Auto and the monitors in Kuma
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KumaDiff {
+    /// Kuma monitors that no healthcheck refers to
+    pub missing: Vec<KumaMissingMonitor>,
+    /// Healthchecks whose Kuma monitor differs from what a sync would push
+    pub drifted: Vec<KumaDrift>,
+    /// Healthchecks whose `kuma_id` doesn't exist in Kuma
+    pub orphaned: Vec<KumaOrphan>,
+}
+
+/// A Kuma monitor without healthcheck
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KumaMissingMonitor {
+    pub kuma_id: i32,
+    pub name: String,
+    /// Kuma monitor type, e.g. http, keyword or port
+    pub monitor_type: String,
+    /// Host the monitor checks
+    pub hostname: Option<String>,
+    /// Domain with that host as fqdn
+    pub domain_id: Option<String>,
+    /// Why the monitor can't be imported
+    pub import_error: Option<String>,
+}
+
+/// A healthcheck whose Kuma monitor has drifted
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KumaDrift {
+    pub healthcheck_id: String,
+    pub name: String,
+    pub kuma_id: i32,
+    /// Monitor fields, `before` is what Kuma has and `after` what a sync
+    /// would set
+    pub changes: Vec<FieldChange>,
+}
+
+/// A healthcheck referring to a Kuma monitor that doesn't exist
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KumaOrphan {
+    pub healthcheck_id: String,
+    pub name: String,
+    pub kuma_id: i32,
+}
+
+/// Pending change to the Kuma monitor of a healthcheck
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct KumaSyncItem {
+    pub healthcheck_id: String,
+    /// push (add or edit the monitor), pause (healthcheck in the trash) or
+    /// delete (healthcheck purged)
+    pub operation: String,
+    pub kuma_id: Option<i32>,
+    pub name: String,
+    /// Failed attempts so far
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: String,
+    pub created_at: String,
+    pub updated_at: String,
+    /// `kuma_version` of the healthcheck when it was (last) queued
+    pub kuma_version: i64,
+}
+
+/// Which Kuma monitors to import as healthchecks
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct KumaImport {
+    /// Monitors to import, all missing ones if not set
+    pub kuma_ids: Option<Vec<i32>>,
+}
+
+/// Result of importing Kuma monitors
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KumaImportResult {
+    pub created: Vec<Healthcheck>,
+    pub skipped: Vec<KumaImportSkipped>,
+}
+
+/// A Kuma monitor that wasn't imported
+#[derive(Debug, Serialize, ToSchema)]
+pub struct KumaImportSkipped {
+    pub kuma_id: i32,
+    pub name: Option<String>,
+    pub reason: String,
+}
+
 impl HealthcheckWithRelations {
     pub fn url(&self) -> String {
-        format!(
-            "{}://{}{}",
-            self.healthcheck.protocol, self.domain_fqdn, self.healthcheck.path
-        )
+        let hc = &self.healthcheck;
+        match hc.kind.as_str() {
+            "tcp" | "tls" => format!("{}://{}:{}", hc.kind, self.domain_fqdn, self.port()),
+            "dns" => format!("dns://{}", self.domain_fqdn),
+            _ => format!("{}://{}{}", hc.protocol, self.domain_fqdn, hc.path),
+        }
+    }
+
+    /// Port a TCP or TLS check connects to
+    pub fn port(&self) -> u16 {
+        let default = if self.healthcheck.kind == "tls" {
+            443
+        } else {
+            0
+        };
+        self.healthcheck
+            .port
+            .and_then(|p| u16::try_from(p).ok())
+            .unwrap_or(default)
     }
 }
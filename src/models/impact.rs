@@ -0,0 +1,37 @@
+use serde::Serialize;
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// Everything affected when an infra or service goes down
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImpactReport {
+    /// The infra or service that goes down
+    pub root: ImpactEntity,
+    pub services: Vec<ImpactEntity>,
+    pub applications: Vec<ImpactEntity>,
+    pub domains: Vec<ImpactEntity>,
+    pub healthchecks: Vec<ImpactEntity>,
+    /// Owners and maintainers of the affected applications
+    pub people: Vec<ImpactContact>,
+}
+
+/// An affected entity
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct ImpactEntity {
+    pub id: String,
+    pub entity_type: String,
+    pub name: String,
+    /// Environment of applications and services
+    pub environment: Option<String>,
+}
+
+/// Someone to notify about an impact
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImpactContact {
+    pub id: String,
+    pub name: String,
+    pub email: Option<String>,
+    /// The affected applications this person owns or maintains, with the
+    /// contribution type, e.g. `web (owner)`
+    pub applications: Vec<String>,
+}
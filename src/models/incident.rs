@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::Json};
+use utoipa::ToSchema;
+
+/// A period in which a healthcheck was down
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct Incident {
+    pub id: String,
+    pub healthcheck_id: String,
+    pub healthcheck_name: String,
+    pub application_id: Option<String>,
+    pub application_name: Option<String>,
+    pub service_id: Option<String>,
+    pub service_name: Option<String>,
+    /// Time of the first down heartbeat
+    pub started_at: String,
+    /// Time of the first up heartbeat after it, if the healthcheck recovered
+    pub resolved_at: Option<String>,
+    /// Seconds until it was resolved, or until now if it is still open
+    pub duration_secs: i64,
+    /// Message of the first down heartbeat
+    pub msg: Option<String>,
+    /// FQDNs of the healthcheck's domain and of the domains of its
+    /// application or service
+    #[schema(value_type = Vec<String>)]
+    pub domains: Json<Vec<String>>,
+}
+
+/// Whether an incident is still going on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IncidentStatus {
+    Open,
+    Resolved,
+}
+
+/// Filters for listing incidents
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct IncidentFilters {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub status: Option<IncidentStatus>,
+    pub healthcheck_id: Option<String>,
+    pub application_id: Option<String>,
+    pub service_id: Option<String>,
+    /// Only incidents that started at or after this timestamp (`YYYY-MM-DD HH:MM:SS`)
+    pub since: Option<String>,
+    /// Only incidents that started before this timestamp (`YYYY-MM-DD HH:MM:SS`)
+    pub until: Option<String>,
+}
@@ -13,6 +13,7 @@ pub struct Infra {
     pub infra_type: String,
     pub created_at: String,
     pub updated_at: String,
+    pub version: i64,
     pub created_by: Option<String>,
 }
 
@@ -32,6 +33,8 @@ pub struct UpdateInfra {
     pub description: Option<String>,
     #[serde(rename = "type")]
     pub infra_type: Option<String>,
+    /// Version the change is based on, like an `If-Match` header
+    pub version: Option<i64>,
 }
 
 /// Infra relation for embedding in Application/Service detail views
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::Json};
+use utoipa::ToSchema;
+
+/// Planned downtime of an infra item, synced to a Kuma maintenance window
+/// that covers the monitors of everything depending on it
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct Maintenance {
+    pub id: String,
+    pub infra_id: String,
+    pub infra_name: Option<String>,
+    pub title: String,
+    pub description: Option<String>,
+    /// Start in UTC (`YYYY-MM-DD HH:MM:SS`)
+    pub starts_at: String,
+    /// End in UTC (`YYYY-MM-DD HH:MM:SS`)
+    pub ends_at: String,
+    pub kuma_id: Option<i32>,
+    /// Monitors last added to the Kuma maintenance window
+    #[schema(value_type = Vec<i32>)]
+    pub kuma_monitors: Json<Vec<i32>>,
+    pub kuma_dirty: bool,
+    /// Failed syncs to Kuma so far
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: String,
+    #[serde(skip)]
+    pub deleted_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub version: i64,
+    pub created_by: Option<String>,
+}
+
+/// DTO for scheduling maintenance
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateMaintenance {
+    pub infra_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// Start in UTC (`YYYY-MM-DD HH:MM:SS`)
+    pub starts_at: String,
+    /// End in UTC (`YYYY-MM-DD HH:MM:SS`)
+    pub ends_at: String,
+}
+
+/// DTO for updating maintenance
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct UpdateMaintenance {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+    /// Version the change is based on, like an `If-Match` header
+    pub version: Option<i64>,
+}
+
+/// Filters for listing maintenance
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct MaintenanceFilters {
+    pub infra_id: Option<String>,
+    /// Only maintenance that hasn't ended yet
+    #[serde(default)]
+    pub upcoming: bool,
+}
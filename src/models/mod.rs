@@ -1,23 +1,47 @@
 mod application;
+mod audit;
+mod auth;
+mod certificate;
+mod csv_import;
 mod domain;
+mod graph;
 mod healthcheck;
+mod impact;
+mod incident;
 mod infra;
+mod maintenance;
 mod network_share;
 mod note;
+mod notification;
 mod person;
+mod revision;
 mod service;
 mod stack;
+mod transfer;
+mod trash;
 mod uptime;
 
 pub use application::*;
+pub use audit::*;
+pub use auth::*;
+pub use certificate::*;
+pub use csv_import::*;
 pub use domain::*;
+pub use graph::*;
 pub use healthcheck::*;
+pub use impact::*;
+pub use incident::*;
 pub use infra::*;
+pub use maintenance::*;
 pub use network_share::*;
 pub use note::*;
+pub use notification::*;
 pub use person::*;
+pub use revision::*;
 pub use service::*;
 pub use stack::*;
+pub use transfer::*;
+pub use trash::*;
 pub use uptime::*;
 
 use serde::{Deserialize, Serialize};
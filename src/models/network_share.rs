@@ -15,6 +15,7 @@ pub struct NetworkShare {
     pub notes: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub version: i64,
     pub created_by: Option<String>,
 }
 
@@ -42,6 +43,8 @@ pub struct UpdateNetworkShare {
     pub purpose: Option<String>,
     pub status: Option<String>,
     pub notes: Option<String>,
+    /// Version the change is based on, like an `If-Match` header
+    pub version: Option<i64>,
 }
 
 fn default_share_type() -> String {
@@ -15,6 +15,7 @@ pub struct Note {
     pub is_pinned: bool,
     pub created_at: String,
     pub updated_at: String,
+    pub version: i64,
     pub created_by: Option<String>,
 }
 
@@ -40,6 +41,8 @@ pub struct UpdateNote {
     pub note_type: Option<String>,
     pub url: Option<String>,
     pub is_pinned: Option<bool>,
+    /// Version the change is based on, like an `If-Match` header
+    pub version: Option<i64>,
 }
 
 fn default_note_type() -> String {
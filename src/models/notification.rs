@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::Json};
+use utoipa::ToSchema;
+
+/// Kinds of notification channels
+pub const NOTIFICATION_KINDS: &[&str] = &["webhook", "email", "slack"];
+
+/// Events a notification channel can subscribe to
+pub const NOTIFICATION_EVENTS: &[&str] = &["down", "up", "domain_expiring"];
+
+/// Notification channel - where to send notifications about healthchecks
+/// going down or up and about expiring domains
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct NotificationChannel {
+    pub id: String,
+    pub name: String,
+    /// webhook, email or slack (also for Mattermost)
+    pub kind: String,
+    /// Webhook or Slack incoming webhook URL
+    pub url: Option<String>,
+    /// Address that email notifications are sent to, next to the owners and
+    /// maintainers of the affected applications
+    pub email: Option<String>,
+    /// down, up and/or domain_expiring
+    #[schema(value_type = Vec<String>)]
+    pub events: Json<Vec<String>>,
+    pub is_enabled: bool,
+    pub created_at: String,
+    pub updated_at: String,
+    pub version: i64,
+    pub created_by: Option<String>,
+}
+
+/// DTO for creating a new notification channel
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateNotificationChannel {
+    pub name: String,
+    pub kind: String,
+    pub url: Option<String>,
+    pub email: Option<String>,
+    #[serde(default = "default_events")]
+    pub events: Vec<String>,
+    #[serde(default = "default_enabled")]
+    pub is_enabled: bool,
+}
+
+/// DTO for updating a notification channel
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateNotificationChannel {
+    pub name: Option<String>,
+    pub kind: Option<String>,
+    pub url: Option<String>,
+    pub email: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub is_enabled: Option<bool>,
+    /// Version the change is based on, like an `If-Match` header
+    pub version: Option<i64>,
+}
+
+fn default_events() -> Vec<String> {
+    NOTIFICATION_EVENTS.iter().map(|e| e.to_string()).collect()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// A notification sent, or attempted, through a channel
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct Notification {
+    pub id: i64,
+    pub channel_id: String,
+    pub channel_name: String,
+    /// down, up, domain_expiring or test
+    pub event: String,
+    /// What the notification is about, e.g. an incident ID
+    pub subject_key: String,
+    pub title: String,
+    /// Email addresses it was sent to
+    #[schema(value_type = Vec<String>)]
+    pub recipients: Json<Vec<String>>,
+    /// Why it could not be delivered
+    pub error: Option<String>,
+    pub created_at: String,
+}
+
+/// Filters for listing sent notifications
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct NotificationFilters {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub channel_id: Option<String>,
+    pub event: Option<String>,
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use utoipa::ToSchema;
 
+use super::Role;
+
 /// Person entity - developers, maintainers, support contacts
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Person {
@@ -13,8 +15,11 @@ pub struct Person {
     pub phone: Option<String>,
     pub is_active: bool,
     pub notes: Option<String>,
+    /// Access level when this person logs in
+    pub access_role: Role,
     pub created_at: String,
     pub updated_at: String,
+    pub version: i64,
     pub created_by: Option<String>,
 }
 
@@ -29,6 +34,8 @@ pub struct CreatePerson {
     #[serde(default = "default_active")]
     pub is_active: bool,
     pub notes: Option<String>,
+    /// Only admins may set this, defaults to viewer
+    pub access_role: Option<Role>,
 }
 
 /// DTO for updating a person
@@ -41,6 +48,10 @@ pub struct UpdatePerson {
     pub phone: Option<String>,
     pub is_active: Option<bool>,
     pub notes: Option<String>,
+    /// Only admins may set this
+    pub access_role: Option<Role>,
+    /// Version the change is based on, like an `If-Match` header
+    pub version: Option<i64>,
 }
 
 fn default_active() -> bool {
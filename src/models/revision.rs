@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, types::Json};
+use utoipa::ToSchema;
+
+/// Full snapshot of an entity after a create or update
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct Revision {
+    pub entity_type: String,
+    pub entity_id: String,
+    /// Per entity revision number, starting at 1
+    pub revision: i64,
+    pub actor: Option<String>,
+    #[schema(value_type = Object)]
+    pub snapshot: Json<Value>,
+    pub created_at: String,
+}
+
+/// Query parameters for diffing a revision
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct RevisionDiffParams {
+    /// Revision to compare with, defaults to the one before
+    pub against: Option<i64>,
+}
+
+/// Changes between two revisions of an entity
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RevisionDiff {
+    pub entity_type: String,
+    pub entity_id: String,
+    /// `None` when diffing the first revision
+    pub from: Option<i64>,
+    pub to: i64,
+    pub changes: Vec<FieldChange>,
+}
+
+/// One field that differs between two revisions
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldChange {
+    pub field: String,
+    #[schema(value_type = Object)]
+    pub before: Value,
+    #[schema(value_type = Object)]
+    pub after: Value,
+}
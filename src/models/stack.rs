@@ -10,6 +10,7 @@ pub struct Stack {
     pub notes: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub version: i64,
 }
 
 /// DTO for creating a new stack
@@ -24,6 +25,8 @@ pub struct CreateStack {
 pub struct UpdateStack {
     pub name: Option<String>,
     pub notes: Option<String>,
+    /// Version the change is based on, like an `If-Match` header
+    pub version: Option<i64>,
 }
 
 /// Stack relation for application detail view
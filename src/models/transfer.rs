@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+
+/// Value of `format` in every export document
+pub const EXPORT_FORMAT: &str = "auto-export";
+
+/// Version of the export document layout, bumped on incompatible changes
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Full dump of the inventory: every entity and junction table, row by row
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportDocument {
+    /// Always `auto-export`
+    pub format: String,
+    /// Layout version of the document
+    pub version: u32,
+    /// Version of Auto that produced the document
+    #[serde(default)]
+    pub app_version: Option<String>,
+    #[serde(default)]
+    pub exported_at: Option<String>,
+    /// Rows per table name, each row an object of column name to value
+    #[schema(value_type = Object)]
+    pub tables: BTreeMap<String, Vec<Map<String, Value>>>,
+}
+
+/// Query parameters for importing an export document
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct ImportParams {
+    /// Only report what would change, without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// What an import changed (or would change) in one table
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TableImportReport {
+    pub table: String,
+    /// IDs of new rows, `left_id/right_id` for junction tables
+    pub created: Vec<String>,
+    /// IDs of existing rows whose values differ
+    pub updated: Vec<String>,
+    /// Number of rows that are already identical
+    pub unchanged: usize,
+}
+
+/// Result of an import
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportReport {
+    /// `true` if nothing was written
+    pub dry_run: bool,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub tables: Vec<TableImportReport>,
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
+
+/// A deleted entity waiting in the trash to be restored or purged
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct TrashItem {
+    /// application, service, infra, domain, person, network_share, stack or healthcheck
+    pub entity_type: String,
+    pub id: String,
+    pub name: String,
+    pub deleted_at: String,
+    pub deleted_by: Option<String>,
+    /// Links and dependents that come back when the item is restored
+    #[sqlx(skip)]
+    pub relations: Vec<TrashRelation>,
+}
+
+/// An entity related to a trashed item
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct TrashRelation {
+    pub entity_type: String,
+    pub id: String,
+    pub name: String,
+    /// Deleted along with the item (a domain or healthcheck belonging to it),
+    /// as opposed to a link to an entity that still exists
+    pub cascaded: bool,
+}
+
+/// Filters for listing the trash
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct TrashFilters {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub entity_type: Option<String>,
+}
@@ -1,12 +1,13 @@
 use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
 use utoipa::ToSchema;
 
 /// How many seconds of heartbeat history to keep in memory (1 hour).
 pub const HEARTBEAT_WINDOW_SECS: i64 = 3600;
 
-/// A single heartbeat record from Kuma.
+/// A single heartbeat record, from Kuma or the native scheduler.
 ///
 /// Status codes: 1 = up, 0 = down, 2 = pending, 3 = maintenance.
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -17,25 +18,204 @@ pub struct HeartbeatEntry {
     pub msg: Option<String>,
 }
 
-/// In-memory uptime state for one Kuma monitor, capped to the last hour.
+/// In-memory uptime state for one healthcheck, capped to the last hour.
 #[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct MonitorUptime {
-    pub kuma_id: i32,
+    pub healthcheck_id: String,
+    /// Kuma monitor the heartbeats come from, if not run natively
+    pub kuma_id: Option<i32>,
     pub heartbeats: Vec<HeartbeatEntry>,
 }
 
-/// Events sent over SSE to Vue clients.
+/// Heartbeats per monitor in a snapshot, the rest is loaded on demand.
+pub const SUMMARY_BEATS: usize = 45;
+
+/// Compact live state of one healthcheck, as sent in snapshots.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MonitorSummary {
+    pub healthcheck_id: String,
+    pub kuma_id: Option<i32>,
+    /// Status of the latest heartbeat
+    pub status: Option<i32>,
+    /// Up and down heartbeats in the last hour
+    pub up: usize,
+    pub down: usize,
+    /// Percentage of up heartbeats out of up and down ones in the last hour
+    pub uptime_percent: Option<f64>,
+    /// Response time of the latest heartbeat, in milliseconds
+    pub ping: Option<i32>,
+    /// The last `SUMMARY_BEATS` heartbeats, oldest first
+    pub heartbeats: Vec<HeartbeatEntry>,
+}
+
+impl From<&MonitorUptime> for MonitorSummary {
+    fn from(monitor: &MonitorUptime) -> Self {
+        let latest = monitor.heartbeats.last();
+        let up = monitor.heartbeats.iter().filter(|h| h.status == 1).count();
+        let down = monitor.heartbeats.iter().filter(|h| h.status == 0).count();
+        let skip = monitor.heartbeats.len().saturating_sub(SUMMARY_BEATS);
+        Self {
+            healthcheck_id: monitor.healthcheck_id.clone(),
+            kuma_id: monitor.kuma_id,
+            status: latest.map(|h| h.status),
+            up,
+            down,
+            uptime_percent: (up + down > 0).then(|| up as f64 * 100.0 / (up + down) as f64),
+            ping: latest.and_then(|h| h.ping),
+            heartbeats: monitor.heartbeats[skip..].to_vec(),
+        }
+    }
+}
+
+/// Events sent over SSE to Vue clients, numbered by their SSE event id.
 ///
-/// `Snapshot` is sent once when a client connects (full current state).
-/// `Update` is sent whenever a new heartbeat arrives for one monitor.
+/// `Snapshot` is sent when a client connects without being able to resume,
+/// and after the Kuma poller (re)loaded all heartbeats. `Update` is sent
+/// whenever a new heartbeat arrives for one healthcheck. Monitors are keyed
+/// by healthcheck ID.
 #[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum UptimeEvent {
     Snapshot {
-        monitors: HashMap<i32, MonitorUptime>,
+        monitors: HashMap<String, MonitorSummary>,
     },
     Update {
-        kuma_id: i32,
+        healthcheck_id: String,
+        kuma_id: Option<i32>,
         entry: HeartbeatEntry,
     },
 }
+
+/// Which healthchecks to stream uptime events of, all when not set
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct UptimeStreamFilters {
+    /// Comma separated Kuma monitor ids
+    pub kuma_ids: Option<String>,
+    pub application_id: Option<String>,
+    pub service_id: Option<String>,
+}
+
+/// Period to compute uptime statistics over, ending now
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum UptimeRange {
+    #[default]
+    #[serde(rename = "24h")]
+    Day,
+    #[serde(rename = "7d")]
+    Week,
+    #[serde(rename = "30d")]
+    Month,
+    #[serde(rename = "90d")]
+    Quarter,
+}
+
+impl UptimeRange {
+    pub fn days(&self) -> i64 {
+        match self {
+            UptimeRange::Day => 1,
+            UptimeRange::Week => 7,
+            UptimeRange::Month => 30,
+            UptimeRange::Quarter => 90,
+        }
+    }
+}
+
+/// How long heartbeats and their rollups are kept, in days
+#[derive(Debug, Clone, Copy)]
+pub struct UptimeRetention {
+    pub raw_days: i64,
+    pub hourly_days: i64,
+    pub daily_days: i64,
+}
+
+/// Where uptime numbers come from: the heartbeats themselves or hourly or
+/// daily rollups of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum UptimeResolution {
+    Raw,
+    Hour,
+    Day,
+}
+
+impl UptimeResolution {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UptimeResolution::Raw => "raw",
+            UptimeResolution::Hour => "hour",
+            UptimeResolution::Day => "day",
+        }
+    }
+}
+
+/// Query parameters for uptime statistics
+#[derive(Debug, Deserialize, Default, ToSchema)]
+pub struct UptimeParams {
+    #[serde(default)]
+    pub range: UptimeRange,
+}
+
+/// Uptime statistics of one healthcheck, from the stored Kuma heartbeats
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UptimeStats {
+    pub healthcheck_id: String,
+    pub healthcheck_name: String,
+    pub range: UptimeRange,
+    /// Raw heartbeats when they are still kept for the whole range,
+    /// otherwise the finest rollup that is
+    pub resolution: UptimeResolution,
+    /// Start of the range (UTC, `YYYY-MM-DD HH:MM:SS`)
+    pub since: String,
+    pub heartbeats: i64,
+    pub up: i64,
+    pub down: i64,
+    /// Percentage of up heartbeats out of up and down ones; pending and
+    /// maintenance heartbeats don't count. `None` without heartbeats.
+    pub uptime_percent: Option<f64>,
+    /// Response times in milliseconds
+    pub ping_mean: Option<f64>,
+    pub ping_min: Option<i32>,
+    pub ping_max: Option<i32>,
+    /// Percentiles are only available from raw heartbeats
+    pub ping_p50: Option<i32>,
+    pub ping_p95: Option<i32>,
+    pub ping_p99: Option<i32>,
+    /// Periods the healthcheck was down, oldest first. From rollups these
+    /// are whole hours or days with down heartbeats.
+    pub incidents: Vec<DowntimeSpan>,
+}
+
+/// Heartbeat counts and response times per hour or day, for charts
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UptimeHistory {
+    pub healthcheck_id: String,
+    pub range: UptimeRange,
+    /// The coarsest rollup with enough buckets for the range
+    pub resolution: UptimeResolution,
+    pub buckets: Vec<UptimeBucket>,
+}
+
+/// Aggregated heartbeats of one hour or day
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct UptimeBucket {
+    /// UTC start of the hour or day
+    pub bucket: String,
+    pub up: i64,
+    pub down: i64,
+    pub pending: i64,
+    pub maintenance: i64,
+    pub ping_min: Option<i32>,
+    pub ping_avg: Option<f64>,
+    pub ping_max: Option<i32>,
+}
+
+/// A period from the first down heartbeat until the next up heartbeat
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct DowntimeSpan {
+    pub start: String,
+    /// `None` while still down
+    pub end: Option<String>,
+    pub duration_secs: i64,
+    /// Message of the first down heartbeat
+    pub msg: Option<String>,
+}
@@ -0,0 +1,142 @@
+/*!
+ * Delivery of notifications to webhooks, Slack or Mattermost incoming
+ * webhooks and email.
+ */
+
+use std::time::Duration;
+
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use serde::Serialize;
+use serde_json::{Value, json};
+use sqlx::SqlitePool;
+use tracing::error;
+
+use crate::models::Incident;
+use crate::{Config, Error, Result, service};
+
+/// A notification about one event, before it is sent to a channel.
+#[derive(Debug, Clone, Serialize)]
+pub struct Message {
+    /// down, up, domain_expiring or test
+    pub event: String,
+    /// What the message is about, so a channel gets it only once
+    #[serde(skip)]
+    pub subject_key: String,
+    pub title: String,
+    pub text: String,
+    /// Page of the healthcheck or domain in the web UI
+    pub url: Option<String>,
+    /// The incident or domain itself
+    pub data: Value,
+}
+
+/// Send a message through a channel of the given kind.
+pub async fn send(
+    config: &Config,
+    kind: &str,
+    url: Option<&str>,
+    recipients: &[String],
+    message: &Message,
+) -> Result<()> {
+    match kind {
+        "webhook" => {
+            let mut payload = json!(message);
+            payload["recipients"] = json!(recipients);
+            post(url, &payload).await
+        }
+        "slack" => {
+            let link = message
+                .url
+                .as_ref()
+                .map(|url| format!("\n<{url}|Open in auto>"))
+                .unwrap_or_default();
+            let text = format!("*{}*\n{}{link}", message.title, message.text);
+            post(url, &json!({ "text": text })).await
+        }
+        "email" => email(config, recipients, message).await,
+        _ => Err(Error::ValidationError(format!(
+            "Unknown notification channel kind '{kind}'"
+        ))),
+    }
+}
+
+async fn post(url: Option<&str>, payload: &Value) -> Result<()> {
+    let url = url.ok_or_else(|| Error::ValidationError("Channel has no URL".to_string()))?;
+
+    reqwest::Client::new()
+        .post(url)
+        .timeout(Duration::from_secs(10))
+        .json(payload)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| Error::InternalError(format!("Webhook request failed: {e}")))?;
+
+    Ok(())
+}
+
+async fn email(config: &Config, recipients: &[String], message: &Message) -> Result<()> {
+    let smtp = config
+        .smtp
+        .as_ref()
+        .ok_or_else(|| Error::InternalError("SMTP_URL is not set".to_string()))?;
+    if recipients.is_empty() {
+        return Err(Error::ValidationError("No one to email".to_string()));
+    }
+
+    let invalid = |e: lettre::address::AddressError| {
+        Error::ValidationError(format!("Invalid email address: {e}"))
+    };
+    let mut builder = lettre::Message::builder()
+        .from(smtp.from.parse().map_err(invalid)?)
+        .subject(&message.title);
+    for recipient in recipients {
+        builder = builder.to(recipient.parse().map_err(invalid)?);
+    }
+    let body = match &message.url {
+        Some(url) => format!("{}\n\n{url}\n", message.text),
+        None => format!("{}\n", message.text),
+    };
+    let email = builder
+        .body(body)
+        .map_err(|e| Error::InternalError(format!("Failed to build email: {e}")))?;
+
+    AsyncSmtpTransport::<Tokio1Executor>::from_url(&smtp.url)
+        .map_err(|e| Error::InternalError(format!("Invalid SMTP_URL: {e}")))?
+        .timeout(Some(Duration::from_secs(10)))
+        .build()
+        .send(email)
+        .await
+        .map_err(|e| Error::InternalError(format!("Failed to send email: {e}")))?;
+
+    Ok(())
+}
+
+/// Spawns a task that notifies about domains that are about to expire,
+/// once at startup and then every day.
+pub fn spawn_domain_expiry_scan(pool: SqlitePool, config: Config) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(24 * 3600));
+        loop {
+            interval.tick().await;
+            if let Err(e) = service::notification::scan_domain_expiry(&pool, &config).await {
+                error!("Failed to notify about expiring domains: {e}");
+            }
+        }
+    });
+}
+
+/// Spawns a task per incident that was opened or resolved to notify about
+/// it, so the heartbeats of other healthchecks aren't held up while sending.
+pub fn spawn_incident_notifications(pool: &SqlitePool, config: &Config, incidents: Vec<Incident>) {
+    for incident in incidents {
+        let pool = pool.clone();
+        let config = config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = service::notification::notify_incident(&pool, &config, &incident).await
+            {
+                error!("Failed to notify about incident {}: {e}", incident.id);
+            }
+        });
+    }
+}
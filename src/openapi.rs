@@ -1,4 +1,6 @@
-use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::security::{
+    ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme,
+};
 use utoipa::{Modify, OpenApi};
 
 #[derive(OpenApi)]
@@ -6,17 +8,30 @@ use utoipa::{Modify, OpenApi};
     info(
         title = "Auto API",
         version = "1.0.1",
-        description = "Digital assets management system API\n\n**Authentication:** This API is protected by HTTP Basic Authentication at the reverse proxy level (Caddy). All endpoints require authentication credentials which are validated by the reverse proxy before requests reach this application.",
+        description = "Digital assets management system API\n\n**Authentication:** All endpoints except health, version and `/api/auth/session` require either a personal API token sent as `Authorization: Bearer <token>`, or the session cookie set by `POST /api/auth/session`.\n\n**Roles:** every person has an `access_role`. Viewers can read everything, editors can also create, update, link and unlink, admins can also delete, trigger Kuma syncs and assign roles. The minimum role of an endpoint is listed as the scope of its security requirement; calls by a lower role get a 403.",
     ),
     paths(
         // Health endpoints
         crate::api::healthcheck,
         crate::api::version,
+
+        // Authentication
+        crate::api::auth::login,
+        crate::api::auth::logout,
+        crate::api::auth::config,
+        crate::api::auth::oidc_login,
+        crate::api::auth::oidc_callback,
+        crate::api::auth::oidc_logout,
+        crate::api::auth::me,
+        crate::api::auth::list_tokens,
+        crate::api::auth::create_token,
+        crate::api::auth::delete_token,
         
         // Applications
         crate::api::applications::list,
         crate::api::applications::get_one,
         crate::api::applications::create,
+        crate::api::applications::import_csv,
         crate::api::applications::update,
         crate::api::applications::delete_one,
         crate::api::applications::link_infra,
@@ -32,6 +47,12 @@ use utoipa::{Modify, OpenApi};
         crate::api::applications::link_stack,
         crate::api::applications::unlink_stack,
         crate::api::applications::sync_outline,
+        crate::api::applications::history,
+        crate::api::applications::uptime,
+        crate::api::applications::incidents,
+        crate::api::applications::revisions,
+        crate::api::applications::revision_diff,
+        crate::api::applications::rollback,
 
         // Services
         crate::api::services::list,
@@ -42,6 +63,13 @@ use utoipa::{Modify, OpenApi};
         crate::api::services::link_infra,
         crate::api::services::unlink_infra,
         crate::api::services::sync_outline,
+        crate::api::services::history,
+        crate::api::services::incidents,
+        crate::api::services::impact,
+        crate::api::services::impact_md,
+        crate::api::services::revisions,
+        crate::api::services::revision_diff,
+        crate::api::services::rollback,
 
         // Infrastructure
         crate::api::infra::list,
@@ -49,20 +77,35 @@ use utoipa::{Modify, OpenApi};
         crate::api::infra::create,
         crate::api::infra::update,
         crate::api::infra::delete_one,
+        crate::api::infra::history,
+        crate::api::infra::impact,
+        crate::api::infra::impact_md,
+        crate::api::infra::revisions,
+        crate::api::infra::revision_diff,
+        crate::api::infra::rollback,
         
         // Domains
         crate::api::domains::list,
         crate::api::domains::get_one,
         crate::api::domains::create,
+        crate::api::domains::import_csv,
         crate::api::domains::update,
         crate::api::domains::delete_one,
+        crate::api::domains::refresh,
+        crate::api::domains::check_certificates,
+        crate::api::domains::history,
+        crate::api::domains::revisions,
+        crate::api::domains::revision_diff,
+        crate::api::domains::rollback,
         
         // People
         crate::api::people::list,
         crate::api::people::get_one,
         crate::api::people::create,
+        crate::api::people::import_csv,
         crate::api::people::update,
         crate::api::people::delete_one,
+        crate::api::people::history,
         
         // Network shares
         crate::api::shares::list,
@@ -70,6 +113,7 @@ use utoipa::{Modify, OpenApi};
         crate::api::shares::create,
         crate::api::shares::update,
         crate::api::shares::delete_one,
+        crate::api::shares::history,
         
         // Notes
         crate::api::notes::list,
@@ -77,6 +121,14 @@ use utoipa::{Modify, OpenApi};
         crate::api::notes::create,
         crate::api::notes::update,
         crate::api::notes::delete_one,
+        crate::api::notes::history,
+        crate::api::maintenance::list,
+        crate::api::maintenance::get_one,
+        crate::api::maintenance::create,
+        crate::api::maintenance::update,
+        crate::api::maintenance::delete_one,
+        crate::api::maintenance::history,
+        crate::api::events::stream_changes,
         
         // Stacks
         crate::api::stacks::list,
@@ -84,6 +136,7 @@ use utoipa::{Modify, OpenApi};
         crate::api::stacks::create,
         crate::api::stacks::update,
         crate::api::stacks::delete_one,
+        crate::api::stacks::history,
         
         // Healthchecks
         crate::api::healthchecks::list,
@@ -96,7 +149,46 @@ use utoipa::{Modify, OpenApi};
         crate::api::healthchecks::kuma_endpoint,
         crate::api::healthchecks::sync_kuma_one,
         crate::api::healthchecks::sync_kuma_all,
+        crate::api::healthchecks::kuma_diff,
+        crate::api::healthchecks::kuma_import,
+        crate::api::healthchecks::kuma_queue,
+        crate::api::healthchecks::history,
+        crate::api::healthchecks::uptime,
+        crate::api::healthchecks::uptime_history,
+        crate::api::healthchecks::uptime_stream,
+        crate::api::healthchecks::uptime_live,
+        crate::api::healthchecks::revisions,
+        crate::api::healthchecks::revision_diff,
+        crate::api::healthchecks::rollback,
         
+        // Incidents
+        crate::api::incidents::list,
+        crate::api::incidents::get_one,
+
+        // Notifications
+        crate::api::notifications::list_sent,
+        crate::api::notifications::list,
+        crate::api::notifications::get_one,
+        crate::api::notifications::create,
+        crate::api::notifications::update,
+        crate::api::notifications::delete_one,
+        crate::api::notifications::test,
+
+        // Audit log
+        crate::api::audit::list,
+
+        // Export and import
+        crate::api::transfer::export,
+        crate::api::transfer::import,
+
+        // Trash
+        crate::api::trash::list,
+        crate::api::trash::restore,
+        crate::api::trash::purge,
+
+        // Dependency graph
+        crate::api::graph::get_graph,
+
         // Dashboard
         crate::api::dashboard::stats,
         
@@ -116,6 +208,7 @@ use utoipa::{Modify, OpenApi};
             crate::models::PaginatedResponse<crate::models::Note>,
             crate::models::PaginatedResponse<crate::models::Stack>,
             crate::models::PaginatedResponse<crate::models::Healthcheck>,
+            crate::models::PaginatedResponse<crate::models::AuditEvent>,
             
             // Applications
             crate::models::Application,
@@ -151,6 +244,8 @@ use utoipa::{Modify, OpenApi};
             crate::models::DomainTarget,
             crate::models::TargetName,
             crate::models::DomainWithRelations,
+            crate::models::DomainLookup,
+            crate::models::Certificate,
             crate::models::ApplicationDomainRelation,
             
             // People
@@ -175,6 +270,9 @@ use utoipa::{Modify, OpenApi};
             crate::models::Note,
             crate::models::CreateNote,
             crate::models::UpdateNote,
+            crate::models::Maintenance,
+            crate::models::CreateMaintenance,
+            crate::models::UpdateMaintenance,
             
             // Stacks
             crate::models::Stack,
@@ -193,16 +291,96 @@ use utoipa::{Modify, OpenApi};
             crate::models::HealthcheckExecuteResult,
             crate::models::KumaMonitor,
             crate::models::KumaEndpoint,
+            crate::models::KumaDiff,
+            crate::models::KumaMissingMonitor,
+            crate::models::KumaDrift,
+            crate::models::KumaOrphan,
+            crate::models::KumaImport,
+            crate::models::KumaImportResult,
+            crate::models::KumaImportSkipped,
+            crate::models::KumaSyncItem,
             
             // Uptime
             crate::models::HeartbeatEntry,
             crate::models::MonitorUptime,
             crate::models::UptimeEvent,
+            crate::models::MonitorSummary,
             
+            // Authentication
+            crate::models::Identity,
+            crate::models::ApiToken,
+            crate::models::CreateApiToken,
+            crate::models::NewApiToken,
+            crate::models::CreateSession,
+            crate::models::AuthConfig,
+
+            // Audit log
+            crate::models::AuditEvent,
+            crate::models::ChangeEvent,
+            crate::models::AuditAction,
+            crate::models::AuditFilters,
+
+            // CSV import
+            crate::models::CsvImportParams,
+            crate::models::CsvImportForm,
+            crate::models::CsvRowReport,
+            crate::models::CsvImportReport,
+
+            // Export and import
+            crate::models::ExportDocument,
+            crate::models::ImportParams,
+            crate::models::ImportReport,
+            crate::models::TableImportReport,
+
+            // Revisions
+            crate::models::Revision,
+            crate::models::RevisionDiff,
+            crate::models::RevisionDiffParams,
+            crate::models::FieldChange,
+
+            // Dependency graph
+            crate::models::Graph,
+            crate::models::GraphNode,
+            crate::models::GraphEdge,
+            crate::models::GraphFormat,
+            crate::models::GraphParams,
+
+            // Impact analysis
+            crate::models::ImpactReport,
+            crate::models::ImpactEntity,
+            crate::models::ImpactContact,
+
+            // Uptime statistics
+            crate::models::UptimeRange,
+            crate::models::UptimeParams,
+            crate::models::UptimeStats,
+            crate::models::UptimeResolution,
+            crate::models::UptimeHistory,
+            crate::models::UptimeBucket,
+            crate::models::DowntimeSpan,
+
+            // Incidents
+            crate::models::Incident,
+            crate::models::IncidentStatus,
+            crate::models::IncidentFilters,
+
+            // Notifications
+            crate::models::NotificationChannel,
+            crate::models::CreateNotificationChannel,
+            crate::models::UpdateNotificationChannel,
+            crate::models::Notification,
+            crate::models::NotificationFilters,
+
+            // Trash
+            crate::models::TrashItem,
+            crate::models::TrashRelation,
+            crate::models::TrashFilters,
+
             // Service layer
             crate::service::dashboard::DashboardStats,
             crate::service::dashboard::EntityStats,
             crate::service::dashboard::ExpiringDomain,
+            crate::service::dashboard::ExpiringCertificate,
             crate::service::dashboard::HealthcheckStats,
             crate::service::dashboard::RecentActivity,
             crate::service::search::SearchResults,
@@ -211,6 +389,7 @@ use utoipa::{Modify, OpenApi};
     ),
     tags(
         (name = "health", description = "Health check endpoints"),
+        (name = "auth", description = "Sessions and personal API tokens"),
         (name = "applications", description = "Application management"),
         (name = "services", description = "Service management"),
         (name = "infra", description = "Infrastructure management"),
@@ -218,8 +397,16 @@ use utoipa::{Modify, OpenApi};
         (name = "people", description = "People management"),
         (name = "shares", description = "Network shares management"),
         (name = "notes", description = "Notes management"),
+        (name = "maintenance", description = "Planned maintenance of infra, as Kuma maintenance windows"),
         (name = "stacks", description = "Technology stacks management"),
         (name = "healthchecks", description = "Health checks management"),
+        (name = "incidents", description = "Downtime of healthchecks, detected from Kuma heartbeats"),
+        (name = "notifications", description = "Notification channels and sent notifications"),
+        (name = "audit", description = "Audit log of all changes"),
+        (name = "events", description = "Live feed of changes, to refresh views"),
+        (name = "export", description = "Full JSON export and import of the inventory"),
+        (name = "trash", description = "Deleted items, restore and purge"),
+        (name = "graph", description = "Dependency graph of applications, services, infra and domains"),
         (name = "dashboard", description = "Dashboard statistics"),
         (name = "search", description = "Global search"),
     ),
@@ -233,22 +420,27 @@ impl Modify for SecurityAddon {
     fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
         if let Some(components) = openapi.components.as_mut() {
             components.add_security_scheme(
-                "http_basic",
+                "bearer_token",
                 SecurityScheme::Http(
                     HttpBuilder::new()
-                        .scheme(HttpAuthScheme::Basic)
-                        .description(Some(
-                            "HTTP Basic Authentication handled by Caddy reverse proxy",
-                        ))
+                        .scheme(HttpAuthScheme::Bearer)
+                        .description(Some("Personal API token"))
                         .build(),
                 ),
             );
+            components.add_security_scheme(
+                "session_cookie",
+                SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::with_description(
+                    crate::api::auth::SESSION_COOKIE,
+                    "Session cookie set by `POST /api/auth/session`",
+                ))),
+            );
         }
 
-        // Apply security globally to all operations
-        openapi.security = Some(vec![utoipa::openapi::security::SecurityRequirement::new(
-            "http_basic",
-            Vec::<String>::new(),
-        )]);
+        // Apply security globally to all operations, either scheme suffices
+        openapi.security = Some(vec![
+            SecurityRequirement::new("bearer_token", Vec::<String>::new()),
+            SecurityRequirement::new("session_cookie", Vec::<String>::new()),
+        ]);
     }
 }
@@ -0,0 +1,300 @@
+/*!
+ * RDAP client to look up the registrar, nameservers and expiry of domains.
+ *
+ * Lookups go to `RDAP_URL`, by default the rdap.org redirector which sends
+ * them on to the registry of the TLD. Any server answering
+ * `GET {RDAP_URL}/domain/{name}` with RDAP JSON will do, e.g. a local
+ * stand-in to try it out without network access.
+ */
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::DateTime;
+use serde_json::Value;
+use sqlx::SqlitePool;
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::models::Domain;
+use crate::{Config, Error, Result, service};
+
+/// Second-level labels under a country code that are public suffixes
+/// themselves, e.g. `co.uk`. Good enough without the full Public Suffix List.
+const PUBLIC_SECOND_LEVELS: &[&str] = &[
+    "ac", "co", "com", "edu", "gov", "go", "gv", "ltd", "me", "ne", "net", "nhs", "or", "org",
+    "plc", "sch",
+];
+
+/// Pause between lookups of a scan, registries rate limit RDAP
+const LOOKUP_PAUSE: Duration = Duration::from_secs(1);
+
+/// Registration data of a domain
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RdapDomain {
+    /// RDAP URL that answered
+    pub source: String,
+    pub registrar: Option<String>,
+    /// Registrable domain of the nameservers, e.g. `cloudflare.com`
+    pub dns_provider: Option<String>,
+    /// Expiry date (`YYYY-MM-DD`)
+    pub expires_at: Option<String>,
+}
+
+pub struct RdapClient {
+    base_url: Url,
+    client: reqwest::Client,
+}
+
+impl RdapClient {
+    pub fn new(base_url: &Url) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .build()
+            .map_err(|e| Error::InternalError(format!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self {
+            base_url: base_url.clone(),
+            client,
+        })
+    }
+
+    /// Looks up a registrable domain, following redirects to the registry.
+    pub async fn lookup(&self, name: &str) -> Result<RdapDomain> {
+        let url = format!(
+            "{}/domain/{name}",
+            self.base_url.as_str().trim_end_matches('/')
+        );
+        let failed = |e: reqwest::Error| {
+            Error::InternalError(format!("RDAP lookup of '{name}' failed: {e}"))
+        };
+
+        let response = self
+            .client
+            .get(&url)
+            .header(reqwest::header::ACCEPT, "application/rdap+json")
+            .send()
+            .await
+            .map_err(failed)?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(Error::NotFound(format!("No RDAP record for '{name}'")));
+        }
+        let response = response.error_for_status().map_err(failed)?;
+        let source = response.url().to_string();
+        let body = response.json::<Value>().await.map_err(failed)?;
+
+        Ok(parse(source, &body))
+    }
+}
+
+/// Registration data from an RDAP domain response
+pub fn parse(source: String, body: &Value) -> RdapDomain {
+    let registrar = body["entities"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|entity| {
+            entity["roles"]
+                .as_array()
+                .is_some_and(|roles| roles.iter().any(|r| r == "registrar"))
+        })
+        .and_then(vcard_name);
+
+    let dns_provider = body["nameservers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|ns| ns["ldhName"].as_str())
+        .find_map(registrable_domain);
+
+    let expires_at = body["events"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|event| event["eventAction"] == "expiration")
+        .and_then(|event| event["eventDate"].as_str())
+        .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string());
+
+    RdapDomain {
+        source,
+        registrar,
+        dns_provider,
+        expires_at,
+    }
+}
+
+/// The `fn` (full name) of an entity's jCard
+fn vcard_name(entity: &Value) -> Option<String> {
+    entity["vcardArray"][1]
+        .as_array()?
+        .iter()
+        .find(|property| property[0] == "fn")
+        .and_then(|property| property[3].as_str())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
+/// The domain that is registered for a name, e.g. `example.co.uk` for
+/// `www.example.co.uk`. `None` for IP addresses and single labels.
+pub fn registrable_domain(fqdn: &str) -> Option<String> {
+    let fqdn = fqdn.trim().trim_end_matches('.').to_lowercase();
+    let labels: Vec<&str> = fqdn.split('.').collect();
+    if labels.len() < 2
+        || labels.iter().any(|label| label.is_empty())
+        || labels.iter().all(|label| label.parse::<u8>().is_ok())
+    {
+        return None;
+    }
+
+    let tld = labels[labels.len() - 1];
+    let second = labels[labels.len() - 2];
+    let keep = if tld.len() == 2 && PUBLIC_SECOND_LEVELS.contains(&second) {
+        3
+    } else {
+        2
+    };
+    if labels.len() < keep {
+        return None;
+    }
+    Some(labels[labels.len() - keep..].join("."))
+}
+
+/// Looks up domains, each registrable parent once, and records the results.
+pub async fn refresh(pool: &SqlitePool, client: &RdapClient, domains: &[Domain]) -> Result<()> {
+    let mut found: HashMap<String, std::result::Result<RdapDomain, String>> = HashMap::new();
+
+    for domain in domains {
+        let Some(name) = registrable_domain(&domain.fqdn) else {
+            service::domain_lookup::failed(
+                pool,
+                &domain.id,
+                &domain.fqdn,
+                "Not a registrable domain",
+            )
+            .await?;
+            continue;
+        };
+
+        if !found.contains_key(&name) {
+            if !found.is_empty() {
+                tokio::time::sleep(LOOKUP_PAUSE).await;
+            }
+            let result = client.lookup(&name).await.map_err(|e| e.to_string());
+            found.insert(name.clone(), result);
+        }
+
+        match &found[&name] {
+            Ok(registration) => {
+                service::domain_lookup::record(pool, domain, &name, registration).await?
+            }
+            Err(e) => service::domain_lookup::failed(pool, &domain.id, &name, e).await?,
+        }
+    }
+    Ok(())
+}
+
+/// Spawns a task that looks up domains whose lookup is due, once at startup
+/// and then every hour.
+pub fn spawn_domain_lookup_scan(pool: SqlitePool, config: Config) {
+    tokio::spawn(async move {
+        let client = match RdapClient::new(&config.rdap_url) {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Not looking up domains: {e}");
+                return;
+            }
+        };
+
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            let domains = match service::domain_lookup::due(&pool).await {
+                Ok(domains) => domains,
+                Err(e) => {
+                    error!("Failed to find domains to look up: {e}");
+                    continue;
+                }
+            };
+            if domains.is_empty() {
+                continue;
+            }
+
+            info!("Looking up {} domains with RDAP", domains.len());
+            if let Err(e) = refresh(&pool, &client, &domains).await {
+                warn!("Failed to record domain lookups: {e}");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn registrable_domain_strips_subdomains() {
+        assert_eq!(
+            registrable_domain("www.ugent.be").as_deref(),
+            Some("ugent.be")
+        );
+        assert_eq!(
+            registrable_domain("Shop.Example.co.uk.").as_deref(),
+            Some("example.co.uk")
+        );
+        assert_eq!(
+            registrable_domain("example.org").as_deref(),
+            Some("example.org")
+        );
+        assert_eq!(registrable_domain("co.uk"), None);
+        assert_eq!(registrable_domain("localhost"), None);
+        assert_eq!(registrable_domain("10.0.0.1"), None);
+    }
+
+    #[test]
+    fn parse_reads_registrar_nameservers_and_expiry() {
+        // Trimmed down answer of a registry
+        let body = json!({
+            "objectClassName": "domain",
+            "ldhName": "example.org",
+            "entities": [
+                {
+                    "roles": ["registrant"],
+                    "vcardArray": ["vcard", [["fn", {}, "text", "Someone"]]]
+                },
+                {
+                    "roles": ["registrar"],
+                    "vcardArray": ["vcard", [
+                        ["version", {}, "text", "4.0"],
+                        ["fn", {}, "text", "Example Registrar, Inc."]
+                    ]]
+                }
+            ],
+            "nameservers": [
+                {"objectClassName": "nameserver", "ldhName": "NS1.CLOUDFLARE.COM"},
+                {"objectClassName": "nameserver", "ldhName": "ns2.cloudflare.com"}
+            ],
+            "events": [
+                {"eventAction": "registration", "eventDate": "1995-08-31T04:00:00Z"},
+                {"eventAction": "expiration", "eventDate": "2027-08-30T04:00:00Z"}
+            ]
+        });
+
+        let registration = parse("http://localhost/domain/example.org".into(), &body);
+        assert_eq!(
+            registration,
+            RdapDomain {
+                source: "http://localhost/domain/example.org".into(),
+                registrar: Some("Example Registrar, Inc.".into()),
+                dns_provider: Some("cloudflare.com".into()),
+                expires_at: Some("2027-08-30".into()),
+            }
+        );
+
+        // Some registries leave out the registrar or the expiry
+        let bare = parse(String::new(), &json!({"ldhName": "example.be"}));
+        assert_eq!(bare, RdapDomain::default());
+    }
+}
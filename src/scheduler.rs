@@ -0,0 +1,278 @@
+/*!
+ * Native healthcheck scheduler, to monitor without Uptime Kuma.
+ *
+ * Runs every enabled healthcheck at its interval with
+ * `service::healthcheck::execute`, and treats the results like the Kuma
+ * poller treats heartbeats: they are stored, broadcast to SSE clients and
+ * open or resolve incidents.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use sqlx::SqlitePool;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{error, info, warn};
+
+use crate::kuma::{UptimeState, UptimeTx, push_heartbeat};
+use crate::models::{
+    HEARTBEAT_WINDOW_SECS, HealthcheckExecuteResult, HeartbeatEntry, MonitorUptime, UptimeEvent,
+};
+use crate::{Config, notify, service};
+
+/// Shortest interval a healthcheck is run at, like Kuma's minimum.
+const MIN_INTERVAL_SECS: u64 = 20;
+
+/// Spawns the scheduler. Every second it picks up new, changed and deleted
+/// healthchecks and starts the ones that are due, unless their previous run
+/// (with retries) is still going.
+pub fn spawn_healthcheck_scheduler(
+    config: Config,
+    pool: SqlitePool,
+    uptime_state: UptimeState,
+    uptime_tx: UptimeTx,
+) {
+    tokio::spawn(async move {
+        let skip_kuma_monitored = config.uptime_source.kuma();
+        let running = Running::default();
+        let mut next_run = HashMap::<String, Instant>::new();
+
+        let mut tick = tokio::time::interval(Duration::from_secs(1));
+        tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            tick.tick().await;
+            let healthchecks =
+                match service::healthcheck::scheduled(&pool, skip_kuma_monitored).await {
+                    Ok(healthchecks) => healthchecks,
+                    Err(e) => {
+                        error!("Scheduler: failed to get healthchecks: {e}");
+                        continue;
+                    }
+                };
+
+            let now = Instant::now();
+            next_run.retain(|id, _| healthchecks.iter().any(|(h, _)| h == id));
+
+            for (id, interval) in healthchecks {
+                let next = match next_run.get_mut(&id) {
+                    Some(next) => next,
+                    None => {
+                        load_recent(&pool, &uptime_state, &id).await;
+                        next_run.entry(id.clone()).or_insert(now)
+                    }
+                };
+                if *next > now {
+                    continue;
+                }
+                let Some(guard) = RunGuard::start(&running, &id) else {
+                    continue;
+                };
+                *next = now + Duration::from_secs((interval.max(0) as u64).max(MIN_INTERVAL_SECS));
+
+                let pool = pool.clone();
+                let config = config.clone();
+                let uptime_state = uptime_state.clone();
+                let uptime_tx = uptime_tx.clone();
+                tokio::spawn(async move {
+                    let _guard = guard;
+                    run(&pool, &config, &uptime_state, &uptime_tx, &id).await;
+                });
+            }
+        }
+    });
+}
+
+/// IDs of the healthchecks being run
+type Running = Arc<Mutex<HashSet<String>>>;
+
+/// Marks a healthcheck as being run until dropped, also when the run
+/// panics, so it gets scheduled again.
+struct RunGuard {
+    running: Running,
+    id: String,
+}
+
+impl RunGuard {
+    /// None if the healthcheck is still being run
+    fn start(running: &Running, id: &str) -> Option<Self> {
+        running
+            .lock()
+            .unwrap()
+            .insert(id.to_string())
+            .then(|| Self {
+                running: running.clone(),
+                id: id.to_string(),
+            })
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        self.running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.id);
+    }
+}
+
+/// Runs one healthcheck, then stores and broadcasts its heartbeat and
+/// notifies about incidents it opened or resolved.
+async fn run(
+    pool: &SqlitePool,
+    config: &Config,
+    uptime_state: &UptimeState,
+    uptime_tx: &UptimeTx,
+    id: &str,
+) {
+    let result = match service::healthcheck::execute(pool, id).await {
+        Ok(result) => result,
+        Err(e) => {
+            warn!("Scheduler: failed to run healthcheck {id}: {e}");
+            return;
+        }
+    };
+    let entry = heartbeat(&result);
+
+    if push_heartbeat(uptime_state, id, None, &entry).await {
+        uptime_tx.send(UptimeEvent::Update {
+            healthcheck_id: id.to_string(),
+            kuma_id: None,
+            entry: entry.clone(),
+        });
+    }
+
+    if let Err(e) = service::heartbeat::record(pool, id, &entry).await {
+        error!("Scheduler: failed to store heartbeat of healthcheck {id}: {e}");
+        return;
+    }
+
+    match service::incident::detect_native(pool, id).await {
+        Ok(incidents) => notify::spawn_incident_notifications(pool, config, incidents),
+        Err(e) => error!("Scheduler: failed to update incidents of healthcheck {id}: {e}"),
+    }
+}
+
+/// Fills the in-memory state of a healthcheck with its stored heartbeats of
+/// the last hour, so a restart doesn't empty the uptime bars.
+async fn load_recent(pool: &SqlitePool, uptime_state: &UptimeState, id: &str) {
+    if uptime_state.read().await.contains_key(id) {
+        return;
+    }
+
+    let since = Utc::now() - chrono::Duration::seconds(HEARTBEAT_WINDOW_SECS);
+    match service::heartbeat::recent(pool, id, since).await {
+        Ok(heartbeats) => {
+            info!(
+                "Scheduler: scheduling healthcheck {id} with {} recent heartbeats",
+                heartbeats.len()
+            );
+            uptime_state
+                .write()
+                .await
+                .entry(id.to_string())
+                .or_insert(MonitorUptime {
+                    healthcheck_id: id.to_string(),
+                    kuma_id: None,
+                    heartbeats,
+                });
+        }
+        Err(e) => error!("Scheduler: failed to load heartbeats of healthcheck {id}: {e}"),
+    }
+}
+
+/// The heartbeat of a run: up when it succeeded, down when all retries
+/// failed, with the error, the status code or what a TCP, DNS or TLS check
+/// found as message.
+fn heartbeat(result: &HealthcheckExecuteResult) -> HeartbeatEntry {
+    let msg = match (&result.error, result.status_code) {
+        (Some(error), _) => error.clone(),
+        (None, Some(code)) => {
+            let reason = StatusCode::from_u16(code)
+                .ok()
+                .and_then(|c| c.canonical_reason())
+                .unwrap_or_default();
+            match result.body_match {
+                Some(false) => format!("{code} - {reason}, expected body not found"),
+                _ => format!("{code} - {reason}"),
+            }
+        }
+        (None, None) => result.detail.clone().unwrap_or_default(),
+    };
+
+    HeartbeatEntry {
+        status: if result.success { 1 } else { 0 },
+        time: result.executed_at.clone(),
+        ping: Some(result.response_time_ms.min(i32::MAX as u64) as i32),
+        msg: Some(msg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(
+        success: bool,
+        status_code: Option<u16>,
+        body_match: Option<bool>,
+    ) -> HealthcheckExecuteResult {
+        HealthcheckExecuteResult {
+            healthcheck_id: "h".into(),
+            url: "https://example.org/".into(),
+            success,
+            status_code,
+            response_time_ms: 42,
+            body_match,
+            detail: None,
+            error: None,
+            executed_at: "2026-03-01T10:00:00+00:00".into(),
+        }
+    }
+
+    #[test]
+    fn heartbeat_describes_the_result() {
+        let up = heartbeat(&result(true, Some(200), None));
+        assert_eq!((up.status, up.ping), (1, Some(42)));
+        assert_eq!(up.msg.as_deref(), Some("200 - OK"));
+
+        let wrong_body = heartbeat(&result(false, Some(200), Some(false)));
+        assert_eq!(wrong_body.status, 0);
+        assert_eq!(
+            wrong_body.msg.as_deref(),
+            Some("200 - OK, expected body not found")
+        );
+
+        let unreachable = heartbeat(&HealthcheckExecuteResult {
+            error: Some("connection refused".into()),
+            ..result(false, None, None)
+        });
+        assert_eq!(unreachable.msg.as_deref(), Some("connection refused"));
+
+        let port = heartbeat(&HealthcheckExecuteResult {
+            detail: Some("Port 5432 is open".into()),
+            ..result(true, None, None)
+        });
+        assert_eq!(port.msg.as_deref(), Some("Port 5432 is open"));
+    }
+
+    #[tokio::test]
+    async fn panicking_run_gets_scheduled_again() {
+        let running = Running::default();
+
+        let guard = RunGuard::start(&running, "h").unwrap();
+        // Not started twice while the previous run is going
+        assert!(RunGuard::start(&running, "h").is_none());
+        assert!(RunGuard::start(&running, "other").is_some());
+
+        let run = tokio::spawn(async move {
+            let _guard = guard;
+            panic!("run failed");
+        });
+        assert!(run.await.unwrap_err().is_panic());
+        assert!(RunGuard::start(&running, "h").is_some());
+    }
+}
@@ -1,11 +1,14 @@
-use sqlx::SqlitePool;
+use serde_json::{Value, json};
+use sqlx::types::Json;
+use sqlx::{SqliteConnection, SqlitePool};
 use tracing::info;
 
 use crate::models::{
-    Application, ApplicationWithRelations, CreateApplication, DomainRelation, InfraRelation,
-    NetworkShareRelation, Note, PaginatedResponse, PaginationParams, PersonRelation,
+    Application, ApplicationWithRelations, AuditAction, CreateApplication, DomainRelation,
+    InfraRelation, NetworkShareRelation, Note, PaginatedResponse, PaginationParams, PersonRelation,
     ServiceRelation, StackRelation, UpdateApplication, new_id,
 };
+use crate::service::{audit, revision, trash, version};
 use crate::{Error, Result, service};
 
 pub async fn list(
@@ -20,9 +23,10 @@ pub async fn list(
 
     let applications = sqlx::query_as::<_, Application>(
         r#"
-        SELECT id, name, description, repository_url, environment, url, status, image_refs, outline_url, created_at, updated_at, created_by
+        SELECT id, name, description, repository_url, environment, url, status, image_refs, outline_url, created_at, updated_at, version, created_by
         FROM application
-        WHERE (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
           AND (?2 IS NULL OR status = ?2)
           AND (?3 IS NULL OR environment = ?3)
         ORDER BY name COLLATE NOCASE ASC
@@ -41,7 +45,8 @@ pub async fn list(
         r#"
         SELECT COUNT(*)
         FROM application
-        WHERE (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
           AND (?2 IS NULL OR status = ?2)
           AND (?3 IS NULL OR environment = ?3)
         "#,
@@ -56,15 +61,20 @@ pub async fn list(
 }
 
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<Application> {
+    get_in(&mut *pool.acquire().await?, id).await
+}
+
+/// [`get`] on a connection, e.g. in a transaction
+pub async fn get_in(conn: &mut SqliteConnection, id: &str) -> Result<Application> {
     sqlx::query_as::<_, Application>(
         r#"
-        SELECT id, name, description, repository_url, environment, url, status, image_refs, outline_url, created_at, updated_at, created_by
+        SELECT id, name, description, repository_url, environment, url, status, image_refs, outline_url, created_at, updated_at, version, created_by
         FROM application
-        WHERE id = ?1
+        WHERE id = ?1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or_else(|| Error::NotFound(format!("Application with id '{}' not found", id)))
 }
@@ -78,6 +88,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<Applicati
         FROM infra i
         JOIN application_infra ai ON i.id = ai.infra_id
         WHERE ai.application_id = ?1
+          AND i.deleted_at IS NULL
         ORDER BY i.name COLLATE NOCASE
         "#,
     )
@@ -91,6 +102,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<Applicati
         FROM service s
         JOIN application_service asvc ON s.id = asvc.service_id
         WHERE asvc.application_id = ?1
+          AND s.deleted_at IS NULL
         ORDER BY s.name COLLATE NOCASE
         "#,
     )
@@ -110,6 +122,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<Applicati
         LEFT JOIN application ap ON d.target_application_id = ap.id
         LEFT JOIN service s ON d.target_service_id = s.id
         WHERE ad.application_id = ?1
+          AND d.deleted_at IS NULL
         ORDER BY d.fqdn COLLATE NOCASE
         "#,
     )
@@ -126,6 +139,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<Applicati
         FROM person p
         JOIN application_person ap ON p.id = ap.person_id
         WHERE ap.application_id = ?1
+          AND p.deleted_at IS NULL
         ORDER BY p.name COLLATE NOCASE
         "#,
     )
@@ -140,6 +154,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<Applicati
         FROM network_share ns
         JOIN application_network_share ans ON ns.id = ans.network_share_id
         WHERE ans.application_id = ?1
+          AND ns.deleted_at IS NULL
         ORDER BY ns.name COLLATE NOCASE
         "#,
     )
@@ -149,7 +164,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<Applicati
 
     let notes = sqlx::query_as::<_, Note>(
         r#"
-        SELECT id, entity_type, entity_id, title, content, note_type, url, is_pinned, created_at, updated_at, created_by
+        SELECT id, entity_type, entity_id, title, content, note_type, url, is_pinned, created_at, updated_at, version, created_by
         FROM note
         WHERE entity_type = 'application' AND entity_id = ?1
         ORDER BY is_pinned DESC, created_at DESC
@@ -165,6 +180,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<Applicati
         FROM stack s
         JOIN application_stack ast ON s.id = ast.stack_id
         WHERE ast.application_id = ?1
+          AND s.deleted_at IS NULL
         ORDER BY s.name COLLATE NOCASE
         "#,
     )
@@ -187,13 +203,26 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<Applicati
     })
 }
 
-pub async fn create(pool: &SqlitePool, input: CreateApplication) -> Result<Application> {
+pub async fn create(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    input: CreateApplication,
+) -> Result<Application> {
+    create_in(&mut *pool.acquire().await?, actor, input).await
+}
+
+/// [`create`] on a connection, e.g. in a transaction
+pub async fn create_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    input: CreateApplication,
+) -> Result<Application> {
     let id = new_id();
 
     sqlx::query(
         r#"
-        INSERT INTO application (id, name, description, repository_url, environment, url, status, image_refs, outline_url)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        INSERT INTO application (id, name, description, repository_url, environment, url, status, image_refs, outline_url, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
         "#,
     )
     .bind(&id)
@@ -205,51 +234,152 @@ pub async fn create(pool: &SqlitePool, input: CreateApplication) -> Result<Appli
     .bind(&input.status)
     .bind(&input.image_refs)
     .bind(&input.outline_url)
-    .execute(pool)
+    .bind(actor)
+    .execute(&mut *conn)
     .await?;
 
-    get(pool, &id).await
+    let application = get_in(&mut *conn, &id).await?;
+    audit::record_in(
+        &mut *conn,
+        actor,
+        "application",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&application),
+    )
+    .await?;
+    revision::record_in(&mut *conn, actor, "application", &id, None, &application).await?;
+
+    Ok(application)
 }
 
-pub async fn update(pool: &SqlitePool, id: &str, input: UpdateApplication) -> Result<Application> {
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdateApplication,
+) -> Result<Application> {
     let existing = get(pool, id).await?;
+    version::check("Application", id, input.version, existing.version)?;
+
+    let values = Application {
+        name: input.name.unwrap_or_else(|| existing.name.clone()),
+        description: input.description.or_else(|| existing.description.clone()),
+        repository_url: input
+            .repository_url
+            .or_else(|| existing.repository_url.clone()),
+        environment: input
+            .environment
+            .unwrap_or_else(|| existing.environment.clone()),
+        url: input.url.or_else(|| existing.url.clone()),
+        status: input.status.unwrap_or_else(|| existing.status.clone()),
+        image_refs: input.image_refs.or_else(|| existing.image_refs.clone()),
+        outline_url: input.outline_url.or_else(|| existing.outline_url.clone()),
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
 
-    let name = input.name.unwrap_or(existing.name);
-    let description = input.description.or(existing.description);
-    let repository_url = input.repository_url.or(existing.repository_url);
-    let environment = input.environment.unwrap_or(existing.environment);
-    let url = input.url.or(existing.url);
-    let status = input.status.unwrap_or(existing.status);
-    let image_refs = input.image_refs.or(existing.image_refs);
-    let outline_url = input.outline_url.or(existing.outline_url);
+/// Roll back to a revision. Unlike [`update`], fields without a value in
+/// the revision are cleared instead of kept.
+pub async fn rollback(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    rev: i64,
+    expected_version: Option<i64>,
+) -> Result<Application> {
+    let existing = get(pool, id).await?;
+    version::check("Application", id, expected_version, existing.version)?;
+    let snapshot = revision::snapshot::<UpdateApplication>(pool, "application", id, rev).await?;
+
+    let values = Application {
+        name: snapshot.name.unwrap_or_else(|| existing.name.clone()),
+        description: snapshot.description,
+        repository_url: snapshot.repository_url,
+        environment: snapshot
+            .environment
+            .unwrap_or_else(|| existing.environment.clone()),
+        url: snapshot.url,
+        status: snapshot.status.unwrap_or_else(|| existing.status.clone()),
+        image_refs: snapshot.image_refs,
+        outline_url: snapshot.outline_url,
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
 
-    sqlx::query(
+/// Store `values` as the next version of `previous`
+async fn write(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    previous: Application,
+    values: Application,
+) -> Result<Application> {
+    let id = previous.id.as_str();
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
         r#"
         UPDATE application
-        SET name = ?1, description = ?2, repository_url = ?3, environment = ?4, url = ?5, status = ?6, image_refs = ?7, outline_url = ?8, updated_at = datetime('now')
-        WHERE id = ?9
+        SET name = ?1, description = ?2, repository_url = ?3, environment = ?4, url = ?5, status = ?6, image_refs = ?7, outline_url = ?8, updated_at = datetime('now'), version = version + 1
+        WHERE id = ?9 AND version = ?10
         "#,
     )
-    .bind(&name)
-    .bind(&description)
-    .bind(&repository_url)
-    .bind(&environment)
-    .bind(&url)
-    .bind(&status)
-    .bind(&image_refs)
-    .bind(&outline_url)
+    .bind(&values.name)
+    .bind(&values.description)
+    .bind(&values.repository_url)
+    .bind(&values.environment)
+    .bind(&values.url)
+    .bind(&values.status)
+    .bind(&values.image_refs)
+    .bind(&values.outline_url)
     .bind(id)
-    .execute(pool)
+    .bind(previous.version)
+    .execute(&mut *tx)
     .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Application", id));
+    }
 
-    get(pool, id).await
+    let application = get_in(&mut tx, id).await?;
+    audit::record_in(
+        &mut tx,
+        actor,
+        "application",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&application),
+    )
+    .await?;
+    revision::record_in(
+        &mut tx,
+        actor,
+        "application",
+        id,
+        Some(&previous),
+        &application,
+    )
+    .await?;
+    if application.name != previous.name || application.environment != previous.environment {
+        service::healthcheck::mark_kuma_dirty_of_in(&mut tx, id).await?;
+    }
+
+    tx.commit().await?;
+    Ok(application)
 }
 
-pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
-    let result = sqlx::query("DELETE FROM application WHERE id = ?1")
-        .bind(id)
-        .execute(pool)
-        .await?;
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Application", id, expected_version, existing.version)?;
+
+    let result = trash::soft_delete(pool, actor, "application", id).await?;
 
     if result.rows_affected() == 0 {
         return Err(Error::NotFound(format!(
@@ -258,6 +388,17 @@ pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
         )));
     }
 
+    audit::record(
+        pool,
+        actor,
+        "application",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
     Ok(())
 }
 
@@ -265,13 +406,25 @@ pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
 
 pub async fn link_infra(
     pool: &SqlitePool,
+    actor: Option<&str>,
+    app_id: &str,
+    infra_id: &str,
+    notes: Option<&str>,
+) -> Result<()> {
+    link_infra_in(&mut *pool.acquire().await?, actor, app_id, infra_id, notes).await
+}
+
+/// [`link_infra`] on a connection, e.g. in a transaction
+pub async fn link_infra_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
     app_id: &str,
     infra_id: &str,
     notes: Option<&str>,
 ) -> Result<()> {
     // Verify both entities exist
-    get(pool, app_id).await?;
-    crate::service::infra::get(pool, infra_id).await?;
+    get_in(&mut *conn, app_id).await?;
+    crate::service::infra::get_in(&mut *conn, infra_id).await?;
 
     sqlx::query(
         r#"
@@ -283,36 +436,81 @@ pub async fn link_infra(
     .bind(app_id)
     .bind(infra_id)
     .bind(notes)
-    .execute(pool)
+    .execute(&mut *conn)
+    .await?;
+
+    audit::record_link_in(
+        &mut *conn,
+        actor,
+        AuditAction::Link,
+        ("application", app_id),
+        ("infra", infra_id),
+        Some(json!({ "notes": notes })),
+    )
     .await?;
 
     Ok(())
 }
 
-pub async fn unlink_infra(pool: &SqlitePool, app_id: &str, infra_id: &str) -> Result<()> {
-    let result =
-        sqlx::query("DELETE FROM application_infra WHERE application_id = ?1 AND infra_id = ?2")
-            .bind(app_id)
-            .bind(infra_id)
-            .execute(pool)
-            .await?;
-
-    if result.rows_affected() == 0 {
-        return Err(Error::NotFound("Relationship not found".to_string()));
-    }
+pub async fn unlink_infra(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    app_id: &str,
+    infra_id: &str,
+) -> Result<()> {
+    let removed = sqlx::query_scalar::<_, Json<Value>>(
+        r#"
+        DELETE FROM application_infra WHERE application_id = ?1 AND infra_id = ?2
+        RETURNING json_object('notes', notes)
+        "#,
+    )
+    .bind(app_id)
+    .bind(infra_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound("Relationship not found".to_string()))?;
+
+    audit::record_link(
+        pool,
+        actor,
+        AuditAction::Unlink,
+        ("application", app_id),
+        ("infra", infra_id),
+        Some(removed.0),
+    )
+    .await?;
 
     Ok(())
 }
 
 pub async fn link_service(
     pool: &SqlitePool,
+    actor: Option<&str>,
+    app_id: &str,
+    service_id: &str,
+    notes: Option<&str>,
+) -> Result<()> {
+    link_service_in(
+        &mut *pool.acquire().await?,
+        actor,
+        app_id,
+        service_id,
+        notes,
+    )
+    .await
+}
+
+/// [`link_service`] on a connection, e.g. in a transaction
+pub async fn link_service_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
     app_id: &str,
     service_id: &str,
     notes: Option<&str>,
 ) -> Result<()> {
     // Verify both entities exist
-    get(pool, app_id).await?;
-    crate::service::service::get(pool, service_id).await?;
+    get_in(&mut *conn, app_id).await?;
+    crate::service::service::get_in(&mut *conn, service_id).await?;
 
     sqlx::query(
         r#"
@@ -324,45 +522,90 @@ pub async fn link_service(
     .bind(app_id)
     .bind(service_id)
     .bind(notes)
-    .execute(pool)
+    .execute(&mut *conn)
+    .await?;
+
+    audit::record_link_in(
+        &mut *conn,
+        actor,
+        AuditAction::Link,
+        ("application", app_id),
+        ("service", service_id),
+        Some(json!({ "notes": notes })),
+    )
     .await?;
 
     Ok(())
 }
 
-pub async fn unlink_service(pool: &SqlitePool, app_id: &str, service_id: &str) -> Result<()> {
-    let result = sqlx::query(
-        "DELETE FROM application_service WHERE application_id = ?1 AND service_id = ?2",
+pub async fn unlink_service(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    app_id: &str,
+    service_id: &str,
+) -> Result<()> {
+    let removed = sqlx::query_scalar::<_, Json<Value>>(
+        r#"
+        DELETE FROM application_service WHERE application_id = ?1 AND service_id = ?2
+        RETURNING json_object('notes', notes)
+        "#,
     )
     .bind(app_id)
     .bind(service_id)
-    .execute(pool)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound("Relationship not found".to_string()))?;
+
+    audit::record_link(
+        pool,
+        actor,
+        AuditAction::Unlink,
+        ("application", app_id),
+        ("service", service_id),
+        Some(removed.0),
+    )
     .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(Error::NotFound("Relationship not found".to_string()));
-    }
-
     Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
 pub async fn link_domain(
     pool: &SqlitePool,
+    actor: Option<&str>,
     app_id: &str,
     domain_id: &str,
     notes: Option<&str>,
 ) -> Result<()> {
-    let app_relations = get_with_relations(pool, app_id).await?;
+    link_domain_in(&mut *pool.acquire().await?, actor, app_id, domain_id, notes).await
+}
 
-    let domain = crate::service::domain::get(pool, domain_id).await?;
+/// [`link_domain`] on a connection, e.g. in a transaction
+#[allow(clippy::too_many_arguments)]
+pub async fn link_domain_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    app_id: &str,
+    domain_id: &str,
+    notes: Option<&str>,
+) -> Result<()> {
+    get_in(&mut *conn, app_id).await?;
+
+    let domain = crate::service::domain::get_in(&mut *conn, domain_id).await?;
 
     // If this application isn't linked to the service target of the domain, link them
     if let Some(id) = domain.target_service_id
-        && !app_relations.services.iter().any(|s| s.id == id)
+        && !sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM application_service WHERE application_id = ?1 AND service_id = ?2)",
+        )
+        .bind(app_id)
+        .bind(&id)
+        .fetch_one(&mut *conn)
+        .await?
     {
-        link_service(
-            pool,
+        link_service_in(
+            &mut *conn,
+            actor,
             app_id,
             &id,
             Some(&format!("through '{}'", domain.fqdn)),
@@ -380,29 +623,57 @@ pub async fn link_domain(
     .bind(app_id)
     .bind(domain_id)
     .bind(notes)
-    .execute(pool)
+    .execute(&mut *conn)
+    .await?;
+
+    audit::record_link_in(
+        &mut *conn,
+        actor,
+        AuditAction::Link,
+        ("application", app_id),
+        ("domain", domain_id),
+        Some(json!({ "notes": notes })),
+    )
     .await?;
 
     Ok(())
 }
 
-pub async fn unlink_domain(pool: &SqlitePool, app_id: &str, domain_id: &str) -> Result<()> {
-    let result =
-        sqlx::query("DELETE FROM application_domain WHERE application_id = ?1 AND domain_id = ?2")
-            .bind(app_id)
-            .bind(domain_id)
-            .execute(pool)
-            .await?;
-
-    if result.rows_affected() == 0 {
-        return Err(Error::NotFound("Relationship not found".to_string()));
-    }
+pub async fn unlink_domain(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    app_id: &str,
+    domain_id: &str,
+) -> Result<()> {
+    let removed = sqlx::query_scalar::<_, Json<Value>>(
+        r#"
+        DELETE FROM application_domain WHERE application_id = ?1 AND domain_id = ?2
+        RETURNING json_object('notes', notes)
+        "#,
+    )
+    .bind(app_id)
+    .bind(domain_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound("Relationship not found".to_string()))?;
+
+    audit::record_link(
+        pool,
+        actor,
+        AuditAction::Unlink,
+        ("application", app_id),
+        ("domain", domain_id),
+        Some(removed.0),
+    )
+    .await?;
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn link_person(
     pool: &SqlitePool,
+    actor: Option<&str>,
     app_id: &str,
     person_id: &str,
     contribution_type: &str,
@@ -410,8 +681,33 @@ pub async fn link_person(
     end_date: Option<&str>,
     notes: Option<&str>,
 ) -> Result<()> {
-    get(pool, app_id).await?;
-    crate::service::person::get(pool, person_id).await?;
+    link_person_in(
+        &mut *pool.acquire().await?,
+        actor,
+        app_id,
+        person_id,
+        contribution_type,
+        start_date,
+        end_date,
+        notes,
+    )
+    .await
+}
+
+/// [`link_person`] on a connection, e.g. in a transaction
+#[allow(clippy::too_many_arguments)]
+pub async fn link_person_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    app_id: &str,
+    person_id: &str,
+    contribution_type: &str,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+    notes: Option<&str>,
+) -> Result<()> {
+    get_in(&mut *conn, app_id).await?;
+    crate::service::person::get_in(&mut *conn, person_id).await?;
 
     sqlx::query(
         r#"
@@ -426,29 +722,63 @@ pub async fn link_person(
     .bind(start_date)
     .bind(end_date)
     .bind(notes)
-    .execute(pool)
+    .execute(&mut *conn)
+    .await?;
+
+    audit::record_link_in(
+        &mut *conn,
+        actor,
+        AuditAction::Link,
+        ("application", app_id),
+        ("person", person_id),
+        Some(json!({
+            "contribution_type": contribution_type,
+            "start_date": start_date,
+            "end_date": end_date,
+            "notes": notes,
+        })),
+    )
     .await?;
 
     Ok(())
 }
 
-pub async fn unlink_person(pool: &SqlitePool, app_id: &str, person_id: &str) -> Result<()> {
-    let result =
-        sqlx::query("DELETE FROM application_person WHERE application_id = ?1 AND person_id = ?2")
-            .bind(app_id)
-            .bind(person_id)
-            .execute(pool)
-            .await?;
-
-    if result.rows_affected() == 0 {
-        return Err(Error::NotFound("Relationship not found".to_string()));
-    }
+pub async fn unlink_person(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    app_id: &str,
+    person_id: &str,
+) -> Result<()> {
+    let removed = sqlx::query_scalar::<_, Json<Value>>(
+        r#"
+        DELETE FROM application_person WHERE application_id = ?1 AND person_id = ?2
+        RETURNING json_object('contribution_type', contribution_type, 'start_date', start_date,
+                              'end_date', end_date, 'notes', notes)
+        "#,
+    )
+    .bind(app_id)
+    .bind(person_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound("Relationship not found".to_string()))?;
+
+    audit::record_link(
+        pool,
+        actor,
+        AuditAction::Unlink,
+        ("application", app_id),
+        ("person", person_id),
+        Some(removed.0),
+    )
+    .await?;
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn link_network_share(
     pool: &SqlitePool,
+    actor: Option<&str>,
     app_id: &str,
     share_id: &str,
     usage: Option<&str>,
@@ -456,8 +786,33 @@ pub async fn link_network_share(
     permissions: Option<&str>,
     notes: Option<&str>,
 ) -> Result<()> {
-    get(pool, app_id).await?;
-    crate::service::network_share::get(pool, share_id).await?;
+    link_network_share_in(
+        &mut *pool.acquire().await?,
+        actor,
+        app_id,
+        share_id,
+        usage,
+        mount_point,
+        permissions,
+        notes,
+    )
+    .await
+}
+
+/// [`link_network_share`] on a connection, e.g. in a transaction
+#[allow(clippy::too_many_arguments)]
+pub async fn link_network_share_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    app_id: &str,
+    share_id: &str,
+    usage: Option<&str>,
+    mount_point: Option<&str>,
+    permissions: Option<&str>,
+    notes: Option<&str>,
+) -> Result<()> {
+    get_in(&mut *conn, app_id).await?;
+    crate::service::network_share::get_in(&mut *conn, share_id).await?;
 
     sqlx::query(
         r#"
@@ -472,31 +827,77 @@ pub async fn link_network_share(
     .bind(mount_point)
     .bind(permissions)
     .bind(notes)
-    .execute(pool)
+    .execute(&mut *conn)
+    .await?;
+
+    audit::record_link_in(
+        &mut *conn,
+        actor,
+        AuditAction::Link,
+        ("application", app_id),
+        ("network_share", share_id),
+        Some(json!({
+            "usage": usage,
+            "mount_point": mount_point,
+            "permissions": permissions,
+            "notes": notes,
+        })),
+    )
     .await?;
 
     Ok(())
 }
 
-pub async fn unlink_network_share(pool: &SqlitePool, app_id: &str, share_id: &str) -> Result<()> {
-    let result = sqlx::query(
-        "DELETE FROM application_network_share WHERE application_id = ?1 AND network_share_id = ?2",
+pub async fn unlink_network_share(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    app_id: &str,
+    share_id: &str,
+) -> Result<()> {
+    let removed = sqlx::query_scalar::<_, Json<Value>>(
+        r#"
+        DELETE FROM application_network_share WHERE application_id = ?1 AND network_share_id = ?2
+        RETURNING json_object('usage', usage, 'mount_point', mount_point,
+                              'permissions', permissions, 'notes', notes)
+        "#,
     )
     .bind(app_id)
     .bind(share_id)
-    .execute(pool)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound("Relationship not found".to_string()))?;
+
+    audit::record_link(
+        pool,
+        actor,
+        AuditAction::Unlink,
+        ("application", app_id),
+        ("network_share", share_id),
+        Some(removed.0),
+    )
     .await?;
 
-    if result.rows_affected() == 0 {
-        return Err(Error::NotFound("Relationship not found".to_string()));
-    }
-
     Ok(())
 }
 
-pub async fn link_stack(pool: &SqlitePool, app_id: &str, stack_id: &str) -> Result<()> {
-    get(pool, app_id).await?;
-    crate::service::stack::get(pool, stack_id).await?;
+pub async fn link_stack(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    app_id: &str,
+    stack_id: &str,
+) -> Result<()> {
+    link_stack_in(&mut *pool.acquire().await?, actor, app_id, stack_id).await
+}
+
+/// [`link_stack`] on a connection, e.g. in a transaction
+pub async fn link_stack_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    app_id: &str,
+    stack_id: &str,
+) -> Result<()> {
+    get_in(&mut *conn, app_id).await?;
+    crate::service::stack::get_in(&mut *conn, stack_id).await?;
 
     sqlx::query(
         r#"
@@ -507,13 +908,29 @@ pub async fn link_stack(pool: &SqlitePool, app_id: &str, stack_id: &str) -> Resu
     )
     .bind(app_id)
     .bind(stack_id)
-    .execute(pool)
+    .execute(&mut *conn)
+    .await?;
+
+    audit::record_link_in(
+        &mut *conn,
+        actor,
+        AuditAction::Link,
+        ("application", app_id),
+        ("stack", stack_id),
+        None,
+    )
     .await?;
+    service::healthcheck::mark_kuma_dirty_of_in(&mut *conn, app_id).await?;
 
     Ok(())
 }
 
-pub async fn unlink_stack(pool: &SqlitePool, app_id: &str, stack_id: &str) -> Result<()> {
+pub async fn unlink_stack(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    app_id: &str,
+    stack_id: &str,
+) -> Result<()> {
     let result =
         sqlx::query("DELETE FROM application_stack WHERE application_id = ?1 AND stack_id = ?2")
             .bind(app_id)
@@ -525,5 +942,16 @@ pub async fn unlink_stack(pool: &SqlitePool, app_id: &str, stack_id: &str) -> Re
         return Err(Error::NotFound("Relationship not found".to_string()));
     }
 
+    audit::record_link(
+        pool,
+        actor,
+        AuditAction::Unlink,
+        ("application", app_id),
+        ("stack", stack_id),
+        None,
+    )
+    .await?;
+    service::healthcheck::mark_kuma_dirty_of(pool, app_id).await?;
+
     Ok(())
 }
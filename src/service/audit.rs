@@ -0,0 +1,421 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::models::{
+    AuditAction, AuditEvent, AuditFilters, ChangeEvent, PaginatedResponse, PaginationParams,
+};
+use crate::{Error, Result};
+
+/// Fields that change on every write and would only add noise to a diff.
+const IGNORED_FIELDS: &[&str] = &["updated_at", "version"];
+
+/// Record a create, update or delete of an entity.
+///
+/// For updates only the fields that actually changed are stored, so
+/// `before` and `after` together form the diff.
+pub async fn record<T: Serialize>(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    entity_type: &str,
+    entity_id: &str,
+    action: AuditAction,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<()> {
+    record_in(
+        &mut *pool.acquire().await?,
+        actor,
+        entity_type,
+        entity_id,
+        action,
+        before,
+        after,
+    )
+    .await
+}
+
+/// [`record`] on a connection, e.g. in a transaction
+pub async fn record_in<T: Serialize>(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    entity_type: &str,
+    entity_id: &str,
+    action: AuditAction,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<()> {
+    let before = before.map(to_value).transpose()?;
+    let after = after.map(to_value).transpose()?;
+
+    let entity_name = after
+        .as_ref()
+        .or(before.as_ref())
+        .and_then(display_name)
+        .map(str::to_string);
+
+    let (before, after) = match (before, after) {
+        (Some(before), Some(after)) => {
+            let (before, after) = diff(&before, &after);
+            (Some(before), Some(after))
+        }
+        other => other,
+    };
+
+    insert(
+        &mut *conn,
+        actor,
+        (entity_type, entity_id),
+        entity_name.as_deref(),
+        action,
+        None,
+        (before, after),
+    )
+    .await
+}
+
+/// Record a link or unlink between two entities.
+///
+/// `data` holds the relation metadata (notes, contribution type, ...) and
+/// is stored as `after` for links and `before` for unlinks.
+pub async fn record_link(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    action: AuditAction,
+    entity: (&str, &str),
+    related: (&str, &str),
+    data: Option<Value>,
+) -> Result<()> {
+    record_link_in(
+        &mut *pool.acquire().await?,
+        actor,
+        action,
+        entity,
+        related,
+        data,
+    )
+    .await
+}
+
+/// [`record_link`] on a connection, e.g. in a transaction
+pub async fn record_link_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    action: AuditAction,
+    entity: (&str, &str),
+    related: (&str, &str),
+    data: Option<Value>,
+) -> Result<()> {
+    let entity_name = entity_name(&mut *conn, entity.0, entity.1).await?;
+
+    let values = match action {
+        AuditAction::Unlink => (data, None),
+        _ => (None, data),
+    };
+
+    insert(
+        &mut *conn,
+        actor,
+        entity,
+        entity_name.as_deref(),
+        action,
+        Some(related),
+        values,
+    )
+    .await
+}
+
+async fn insert(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    (entity_type, entity_id): (&str, &str),
+    entity_name: Option<&str>,
+    action: AuditAction,
+    related: Option<(&str, &str)>,
+    (before, after): (Option<Value>, Option<Value>),
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO audit_event (actor, entity_type, entity_id, entity_name, action, related_type, related_id, before, after)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+        "#,
+    )
+    .bind(actor)
+    .bind(entity_type)
+    .bind(entity_id)
+    .bind(entity_name)
+    .bind(action.as_str())
+    .bind(related.map(|r| r.0))
+    .bind(related.map(|r| r.1))
+    .bind(before.map(|v| v.to_string()))
+    .bind(after.map(|v| v.to_string()))
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn list(
+    pool: &SqlitePool,
+    filters: &AuditFilters,
+) -> Result<PaginatedResponse<AuditEvent>> {
+    let params = PaginationParams {
+        page: filters.page,
+        per_page: filters.per_page,
+        search: None,
+    };
+    let limit = params.limit() as i32;
+    let offset = params.offset() as i32;
+
+    let events = sqlx::query_as::<_, AuditEvent>(
+        r#"
+        SELECT id, actor, entity_type, entity_id, entity_name, action, related_type, related_id, before, after, created_at
+        FROM audit_event
+        WHERE (?1 IS NULL OR actor = ?1)
+          AND (?2 IS NULL OR entity_type = ?2 OR related_type = ?2)
+          AND (?3 IS NULL OR entity_id = ?3 OR related_id = ?3)
+          AND (?4 IS NULL OR action = ?4)
+          AND (?5 IS NULL OR created_at >= ?5)
+          AND (?6 IS NULL OR created_at < ?6)
+        ORDER BY id DESC
+        LIMIT ?7 OFFSET ?8
+        "#,
+    )
+    .bind(&filters.actor)
+    .bind(&filters.entity_type)
+    .bind(&filters.entity_id)
+    .bind(&filters.action)
+    .bind(&filters.since)
+    .bind(&filters.until)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let (total,) = sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT COUNT(*)
+        FROM audit_event
+        WHERE (?1 IS NULL OR actor = ?1)
+          AND (?2 IS NULL OR entity_type = ?2 OR related_type = ?2)
+          AND (?3 IS NULL OR entity_id = ?3 OR related_id = ?3)
+          AND (?4 IS NULL OR action = ?4)
+          AND (?5 IS NULL OR created_at >= ?5)
+          AND (?6 IS NULL OR created_at < ?6)
+        "#,
+    )
+    .bind(&filters.actor)
+    .bind(&filters.entity_type)
+    .bind(&filters.entity_id)
+    .bind(&filters.action)
+    .bind(&filters.since)
+    .bind(&filters.until)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(PaginatedResponse::new(events, total, &params))
+}
+
+/// All events for one entity, including links and unlinks made from the
+/// other side of a relation (e.g. unlinking a share from an application
+/// shows up in the share's history too).
+pub async fn history(
+    pool: &SqlitePool,
+    entity_type: &str,
+    entity_id: &str,
+    params: &PaginationParams,
+) -> Result<PaginatedResponse<AuditEvent>> {
+    let limit = params.limit() as i32;
+    let offset = params.offset() as i32;
+
+    let events = sqlx::query_as::<_, AuditEvent>(
+        r#"
+        SELECT id, actor, entity_type, entity_id, entity_name, action, related_type, related_id, before, after, created_at
+        FROM audit_event
+        WHERE (entity_type = ?1 AND entity_id = ?2)
+           OR (related_type = ?1 AND related_id = ?2)
+        ORDER BY id DESC
+        LIMIT ?3 OFFSET ?4
+        "#,
+    )
+    .bind(entity_type)
+    .bind(entity_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let (total,) = sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT COUNT(*)
+        FROM audit_event
+        WHERE (entity_type = ?1 AND entity_id = ?2)
+           OR (related_type = ?1 AND related_id = ?2)
+        "#,
+    )
+    .bind(entity_type)
+    .bind(entity_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(PaginatedResponse::new(events, total, params))
+}
+
+/// Changes recorded after audit event `after_id`, oldest first
+pub async fn changes_since(
+    pool: &SqlitePool,
+    after_id: i64,
+    limit: i64,
+) -> Result<Vec<ChangeEvent>> {
+    sqlx::query_as::<_, ChangeEvent>(
+        r#"
+        SELECT id, actor, entity_type, entity_id, entity_name, action, related_type, related_id,
+               CASE WHEN action = 'update'
+                    THEN (SELECT json_group_array(key) FROM json_each(after))
+                    ELSE '[]' END AS fields,
+               created_at
+        FROM audit_event
+        WHERE id > ?1
+        ORDER BY id
+        LIMIT ?2
+        "#,
+    )
+    .bind(after_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Id of the latest audit event, 0 when there are none
+pub async fn last_id(pool: &SqlitePool) -> Result<i64> {
+    sqlx::query_scalar::<_, i64>("SELECT COALESCE(MAX(id), 0) FROM audit_event")
+        .fetch_one(pool)
+        .await
+        .map_err(Into::into)
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value> {
+    serde_json::to_value(value)
+        .map_err(|e| Error::InternalError(format!("Failed to serialize audit value: {e}")))
+}
+
+/// Human readable name of an entity snapshot: `name`, `fqdn` or `title`.
+fn display_name(value: &Value) -> Option<&str> {
+    ["name", "fqdn", "title"]
+        .iter()
+        .find_map(|key| value.get(*key).and_then(Value::as_str))
+}
+
+/// Look up the current name of an entity for link/unlink events.
+async fn entity_name(
+    conn: &mut SqliteConnection,
+    entity_type: &str,
+    id: &str,
+) -> Result<Option<String>> {
+    let query = match entity_type {
+        "application" => "SELECT name FROM application WHERE id = ?1",
+        "service" => "SELECT name FROM service WHERE id = ?1",
+        _ => return Ok(None),
+    };
+
+    Ok(sqlx::query_scalar::<_, String>(query)
+        .bind(id)
+        .fetch_optional(conn)
+        .await?)
+}
+
+/// Reduce two JSON objects to only the fields whose values differ.
+fn diff(before: &Value, after: &Value) -> (Value, Value) {
+    let (Some(before), Some(after)) = (before.as_object(), after.as_object()) else {
+        return (before.clone(), after.clone());
+    };
+
+    let mut changed_before = Map::new();
+    let mut changed_after = Map::new();
+
+    for (key, after_value) in after {
+        if IGNORED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+        let before_value = before.get(key).unwrap_or(&Value::Null);
+        if before_value != after_value {
+            changed_before.insert(key.clone(), before_value.clone());
+            changed_after.insert(key.clone(), after_value.clone());
+        }
+    }
+
+    (Value::Object(changed_before), Value::Object(changed_after))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use sqlx::types::Json;
+
+    use crate::service::application;
+
+    #[test]
+    fn diff_keeps_only_changed_fields() {
+        let before = json!({"name": "a", "status": "active", "updated_at": "1"});
+        let after = json!({"name": "b", "status": "active", "updated_at": "2"});
+
+        let (before, after) = diff(&before, &after);
+
+        assert_eq!(before, json!({"name": "a"}));
+        assert_eq!(after, json!({"name": "b"}));
+    }
+
+    #[test]
+    fn display_name_falls_back_to_fqdn_and_title() {
+        assert_eq!(display_name(&json!({"fqdn": "x.be"})), Some("x.be"));
+        assert_eq!(display_name(&json!({"title": "t"})), Some("t"));
+        assert_eq!(display_name(&json!({"id": "1"})), None);
+    }
+
+    #[tokio::test]
+    async fn unlink_keeps_what_the_link_was() {
+        let pool = crate::testing::pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO application (id, name) VALUES ('a', 'web');
+            INSERT INTO person (id, name) VALUES ('p', 'Jo');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        application::link_person(
+            &pool,
+            None,
+            "a",
+            "p",
+            "owner",
+            Some("2024-01-01"),
+            None,
+            Some("Lead"),
+        )
+        .await
+        .unwrap();
+        application::unlink_person(&pool, None, "a", "p")
+            .await
+            .unwrap();
+
+        let before = sqlx::query_scalar::<_, Json<Value>>(
+            "SELECT before FROM audit_event WHERE action = 'unlink'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            before.0,
+            json!({
+                "contribution_type": "owner",
+                "start_date": "2024-01-01",
+                "end_date": null,
+                "notes": "Lead",
+            })
+        );
+    }
+}
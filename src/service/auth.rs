@@ -0,0 +1,399 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+
+use crate::models::{
+    ApiToken, AuditAction, CreateApiToken, CreatePerson, Identity, NewApiToken, OidcLogin, new_id,
+};
+use crate::oidc::IdTokenClaims;
+use crate::service::{audit, person};
+use crate::{Error, Result};
+
+/// Prefix of every generated secret, so leaked tokens are easy to recognise.
+const SECRET_PREFIX: &str = "auto_";
+
+/// Number of characters of a token kept in plain text for listings.
+const VISIBLE_PREFIX_LEN: usize = 12;
+
+/// How long a cookie session stays valid.
+pub const SESSION_TTL_DAYS: i64 = 14;
+
+/// How long a pending OIDC login may take before its callback is rejected.
+pub const OIDC_LOGIN_TTL_MINUTES: i64 = 10;
+
+/// Name of the bootstrap admin identity configured via `AUTH_ADMIN_TOKEN`.
+pub const ADMIN_NAME: &str = "admin";
+
+/// Generate a new random secret for an API token or session.
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    format!("{SECRET_PREFIX}{}", hex::encode(bytes))
+}
+
+/// SHA-256 hash of a secret, the only form in which secrets are stored.
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+/// Check a presented secret against the configured bootstrap admin token.
+pub fn is_admin_token(secret: &str, admin_token: Option<&str>) -> bool {
+    // Compare hashes rather than the raw secrets, so the comparison time
+    // doesn't reveal how much of the token was right.
+    admin_token.is_some_and(|admin| hash_secret(admin) == hash_secret(secret))
+}
+
+pub async fn list_tokens(pool: &SqlitePool, person_id: Option<&str>) -> Result<Vec<ApiToken>> {
+    let tokens = sqlx::query_as::<_, ApiToken>(
+        r#"
+        SELECT id, person_id, name, prefix, expires_at, last_used_at, created_at
+        FROM api_token
+        WHERE (?1 IS NULL OR person_id = ?1)
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(person_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tokens)
+}
+
+pub async fn get_token(pool: &SqlitePool, id: &str) -> Result<ApiToken> {
+    sqlx::query_as::<_, ApiToken>(
+        r#"
+        SELECT id, person_id, name, prefix, expires_at, last_used_at, created_at
+        FROM api_token
+        WHERE id = ?1
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound(format!("API token with id '{}' not found", id)))
+}
+
+pub async fn create_token(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    person_id: &str,
+    input: CreateApiToken,
+) -> Result<NewApiToken> {
+    if input.name.trim().is_empty() {
+        return Err(Error::ValidationError("Token name is required".to_string()));
+    }
+
+    // Make sure the owner exists before handing out a token for it
+    person::get(pool, person_id).await?;
+
+    let expires_at = match &input.expires_at {
+        Some(expires_at) => Some(
+            sqlx::query_scalar::<_, Option<String>>("SELECT datetime(?1)")
+                .bind(expires_at)
+                .fetch_one(pool)
+                .await?
+                .ok_or_else(|| {
+                    Error::ValidationError(format!("Invalid expires_at '{}'", expires_at))
+                })?,
+        ),
+        None => None,
+    };
+
+    let id = new_id();
+    let secret = generate_secret();
+    let prefix = secret[..VISIBLE_PREFIX_LEN].to_string();
+
+    sqlx::query(
+        r#"
+        INSERT INTO api_token (id, person_id, name, token_hash, prefix, expires_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        "#,
+    )
+    .bind(&id)
+    .bind(person_id)
+    .bind(&input.name)
+    .bind(hash_secret(&secret))
+    .bind(&prefix)
+    .bind(&expires_at)
+    .execute(pool)
+    .await?;
+
+    let token = get_token(pool, &id).await?;
+    audit::record(
+        pool,
+        actor,
+        "api_token",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&token),
+    )
+    .await?;
+
+    Ok(NewApiToken { token, secret })
+}
+
+pub async fn delete_token(pool: &SqlitePool, actor: Option<&str>, id: &str) -> Result<()> {
+    let existing = get_token(pool, id).await?;
+
+    sqlx::query("DELETE FROM api_token WHERE id = ?1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    audit::record(
+        pool,
+        actor,
+        "api_token",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Resolve a Bearer token to the identity of its (active) owner.
+pub async fn authenticate_token(pool: &SqlitePool, secret: &str) -> Result<Option<Identity>> {
+    let token_hash = hash_secret(secret);
+
+    let identity = sqlx::query_as::<_, Identity>(
+        r#"
+        SELECT p.id as person_id, COALESCE(p.email, p.name) as name, p.access_role as role
+        FROM api_token t
+        JOIN person p ON p.id = t.person_id
+        WHERE t.token_hash = ?1
+          AND p.is_active = 1
+          AND p.deleted_at IS NULL
+          AND (t.expires_at IS NULL OR t.expires_at > datetime('now'))
+        "#,
+    )
+    .bind(&token_hash)
+    .fetch_optional(pool)
+    .await?;
+
+    if identity.is_some() {
+        sqlx::query("UPDATE api_token SET last_used_at = datetime('now') WHERE token_hash = ?1")
+            .bind(&token_hash)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(identity)
+}
+
+/// Start a cookie session for an identity, returning the cookie value.
+pub async fn create_session(pool: &SqlitePool, identity: &Identity) -> Result<String> {
+    // Opportunistically clean up sessions that can no longer be used
+    sqlx::query("DELETE FROM session WHERE expires_at <= datetime('now')")
+        .execute(pool)
+        .await?;
+
+    let secret = generate_secret();
+
+    sqlx::query(
+        r#"
+        INSERT INTO session (id_hash, person_id, name, expires_at)
+        VALUES (?1, ?2, ?3, datetime('now', ?4))
+        "#,
+    )
+    .bind(hash_secret(&secret))
+    .bind(&identity.person_id)
+    .bind(&identity.name)
+    .bind(format!("+{SESSION_TTL_DAYS} days"))
+    .execute(pool)
+    .await?;
+
+    Ok(secret)
+}
+
+/// Resolve a session cookie to the identity that started the session.
+pub async fn authenticate_session(pool: &SqlitePool, secret: &str) -> Result<Option<Identity>> {
+    let identity = sqlx::query_as::<_, Identity>(
+        r#"
+        SELECT s.person_id, COALESCE(p.email, p.name, s.name) as name,
+               COALESCE(p.access_role, 'admin') as role
+        FROM session s
+        LEFT JOIN person p ON p.id = s.person_id
+        WHERE s.id_hash = ?1
+          AND s.expires_at > datetime('now')
+          AND (s.person_id IS NULL OR (p.is_active = 1 AND p.deleted_at IS NULL))
+        "#,
+    )
+    .bind(hash_secret(secret))
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(identity)
+}
+
+pub async fn delete_session(pool: &SqlitePool, secret: &str) -> Result<()> {
+    sqlx::query("DELETE FROM session WHERE id_hash = ?1")
+        .bind(hash_secret(secret))
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Remember a pending OIDC login until the provider redirects back.
+pub async fn create_oidc_login(pool: &SqlitePool, login: &OidcLogin) -> Result<()> {
+    sqlx::query("DELETE FROM oidc_login WHERE created_at <= datetime('now', ?1)")
+        .bind(format!("-{OIDC_LOGIN_TTL_MINUTES} minutes"))
+        .execute(pool)
+        .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO oidc_login (state, nonce, code_verifier, redirect_to)
+        VALUES (?1, ?2, ?3, ?4)
+        "#,
+    )
+    .bind(&login.state)
+    .bind(&login.nonce)
+    .bind(&login.code_verifier)
+    .bind(&login.redirect_to)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Look up and consume a pending OIDC login, so every `state` is used once.
+pub async fn take_oidc_login(pool: &SqlitePool, state: &str) -> Result<OidcLogin> {
+    sqlx::query_as::<_, OidcLogin>(
+        r#"
+        DELETE FROM oidc_login
+        WHERE state = ?1
+          AND created_at > datetime('now', ?2)
+        RETURNING state, nonce, code_verifier, redirect_to
+        "#,
+    )
+    .bind(state)
+    .bind(format!("-{OIDC_LOGIN_TTL_MINUTES} minutes"))
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::Unauthorized("Unknown or expired login attempt".to_string()))
+}
+
+/// Match the verified claims of an ID token to a person by subject
+/// identifier. On first login the person is matched by email instead, and
+/// created (as viewer) if there is none.
+pub async fn identity_from_oidc(pool: &SqlitePool, claims: &IdTokenClaims) -> Result<Identity> {
+    let email = claims.email.as_deref().ok_or_else(|| {
+        Error::Unauthorized("The identity provider did not return an email address".to_string())
+    })?;
+    if claims.email_verified == Some(false) {
+        return Err(Error::Unauthorized(format!(
+            "Email address '{email}' is not verified"
+        )));
+    }
+
+    let person = if let Some(person) = person::find_by_oidc_sub(pool, &claims.sub).await? {
+        person
+    } else if let Some(person) = person::link_oidc_sub(pool, email, &claims.sub).await? {
+        person
+    } else if person::find_by_email(pool, email).await?.is_some() {
+        return Err(Error::Unauthorized(format!(
+            "The account of '{email}' belongs to another login"
+        )));
+    } else if person::is_login_in_trash(pool, &claims.sub, email).await? {
+        return Err(Error::Unauthorized(format!(
+            "The account of '{email}' is deleted"
+        )));
+    } else {
+        let name = claims
+            .name
+            .clone()
+            .or_else(|| claims.preferred_username.clone())
+            .unwrap_or_else(|| email.to_string());
+        let input = CreatePerson {
+            name,
+            email: Some(email.to_string()),
+            role: None,
+            department: None,
+            phone: None,
+            is_active: true,
+            notes: None,
+            access_role: None,
+        };
+        let person = person::create(pool, Some(email), input).await?;
+        person::link_oidc_sub(pool, email, &claims.sub)
+            .await?
+            .unwrap_or(person)
+    };
+
+    if !person.is_active {
+        return Err(Error::Unauthorized(format!(
+            "The account of '{email}' is deactivated"
+        )));
+    }
+
+    Ok(Identity {
+        person_id: Some(person.id),
+        name: person.email.unwrap_or(person.name),
+        role: person.access_role,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_secrets_are_unique_and_prefixed() {
+        let a = generate_secret();
+        let b = generate_secret();
+
+        assert!(a.starts_with(SECRET_PREFIX));
+        assert_ne!(a, b);
+        assert_ne!(hash_secret(&a), hash_secret(&b));
+    }
+
+    #[test]
+    fn admin_token_must_match_exactly() {
+        assert!(is_admin_token("secret", Some("secret")));
+        assert!(!is_admin_token("secret ", Some("secret")));
+        assert!(!is_admin_token("secret", None));
+    }
+
+    #[tokio::test]
+    async fn oidc_login_sticks_to_the_first_subject() {
+        let pool = crate::testing::pool().await;
+        let claims = |sub: &str, email: &str| IdTokenClaims {
+            sub: sub.to_string(),
+            email: Some(email.to_string()),
+            email_verified: Some(true),
+            name: None,
+            preferred_username: None,
+            nonce: None,
+        };
+
+        let first = identity_from_oidc(&pool, &claims("alice", "alice@example.com"))
+            .await
+            .unwrap();
+        let id = first.person_id.unwrap();
+        let update = crate::models::UpdatePerson {
+            name: None,
+            email: Some("mallory@example.com".to_string()),
+            role: None,
+            department: None,
+            phone: None,
+            is_active: None,
+            notes: None,
+            access_role: None,
+            version: None,
+        };
+        person::update(&pool, None, &id, update).await.unwrap();
+
+        // The new email address doesn't give another login the account
+        let other = identity_from_oidc(&pool, &claims("mallory", "Mallory@example.com")).await;
+        assert!(matches!(other, Err(Error::Unauthorized(_))));
+        let again = identity_from_oidc(&pool, &claims("alice", "alice@example.com"))
+            .await
+            .unwrap();
+        assert_eq!(again.person_id.as_deref(), Some(id.as_str()));
+    }
+}
@@ -0,0 +1,181 @@
+//! Inventory of the TLS certificates domains serve, see [`crate::tls`].
+
+use std::time::Duration;
+
+use futures::{StreamExt as _, stream};
+use sqlx::SqlitePool;
+use sqlx::types::Json;
+
+use crate::models::{Certificate, Domain};
+use crate::tls::{self, PeerCertificate};
+use crate::{Result, service};
+
+/// Port checked for domains without TLS healthchecks
+const DEFAULT_PORT: u16 = 443;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Certificates of a domain, by port
+pub async fn list_for_domain(pool: &SqlitePool, domain_id: &str) -> Result<Vec<Certificate>> {
+    sqlx::query_as::<_, Certificate>(
+        r#"
+        SELECT domain_id, port, subject, issuer, sans, serial, not_before, not_after,
+               chain_valid, chain_error, error, checked_at
+        FROM certificate
+        WHERE domain_id = ?1
+        ORDER BY port
+        "#,
+    )
+    .bind(domain_id)
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Ports to check a domain's certificates on: those of its TLS healthchecks,
+/// or 443
+async fn ports(pool: &SqlitePool, domain_id: &str) -> Result<Vec<u16>> {
+    let ports = sqlx::query_as::<_, (Option<i64>,)>(
+        r#"
+        SELECT port
+        FROM healthcheck
+        WHERE domain_id = ?1 AND kind = 'tls' AND deleted_at IS NULL
+        "#,
+    )
+    .bind(domain_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(certificate_ports(ports.into_iter().map(|(port,)| port)))
+}
+
+fn certificate_ports(healthcheck_ports: impl Iterator<Item = Option<i64>>) -> Vec<u16> {
+    let mut ports: Vec<u16> = healthcheck_ports
+        .map(|port| {
+            port.and_then(|p| u16::try_from(p).ok())
+                .unwrap_or(DEFAULT_PORT)
+        })
+        .collect();
+    ports.sort_unstable();
+    ports.dedup();
+    if ports.is_empty() {
+        ports.push(DEFAULT_PORT);
+    }
+    ports
+}
+
+/// Connects to a domain on each of its ports and records the certificates.
+/// Certificates of ports that are no longer checked are removed.
+pub async fn check(pool: &SqlitePool, domain: &Domain) -> Result<()> {
+    let ports = ports(pool, &domain.id).await?;
+
+    for &port in &ports {
+        let fetched = tokio::time::timeout(CHECK_TIMEOUT, tls::fetch(&domain.fqdn, port))
+            .await
+            .unwrap_or_else(|_| Err(format!("Timed out after {}s", CHECK_TIMEOUT.as_secs())));
+        match fetched {
+            Ok(certificate) => record(pool, &domain.id, port, &certificate).await?,
+            Err(e) => failed(pool, &domain.id, port, &e).await?,
+        }
+    }
+
+    let ports = serde_json::to_string(&ports).unwrap_or_default();
+    sqlx::query(
+        r#"
+        DELETE FROM certificate
+        WHERE domain_id = ?1 AND port NOT IN (SELECT value FROM json_each(?2))
+        "#,
+    )
+    .bind(&domain.id)
+    .bind(ports)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Checks the certificates of every domain that is not in the trash.
+pub async fn check_all(pool: &SqlitePool) -> Result<()> {
+    let domains = sqlx::query_as::<_, (String,)>(
+        "SELECT id FROM domain WHERE deleted_at IS NULL ORDER BY fqdn COLLATE NOCASE",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let results: Vec<Result<()>> = stream::iter(domains)
+        .map(|(id,)| async move {
+            let domain = service::domain::get(pool, &id).await?;
+            check(pool, &domain).await
+        })
+        .buffer_unordered(10)
+        .collect()
+        .await;
+    results.into_iter().collect()
+}
+
+async fn record(
+    pool: &SqlitePool,
+    domain_id: &str,
+    port: u16,
+    certificate: &PeerCertificate,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO certificate (domain_id, port, subject, issuer, sans, serial, not_before,
+                                 not_after, chain_valid, chain_error)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime(?7), datetime(?8), ?9, ?10)
+        ON CONFLICT (domain_id, port) DO UPDATE SET
+            subject = excluded.subject, issuer = excluded.issuer, sans = excluded.sans,
+            serial = excluded.serial, not_before = excluded.not_before,
+            not_after = excluded.not_after, chain_valid = excluded.chain_valid,
+            chain_error = excluded.chain_error, error = NULL, checked_at = datetime('now')
+        "#,
+    )
+    .bind(domain_id)
+    .bind(port)
+    .bind(&certificate.subject)
+    .bind(&certificate.issuer)
+    .bind(Json(&certificate.sans))
+    .bind(&certificate.serial)
+    .bind(certificate.not_before.to_rfc3339())
+    .bind(certificate.not_after.to_rfc3339())
+    .bind(certificate.chain_error.is_none())
+    .bind(&certificate.chain_error)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a failed check, keeping the certificate found before.
+async fn failed(pool: &SqlitePool, domain_id: &str, port: u16, error: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO certificate (domain_id, port, error)
+        VALUES (?1, ?2, ?3)
+        ON CONFLICT (domain_id, port) DO UPDATE SET
+            error = excluded.error, checked_at = datetime('now')
+        "#,
+    )
+    .bind(domain_id)
+    .bind(port)
+    .bind(error)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ports_of_tls_healthchecks_or_443() {
+        assert_eq!(certificate_ports(std::iter::empty()), vec![443]);
+        assert_eq!(
+            certificate_ports([Some(8443), None, Some(8443)].into_iter()),
+            vec![443, 8443]
+        );
+        assert_eq!(certificate_ports([Some(993)].into_iter()), vec![993]);
+    }
+}
@@ -0,0 +1,601 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+use serde_json::{Map, Value};
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::models::{CreateApplication, CreateDomain, CreatePerson, CsvImportReport, CsvRowReport};
+use crate::service::{application, domain, person};
+use crate::{Error, Result};
+
+/// Separator between several names in one relation column.
+const LIST_SEPARATOR: char = ';';
+
+/// Contribution type of people linked to an application by an import.
+const DEFAULT_CONTRIBUTION: &str = "developer";
+
+/// The kind of entity a CSV file creates, one per row.
+#[derive(Debug, Clone, Copy)]
+pub enum CsvEntity {
+    Application,
+    Domain,
+    Person,
+}
+
+impl CsvEntity {
+    /// Columns that map onto a field of the create DTO.
+    fn fields(&self) -> &'static [&'static str] {
+        match self {
+            CsvEntity::Application => &[
+                "name",
+                "description",
+                "repository_url",
+                "environment",
+                "url",
+                "status",
+                "image_refs",
+                "outline_url",
+            ],
+            CsvEntity::Domain => &["fqdn", "registrar", "dns_provider", "expires_at", "notes"],
+            CsvEntity::Person => &[
+                "name",
+                "email",
+                "role",
+                "department",
+                "phone",
+                "is_active",
+                "notes",
+            ],
+        }
+    }
+
+    /// Columns that name related entities, resolved to their IDs.
+    fn relations(&self) -> &'static [(&'static str, Related)] {
+        match self {
+            CsvEntity::Application => &[
+                ("infra", Related::Infra),
+                ("services", Related::Service),
+                ("domains", Related::Domain),
+                ("people", Related::Person),
+                ("shares", Related::NetworkShare),
+                ("stacks", Related::Stack),
+            ],
+            CsvEntity::Domain => &[
+                ("target_application", Related::Application),
+                ("target_service", Related::Service),
+                ("applications", Related::Application),
+            ],
+            CsvEntity::Person => &[("applications", Related::Application)],
+        }
+    }
+
+    fn columns(&self) -> impl Iterator<Item = &'static str> {
+        self.fields()
+            .iter()
+            .copied()
+            .chain(self.relations().iter().map(|(column, _)| *column))
+    }
+}
+
+/// An entity referenced by name (or ID) in a relation column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Related {
+    Application,
+    Service,
+    Infra,
+    Domain,
+    Person,
+    NetworkShare,
+    Stack,
+}
+
+impl Related {
+    fn label(&self) -> &'static str {
+        match self {
+            Related::Application => "application",
+            Related::Service => "service",
+            Related::Infra => "infra",
+            Related::Domain => "domain",
+            Related::Person => "person",
+            Related::NetworkShare => "network share",
+            Related::Stack => "stack",
+        }
+    }
+
+    /// Query returning the ID and environment (if any) of every match.
+    fn lookup(&self) -> &'static str {
+        match self {
+            Related::Application => {
+                "SELECT id, environment FROM application WHERE deleted_at IS NULL AND (id = ?1 OR name = ?1 COLLATE NOCASE)"
+            }
+            Related::Service => {
+                "SELECT id, environment FROM service WHERE deleted_at IS NULL AND (id = ?1 OR name = ?1 COLLATE NOCASE)"
+            }
+            Related::Infra => {
+                "SELECT id, NULL FROM infra WHERE deleted_at IS NULL AND (id = ?1 OR name = ?1 COLLATE NOCASE)"
+            }
+            Related::Domain => {
+                "SELECT id, NULL FROM domain WHERE deleted_at IS NULL AND (id = ?1 OR fqdn = ?1 COLLATE NOCASE)"
+            }
+            Related::Person => {
+                "SELECT id, NULL FROM person WHERE deleted_at IS NULL AND (id = ?1 OR name = ?1 COLLATE NOCASE OR email = ?1 COLLATE NOCASE)"
+            }
+            Related::NetworkShare => {
+                "SELECT id, NULL FROM network_share WHERE deleted_at IS NULL AND (id = ?1 OR name = ?1 COLLATE NOCASE)"
+            }
+            Related::Stack => {
+                "SELECT id, NULL FROM stack WHERE deleted_at IS NULL AND (id = ?1 OR name = ?1 COLLATE NOCASE)"
+            }
+        }
+    }
+}
+
+/// One data row of a CSV file, keyed by field name.
+struct CsvRow {
+    line: usize,
+    values: HashMap<String, String>,
+    errors: Vec<String>,
+}
+
+impl CsvRow {
+    fn get(&self, column: &str) -> Option<&str> {
+        self.values.get(column).map(String::as_str)
+    }
+}
+
+/// What to create for a row.
+enum Plan {
+    Application(CreateApplication),
+    Domain(CreateDomain),
+    Person(CreatePerson),
+}
+
+/// A validated row, ready to be created.
+struct ValidRow {
+    plan: Plan,
+    /// Must be unique within the file, e.g. the fqdn of a domain
+    key: Option<String>,
+    links: Vec<(Related, String)>,
+}
+
+/// Turn a header into a field name: `Repository URL` becomes `repository_url`.
+fn normalize_header(header: &str) -> String {
+    header.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+fn parse(data: &[u8], mapping: &HashMap<String, String>, entity: CsvEntity) -> Result<Vec<CsvRow>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|e| Error::ValidationError(format!("Invalid CSV header: {e}")))?
+        .clone();
+
+    if let Some(header) = mapping.keys().find(|h| !headers.iter().any(|x| x == *h)) {
+        return Err(Error::ValidationError(format!(
+            "Mapped column '{header}' is not in the file"
+        )));
+    }
+
+    let mut columns = Vec::new();
+    for header in &headers {
+        let field = mapping
+            .get(header)
+            .map(|field| field.trim().to_string())
+            .unwrap_or_else(|| normalize_header(header));
+
+        if field.is_empty() {
+            columns.push(None);
+            continue;
+        }
+        if !entity.columns().any(|column| column == field) {
+            return Err(Error::ValidationError(format!(
+                "Column '{header}' doesn't match a field, expected one of: {}",
+                entity.columns().join(", ")
+            )));
+        }
+        if columns.contains(&Some(field.clone())) {
+            return Err(Error::ValidationError(format!(
+                "More than one column maps to '{field}'"
+            )));
+        }
+        columns.push(Some(field));
+    }
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record.map_err(|e| Error::ValidationError(format!("Invalid CSV: {e}")))?;
+            let line = record.position().map_or(0, |p| p.line() as usize);
+
+            let mut errors = Vec::new();
+            if record.len() != headers.len() {
+                errors.push(format!(
+                    "Expected {} fields, found {}",
+                    headers.len(),
+                    record.len()
+                ));
+            }
+
+            let values = columns
+                .iter()
+                .zip(record.iter())
+                .filter(|(_, value)| !value.is_empty())
+                .filter_map(|(column, value)| Some((column.clone()?, value.to_string())))
+                .collect();
+
+            Ok(CsvRow {
+                line,
+                values,
+                errors,
+            })
+        })
+        .collect()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" => Some(true),
+        "false" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Find the ID of an entity by name or ID. Names matching several
+/// applications or services are narrowed down to the given environment.
+async fn resolve(
+    pool: &SqlitePool,
+    related: Related,
+    value: &str,
+    environment: Option<&str>,
+) -> Result<std::result::Result<String, String>> {
+    let matches = sqlx::query_as::<_, (String, Option<String>)>(related.lookup())
+        .bind(value)
+        .fetch_all(pool)
+        .await?;
+
+    let matches = match environment {
+        Some(environment) if matches.len() > 1 => matches
+            .into_iter()
+            .filter(|(_, env)| env.as_deref() == Some(environment))
+            .collect(),
+        _ => matches,
+    };
+
+    Ok(match matches.as_slice() {
+        [(id, _)] => Ok(id.clone()),
+        [] => Err(format!("No {} named '{value}'", related.label())),
+        _ => Err(format!(
+            "'{value}' matches {} {}s, use its ID instead",
+            matches.len(),
+            related.label()
+        )),
+    })
+}
+
+/// Validate a row and resolve its relations, or list every problem with it.
+async fn validate(
+    pool: &SqlitePool,
+    entity: CsvEntity,
+    row: &CsvRow,
+) -> Result<std::result::Result<ValidRow, Vec<String>>> {
+    let mut errors = row.errors.clone();
+
+    let mut fields = Map::new();
+    for field in entity.fields() {
+        let Some(value) = row.get(field) else {
+            continue;
+        };
+        let value = if *field == "is_active" {
+            match parse_bool(value) {
+                Some(value) => Value::Bool(value),
+                None => {
+                    errors.push(format!("Invalid {field} '{value}', expected true or false"));
+                    continue;
+                }
+            }
+        } else {
+            Value::String(value.to_string())
+        };
+        fields.insert(field.to_string(), value);
+    }
+
+    let environment = match entity {
+        CsvEntity::Application => Some(row.get("environment").unwrap_or("prd")),
+        _ => None,
+    };
+
+    let mut links = Vec::new();
+    for (column, related) in entity.relations() {
+        let Some(value) = row.get(column) else {
+            continue;
+        };
+        for name in value.split(LIST_SEPARATOR).map(str::trim) {
+            if name.is_empty() {
+                continue;
+            }
+            match resolve(pool, *related, name, environment).await? {
+                Ok(id) if column.starts_with("target_") => {
+                    fields.insert(format!("{column}_id"), Value::String(id));
+                }
+                Ok(id) => links.push((*related, id)),
+                Err(error) => errors.push(error),
+            }
+        }
+    }
+
+    let plan = match entity {
+        CsvEntity::Application => {
+            serde_json::from_value(Value::Object(fields)).map(Plan::Application)
+        }
+        CsvEntity::Domain => serde_json::from_value(Value::Object(fields)).map(Plan::Domain),
+        CsvEntity::Person => serde_json::from_value(Value::Object(fields)).map(Plan::Person),
+    };
+    let plan = match plan {
+        Ok(plan) => plan,
+        Err(e) => {
+            errors.push(format!("Invalid row: {e}"));
+            return Ok(Err(errors));
+        }
+    };
+
+    let key = match &plan {
+        Plan::Application(input) => {
+            let (exists,) = sqlx::query_as::<_, (bool,)>(
+                "SELECT EXISTS(SELECT 1 FROM application WHERE name = ?1 AND environment = ?2 AND deleted_at IS NULL)",
+            )
+            .bind(&input.name)
+            .bind(&input.environment)
+            .fetch_one(pool)
+            .await?;
+            if exists {
+                errors.push(format!(
+                    "Application '{}' already exists in environment '{}'",
+                    input.name, input.environment
+                ));
+            }
+            Some(format!("{}\n{}", input.name, input.environment))
+        }
+        Plan::Domain(input) => {
+            let (exists,) = sqlx::query_as::<_, (bool,)>(
+                "SELECT EXISTS(SELECT 1 FROM domain WHERE fqdn = ?1 COLLATE NOCASE AND deleted_at IS NULL)",
+            )
+            .bind(&input.fqdn)
+            .fetch_one(pool)
+            .await?;
+            if exists {
+                errors.push(format!("Domain '{}' already exists", input.fqdn));
+            }
+            if input.target_application_id.is_some() == input.target_service_id.is_some() {
+                errors.push(
+                    "Exactly one of target_application and target_service is required".to_string(),
+                );
+            }
+            Some(input.fqdn.to_lowercase())
+        }
+        Plan::Person(input) => {
+            if let Some(email) = &input.email
+                && person::find_by_email(pool, email).await?.is_some()
+            {
+                errors.push(format!("A person with email '{email}' already exists"));
+            }
+            input.email.as_ref().map(|email| email.to_lowercase())
+        }
+    };
+
+    if errors.is_empty() {
+        Ok(Ok(ValidRow { plan, key, links }))
+    } else {
+        Ok(Err(errors))
+    }
+}
+
+/// Link an application to an entity of another kind.
+async fn link_application(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    app_id: &str,
+    related: Related,
+    id: &str,
+) -> Result<()> {
+    match related {
+        Related::Infra => application::link_infra_in(conn, actor, app_id, id, None).await,
+        Related::Service => application::link_service_in(conn, actor, app_id, id, None).await,
+        Related::Domain => application::link_domain_in(conn, actor, app_id, id, None).await,
+        Related::Person => {
+            application::link_person_in(
+                conn,
+                actor,
+                app_id,
+                id,
+                DEFAULT_CONTRIBUTION,
+                None,
+                None,
+                None,
+            )
+            .await
+        }
+        Related::NetworkShare => {
+            application::link_network_share_in(conn, actor, app_id, id, None, None, None, None)
+                .await
+        }
+        Related::Stack => application::link_stack_in(conn, actor, app_id, id).await,
+        Related::Application => Err(Error::InternalError(
+            "Applications can't be linked to applications".to_string(),
+        )),
+    }
+}
+
+/// Create the entity of a validated row and link it, returning its ID.
+async fn create(conn: &mut SqliteConnection, actor: Option<&str>, row: ValidRow) -> Result<String> {
+    let ValidRow { plan, links, .. } = row;
+    match plan {
+        Plan::Application(input) => {
+            let created = application::create_in(&mut *conn, actor, input).await?;
+            for (related, id) in links {
+                link_application(&mut *conn, actor, &created.id, related, &id).await?;
+            }
+            Ok(created.id)
+        }
+        Plan::Domain(input) => {
+            let created = domain::create_in(&mut *conn, actor, input).await?;
+            for (_, app_id) in links {
+                link_application(&mut *conn, actor, &app_id, Related::Domain, &created.id).await?;
+            }
+            Ok(created.id)
+        }
+        Plan::Person(input) => {
+            let created = person::create_in(&mut *conn, actor, input).await?;
+            for (_, app_id) in links {
+                link_application(&mut *conn, actor, &app_id, Related::Person, &created.id).await?;
+            }
+            Ok(created.id)
+        }
+    }
+}
+
+/// Create one entity per CSV row.
+///
+/// Every row is validated first. Unless all rows are valid or `partial` is
+/// set, nothing is created and the report only lists the problems. The rows
+/// are created in one transaction, so nothing is kept if one of them fails.
+pub async fn import(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    entity: CsvEntity,
+    data: &[u8],
+    mapping: &HashMap<String, String>,
+    partial: bool,
+) -> Result<CsvImportReport> {
+    let rows = parse(data, mapping, entity)?;
+
+    let mut keys = HashSet::new();
+    let mut validated = Vec::new();
+    for row in &rows {
+        let result = match validate(pool, entity, row).await? {
+            Ok(ValidRow { key: Some(key), .. }) if !keys.insert(key.clone()) => {
+                Err(vec!["Duplicate of an earlier row".to_string()])
+            }
+            result => result,
+        };
+        validated.push((row.line, result));
+    }
+
+    let committed = partial || validated.iter().all(|(_, result)| result.is_ok());
+
+    let mut tx = pool.begin().await?;
+    let mut reports = Vec::new();
+    let mut failed = false;
+    for (line, result) in validated {
+        let (id, errors) = match result {
+            Ok(row) if committed && !failed => match create(&mut tx, actor, row).await {
+                Ok(id) => (Some(id), Vec::new()),
+                Err(e) => {
+                    failed = true;
+                    (None, vec![e.to_string()])
+                }
+            },
+            Ok(_) => (None, Vec::new()),
+            Err(errors) => (None, errors),
+        };
+        reports.push(CsvRowReport { line, id, errors });
+    }
+
+    let committed = committed && !failed;
+    if committed {
+        tx.commit().await?;
+    } else {
+        tx.rollback().await?;
+        for report in &mut reports {
+            report.id = None;
+        }
+    }
+
+    Ok(CsvImportReport {
+        committed,
+        total: reports.len(),
+        imported: reports.iter().filter(|r| r.id.is_some()).count(),
+        failed: reports.iter().filter(|r| !r.errors.is_empty()).count(),
+        rows: reports,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_are_normalized_to_field_names() {
+        assert_eq!(normalize_header(" Repository URL "), "repository_url");
+        assert_eq!(normalize_header("dns-provider"), "dns_provider");
+    }
+
+    #[test]
+    fn parse_applies_mapping_and_reports_short_rows() {
+        let data = b"App,Cluster,Ignored\nweb,c1,x\napi\n";
+        let mapping = HashMap::from([
+            ("App".to_string(), "name".to_string()),
+            ("Cluster".to_string(), "infra".to_string()),
+            ("Ignored".to_string(), String::new()),
+        ]);
+
+        let rows = parse(data, &mapping, CsvEntity::Application).unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].line, 2);
+        assert_eq!(rows[0].get("name"), Some("web"));
+        assert_eq!(rows[0].get("infra"), Some("c1"));
+        assert_eq!(rows[0].values.len(), 2);
+        assert!(rows[0].errors.is_empty());
+        assert_eq!(rows[1].errors.len(), 1);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_columns() {
+        let result = parse(
+            b"name,colour\nweb,red\n",
+            &HashMap::new(),
+            CsvEntity::Application,
+        );
+        assert!(matches!(result, Err(Error::ValidationError(_))));
+    }
+
+    #[tokio::test]
+    async fn failed_row_rolls_back_the_whole_import() {
+        let pool = crate::testing::pool().await;
+        // Rows that pass validation but fail to insert
+        sqlx::query(
+            r#"
+            CREATE TRIGGER fail_import BEFORE INSERT ON application WHEN NEW.name = 'broken'
+            BEGIN SELECT RAISE(ABORT, 'insert failed'); END
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let report = import(
+            &pool,
+            None,
+            CsvEntity::Application,
+            b"name\nweb\nbroken\napi\n",
+            &HashMap::new(),
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert!(!report.committed);
+        assert_eq!(report.imported, 0);
+        assert!(report.rows[1].errors[0].contains("insert failed"));
+        let kept: (i64, i64) = sqlx::query_as(
+            "SELECT (SELECT COUNT(*) FROM application), (SELECT COUNT(*) FROM audit_event)",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(kept, (0, 0));
+    }
+}
@@ -15,6 +15,7 @@ pub struct DashboardStats {
     pub network_shares: EntityStats,
     pub notes: i64,
     pub expiring_domains: Vec<ExpiringDomain>,
+    pub expiring_certificates: Vec<ExpiringCertificate>,
     pub healthchecks: HealthcheckStats,
     pub recent_activity: Vec<RecentActivity>,
 }
@@ -32,6 +33,8 @@ pub struct RecentActivity {
     pub name: String,
     pub entity_type: String,
     pub updated_at: String,
+    pub action: String,
+    pub actor: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -47,57 +50,76 @@ pub struct ExpiringDomain {
     pub expires_at: Option<String>,
 }
 
+/// A certificate that expires soon, or already expired but is still served
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ExpiringCertificate {
+    pub domain_id: String,
+    pub fqdn: String,
+    pub port: i64,
+    pub issuer: Option<String>,
+    pub not_after: String,
+}
+
 pub async fn get_stats(pool: &SqlitePool) -> Result<DashboardStats> {
     // Get application stats
     info!("Application stats");
-    let app_total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM application")
-        .fetch_one(pool)
-        .await?;
-    let app_active: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM application WHERE status = 'active'")
+    let app_total: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM application WHERE deleted_at IS NULL")
             .fetch_one(pool)
             .await?;
+    let app_active: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM application WHERE status = 'active' AND deleted_at IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
 
     // Get service stats
     info!("Service stats");
-    let service_total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM service")
-        .fetch_one(pool)
-        .await?;
-    let service_active: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM service WHERE status = 'active'")
+    let service_total: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM service WHERE deleted_at IS NULL")
             .fetch_one(pool)
             .await?;
+    let service_active: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM service WHERE status = 'active' AND deleted_at IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
 
     // Get infra stats
     info!("Infra stats");
-    let infra_total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM infra")
+    let infra_total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM infra WHERE deleted_at IS NULL")
         .fetch_one(pool)
         .await?;
 
     // Get domain stats
     info!("Domain stats");
-    let domain_total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM domain")
-        .fetch_one(pool)
-        .await?;
+    let domain_total: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM domain WHERE deleted_at IS NULL")
+            .fetch_one(pool)
+            .await?;
 
     // Get person stats
     info!("Person stats");
-    let person_total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM person")
-        .fetch_one(pool)
-        .await?;
-    let person_active: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM person WHERE is_active = 1")
-        .fetch_one(pool)
-        .await?;
+    let person_total: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM person WHERE deleted_at IS NULL")
+            .fetch_one(pool)
+            .await?;
+    let person_active: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM person WHERE is_active = 1 AND deleted_at IS NULL")
+            .fetch_one(pool)
+            .await?;
 
     // Get network share stats
     info!("Network stats");
-    let share_total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM network_share")
-        .fetch_one(pool)
-        .await?;
-    let share_active: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM network_share WHERE status = 'active'")
+    let share_total: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM network_share WHERE deleted_at IS NULL")
             .fetch_one(pool)
             .await?;
+    let share_active: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM network_share WHERE status = 'active' AND deleted_at IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
 
     // Get note count
     info!("Note stats");
@@ -107,35 +129,29 @@ pub async fn get_stats(pool: &SqlitePool) -> Result<DashboardStats> {
 
     // Get healthcheck stats
     info!("Healthcheck stats");
-    let hc_total: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM healthcheck")
-        .fetch_one(pool)
-        .await?;
-    let hc_enabled: (i64,) =
-        sqlx::query_as("SELECT COUNT(*) FROM healthcheck WHERE is_enabled = 1")
+    let hc_total: (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM healthcheck WHERE deleted_at IS NULL")
             .fetch_one(pool)
             .await?;
-    let hc_dirty: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM healthcheck WHERE kuma_dirty = 1")
-        .fetch_one(pool)
-        .await?;
+    let hc_enabled: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM healthcheck WHERE is_enabled = 1 AND deleted_at IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
+    let hc_dirty: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM healthcheck WHERE kuma_dirty = 1 AND deleted_at IS NULL",
+    )
+    .fetch_one(pool)
+    .await?;
 
-    // Get recent activity across all entity types
+    // Get recent activity from the audit log
     info!("Recent activity");
     let recent_activity = sqlx::query_as::<_, RecentActivity>(
         r#"
-        SELECT id, name, 'application' as entity_type, updated_at FROM application
-        UNION ALL
-        SELECT id, name, 'service' as entity_type, updated_at FROM service
-        UNION ALL
-        SELECT id, name, 'infra' as entity_type, updated_at FROM infra
-        UNION ALL
-        SELECT id, fqdn as name, 'domain' as entity_type, updated_at FROM domain
-        UNION ALL
-        SELECT id, name, 'person' as entity_type, updated_at FROM person
-        UNION ALL
-        SELECT id, name, 'network_share' as entity_type, updated_at FROM network_share
-        UNION ALL
-        SELECT id, name, 'healthcheck' as entity_type, updated_at FROM healthcheck
-        ORDER BY updated_at DESC
+        SELECT entity_id as id, COALESCE(entity_name, entity_id) as name, entity_type,
+               created_at as updated_at, action, actor
+        FROM audit_event
+        ORDER BY audit_event.id DESC
         LIMIT 15
         "#,
     )
@@ -148,6 +164,7 @@ pub async fn get_stats(pool: &SqlitePool) -> Result<DashboardStats> {
         SELECT id, fqdn, expires_at
         FROM domain
         WHERE expires_at IS NOT NULL
+          AND deleted_at IS NULL
           AND date(expires_at) <= date('now', '+90 days')
           AND date(expires_at) >= date('now')
         ORDER BY expires_at ASC 
@@ -157,6 +174,22 @@ pub async fn get_stats(pool: &SqlitePool) -> Result<DashboardStats> {
     .fetch_all(pool)
     .await?;
 
+    // Get expiring certificates (within 30 days)
+    let expiring_certificates = sqlx::query_as::<_, ExpiringCertificate>(
+        r#"
+        SELECT c.domain_id, d.fqdn, c.port, c.issuer, c.not_after
+        FROM certificate c
+        JOIN domain d ON d.id = c.domain_id
+        WHERE c.not_after IS NOT NULL
+          AND d.deleted_at IS NULL
+          AND c.not_after <= datetime('now', '+30 days')
+        ORDER BY c.not_after ASC
+        LIMIT 10
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
     Ok(DashboardStats {
         applications: EntityStats {
             total: app_total.0,
@@ -184,6 +217,7 @@ pub async fn get_stats(pool: &SqlitePool) -> Result<DashboardStats> {
         },
         notes: note_count.0,
         expiring_domains,
+        expiring_certificates,
         healthchecks: HealthcheckStats {
             total: hc_total.0,
             enabled: hc_enabled.0,
@@ -1,10 +1,11 @@
 use futures::future::try_join_all;
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 
 use crate::models::{
-    ApplicationDomainRelation, CreateDomain, Domain, DomainWithRelations, PaginatedResponse,
-    PaginationParams, TargetName, UpdateDomain, new_id,
+    ApplicationDomainRelation, AuditAction, CreateDomain, Domain, DomainWithRelations,
+    PaginatedResponse, PaginationParams, TargetName, UpdateDomain, new_id,
 };
+use crate::service::{audit, certificate, domain_lookup, revision, trash, version};
 use crate::{Error, Result, service};
 
 pub async fn list(
@@ -18,9 +19,10 @@ pub async fn list(
     let domains = sqlx::query_as::<_, Domain>(
         r#"
         SELECT id, fqdn, registrar, dns_provider, expires_at, notes, 
-            target_application_id, target_service_id, created_at, updated_at, created_by
+            target_application_id, target_service_id, created_at, updated_at, version, created_by
         FROM domain
-        WHERE (?1 IS NULL OR fqdn LIKE ?1 OR registrar LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR fqdn LIKE ?1 OR registrar LIKE ?1)
         ORDER BY fqdn COLLATE NOCASE ASC
         LIMIT ?2 OFFSET ?3
         "#,
@@ -37,7 +39,8 @@ pub async fn list(
         r#"
         SELECT COUNT(*)
         FROM domain
-        WHERE (?1 IS NULL OR fqdn LIKE ?1 OR registrar LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR fqdn LIKE ?1 OR registrar LIKE ?1)
         "#,
     )
     .bind(&search_pattern)
@@ -48,20 +51,41 @@ pub async fn list(
 }
 
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<Domain> {
+    get_in(&mut *pool.acquire().await?, id).await
+}
+
+/// [`get`] on a connection, e.g. in a transaction
+pub async fn get_in(conn: &mut SqliteConnection, id: &str) -> Result<Domain> {
     sqlx::query_as::<_, Domain>(
         r#"
         SELECT id, fqdn, registrar, dns_provider, expires_at, notes, 
-            target_application_id, target_service_id, created_at, updated_at, created_by
+            target_application_id, target_service_id, created_at, updated_at, version, created_by
         FROM domain
-        WHERE id = ?1
+        WHERE id = ?1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or_else(|| Error::NotFound(format!("Domain with id '{}' not found", id)))
 }
 
+/// The domain with this fqdn, ignoring case
+pub async fn find_by_fqdn(pool: &SqlitePool, fqdn: &str) -> Result<Option<Domain>> {
+    sqlx::query_as::<_, Domain>(
+        r#"
+        SELECT id, fqdn, registrar, dns_provider, expires_at, notes,
+            target_application_id, target_service_id, created_at, updated_at, version, created_by
+        FROM domain
+        WHERE fqdn = ?1 COLLATE NOCASE AND deleted_at IS NULL
+        "#,
+    )
+    .bind(fqdn)
+    .fetch_optional(pool)
+    .await
+    .map_err(Into::into)
+}
+
 pub async fn extend_relations(pool: &SqlitePool, domain: Domain) -> Result<DomainWithRelations> {
     let applications = sqlx::query_as::<_, ApplicationDomainRelation>(
         r#"
@@ -69,6 +93,7 @@ pub async fn extend_relations(pool: &SqlitePool, domain: Domain) -> Result<Domai
         FROM application a
         JOIN application_domain ad ON a.id = ad.application_id
         WHERE ad.domain_id = ?1
+          AND a.deleted_at IS NULL
         ORDER BY a.name COLLATE NOCASE
         "#,
     )
@@ -112,11 +137,16 @@ pub async fn extend_relations(pool: &SqlitePool, domain: Domain) -> Result<Domai
         None
     };
 
+    let lookup = domain_lookup::get(pool, &domain).await?;
+    let certificates = certificate::list_for_domain(pool, &domain.id).await?;
+
     Ok(DomainWithRelations {
         domain,
         applications,
         target_application_name,
         target_service_name,
+        lookup,
+        certificates,
     })
 }
 
@@ -126,13 +156,30 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<DomainWit
     extend_relations(pool, domain).await
 }
 
-pub async fn create(pool: &SqlitePool, input: CreateDomain) -> Result<Domain> {
+pub async fn create(pool: &SqlitePool, actor: Option<&str>, input: CreateDomain) -> Result<Domain> {
+    create_in(&mut *pool.acquire().await?, actor, input).await
+}
+
+/// [`create`] on a connection, e.g. in a transaction
+pub async fn create_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    input: CreateDomain,
+) -> Result<Domain> {
+    // The targets must exist and not be in the trash
+    if let Some(app_id) = &input.target_application_id {
+        service::application::get_in(&mut *conn, app_id).await?;
+    }
+    if let Some(service_id) = &input.target_service_id {
+        service::service::get_in(&mut *conn, service_id).await?;
+    }
+
     let id = new_id();
 
     sqlx::query(
         r#"
-        INSERT INTO domain (id, fqdn, registrar, dns_provider, expires_at, notes, target_application_id, target_service_id)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        INSERT INTO domain (id, fqdn, registrar, dns_provider, expires_at, notes, target_application_id, target_service_id, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
         "#,
     )
     .bind(&id)
@@ -143,42 +190,117 @@ pub async fn create(pool: &SqlitePool, input: CreateDomain) -> Result<Domain> {
     .bind(&input.notes)
     .bind(&input.target_application_id)
     .bind(&input.target_service_id)
-    .execute(pool)
+    .bind(actor)
+    .execute(&mut *conn)
     .await?;
 
     if let Some(app_id) = input.target_application_id {
         // link this domain to the application
-        service::application::link_domain(pool, &app_id, &id, None).await?;
+        service::application::link_domain_in(&mut *conn, actor, &app_id, &id, None).await?;
     }
 
-    get(pool, &id).await
+    let domain = get_in(&mut *conn, &id).await?;
+    audit::record_in(
+        &mut *conn,
+        actor,
+        "domain",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&domain),
+    )
+    .await?;
+    revision::record_in(&mut *conn, actor, "domain", &id, None, &domain).await?;
+
+    Ok(domain)
 }
 
-pub async fn update(pool: &SqlitePool, id: &str, input: UpdateDomain) -> Result<Domain> {
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdateDomain,
+) -> Result<Domain> {
     let existing = get(pool, id).await?;
+    version::check("Domain", id, input.version, existing.version)?;
 
-    let fqdn = input.fqdn.unwrap_or(existing.fqdn);
-    let registrar = input.registrar.or(existing.registrar);
-    let dns_provider = input.dns_provider.or(existing.dns_provider);
-    let expires_at = input.expires_at.or(existing.expires_at);
-    let notes = input.notes.or(existing.notes);
-    let (target_application_id, target_service_id) =
-        match (input.target_application_id, input.target_service_id) {
-            (Some(app_id), None) => (Some(app_id), None),
-            (None, Some(service_id)) => (None, Some(service_id)),
-            (None, None) => (None, None),
-            (Some(_), Some(_)) => {
-                return Err(Error::ValidationError(
-                    "can't set both target application and target service".into(),
-                ));
-            }
-        };
+    let values = Domain {
+        fqdn: input.fqdn.unwrap_or_else(|| existing.fqdn.clone()),
+        registrar: input.registrar.or_else(|| existing.registrar.clone()),
+        dns_provider: input.dns_provider.or_else(|| existing.dns_provider.clone()),
+        expires_at: input.expires_at.or_else(|| existing.expires_at.clone()),
+        notes: input.notes.or_else(|| existing.notes.clone()),
+        target_application_id: input.target_application_id,
+        target_service_id: input.target_service_id,
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
 
-    sqlx::query(
+/// Roll back to a revision. Unlike [`update`], fields without a value in
+/// the revision are cleared instead of kept.
+pub async fn rollback(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    rev: i64,
+    expected_version: Option<i64>,
+) -> Result<Domain> {
+    let existing = get(pool, id).await?;
+    version::check("Domain", id, expected_version, existing.version)?;
+    let snapshot = revision::snapshot::<UpdateDomain>(pool, "domain", id, rev).await?;
+
+    let values = Domain {
+        fqdn: snapshot.fqdn.unwrap_or_else(|| existing.fqdn.clone()),
+        registrar: snapshot.registrar,
+        dns_provider: snapshot.dns_provider,
+        expires_at: snapshot.expires_at,
+        notes: snapshot.notes,
+        target_application_id: snapshot.target_application_id,
+        target_service_id: snapshot.target_service_id,
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
+
+/// Store `values` as the next version of `previous`, and link the domain to
+/// its target
+async fn write(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    previous: Domain,
+    values: Domain,
+) -> Result<Domain> {
+    let id = previous.id.as_str();
+    let Domain {
+        fqdn,
+        registrar,
+        dns_provider,
+        expires_at,
+        notes,
+        target_application_id,
+        target_service_id,
+        ..
+    } = values;
+    if target_application_id.is_some() && target_service_id.is_some() {
+        return Err(Error::ValidationError(
+            "can't set both target application and target service".into(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    if let Some(app_id) = &target_application_id {
+        service::application::get_in(&mut tx, app_id).await?;
+    }
+    if let Some(service_id) = &target_service_id {
+        service::service::get_in(&mut tx, service_id).await?;
+    }
+
+    let result = sqlx::query(
         r#"
         UPDATE domain
-        SET fqdn = ?1, registrar = ?2, dns_provider = ?3, expires_at = ?4, notes = ?5, target_application_id = ?6, target_service_id = ?7, updated_at = datetime('now')
-        WHERE id = ?8
+        SET fqdn = ?1, registrar = ?2, dns_provider = ?3, expires_at = ?4, notes = ?5, target_application_id = ?6, target_service_id = ?7, updated_at = datetime('now'), version = version + 1
+        WHERE id = ?8 AND version = ?9
         "#,
     )
     .bind(&fqdn)
@@ -189,12 +311,16 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdateDomain) -> Result<
     .bind(&target_application_id)
     .bind(&target_service_id)
     .bind(id)
-    .execute(pool)
+    .bind(previous.version)
+    .execute(&mut *tx)
     .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Domain", id));
+    }
 
     if let Some(app_id) = target_application_id {
         // link this domain to the application
-        service::application::link_domain(pool, &app_id, id, None).await?;
+        service::application::link_domain_in(&mut tx, actor, &app_id, id, None).await?;
     } else if let Some(service_id) = target_service_id {
         // if this domain is linked to an application, and we're targetting a service, add that service
         // to the application
@@ -204,15 +330,17 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdateDomain) -> Result<
         FROM application a
         JOIN application_domain ad ON a.id = ad.application_id
         WHERE ad.domain_id = ?1
+          AND a.deleted_at IS NULL
         "#,
         )
         .bind(id)
-        .fetch_all(pool)
+        .fetch_all(&mut *tx)
         .await?;
 
         for app in applications {
-            service::application::link_service(
-                pool,
+            service::application::link_service_in(
+                &mut tx,
+                actor,
                 &app.id,
                 &service_id,
                 Some(&format!("through '{}'", fqdn)),
@@ -221,14 +349,33 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdateDomain) -> Result<
         }
     }
 
-    get(pool, id).await
+    let domain = get_in(&mut tx, id).await?;
+    audit::record_in(
+        &mut tx,
+        actor,
+        "domain",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&domain),
+    )
+    .await?;
+    revision::record_in(&mut tx, actor, "domain", id, Some(&previous), &domain).await?;
+
+    tx.commit().await?;
+    Ok(domain)
 }
 
-pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
-    let result = sqlx::query("DELETE FROM domain WHERE id = ?1")
-        .bind(id)
-        .execute(pool)
-        .await?;
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Domain", id, expected_version, existing.version)?;
+
+    let result = trash::soft_delete(pool, actor, "domain", id).await?;
 
     if result.rows_affected() == 0 {
         return Err(Error::NotFound(format!(
@@ -237,5 +384,16 @@ pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
         )));
     }
 
+    audit::record(
+        pool,
+        actor,
+        "domain",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
     Ok(())
 }
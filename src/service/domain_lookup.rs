@@ -0,0 +1,284 @@
+//! Registration data of domains from RDAP, see [`crate::rdap`]. A lookup
+//! fills in the registrar, DNS provider and expiry of a domain, unless they
+//! were entered by hand; those are kept and flagged when the lookup differs.
+
+use sqlx::SqlitePool;
+
+use crate::models::{AuditAction, Domain, DomainLookup};
+use crate::rdap::RdapDomain;
+use crate::service::{audit, revision};
+use crate::{Result, service};
+
+/// Looked up again after a week, or after a day when the lookup failed
+const LOOKUP_MAX_AGE: &str = "-7 days";
+const RETRY_AFTER: &str = "-1 day";
+
+/// The last lookup of a domain, with the fields that differ from it
+pub async fn get(pool: &SqlitePool, domain: &Domain) -> Result<Option<DomainLookup>> {
+    let lookup = sqlx::query_as::<_, DomainLookup>(
+        r#"
+        SELECT name, source, registrar, dns_provider, expires_at, error, checked_at
+        FROM domain_lookup
+        WHERE domain_id = ?1
+        "#,
+    )
+    .bind(&domain.id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(lookup.map(|mut lookup| {
+        lookup.mismatches = mismatches(domain, &lookup);
+        lookup
+    }))
+}
+
+/// Domains never looked up, or whose lookup is outdated
+pub async fn due(pool: &SqlitePool) -> Result<Vec<Domain>> {
+    sqlx::query_as::<_, Domain>(
+        r#"
+        SELECT d.id, d.fqdn, d.registrar, d.dns_provider, d.expires_at, d.notes,
+            d.target_application_id, d.target_service_id, d.created_at, d.updated_at,
+            d.version, d.created_by
+        FROM domain d
+        LEFT JOIN domain_lookup l ON l.domain_id = d.id
+        WHERE d.deleted_at IS NULL
+          AND (l.domain_id IS NULL
+            OR l.checked_at < datetime('now', ?1)
+            OR (l.error IS NOT NULL AND l.checked_at < datetime('now', ?2)))
+        ORDER BY d.fqdn COLLATE NOCASE
+        "#,
+    )
+    .bind(LOOKUP_MAX_AGE)
+    .bind(RETRY_AFTER)
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Records a failed lookup, keeping what the previous one found.
+pub async fn failed(pool: &SqlitePool, domain_id: &str, name: &str, error: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO domain_lookup (domain_id, name, error)
+        VALUES (?1, ?2, ?3)
+        ON CONFLICT (domain_id) DO UPDATE SET
+            name = excluded.name, error = excluded.error, checked_at = datetime('now')
+        "#,
+    )
+    .bind(domain_id)
+    .bind(name)
+    .bind(error)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a lookup and fills in the domain fields that follow it.
+pub async fn record(
+    pool: &SqlitePool,
+    domain: &Domain,
+    name: &str,
+    found: &RdapDomain,
+) -> Result<()> {
+    // The domain may have been edited since the lookup started
+    let existing = service::domain::get(pool, &domain.id).await?;
+    let previous = sqlx::query_as::<_, DomainLookup>(
+        r#"
+        SELECT name, source, registrar, dns_provider, expires_at, error, checked_at
+        FROM domain_lookup
+        WHERE domain_id = ?1
+        "#,
+    )
+    .bind(&domain.id)
+    .fetch_optional(pool)
+    .await?;
+    let previous = previous.as_ref();
+
+    let registrar = follow(
+        &existing.registrar,
+        previous.and_then(|l| l.registrar.as_ref()),
+        &found.registrar,
+    );
+    let dns_provider = follow(
+        &existing.dns_provider,
+        previous.and_then(|l| l.dns_provider.as_ref()),
+        &found.dns_provider,
+    );
+    let expires_at = follow(
+        &existing.expires_at,
+        previous.and_then(|l| l.expires_at.as_ref()),
+        &found.expires_at,
+    );
+
+    if registrar != existing.registrar
+        || dns_provider != existing.dns_provider
+        || expires_at != existing.expires_at
+    {
+        let result = sqlx::query(
+            r#"
+            UPDATE domain
+            SET registrar = ?1, dns_provider = ?2, expires_at = ?3, updated_at = datetime('now'), version = version + 1
+            WHERE id = ?4 AND version = ?5
+            "#,
+        )
+        .bind(&registrar)
+        .bind(&dns_provider)
+        .bind(&expires_at)
+        .bind(&existing.id)
+        .bind(existing.version)
+        .execute(pool)
+        .await?;
+
+        // Edited meanwhile, the next lookup fills it in
+        if result.rows_affected() > 0 {
+            let updated = service::domain::get(pool, &existing.id).await?;
+            audit::record(
+                pool,
+                None,
+                "domain",
+                &existing.id,
+                AuditAction::Update,
+                Some(&existing),
+                Some(&updated),
+            )
+            .await?;
+            revision::record(
+                pool,
+                None,
+                "domain",
+                &existing.id,
+                Some(&existing),
+                &updated,
+            )
+            .await?;
+        }
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO domain_lookup (domain_id, name, source, registrar, dns_provider, expires_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ON CONFLICT (domain_id) DO UPDATE SET
+            name = excluded.name, source = excluded.source, registrar = excluded.registrar,
+            dns_provider = excluded.dns_provider, expires_at = excluded.expires_at,
+            error = NULL, checked_at = datetime('now')
+        "#,
+    )
+    .bind(&domain.id)
+    .bind(name)
+    .bind(&found.source)
+    .bind(&found.registrar)
+    .bind(&found.dns_provider)
+    .bind(&found.expires_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The value of a domain field after a lookup: what was found, unless the
+/// field was entered by hand, i.e. it is set and not what was found before.
+fn follow(
+    current: &Option<String>,
+    previous: Option<&String>,
+    found: &Option<String>,
+) -> Option<String> {
+    match (current, found) {
+        (None, _) => found.clone(),
+        (Some(current), Some(_)) if Some(current) == previous => found.clone(),
+        _ => current.clone(),
+    }
+}
+
+/// Domain fields that are set and differ from the lookup
+fn mismatches(domain: &Domain, lookup: &DomainLookup) -> Vec<String> {
+    let mut fields = Vec::new();
+    if differs(&domain.registrar, &lookup.registrar, usize::MAX) {
+        fields.push("registrar".to_string());
+    }
+    if differs(&domain.dns_provider, &lookup.dns_provider, usize::MAX) {
+        fields.push("dns_provider".to_string());
+    }
+    // Only the date, a time of day is left out of the lookup
+    if differs(&domain.expires_at, &lookup.expires_at, 10) {
+        fields.push("expires_at".to_string());
+    }
+    fields
+}
+
+/// Whether both values are set and their first `len` characters differ,
+/// ignoring case and surrounding whitespace
+fn differs(current: &Option<String>, found: &Option<String>, len: usize) -> bool {
+    let normalize = |s: &str| {
+        s.trim()
+            .to_lowercase()
+            .chars()
+            .take(len)
+            .collect::<String>()
+    };
+    match (current, found) {
+        (Some(current), Some(found)) => normalize(current) != normalize(found),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn some(s: &str) -> Option<String> {
+        Some(s.to_string())
+    }
+
+    #[test]
+    fn follow_keeps_values_entered_by_hand() {
+        let previous = some("Old Registrar");
+        // Empty, or still what the last lookup found
+        assert_eq!(follow(&None, None, &some("New")), some("New"));
+        assert_eq!(
+            follow(&previous, previous.as_ref(), &some("New")),
+            some("New")
+        );
+        // Entered by hand
+        assert_eq!(follow(&some("Manual"), None, &some("New")), some("Manual"));
+        assert_eq!(
+            follow(&some("Manual"), previous.as_ref(), &some("New")),
+            some("Manual")
+        );
+        // Nothing found doesn't clear a value
+        assert_eq!(
+            follow(&previous, previous.as_ref(), &None),
+            some("Old Registrar")
+        );
+    }
+
+    #[test]
+    fn mismatches_ignore_case_and_time_of_day() {
+        let domain = Domain {
+            id: "d1".into(),
+            fqdn: "www.example.org".into(),
+            registrar: some("Example Registrar"),
+            dns_provider: some("ns.example.net"),
+            expires_at: some("2027-08-30 04:00:00"),
+            notes: None,
+            target_application_id: None,
+            target_service_id: None,
+            created_at: String::new(),
+            updated_at: String::new(),
+            version: 1,
+            created_by: None,
+        };
+        let lookup = DomainLookup {
+            name: "example.org".into(),
+            source: None,
+            registrar: some("example registrar "),
+            dns_provider: some("cloudflare.com"),
+            expires_at: some("2027-08-30"),
+            error: None,
+            checked_at: String::new(),
+            mismatches: Vec::new(),
+        };
+        assert_eq!(mismatches(&domain, &lookup), vec!["dns_provider"]);
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+
+use sqlx::SqlitePool;
+
+use crate::models::{Graph, GraphEdge, GraphNode};
+use crate::{Error, Result};
+
+/// The full dependency graph, or only the part within `depth` edges of
+/// `root` (in either direction).
+pub async fn get(pool: &SqlitePool, root: Option<&str>, depth: Option<u32>) -> Result<Graph> {
+    let nodes = sqlx::query_as::<_, GraphNode>(
+        r#"
+        SELECT id, 'application' AS entity_type, name, environment FROM application WHERE deleted_at IS NULL
+        UNION ALL SELECT id, 'service', name, environment FROM service WHERE deleted_at IS NULL
+        UNION ALL SELECT id, 'infra', name, NULL FROM infra WHERE deleted_at IS NULL
+        UNION ALL SELECT id, 'domain', fqdn, NULL FROM domain WHERE deleted_at IS NULL
+        ORDER BY 2, 3 COLLATE NOCASE
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let edges = sqlx::query_as::<_, GraphEdge>(
+        r#"
+        SELECT application_id AS source, service_id AS target, 'uses' AS relation FROM application_service
+        UNION ALL SELECT application_id, infra_id, 'runs_on' FROM application_infra
+        UNION ALL SELECT service_id, infra_id, 'runs_on' FROM service_infra
+        UNION ALL SELECT application_id, domain_id, 'has_domain' FROM application_domain
+        UNION ALL SELECT id, target_application_id, 'targets' FROM domain WHERE target_application_id IS NOT NULL
+        UNION ALL SELECT id, target_service_id, 'targets' FROM domain WHERE target_service_id IS NOT NULL
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    // Links to trashed entities stay in the junction tables
+    let ids = nodes.iter().map(|n| n.id.as_str()).collect::<HashSet<_>>();
+    let edges = edges
+        .into_iter()
+        .filter(|e| ids.contains(e.source.as_str()) && ids.contains(e.target.as_str()))
+        .collect();
+
+    let graph = Graph { nodes, edges };
+    match root {
+        Some(root) => subgraph(&graph, root, depth),
+        None => Ok(graph),
+    }
+}
+
+/// Nodes reachable from `root` in at most `depth` steps, ignoring the
+/// direction of the edges, with the edges between them.
+fn subgraph(graph: &Graph, root: &str, depth: Option<u32>) -> Result<Graph> {
+    if !graph.nodes.iter().any(|n| n.id == root) {
+        return Err(Error::NotFound(format!(
+            "No application, service, infra or domain with id '{root}'"
+        )));
+    }
+
+    let mut neighbours: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in &graph.edges {
+        neighbours
+            .entry(&edge.source)
+            .or_default()
+            .push(&edge.target);
+        neighbours
+            .entry(&edge.target)
+            .or_default()
+            .push(&edge.source);
+    }
+
+    let mut distances = HashMap::from([(root, 0)]);
+    let mut queue = VecDeque::from([root]);
+    while let Some(id) = queue.pop_front() {
+        let distance = distances[id];
+        if depth.is_some_and(|depth| distance >= depth) {
+            continue;
+        }
+        for next in neighbours.get(id).into_iter().flatten() {
+            if !distances.contains_key(next) {
+                distances.insert(next, distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    Ok(Graph {
+        nodes: graph
+            .nodes
+            .iter()
+            .filter(|n| distances.contains_key(n.id.as_str()))
+            .cloned()
+            .collect(),
+        edges: graph
+            .edges
+            .iter()
+            .filter(|e| {
+                distances.contains_key(e.source.as_str())
+                    && distances.contains_key(e.target.as_str())
+            })
+            .cloned()
+            .collect(),
+    })
+}
+
+fn label(node: &GraphNode) -> String {
+    match &node.environment {
+        Some(environment) => format!("{} ({environment})", node.name),
+        None => node.name.clone(),
+    }
+}
+
+/// Render the graph in Graphviz DOT.
+pub fn to_dot(graph: &Graph) -> String {
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+
+    let mut dot = String::from("digraph auto {\n    rankdir=LR;\n");
+    for node in &graph.nodes {
+        let shape = match node.entity_type.as_str() {
+            "application" => "box",
+            "service" => "ellipse",
+            "infra" => "cylinder",
+            _ => "note",
+        };
+        let _ = writeln!(
+            dot,
+            "    \"{}\" [label=\"{}\", shape={shape}];",
+            node.id,
+            escape(&label(node))
+        );
+    }
+    for edge in &graph.edges {
+        let _ = writeln!(
+            dot,
+            "    \"{}\" -> \"{}\" [label=\"{}\"];",
+            edge.source, edge.target, edge.relation
+        );
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Render the graph as a Mermaid flowchart.
+pub fn to_mermaid(graph: &Graph) -> String {
+    // Mermaid node IDs can't contain dashes, so number the nodes instead
+    let ids = graph
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.id.as_str(), format!("n{i}")))
+        .collect::<HashMap<_, _>>();
+
+    let mut mermaid = String::from("flowchart LR\n");
+    for node in &graph.nodes {
+        let label = label(node).replace('"', "#quot;");
+        let (open, close) = match node.entity_type.as_str() {
+            "application" => ("[", "]"),
+            "service" => ("(", ")"),
+            "infra" => ("[(", ")]"),
+            _ => (">", "]"),
+        };
+        let _ = writeln!(
+            mermaid,
+            "    {}{open}\"{label}\"{close}",
+            ids[node.id.as_str()]
+        );
+    }
+    for edge in &graph.edges {
+        let _ = writeln!(
+            mermaid,
+            "    {} -->|{}| {}",
+            ids[edge.source.as_str()],
+            edge.relation,
+            ids[edge.target.as_str()]
+        );
+    }
+    mermaid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, entity_type: &str, name: &str) -> GraphNode {
+        GraphNode {
+            id: id.to_string(),
+            entity_type: entity_type.to_string(),
+            name: name.to_string(),
+            environment: None,
+        }
+    }
+
+    fn edge(source: &str, target: &str, relation: &str) -> GraphEdge {
+        GraphEdge {
+            source: source.to_string(),
+            target: target.to_string(),
+            relation: relation.to_string(),
+        }
+    }
+
+    /// web -> api -> db, and web.example.org -> web
+    fn graph() -> Graph {
+        Graph {
+            nodes: vec![
+                node("app", "application", "web"),
+                node("svc", "service", "api"),
+                node("inf", "infra", "db \"main\""),
+                node("dom", "domain", "web.example.org"),
+            ],
+            edges: vec![
+                edge("app", "svc", "uses"),
+                edge("svc", "inf", "runs_on"),
+                edge("dom", "app", "targets"),
+            ],
+        }
+    }
+
+    #[test]
+    fn subgraph_follows_edges_both_ways_up_to_depth() {
+        let ids = |g: Graph| g.nodes.into_iter().map(|n| n.id).collect::<Vec<_>>();
+
+        assert_eq!(
+            ids(subgraph(&graph(), "svc", Some(1)).unwrap()),
+            ["app", "svc", "inf"]
+        );
+        assert_eq!(ids(subgraph(&graph(), "inf", None).unwrap()).len(), 4);
+        assert_eq!(subgraph(&graph(), "svc", Some(1)).unwrap().edges.len(), 2);
+        assert!(subgraph(&graph(), "nope", None).is_err());
+    }
+
+    #[test]
+    fn dot_quotes_ids_and_escapes_labels() {
+        let dot = to_dot(&graph());
+
+        assert!(dot.starts_with("digraph auto {\n"));
+        assert!(dot.contains("    \"inf\" [label=\"db \\\"main\\\"\", shape=cylinder];\n"));
+        assert!(dot.contains("    \"app\" -> \"svc\" [label=\"uses\"];\n"));
+    }
+
+    #[test]
+    fn mermaid_numbers_nodes() {
+        let mermaid = to_mermaid(&graph());
+
+        assert!(mermaid.starts_with("flowchart LR\n"));
+        assert!(mermaid.contains("    n2[(\"db #quot;main#quot;\")]\n"));
+        assert!(mermaid.contains("    n3 -->|targets| n0\n"));
+    }
+}
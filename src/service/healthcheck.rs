@@ -1,16 +1,30 @@
 use std::collections::HashMap;
-use std::time::Instant;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
 use futures::{StreamExt as _, stream};
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::proto::rr::RecordType;
+use hickory_resolver::{Resolver, TokioResolver};
 use reqwest::Client;
-use sqlx::SqlitePool;
+use serde_json_path::JsonPath;
+use sqlx::{SqliteConnection, SqlitePool};
+use tokio::net::TcpStream;
 
 use crate::models::{
-    CreateHealthcheck, Healthcheck, HealthcheckExecuteResult, HealthcheckRelation,
-    HealthcheckWithRelations, KumaMonitor, PaginatedResponse, PaginationParams, UpdateHealthcheck,
-    new_id,
+    AuditAction, CreateHealthcheck, DNS_RECORD_TYPES, HEALTHCHECK_KINDS, Healthcheck,
+    HealthcheckExecuteResult, HealthcheckRelation, HealthcheckWithRelations, KumaMonitor,
+    PaginatedResponse, PaginationParams, UpdateHealthcheck, new_id,
 };
-use crate::{Error, Result};
+use crate::service::{audit, revision, trash, version};
+use crate::{Error, Result, tls};
+
+/// A TLS healthcheck fails when the certificate expires within this many
+/// days, unless it sets `tls_expiry_days`
+const DEFAULT_TLS_EXPIRY_DAYS: i32 = 14;
 
 pub async fn list(
     pool: &SqlitePool,
@@ -29,10 +43,13 @@ pub async fn list(
                h.protocol, h.path, h.method, h.headers, h.expected_status,
                h.expected_body, h.timeout_seconds, h.interval, h.is_enabled, h.notes,
                h.retry, h.retry_interval, h.request_body_encoding, h.request_body,
-               h.http_auth_user, h.http_auth_pass, h.kuma_id, h.kuma_dirty,
-               h.created_at, h.updated_at, h.created_by
+               h.http_auth_user, h.http_auth_pass, h.kind, h.port, h.dns_record_type,
+               h.dns_resolver, h.tls_expiry_days, h.json_path, h.json_expected_value,
+               h.kuma_id, h.kuma_dirty,
+               h.created_at, h.updated_at, h.version, h.created_by
         FROM healthcheck h
-        WHERE (?1 IS NULL OR h.name LIKE ?1)
+        WHERE h.deleted_at IS NULL
+          AND (?1 IS NULL OR h.name LIKE ?1)
           AND (?2 IS NULL OR h.application_id = ?2)
           AND (?3 IS NULL OR h.service_id = ?3)
           AND (?4 IS NULL OR h.is_enabled = ?4)
@@ -58,7 +75,8 @@ pub async fn list(
         r#"
         SELECT COUNT(*)
         FROM healthcheck h
-        WHERE (?1 IS NULL OR h.name LIKE ?1)
+        WHERE h.deleted_at IS NULL
+          AND (?1 IS NULL OR h.name LIKE ?1)
           AND (?2 IS NULL OR h.application_id = ?2)
           AND (?3 IS NULL OR h.service_id = ?3)
           AND (?4 IS NULL OR h.is_enabled = ?4)
@@ -75,20 +93,27 @@ pub async fn list(
 }
 
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<Healthcheck> {
+    get_in(&mut *pool.acquire().await?, id).await
+}
+
+/// [`get`] on a connection, e.g. in a transaction
+pub async fn get_in(conn: &mut SqliteConnection, id: &str) -> Result<Healthcheck> {
     sqlx::query_as::<_, Healthcheck>(
         r#"
         SELECT id, name, application_id, service_id, domain_id,
                protocol, path, method, headers, expected_status,
                expected_body, timeout_seconds, interval, is_enabled, notes,
                retry, retry_interval, request_body_encoding, request_body,
-               http_auth_user, http_auth_pass, kuma_id, kuma_dirty,
-               created_at, updated_at, created_by
+               http_auth_user, http_auth_pass, kind, port, dns_record_type,
+               dns_resolver, tls_expiry_days, json_path, json_expected_value,
+               kuma_id, kuma_dirty,
+               created_at, updated_at, version, created_by
         FROM healthcheck
-        WHERE id = ?1
+        WHERE id = ?1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or_else(|| Error::NotFound(format!("Healthcheck with id '{}' not found", id)))
 }
@@ -100,9 +125,12 @@ pub async fn get_all(pool: &SqlitePool) -> Result<Vec<Healthcheck>> {
                protocol, path, method, headers, expected_status,
                expected_body, timeout_seconds, interval, is_enabled, notes,
                retry, retry_interval, request_body_encoding, request_body,
-               http_auth_user, http_auth_pass, kuma_id, kuma_dirty,
-               created_at, updated_at, created_by
+               http_auth_user, http_auth_pass, kind, port, dns_record_type,
+               dns_resolver, tls_expiry_days, json_path, json_expected_value,
+               kuma_id, kuma_dirty,
+               created_at, updated_at, version, created_by
         FROM healthcheck
+        WHERE deleted_at IS NULL
         "#,
     )
     .fetch_all(pool)
@@ -171,7 +199,11 @@ pub async fn get_all_with_relations(pool: &SqlitePool) -> Result<Vec<Healthcheck
         .collect()
 }
 
-pub async fn create(pool: &SqlitePool, input: CreateHealthcheck) -> Result<Healthcheck> {
+pub async fn create(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    input: CreateHealthcheck,
+) -> Result<Healthcheck> {
     // Validate XOR constraint
     match (&input.application_id, &input.service_id) {
         (Some(_), Some(_)) => {
@@ -205,6 +237,14 @@ pub async fn create(pool: &SqlitePool, input: CreateHealthcheck) -> Result<Healt
         })?;
     }
 
+    validate_kind(
+        &input.kind,
+        input.port,
+        input.dns_record_type.as_deref(),
+        input.dns_resolver.as_deref(),
+        input.json_path.as_deref(),
+    )?;
+
     let id = new_id();
 
     sqlx::query(
@@ -213,8 +253,11 @@ pub async fn create(pool: &SqlitePool, input: CreateHealthcheck) -> Result<Healt
                                  protocol, path, method, headers, expected_status,
                                  expected_body, timeout_seconds, interval, is_enabled, notes,
                                  retry, retry_interval, request_body_encoding, request_body,
-                                 http_auth_user, http_auth_pass, kuma_dirty)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, 1)
+                                 http_auth_user, http_auth_pass, kuma_dirty, created_by,
+                                 kind, port, dns_record_type, dns_resolver, tls_expiry_days,
+                                 json_path, json_expected_value)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, 1, ?23,
+                ?24, ?25, ?26, ?27, ?28, ?29, ?30)
         "#,
     )
     .bind(&id)
@@ -239,26 +282,198 @@ pub async fn create(pool: &SqlitePool, input: CreateHealthcheck) -> Result<Healt
     .bind(&input.request_body)
     .bind(&input.http_auth_user)
     .bind(&input.http_auth_pass)
+    .bind(actor)
+    .bind(&input.kind)
+    .bind(input.port)
+    .bind(&input.dns_record_type)
+    .bind(&input.dns_resolver)
+    .bind(input.tls_expiry_days)
+    .bind(&input.json_path)
+    .bind(&input.json_expected_value)
     .execute(pool)
     .await?;
 
-    get(pool, &id).await
+    let healthcheck = get(pool, &id).await?;
+    audit::record(
+        pool,
+        actor,
+        "healthcheck",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&healthcheck),
+    )
+    .await?;
+    revision::record(pool, actor, "healthcheck", &id, None, &healthcheck).await?;
+
+    Ok(healthcheck)
 }
 
-pub async fn update(pool: &SqlitePool, id: &str, input: UpdateHealthcheck) -> Result<Healthcheck> {
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdateHealthcheck,
+) -> Result<Healthcheck> {
     let existing = get(pool, id).await?;
+    version::check("Healthcheck", id, input.version, existing.version)?;
 
-    // Determine new target values
-    let (application_id, service_id) = match (&input.application_id, &input.service_id) {
-        (Some(app_id), None) => (Some(app_id.clone()), None),
-        (None, Some(svc_id)) => (None, Some(svc_id.clone())),
-        (Some(_), Some(_)) => {
-            return Err(Error::ValidationError(
-                "Cannot set both application_id and service_id".into(),
-            ));
-        }
-        (None, None) => (existing.application_id, existing.service_id),
+    // Setting either target replaces the other
+    let (application_id, service_id) = match (input.application_id, input.service_id) {
+        (None, None) => (existing.application_id.clone(), existing.service_id.clone()),
+        targets => targets,
     };
+    let values = Healthcheck {
+        name: input.name.unwrap_or_else(|| existing.name.clone()),
+        application_id,
+        service_id,
+        kuma_id: input.kuma_id.or(existing.kuma_id),
+        domain_id: input
+            .domain_id
+            .unwrap_or_else(|| existing.domain_id.clone()),
+        protocol: input.protocol.unwrap_or_else(|| existing.protocol.clone()),
+        path: input.path.unwrap_or_else(|| existing.path.clone()),
+        method: input.method.unwrap_or_else(|| existing.method.clone()),
+        headers: input.headers.or_else(|| existing.headers.clone()),
+        expected_status: input.expected_status.unwrap_or(existing.expected_status),
+        expected_body: input
+            .expected_body
+            .or_else(|| existing.expected_body.clone()),
+        timeout_seconds: input.timeout_seconds.unwrap_or(existing.timeout_seconds),
+        interval: input.interval.unwrap_or(existing.interval),
+        is_enabled: input.is_enabled.unwrap_or(existing.is_enabled),
+        notes: input.notes.or_else(|| existing.notes.clone()),
+        retry: input.retry.unwrap_or(existing.retry),
+        retry_interval: input.retry_interval.unwrap_or(existing.retry_interval),
+        request_body_encoding: input
+            .request_body_encoding
+            .unwrap_or_else(|| existing.request_body_encoding.clone()),
+        request_body: input.request_body.or_else(|| existing.request_body.clone()),
+        http_auth_user: input
+            .http_auth_user
+            .or_else(|| existing.http_auth_user.clone()),
+        http_auth_pass: input
+            .http_auth_pass
+            .or_else(|| existing.http_auth_pass.clone()),
+        kind: input.kind.unwrap_or_else(|| existing.kind.clone()),
+        port: input.port.or(existing.port),
+        dns_record_type: input
+            .dns_record_type
+            .or_else(|| existing.dns_record_type.clone()),
+        dns_resolver: input.dns_resolver.or_else(|| existing.dns_resolver.clone()),
+        tls_expiry_days: input.tls_expiry_days.or(existing.tls_expiry_days),
+        json_path: input.json_path.or_else(|| existing.json_path.clone()),
+        json_expected_value: input
+            .json_expected_value
+            .or_else(|| existing.json_expected_value.clone()),
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
+
+/// Roll back to a revision. Unlike [`update`], fields without a value in
+/// the revision are cleared instead of kept, except the Kuma monitor, which
+/// may have been recreated since.
+pub async fn rollback(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    rev: i64,
+    expected_version: Option<i64>,
+) -> Result<Healthcheck> {
+    let existing = get(pool, id).await?;
+    version::check("Healthcheck", id, expected_version, existing.version)?;
+    let snapshot = revision::snapshot::<UpdateHealthcheck>(pool, "healthcheck", id, rev).await?;
+
+    let (application_id, service_id) = match (snapshot.application_id, snapshot.service_id) {
+        (None, None) => (existing.application_id.clone(), existing.service_id.clone()),
+        targets => targets,
+    };
+    let values = Healthcheck {
+        name: snapshot.name.unwrap_or_else(|| existing.name.clone()),
+        application_id,
+        service_id,
+        kuma_id: existing.kuma_id,
+        domain_id: snapshot
+            .domain_id
+            .unwrap_or_else(|| existing.domain_id.clone()),
+        protocol: snapshot
+            .protocol
+            .unwrap_or_else(|| existing.protocol.clone()),
+        path: snapshot.path.unwrap_or_else(|| existing.path.clone()),
+        method: snapshot.method.unwrap_or_else(|| existing.method.clone()),
+        headers: snapshot.headers,
+        expected_status: snapshot.expected_status.unwrap_or(existing.expected_status),
+        expected_body: snapshot.expected_body,
+        timeout_seconds: snapshot.timeout_seconds.unwrap_or(existing.timeout_seconds),
+        interval: snapshot.interval.unwrap_or(existing.interval),
+        is_enabled: snapshot.is_enabled.unwrap_or(existing.is_enabled),
+        notes: snapshot.notes,
+        retry: snapshot.retry.unwrap_or(existing.retry),
+        retry_interval: snapshot.retry_interval.unwrap_or(existing.retry_interval),
+        request_body_encoding: snapshot
+            .request_body_encoding
+            .unwrap_or_else(|| existing.request_body_encoding.clone()),
+        request_body: snapshot.request_body,
+        http_auth_user: snapshot.http_auth_user,
+        http_auth_pass: snapshot.http_auth_pass,
+        kind: snapshot.kind.unwrap_or_else(|| existing.kind.clone()),
+        port: snapshot.port,
+        dns_record_type: snapshot.dns_record_type,
+        dns_resolver: snapshot.dns_resolver,
+        tls_expiry_days: snapshot.tls_expiry_days,
+        json_path: snapshot.json_path,
+        json_expected_value: snapshot.json_expected_value,
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
+
+/// Validate `values` and store them as the next version of `previous`
+async fn write(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    previous: Healthcheck,
+    values: Healthcheck,
+) -> Result<Healthcheck> {
+    let id = previous.id.as_str();
+    let Healthcheck {
+        name,
+        application_id,
+        service_id,
+        kuma_id,
+        domain_id,
+        protocol,
+        path,
+        method,
+        headers,
+        expected_status,
+        expected_body,
+        timeout_seconds,
+        interval,
+        is_enabled,
+        notes,
+        retry,
+        retry_interval,
+        request_body_encoding,
+        request_body,
+        http_auth_user,
+        http_auth_pass,
+        kind,
+        port,
+        dns_record_type,
+        dns_resolver,
+        tls_expiry_days,
+        json_path,
+        json_expected_value,
+        ..
+    } = values;
+
+    if application_id.is_some() && service_id.is_some() {
+        return Err(Error::ValidationError(
+            "Cannot set both application_id and service_id".into(),
+        ));
+    }
 
     // Validate target exists if changed
     if let Some(app_id) = &application_id {
@@ -269,38 +484,25 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdateHealthcheck) -> Re
     }
 
     // Validate domain exists if changed
-    let domain_id = input.domain_id.unwrap_or(existing.domain_id);
     crate::service::domain::get(pool, &domain_id).await?;
 
     // Validate headers is valid JSON if provided
-    let headers = input.headers.or(existing.headers);
     if let Some(h) = &headers {
         serde_json::from_str::<HashMap<String, String>>(h).map_err(|_| {
             Error::ValidationError("headers must be valid JSON object with string values".into())
         })?;
     }
 
-    let name = input.name.unwrap_or(existing.name);
-    let protocol = input.protocol.unwrap_or(existing.protocol);
-    let path = input.path.unwrap_or(existing.path);
-    let method = input.method.unwrap_or(existing.method);
-    let expected_status = input.expected_status.unwrap_or(existing.expected_status);
-    let expected_body = input.expected_body.or(existing.expected_body);
-    let timeout_seconds = input.timeout_seconds.unwrap_or(existing.timeout_seconds);
-    let interval = input.interval.unwrap_or(existing.interval);
-    let is_enabled = input.is_enabled.unwrap_or(existing.is_enabled);
-    let notes = input.notes.or(existing.notes);
-    let retry = input.retry.unwrap_or(existing.retry);
-    let retry_interval = input.retry_interval.unwrap_or(existing.retry_interval);
-    let request_body_encoding = input
-        .request_body_encoding
-        .unwrap_or(existing.request_body_encoding);
-    let request_body = input.request_body.or(existing.request_body);
-    let http_auth_user = input.http_auth_user.or(existing.http_auth_user);
-    let http_auth_pass = input.http_auth_pass.or(existing.http_auth_pass);
-    let kuma_id = input.kuma_id.or(existing.kuma_id);
+    validate_kind(
+        &kind,
+        port,
+        dns_record_type.as_deref(),
+        dns_resolver.as_deref(),
+        json_path.as_deref(),
+    )?;
 
-    sqlx::query(
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
         r#"
         UPDATE healthcheck
         SET name = ?1, application_id = ?2, service_id = ?3, domain_id = ?4,
@@ -309,8 +511,11 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdateHealthcheck) -> Re
             is_enabled = ?12, notes = ?13, retry = ?14, retry_interval = ?15,
             request_body_encoding = ?16, request_body = ?17, interval = ?22,
             http_auth_user = ?18, http_auth_pass = ?19, kuma_id = ?21,
-            kuma_dirty = 1, updated_at = datetime('now')
-        WHERE id = ?20
+            kind = ?23, port = ?24, dns_record_type = ?25, dns_resolver = ?26,
+            tls_expiry_days = ?27, json_path = ?28, json_expected_value = ?29,
+            kuma_dirty = 1, kuma_version = kuma_version + 1, updated_at = datetime('now'),
+            version = version + 1
+        WHERE id = ?20 AND version = ?30
         "#,
     )
     .bind(&name)
@@ -335,17 +540,55 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdateHealthcheck) -> Re
     .bind(id)
     .bind(kuma_id)
     .bind(interval)
-    .execute(pool)
+    .bind(&kind)
+    .bind(port)
+    .bind(&dns_record_type)
+    .bind(&dns_resolver)
+    .bind(tls_expiry_days)
+    .bind(&json_path)
+    .bind(&json_expected_value)
+    .bind(previous.version)
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Healthcheck", id));
+    }
+
+    let healthcheck = get_in(&mut tx, id).await?;
+    audit::record_in(
+        &mut tx,
+        actor,
+        "healthcheck",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&healthcheck),
+    )
+    .await?;
+    revision::record_in(
+        &mut tx,
+        actor,
+        "healthcheck",
+        id,
+        Some(&previous),
+        &healthcheck,
+    )
     .await?;
 
-    get(pool, id).await
+    tx.commit().await?;
+    Ok(healthcheck)
 }
 
-pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
-    let result = sqlx::query("DELETE FROM healthcheck WHERE id = ?1")
-        .bind(id)
-        .execute(pool)
-        .await?;
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Healthcheck", id, expected_version, existing.version)?;
+
+    let result = trash::soft_delete(pool, actor, "healthcheck", id).await?;
 
     if result.rows_affected() == 0 {
         return Err(Error::NotFound(format!(
@@ -354,12 +597,26 @@ pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
         )));
     }
 
+    audit::record(
+        pool,
+        actor,
+        "healthcheck",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
     Ok(())
 }
 
 /// Execute a healthcheck and return the result
 pub async fn execute(pool: &SqlitePool, id: &str) -> Result<HealthcheckExecuteResult> {
     let healthcheck = get_with_relations(pool, id).await?;
+    if healthcheck.healthcheck.kind != "http" {
+        return Ok(execute_probe(id, &healthcheck).await);
+    }
 
     // Build URL
     let url = healthcheck.url();
@@ -455,25 +712,24 @@ pub async fn execute(pool: &SqlitePool, id: &str) -> Result<HealthcheckExecuteRe
             let status_code = response.status().as_u16();
             let status_ok = status_code == healthcheck.healthcheck.expected_status as u16;
 
-            // Check body if expected_body is set
-            let body_match = if healthcheck.healthcheck.expected_body.is_some() {
-                match response.text().await {
-                    Ok(body) => Some(
-                        body.contains(
-                            healthcheck
-                                .healthcheck
-                                .expected_body
-                                .as_ref()
-                                .unwrap_or(&String::new()),
-                        ),
-                    ),
-                    Err(_) => Some(false),
-                }
+            // Check body if expected_body or json_path is set
+            let hc = &healthcheck.healthcheck;
+            let body = if hc.expected_body.is_some() || hc.json_path.is_some() {
+                response.text().await.ok()
             } else {
                 None
             };
+            let body_match = hc
+                .expected_body
+                .as_ref()
+                .map(|expected| body.as_ref().is_some_and(|b| b.contains(expected)));
+            let json = hc.json_path.as_ref().map(|path| match &body {
+                Some(body) => json_path_value(body, path, hc.json_expected_value.as_deref()),
+                None => Err("Failed to read the response body".to_string()),
+            });
 
-            let success = status_ok && body_match.unwrap_or(true);
+            let success =
+                status_ok && body_match.unwrap_or(true) && json.as_ref().is_none_or(|j| j.is_ok());
 
             Ok(HealthcheckExecuteResult {
                 healthcheck_id: id.to_string(),
@@ -482,7 +738,8 @@ pub async fn execute(pool: &SqlitePool, id: &str) -> Result<HealthcheckExecuteRe
                 status_code: Some(status_code),
                 response_time_ms,
                 body_match,
-                error: None,
+                detail: json.as_ref().and_then(|j| j.as_ref().ok().cloned()),
+                error: json.and_then(|j| j.err()),
                 executed_at,
             })
         }
@@ -493,12 +750,218 @@ pub async fn execute(pool: &SqlitePool, id: &str) -> Result<HealthcheckExecuteRe
             status_code: None,
             response_time_ms,
             body_match: None,
+            detail: None,
             error: Some(e.to_string()),
             executed_at,
         }),
     }
 }
 
+/// Run a TCP, DNS or TLS healthcheck, with retries
+async fn execute_probe(
+    id: &str,
+    healthcheck: &HealthcheckWithRelations,
+) -> HealthcheckExecuteResult {
+    let hc = &healthcheck.healthcheck;
+    let timeout = Duration::from_secs(hc.timeout_seconds.max(1) as u64);
+    let start = Instant::now();
+    let executed_at = chrono::Utc::now().to_rfc3339();
+
+    let mut result = Err(String::new());
+    for attempt in 0..=hc.retry.max(0) {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_secs(hc.retry_interval.max(0) as u64)).await;
+        }
+
+        let probe = async {
+            match hc.kind.as_str() {
+                "tcp" => probe_tcp(&healthcheck.domain_fqdn, healthcheck.port()).await,
+                "dns" => {
+                    probe_dns(
+                        &healthcheck.domain_fqdn,
+                        hc.dns_record_type.as_deref().unwrap_or("A"),
+                        hc.dns_resolver.as_deref(),
+                    )
+                    .await
+                }
+                "tls" => {
+                    probe_tls(
+                        &healthcheck.domain_fqdn,
+                        healthcheck.port(),
+                        hc.tls_expiry_days.unwrap_or(DEFAULT_TLS_EXPIRY_DAYS),
+                    )
+                    .await
+                }
+                kind => Err(format!("Unsupported healthcheck kind: {kind}")),
+            }
+        };
+        result = tokio::time::timeout(timeout, probe)
+            .await
+            .unwrap_or_else(|_| Err(format!("Timed out after {}s", timeout.as_secs())));
+        if result.is_ok() {
+            break;
+        }
+    }
+
+    HealthcheckExecuteResult {
+        healthcheck_id: id.to_string(),
+        url: healthcheck.url(),
+        success: result.is_ok(),
+        status_code: None,
+        response_time_ms: start.elapsed().as_millis() as u64,
+        body_match: None,
+        detail: result.as_ref().ok().cloned(),
+        error: result.err(),
+        executed_at,
+    }
+}
+
+async fn probe_tcp(host: &str, port: u16) -> std::result::Result<String, String> {
+    TcpStream::connect((host, port))
+        .await
+        .map(|_| format!("Port {port} is open"))
+        .map_err(|e| e.to_string())
+}
+
+async fn probe_dns(
+    fqdn: &str,
+    record_type: &str,
+    resolver: Option<&str>,
+) -> std::result::Result<String, String> {
+    let resolver = match resolver {
+        Some(server) => {
+            let server = parse_resolver(server).ok_or("Invalid DNS resolver")?;
+            let config = ResolverConfig::from_parts(
+                None,
+                vec![],
+                NameServerConfigGroup::from_ips_clear(&[server.ip()], server.port(), true),
+            );
+            Resolver::builder_with_config(config, TokioConnectionProvider::default()).build()
+        }
+        None => TokioResolver::builder_tokio()
+            .map_err(|e| e.to_string())?
+            .build(),
+    };
+    let record_type = RecordType::from_str(record_type)
+        .map_err(|_| format!("Invalid record type {record_type}"))?;
+
+    let lookup = resolver
+        .lookup(fqdn, record_type)
+        .await
+        .map_err(|e| e.to_string())?;
+    let records = lookup.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+    if records.is_empty() {
+        return Err(format!("No {record_type} records"));
+    }
+    Ok(records.join(", "))
+}
+
+async fn probe_tls(host: &str, port: u16, expiry_days: i32) -> std::result::Result<String, String> {
+    let certificate = tls::fetch(host, port).await?;
+    if let Some(error) = certificate.chain_error {
+        return Err(error);
+    }
+
+    certificate_expiry(certificate.not_after, Utc::now(), expiry_days)
+}
+
+/// A DNS server as `ip` or `ip:port`
+pub fn parse_resolver(server: &str) -> Option<SocketAddr> {
+    server
+        .parse::<SocketAddr>()
+        .ok()
+        .or_else(|| Some(SocketAddr::new(server.parse::<IpAddr>().ok()?, 53)))
+}
+
+/// Whether a certificate expiring at `not_after` is still valid for more
+/// than `expiry_days`.
+fn certificate_expiry(
+    not_after: DateTime<Utc>,
+    now: DateTime<Utc>,
+    expiry_days: i32,
+) -> std::result::Result<String, String> {
+    let days = (not_after - now).num_days();
+    let date = not_after.format("%Y-%m-%d");
+    if not_after <= now {
+        Err(format!("Certificate expired on {date}"))
+    } else if days < expiry_days as i64 {
+        Err(format!("Certificate expires in {days} days, on {date}"))
+    } else {
+        Ok(format!("Certificate valid until {date} ({days} days)"))
+    }
+}
+
+/// The value at `path` in a JSON body, as text. Fails if the body isn't
+/// JSON, nothing matches or the value isn't the expected one.
+fn json_path_value(
+    body: &str,
+    path: &str,
+    expected: Option<&str>,
+) -> std::result::Result<String, String> {
+    let path = JsonPath::parse(path).map_err(|e| e.to_string())?;
+    let json = serde_json::from_str::<serde_json::Value>(body)
+        .map_err(|_| "Response is not JSON".to_string())?;
+
+    let value = match path.query(&json).first() {
+        Some(serde_json::Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+        None => return Err(format!("{path} not found")),
+    };
+
+    match expected {
+        Some(expected) if value != expected => {
+            Err(format!("{path} is {value}, expected {expected}"))
+        }
+        _ => Ok(format!("{path} is {value}")),
+    }
+}
+
+fn validate_kind(
+    kind: &str,
+    port: Option<i32>,
+    dns_record_type: Option<&str>,
+    dns_resolver: Option<&str>,
+    json_path: Option<&str>,
+) -> Result<()> {
+    if !HEALTHCHECK_KINDS.contains(&kind) {
+        return Err(Error::ValidationError(format!(
+            "kind must be one of {}",
+            HEALTHCHECK_KINDS.join(", ")
+        )));
+    }
+    if kind == "tcp" && port.is_none() {
+        return Err(Error::ValidationError(
+            "A tcp healthcheck needs a port".into(),
+        ));
+    }
+    if port.is_some_and(|p| !(1..=65535).contains(&p)) {
+        return Err(Error::ValidationError(
+            "port must be between 1 and 65535".into(),
+        ));
+    }
+    if let Some(record_type) = dns_record_type
+        && !DNS_RECORD_TYPES.contains(&record_type)
+    {
+        return Err(Error::ValidationError(format!(
+            "dns_record_type must be one of {}",
+            DNS_RECORD_TYPES.join(", ")
+        )));
+    }
+    if dns_resolver.is_some_and(|r| parse_resolver(r).is_none()) {
+        return Err(Error::ValidationError(
+            "dns_resolver must be an IP address, optionally with a port".into(),
+        ));
+    }
+    if let Some(path) = json_path
+        && let Err(e) = JsonPath::parse(path)
+    {
+        return Err(Error::ValidationError(format!(
+            "json_path is not a valid JSONPath: {e}"
+        )));
+    }
+    Ok(())
+}
+
 /// Export all enabled healthchecks in Uptime Kuma format
 pub async fn export_kuma(pool: &SqlitePool) -> Result<Vec<KumaMonitor>> {
     let healthchecks = sqlx::query_as::<_, Healthcheck>(
@@ -507,10 +970,12 @@ pub async fn export_kuma(pool: &SqlitePool) -> Result<Vec<KumaMonitor>> {
                protocol, path, method, headers, expected_status,
                expected_body, timeout_seconds, interval, is_enabled, notes,
                retry, retry_interval, request_body_encoding, request_body,
-               http_auth_user, http_auth_pass, kuma_id, kuma_dirty,
-               created_at, updated_at, created_by
+               http_auth_user, http_auth_pass, kind, port, dns_record_type,
+               dns_resolver, tls_expiry_days, json_path, json_expected_value,
+               kuma_id, kuma_dirty,
+               created_at, updated_at, version, created_by
         FROM healthcheck
-        WHERE is_enabled = 1
+        WHERE is_enabled = 1 AND deleted_at IS NULL
         ORDER BY name COLLATE NOCASE
         "#,
     )
@@ -568,6 +1033,7 @@ pub async fn get_for_application(
         FROM healthcheck h
         JOIN domain d ON h.domain_id = d.id
         WHERE h.application_id = ?1
+          AND h.deleted_at IS NULL
         ORDER BY h.name COLLATE NOCASE
         "#,
     )
@@ -589,6 +1055,7 @@ pub async fn get_for_service(
         FROM healthcheck h
         JOIN domain d ON h.domain_id = d.id
         WHERE h.service_id = ?1
+          AND h.deleted_at IS NULL
         ORDER BY h.name COLLATE NOCASE
         "#,
     )
@@ -598,11 +1065,130 @@ pub async fn get_for_service(
     .map_err(Into::into)
 }
 
-/// Clear the kuma_dirty flag after a successful sync to Kuma
-pub async fn clear_kuma_dirty(pool: &SqlitePool, id: &str) -> Result<()> {
-    sqlx::query("UPDATE healthcheck SET kuma_dirty = 0 WHERE id = ?1")
+/// Store the id of the monitor Kuma added for a healthcheck. This isn't an
+/// edit of the healthcheck, so it's neither audited nor versioned.
+pub async fn set_kuma_id(pool: &SqlitePool, id: &str, kuma_id: i32) -> Result<()> {
+    sqlx::query("UPDATE healthcheck SET kuma_id = ?2 WHERE id = ?1")
+        .bind(id)
+        .bind(kuma_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Clear the kuma_dirty flag after a successful sync to Kuma of
+/// `kuma_version`, unless the healthcheck changed since
+pub async fn clear_kuma_dirty(pool: &SqlitePool, id: &str, kuma_version: i64) -> Result<()> {
+    sqlx::query("UPDATE healthcheck SET kuma_dirty = 0 WHERE id = ?1 AND kuma_version = ?2")
         .bind(id)
+        .bind(kuma_version)
         .execute(pool)
         .await?;
     Ok(())
 }
+
+/// Flag the healthchecks with a Kuma monitor of an application, service or
+/// stack whose name, environment or stacks changed, to update their group
+/// and tags in Kuma. Their `kuma_version` is bumped, so a push of the old
+/// one doesn't clear the flag.
+pub async fn mark_kuma_dirty_of(pool: &SqlitePool, target_id: &str) -> Result<()> {
+    mark_kuma_dirty_of_in(&mut *pool.acquire().await?, target_id).await
+}
+
+/// [`mark_kuma_dirty_of`] on a connection, e.g. in a transaction
+pub async fn mark_kuma_dirty_of_in(conn: &mut SqliteConnection, target_id: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE healthcheck SET kuma_dirty = 1, kuma_version = kuma_version + 1
+        WHERE kuma_id IS NOT NULL
+          AND (application_id = ?1 OR service_id = ?1
+               OR application_id IN (SELECT application_id FROM application_stack WHERE stack_id = ?1))
+        "#,
+    )
+    .bind(target_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+/// IDs of the healthchecks monitored by a Kuma monitor
+pub async fn ids_for_kuma(pool: &SqlitePool, kuma_id: i32) -> Result<Vec<String>> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT id FROM healthcheck WHERE kuma_id = ?1 AND deleted_at IS NULL",
+    )
+    .bind(kuma_id)
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Kuma monitor IDs of all healthchecks, including those in the trash and
+/// purged ones whose monitor is still to be deleted
+pub async fn kuma_ids(pool: &SqlitePool) -> Result<Vec<i32>> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+        SELECT kuma_id FROM healthcheck WHERE kuma_id IS NOT NULL
+        UNION
+        SELECT kuma_id FROM kuma_sync_queue WHERE operation = 'delete'
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// IDs and intervals of the enabled healthchecks the native scheduler runs:
+/// all of them, or only those without a Kuma monitor when Kuma runs the rest
+pub async fn scheduled(pool: &SqlitePool, skip_kuma_monitored: bool) -> Result<Vec<(String, i32)>> {
+    sqlx::query_as::<_, (String, i32)>(
+        r#"
+        SELECT id, interval
+        FROM healthcheck
+        WHERE is_enabled = 1
+          AND deleted_at IS NULL
+          AND (?1 = 0 OR kuma_id IS NULL)
+        "#,
+    )
+    .bind(skip_kuma_monitored)
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_path_value_compares_as_text() {
+        let body = r#"{"status": "ok", "checks": [{"db": true}], "version": 3}"#;
+
+        assert_eq!(
+            json_path_value(body, "$.status", Some("ok")),
+            Ok("$.status is ok".to_string())
+        );
+        assert!(json_path_value(body, "$.checks[0].db", Some("true")).is_ok());
+        assert!(json_path_value(body, "$.version", None).is_ok());
+        assert_eq!(
+            json_path_value(body, "$.status", Some("down")),
+            Err("$.status is ok, expected down".to_string())
+        );
+        assert!(json_path_value(body, "$.missing", None).is_err());
+        assert!(json_path_value("<html>", "$.status", None).is_err());
+    }
+
+    #[test]
+    fn certificate_expiry_respects_threshold() {
+        let now = DateTime::parse_from_rfc3339("2026-03-01T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let in_days = |days| now + chrono::Duration::days(days);
+
+        assert!(certificate_expiry(in_days(60), now, 14).is_ok());
+        assert_eq!(
+            certificate_expiry(in_days(10), now, 14),
+            Err("Certificate expires in 10 days, on 2026-03-11".to_string())
+        );
+        assert!(certificate_expiry(in_days(-1), now, 14).is_err());
+    }
+}
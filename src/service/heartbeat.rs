@@ -0,0 +1,638 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::SqlitePool;
+
+use crate::Result;
+use crate::models::{
+    DowntimeSpan, HeartbeatEntry, UptimeBucket, UptimeHistory, UptimeRange, UptimeResolution,
+    UptimeRetention, UptimeStats,
+};
+use crate::service::healthcheck;
+
+/// Format of `heartbeat.time`, comparable with SQLite's `datetime()`.
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Fewest buckets a history chart should have.
+const MIN_HISTORY_BUCKETS: i64 = 24;
+
+/// A stored heartbeat: time, status, ping and message.
+type StoredHeartbeat = (String, i32, Option<i32>, Option<String>);
+
+/// Store heartbeats of a Kuma monitor. Heartbeats that are already stored,
+/// and those of monitors that don't belong to a healthcheck, are skipped.
+pub async fn store(pool: &SqlitePool, kuma_id: i32, entries: &[HeartbeatEntry]) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+    let mut stored = 0;
+
+    for entry in entries {
+        let Some(time) = stored_time(entry) else {
+            continue;
+        };
+
+        stored += sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO heartbeat (healthcheck_id, time, kuma_id, status, ping, msg)
+            SELECT id, ?2, ?1, ?3, ?4, ?5 FROM healthcheck WHERE kuma_id = ?1
+            "#,
+        )
+        .bind(kuma_id)
+        .bind(&time)
+        .bind(entry.status)
+        .bind(entry.ping)
+        .bind(&entry.msg)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    }
+
+    tx.commit().await?;
+    Ok(stored)
+}
+
+/// Store a heartbeat of a healthcheck run by the native scheduler.
+pub async fn record(pool: &SqlitePool, healthcheck_id: &str, entry: &HeartbeatEntry) -> Result<()> {
+    let Some(time) = stored_time(entry) else {
+        return Ok(());
+    };
+
+    sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO heartbeat (healthcheck_id, time, status, ping, msg)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        "#,
+    )
+    .bind(healthcheck_id)
+    .bind(&time)
+    .bind(entry.status)
+    .bind(entry.ping)
+    .bind(&entry.msg)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Stored heartbeats of a healthcheck since `since`, oldest first.
+pub async fn recent(
+    pool: &SqlitePool,
+    healthcheck_id: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<HeartbeatEntry>> {
+    let heartbeats = sqlx::query_as::<_, StoredHeartbeat>(
+        r#"
+        SELECT time, status, ping, msg
+        FROM heartbeat
+        WHERE healthcheck_id = ?1 AND time > ?2
+        ORDER BY time
+        "#,
+    )
+    .bind(healthcheck_id)
+    .bind(since.format(TIME_FORMAT).to_string())
+    .fetch_all(pool)
+    .await?;
+
+    Ok(heartbeats
+        .into_iter()
+        .map(|(time, status, ping, msg)| HeartbeatEntry {
+            status,
+            time: format!("{time}Z"),
+            ping,
+            msg,
+        })
+        .collect())
+}
+
+/// Add the heartbeats stored since the last run to their hourly and daily
+/// buckets, however old they are.
+pub async fn rollup(pool: &SqlitePool) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    for (resolution, bucket) in [("hour", "%Y-%m-%d %H:00:00"), ("day", "%Y-%m-%d 00:00:00")] {
+        sqlx::query(
+            r#"
+            INSERT INTO heartbeat_rollup
+                (healthcheck_id, resolution, bucket, up, down, pending, maintenance, ping_count, ping_sum, ping_min, ping_max)
+            SELECT healthcheck_id, ?1, strftime(?2, time) AS bucket,
+                   SUM(status = 1), SUM(status = 0), SUM(status = 2), SUM(status = 3),
+                   COUNT(ping), SUM(ping), MIN(ping), MAX(ping)
+            FROM heartbeat
+            WHERE rolled_up = 0
+            GROUP BY healthcheck_id, bucket
+            ON CONFLICT (healthcheck_id, resolution, bucket) DO UPDATE SET
+                up = up + excluded.up,
+                down = down + excluded.down,
+                pending = pending + excluded.pending,
+                maintenance = maintenance + excluded.maintenance,
+                ping_count = ping_count + excluded.ping_count,
+                ping_sum = COALESCE(ping_sum + excluded.ping_sum, ping_sum, excluded.ping_sum),
+                ping_min = MIN(COALESCE(ping_min, excluded.ping_min), COALESCE(excluded.ping_min, ping_min)),
+                ping_max = MAX(COALESCE(ping_max, excluded.ping_max), COALESCE(excluded.ping_max, ping_max))
+            "#,
+        )
+        .bind(resolution)
+        .bind(bucket)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query("UPDATE heartbeat SET rolled_up = 1 WHERE rolled_up = 0")
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Delete heartbeats and rollups older than their retention period.
+pub async fn prune(pool: &SqlitePool, retention: UptimeRetention) -> Result<u64> {
+    let days = |days: i64| format!("-{days} days");
+
+    let raw = sqlx::query("DELETE FROM heartbeat WHERE time < datetime('now', ?1)")
+        .bind(days(retention.raw_days))
+        .execute(pool)
+        .await?;
+
+    let rollups = sqlx::query(
+        r#"
+        DELETE FROM heartbeat_rollup
+        WHERE (resolution = 'hour' AND bucket < datetime('now', ?1))
+           OR (resolution = 'day' AND bucket < datetime('now', ?2))
+        "#,
+    )
+    .bind(days(retention.hourly_days))
+    .bind(days(retention.daily_days))
+    .execute(pool)
+    .await?;
+
+    Ok(raw.rows_affected() + rollups.rows_affected())
+}
+
+pub async fn stats(
+    pool: &SqlitePool,
+    healthcheck_id: &str,
+    range: UptimeRange,
+    retention: UptimeRetention,
+) -> Result<UptimeStats> {
+    let healthcheck = healthcheck::get(pool, healthcheck_id).await?;
+
+    let now = Utc::now().naive_utc();
+    let since = (now - chrono::Duration::days(range.days()))
+        .format(TIME_FORMAT)
+        .to_string();
+
+    let resolution = stats_resolution(range, retention);
+    if resolution != UptimeResolution::Raw {
+        let buckets = buckets(pool, healthcheck_id, resolution, &since).await?;
+        let (ping_count, ping_sum) = sqlx::query_as::<_, (i64, Option<i64>)>(
+            r#"
+            SELECT COALESCE(SUM(ping_count), 0), SUM(ping_sum)
+            FROM heartbeat_rollup
+            WHERE healthcheck_id = ?1 AND resolution = ?2 AND bucket >= ?3
+            "#,
+        )
+        .bind(healthcheck_id)
+        .bind(resolution.as_str())
+        .bind(bucket_start(&since, resolution))
+        .fetch_one(pool)
+        .await?;
+
+        let up = buckets.iter().map(|b| b.up).sum::<i64>();
+        let down = buckets.iter().map(|b| b.down).sum::<i64>();
+        return Ok(UptimeStats {
+            healthcheck_id: healthcheck.id,
+            healthcheck_name: healthcheck.name,
+            range,
+            resolution,
+            since,
+            heartbeats: buckets
+                .iter()
+                .map(|b| b.up + b.down + b.pending + b.maintenance)
+                .sum(),
+            up,
+            down,
+            uptime_percent: (up + down > 0).then(|| up as f64 * 100.0 / (up + down) as f64),
+            ping_mean: ping_sum
+                .filter(|_| ping_count > 0)
+                .map(|sum| sum as f64 / ping_count as f64),
+            ping_min: buckets.iter().filter_map(|b| b.ping_min).min(),
+            ping_max: buckets.iter().filter_map(|b| b.ping_max).max(),
+            ping_p50: None,
+            ping_p95: None,
+            ping_p99: None,
+            incidents: bucket_downtime(&buckets, resolution, now),
+        });
+    }
+
+    let heartbeats = sqlx::query_as::<_, StoredHeartbeat>(
+        r#"
+        SELECT time, status, ping, msg
+        FROM heartbeat
+        WHERE healthcheck_id = ?1 AND time >= ?2
+        ORDER BY time
+        "#,
+    )
+    .bind(healthcheck_id)
+    .bind(&since)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(summarize(
+        healthcheck.id,
+        healthcheck.name,
+        range,
+        since,
+        &heartbeats,
+        now,
+    ))
+}
+
+/// Statistics of all healthchecks of an application.
+pub async fn application_stats(
+    pool: &SqlitePool,
+    application_id: &str,
+    range: UptimeRange,
+    retention: UptimeRetention,
+) -> Result<Vec<UptimeStats>> {
+    crate::service::application::get(pool, application_id).await?;
+
+    let mut stats = Vec::new();
+    for healthcheck in healthcheck::get_for_application(pool, application_id).await? {
+        stats.push(self::stats(pool, &healthcheck.id, range, retention).await?);
+    }
+    Ok(stats)
+}
+
+/// Hourly or daily buckets over a range, for charts.
+pub async fn history(
+    pool: &SqlitePool,
+    healthcheck_id: &str,
+    range: UptimeRange,
+    retention: UptimeRetention,
+) -> Result<UptimeHistory> {
+    healthcheck::get(pool, healthcheck_id).await?;
+
+    let since = (Utc::now().naive_utc() - chrono::Duration::days(range.days()))
+        .format(TIME_FORMAT)
+        .to_string();
+    let resolution = history_resolution(range, retention);
+
+    Ok(UptimeHistory {
+        healthcheck_id: healthcheck_id.to_string(),
+        range,
+        resolution,
+        buckets: buckets(pool, healthcheck_id, resolution, &since).await?,
+    })
+}
+
+async fn buckets(
+    pool: &SqlitePool,
+    healthcheck_id: &str,
+    resolution: UptimeResolution,
+    since: &str,
+) -> Result<Vec<UptimeBucket>> {
+    Ok(sqlx::query_as::<_, UptimeBucket>(
+        r#"
+        SELECT bucket, up, down, pending, maintenance, ping_min,
+               CAST(ping_sum AS REAL) / NULLIF(ping_count, 0) AS ping_avg, ping_max
+        FROM heartbeat_rollup
+        WHERE healthcheck_id = ?1 AND resolution = ?2 AND bucket >= ?3
+        ORDER BY bucket
+        "#,
+    )
+    .bind(healthcheck_id)
+    .bind(resolution.as_str())
+    .bind(bucket_start(since, resolution))
+    .fetch_all(pool)
+    .await?)
+}
+
+/// Time of a heartbeat as stored, `None` if it can't be parsed.
+fn stored_time(entry: &HeartbeatEntry) -> Option<String> {
+    let time = DateTime::parse_from_rfc3339(&entry.time).ok()?;
+    Some(time.with_timezone(&Utc).format(TIME_FORMAT).to_string())
+}
+
+/// Raw heartbeats if they're still kept for the whole range, otherwise the
+/// finest rollup that is.
+fn stats_resolution(range: UptimeRange, retention: UptimeRetention) -> UptimeResolution {
+    let days = range.days();
+    if days <= retention.raw_days {
+        UptimeResolution::Raw
+    } else if days <= retention.hourly_days {
+        UptimeResolution::Hour
+    } else {
+        UptimeResolution::Day
+    }
+}
+
+/// The coarsest rollup that still has enough buckets over the range.
+fn history_resolution(range: UptimeRange, retention: UptimeRetention) -> UptimeResolution {
+    let days = range.days();
+    if days >= MIN_HISTORY_BUCKETS || days > retention.hourly_days {
+        UptimeResolution::Day
+    } else {
+        UptimeResolution::Hour
+    }
+}
+
+/// Start of the bucket a `TIME_FORMAT` timestamp falls in.
+fn bucket_start(time: &str, resolution: UptimeResolution) -> String {
+    match resolution {
+        UptimeResolution::Raw => time.to_string(),
+        UptimeResolution::Hour => format!("{}:00:00", &time[..13]),
+        UptimeResolution::Day => format!("{} 00:00:00", &time[..10]),
+    }
+}
+
+fn summarize(
+    healthcheck_id: String,
+    healthcheck_name: String,
+    range: UptimeRange,
+    since: String,
+    heartbeats: &[StoredHeartbeat],
+    now: NaiveDateTime,
+) -> UptimeStats {
+    let count = |status| heartbeats.iter().filter(|(_, s, ..)| *s == status).count() as i64;
+    let (up, down) = (count(1), count(0));
+    let uptime_percent = (up + down > 0).then(|| up as f64 * 100.0 / (up + down) as f64);
+
+    let mut pings = heartbeats
+        .iter()
+        .filter_map(|(_, _, ping, _)| *ping)
+        .collect::<Vec<_>>();
+    pings.sort_unstable();
+    let ping_mean = (!pings.is_empty())
+        .then(|| pings.iter().map(|&p| p as f64).sum::<f64>() / pings.len() as f64);
+
+    UptimeStats {
+        healthcheck_id,
+        healthcheck_name,
+        range,
+        resolution: UptimeResolution::Raw,
+        since,
+        heartbeats: heartbeats.len() as i64,
+        up,
+        down,
+        uptime_percent,
+        ping_mean,
+        ping_min: pings.first().copied(),
+        ping_max: pings.last().copied(),
+        ping_p50: percentile(&pings, 50),
+        ping_p95: percentile(&pings, 95),
+        ping_p99: percentile(&pings, 99),
+        incidents: downtime(heartbeats, now),
+    }
+}
+
+/// Nearest-rank percentile of sorted values.
+fn percentile(sorted: &[i32], percent: usize) -> Option<i32> {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted.get(rank - 1).copied()
+}
+
+/// Spans from a down heartbeat until the next up heartbeat. Pending and
+/// maintenance heartbeats neither start nor end a span.
+fn downtime(heartbeats: &[StoredHeartbeat], now: NaiveDateTime) -> Vec<DowntimeSpan> {
+    let parse = |time: &str| NaiveDateTime::parse_from_str(time, TIME_FORMAT).ok();
+    let span = |start: &str, end: Option<&str>, msg: &Option<String>| DowntimeSpan {
+        start: start.to_string(),
+        end: end.map(str::to_string),
+        duration_secs: match (parse(start), end.map_or(Some(now), parse)) {
+            (Some(start), Some(end)) => (end - start).num_seconds(),
+            _ => 0,
+        },
+        msg: msg.clone(),
+    };
+
+    let mut spans = Vec::new();
+    let mut open: Option<&StoredHeartbeat> = None;
+    for heartbeat in heartbeats {
+        let (time, status, ..) = heartbeat;
+        match (status, open) {
+            (0, None) => open = Some(heartbeat),
+            (1, Some((start, _, _, msg))) => {
+                spans.push(span(start, Some(time), msg));
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((start, _, _, msg)) = open {
+        spans.push(span(start, None, msg));
+    }
+
+    spans
+}
+
+/// Spans of consecutive buckets with down heartbeats. A span that reaches
+/// the current bucket has no end yet.
+fn bucket_downtime(
+    buckets: &[UptimeBucket],
+    resolution: UptimeResolution,
+    now: NaiveDateTime,
+) -> Vec<DowntimeSpan> {
+    let length = match resolution {
+        UptimeResolution::Day => chrono::Duration::days(1),
+        _ => chrono::Duration::hours(1),
+    };
+
+    let mut spans: Vec<(NaiveDateTime, NaiveDateTime)> = Vec::new();
+    for bucket in buckets.iter().filter(|b| b.down > 0) {
+        let Ok(start) = NaiveDateTime::parse_from_str(&bucket.bucket, TIME_FORMAT) else {
+            continue;
+        };
+        match spans.last_mut() {
+            Some((_, end)) if *end == start => *end = start + length,
+            _ => spans.push((start, start + length)),
+        }
+    }
+
+    spans
+        .into_iter()
+        .map(|(start, end)| {
+            let end = (end <= now).then_some(end);
+            DowntimeSpan {
+                start: start.format(TIME_FORMAT).to_string(),
+                end: end.map(|end| end.format(TIME_FORMAT).to_string()),
+                duration_secs: (end.unwrap_or(now) - start).num_seconds(),
+                msg: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beat(time: &str, status: i32, ping: Option<i32>) -> StoredHeartbeat {
+        (
+            format!("2026-03-01 {time}"),
+            status,
+            ping,
+            Some(format!("at {time}")),
+        )
+    }
+
+    fn time(time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(time, TIME_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn summarize_computes_uptime_pings_and_downtime() {
+        let heartbeats = vec![
+            beat("10:00:00", 1, Some(100)),
+            beat("10:01:00", 0, None),
+            beat("10:02:00", 0, None),
+            beat("10:03:00", 2, None),
+            beat("10:04:00", 1, Some(300)),
+            beat("10:05:00", 1, Some(200)),
+            beat("10:06:00", 0, None),
+        ];
+        let now = time("2026-03-01 10:07:30");
+
+        let stats = summarize(
+            "h".into(),
+            "web".into(),
+            UptimeRange::Day,
+            "2026-02-28 10:07:30".into(),
+            &heartbeats,
+            now,
+        );
+
+        assert_eq!((stats.heartbeats, stats.up, stats.down), (7, 3, 3));
+        assert_eq!(stats.uptime_percent, Some(50.0));
+        assert_eq!(stats.ping_mean, Some(200.0));
+        assert_eq!((stats.ping_min, stats.ping_max), (Some(100), Some(300)));
+        assert_eq!((stats.ping_p50, stats.ping_p99), (Some(200), Some(300)));
+        assert_eq!(
+            stats.incidents,
+            vec![
+                DowntimeSpan {
+                    start: "2026-03-01 10:01:00".into(),
+                    end: Some("2026-03-01 10:04:00".into()),
+                    duration_secs: 180,
+                    msg: Some("at 10:01:00".into()),
+                },
+                DowntimeSpan {
+                    start: "2026-03-01 10:06:00".into(),
+                    end: None,
+                    duration_secs: 90,
+                    msg: Some("at 10:06:00".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn summarize_without_heartbeats_has_no_percentages() {
+        let stats = summarize(
+            "h".into(),
+            "web".into(),
+            UptimeRange::Week,
+            String::new(),
+            &[],
+            Utc::now().naive_utc(),
+        );
+
+        assert_eq!(stats.uptime_percent, None);
+        assert_eq!(stats.ping_p95, None);
+        assert!(stats.incidents.is_empty());
+    }
+
+    #[test]
+    fn resolutions_depend_on_range_and_retention() {
+        let retention = UptimeRetention {
+            raw_days: 7,
+            hourly_days: 30,
+            daily_days: 365,
+        };
+        let stats = |range| stats_resolution(range, retention);
+        let history = |range| history_resolution(range, retention);
+
+        assert_eq!(stats(UptimeRange::Week), UptimeResolution::Raw);
+        assert_eq!(stats(UptimeRange::Month), UptimeResolution::Hour);
+        assert_eq!(stats(UptimeRange::Quarter), UptimeResolution::Day);
+        assert_eq!(history(UptimeRange::Day), UptimeResolution::Hour);
+        assert_eq!(history(UptimeRange::Week), UptimeResolution::Hour);
+        assert_eq!(history(UptimeRange::Month), UptimeResolution::Day);
+    }
+
+    #[test]
+    fn bucket_downtime_merges_consecutive_buckets() {
+        let bucket = |hour: u32, down: i64| UptimeBucket {
+            bucket: format!("2026-03-01 {hour:02}:00:00"),
+            up: 60 - down,
+            down,
+            pending: 0,
+            maintenance: 0,
+            ping_min: None,
+            ping_avg: None,
+            ping_max: None,
+        };
+        let buckets = [bucket(8, 2), bucket(9, 60), bucket(10, 0), bucket(11, 1)];
+
+        let spans = bucket_downtime(
+            &buckets,
+            UptimeResolution::Hour,
+            time("2026-03-01 11:30:00"),
+        );
+
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[0].start, "2026-03-01 08:00:00");
+        assert_eq!(spans[0].end.as_deref(), Some("2026-03-01 10:00:00"));
+        assert_eq!(spans[0].duration_secs, 7200);
+        assert_eq!((spans[1].end.clone(), spans[1].duration_secs), (None, 1800));
+    }
+
+    #[tokio::test]
+    async fn rollup_counts_late_heartbeats() {
+        let pool = crate::testing::pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO application (id, name) VALUES ('a', 'web');
+            INSERT INTO domain (id, fqdn, target_application_id) VALUES ('d', 'web.example.org', 'a');
+            INSERT INTO healthcheck (id, name, application_id, domain_id) VALUES ('h', 'web', 'a', 'd');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let entry = |time: &str, status: i32, ping: i32| HeartbeatEntry {
+            status,
+            time: format!("2026-03-01T{time}Z"),
+            ping: Some(ping),
+            msg: None,
+        };
+        let rollups = || {
+            sqlx::query_as::<_, (String, i64, i64, Option<i64>, Option<i64>)>(
+                "SELECT resolution, up, down, ping_min, ping_max FROM heartbeat_rollup ORDER BY resolution",
+            )
+            .fetch_all(&pool)
+        };
+
+        for beat in [entry("10:05:00", 1, 100), entry("11:05:00", 1, 200)] {
+            record(&pool, "h", &beat).await.unwrap();
+        }
+        rollup(&pool).await.unwrap();
+        // Rolling up again doesn't count them twice
+        rollup(&pool).await.unwrap();
+        assert_eq!(rollups().await.unwrap().len(), 3);
+
+        // Older than the latest bucket, e.g. history sent after a reconnect
+        record(&pool, "h", &entry("10:01:00", 0, 50)).await.unwrap();
+        rollup(&pool).await.unwrap();
+
+        let hour = sqlx::query_as::<_, (i64, i64, Option<i64>)>(
+            r#"
+            SELECT up, down, ping_min FROM heartbeat_rollup
+            WHERE resolution = 'hour' AND bucket = '2026-03-01 10:00:00'
+            "#,
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(hour, (1, 1, Some(50)));
+        let all = rollups().await.unwrap();
+        assert_eq!(all[0], ("day".into(), 2, 1, Some(50), Some(200)));
+    }
+}
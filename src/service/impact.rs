@@ -0,0 +1,265 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use itertools::Itertools;
+use serde_json::json;
+use sqlx::SqlitePool;
+
+use crate::models::{Graph, ImpactContact, ImpactEntity, ImpactReport};
+use crate::service::graph;
+use crate::{Error, Result};
+
+/// Contribution types of the people to notify about an impact.
+pub const NOTIFY_CONTRIBUTION_TYPES: &[&str] = &["owner", "maintainer"];
+
+/// Everything that transitively depends on an infra or service: services
+/// and applications running on it, applications using those services,
+/// their domains and all healthchecks of any of them.
+pub async fn analyze(pool: &SqlitePool, entity_type: &str, id: &str) -> Result<ImpactReport> {
+    let graph = graph::get(pool, None, None).await?;
+
+    let root = graph
+        .nodes
+        .iter()
+        .find(|n| n.id == id && n.entity_type == entity_type)
+        .ok_or_else(|| Error::NotFound(format!("No {entity_type} with id '{id}'")))?;
+
+    let affected = affected(&graph, id);
+    let of_type = |entity_type: &str| -> Vec<ImpactEntity> {
+        graph
+            .nodes
+            .iter()
+            .filter(|n| n.entity_type == entity_type && n.id != id)
+            .filter(|n| affected.contains(n.id.as_str()))
+            .map(|n| ImpactEntity {
+                id: n.id.clone(),
+                entity_type: n.entity_type.clone(),
+                name: n.name.clone(),
+                environment: n.environment.clone(),
+            })
+            .collect()
+    };
+
+    let root = ImpactEntity {
+        id: root.id.clone(),
+        entity_type: root.entity_type.clone(),
+        name: root.name.clone(),
+        environment: root.environment.clone(),
+    };
+    let services = of_type("service");
+    let applications = of_type("application");
+    let domains = of_type("domain");
+
+    let ids = |entities: &[ImpactEntity]| json!(entities.iter().map(|e| &e.id).collect_vec());
+    // Healthchecks of the root service itself are affected too
+    let service_ids = json!(
+        services
+            .iter()
+            .map(|s| s.id.as_str())
+            .chain((entity_type == "service").then_some(id))
+            .collect_vec()
+    );
+    let application_ids = ids(&applications);
+
+    let healthchecks = sqlx::query_as::<_, ImpactEntity>(
+        r#"
+        SELECT id, 'healthcheck' AS entity_type, name, NULL AS environment
+        FROM healthcheck
+        WHERE deleted_at IS NULL
+          AND (application_id IN (SELECT value FROM json_each(?1))
+               OR service_id IN (SELECT value FROM json_each(?2))
+               OR domain_id IN (SELECT value FROM json_each(?3)))
+        ORDER BY name COLLATE NOCASE
+        "#,
+    )
+    .bind(application_ids.to_string())
+    .bind(service_ids.to_string())
+    .bind(ids(&domains).to_string())
+    .fetch_all(pool)
+    .await?;
+
+    let contributions = sqlx::query_as::<_, (String, String, Option<String>, String, String)>(
+        r#"
+        SELECT p.id, p.name, p.email, a.name, ap.contribution_type
+        FROM application_person ap
+        JOIN person p ON p.id = ap.person_id
+        JOIN application a ON a.id = ap.application_id
+        WHERE ap.application_id IN (SELECT value FROM json_each(?1))
+          AND ap.contribution_type IN (SELECT value FROM json_each(?2))
+          AND p.deleted_at IS NULL
+        ORDER BY p.name COLLATE NOCASE, p.id, a.name COLLATE NOCASE
+        "#,
+    )
+    .bind(application_ids.to_string())
+    .bind(json!(NOTIFY_CONTRIBUTION_TYPES).to_string())
+    .fetch_all(pool)
+    .await?;
+
+    let people = contributions
+        .into_iter()
+        .chunk_by(|(id, ..)| id.clone())
+        .into_iter()
+        .filter_map(|(_, rows)| {
+            let rows = rows.collect_vec();
+            let (id, name, email, ..) = rows.first()?.clone();
+            Some(ImpactContact {
+                id,
+                name,
+                email,
+                applications: rows
+                    .iter()
+                    .map(|(.., application, contribution)| {
+                        format!("{application} ({contribution})")
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+
+    Ok(ImpactReport {
+        root,
+        services,
+        applications,
+        domains,
+        healthchecks,
+        people,
+    })
+}
+
+/// IDs of `root` and every node that transitively depends on it.
+///
+/// Graph edges point from the dependent entity to its dependency, except
+/// `has_domain`: a domain goes down with its application.
+fn affected<'a>(graph: &'a Graph, root: &'a str) -> HashSet<&'a str> {
+    let mut affected = HashSet::from([root]);
+    loop {
+        let before = affected.len();
+        for edge in &graph.edges {
+            let (dependent, dependency) = match edge.relation.as_str() {
+                "has_domain" => (&edge.target, &edge.source),
+                _ => (&edge.source, &edge.target),
+            };
+            if affected.contains(dependency.as_str()) {
+                affected.insert(dependent);
+            }
+        }
+        if affected.len() == before {
+            return affected;
+        }
+    }
+}
+
+fn entity_label(entity: &ImpactEntity) -> String {
+    match &entity.environment {
+        Some(environment) => format!("{} ({environment})", entity.name),
+        None => entity.name.clone(),
+    }
+}
+
+/// Render an impact report as markdown, to paste into a maintenance
+/// announcement or incident review.
+pub fn to_md(report: &ImpactReport) -> String {
+    let mut md = format!("# Impact of {} going down\n", entity_label(&report.root));
+
+    let sections = [
+        ("Services", &report.services),
+        ("Applications", &report.applications),
+        ("Domains", &report.domains),
+        ("Healthchecks", &report.healthchecks),
+    ];
+    for (title, entities) in sections {
+        let _ = write!(md, "\n## {title} ({})\n\n", entities.len());
+        if entities.is_empty() {
+            md.push_str("None\n");
+        }
+        for entity in entities {
+            let _ = writeln!(md, "- {}", entity_label(entity));
+        }
+    }
+
+    let _ = write!(md, "\n## People to notify ({})\n\n", report.people.len());
+    if report.people.is_empty() {
+        md.push_str("None\n");
+    }
+    for person in &report.people {
+        let email = person
+            .email
+            .as_ref()
+            .map(|email| format!(" <{email}>"))
+            .unwrap_or_default();
+        let _ = writeln!(
+            md,
+            "- {}{email}: {}",
+            person.name,
+            person.applications.join(", ")
+        );
+    }
+
+    md
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{GraphEdge, GraphNode};
+
+    fn entity(name: &str, environment: Option<&str>) -> ImpactEntity {
+        ImpactEntity {
+            id: name.to_string(),
+            entity_type: String::new(),
+            name: name.to_string(),
+            environment: environment.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn affected_follows_dependencies_transitively() {
+        let edge = |source: &str, target: &str, relation: &str| GraphEdge {
+            source: source.to_string(),
+            target: target.to_string(),
+            relation: relation.to_string(),
+        };
+        let graph = Graph {
+            nodes: Vec::<GraphNode>::new(),
+            edges: vec![
+                edge("es", "nomad", "runs_on"),
+                edge("web", "es", "uses"),
+                edge("web", "web.example.org", "has_domain"),
+                edge("api.example.org", "web", "targets"),
+                edge("other", "elsewhere", "runs_on"),
+                edge("web", "elsewhere", "runs_on"),
+            ],
+        };
+
+        let affected = affected(&graph, "nomad");
+
+        assert_eq!(
+            affected.into_iter().sorted().collect_vec(),
+            ["api.example.org", "es", "nomad", "web", "web.example.org"]
+        );
+    }
+
+    #[test]
+    fn markdown_lists_every_section() {
+        let report = ImpactReport {
+            root: entity("nomad", None),
+            services: vec![entity("es", Some("prd"))],
+            applications: vec![],
+            domains: vec![],
+            healthchecks: vec![],
+            people: vec![ImpactContact {
+                id: "p".to_string(),
+                name: "Jane".to_string(),
+                email: Some("jane@example.org".to_string()),
+                applications: vec!["web (owner)".to_string()],
+            }],
+        };
+
+        let md = to_md(&report);
+
+        assert!(md.starts_with("# Impact of nomad going down\n"));
+        assert!(md.contains("\n## Services (1)\n\n- es (prd)\n"));
+        assert!(md.contains("\n## Applications (0)\n\nNone\n"));
+        assert!(md.ends_with("- Jane <jane@example.org>: web (owner)\n"));
+    }
+}
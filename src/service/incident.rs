@@ -0,0 +1,307 @@
+use sqlx::SqlitePool;
+use tracing::info;
+
+use crate::models::{
+    Incident, IncidentFilters, IncidentStatus, PaginatedResponse, PaginationParams, new_id,
+};
+use crate::{Error, Result};
+
+/// A heartbeat as far as incidents are concerned: time, status and message.
+type Beat = (String, i32, Option<String>);
+
+/// A healthcheck to detect incidents of: ID, application and service.
+type Checked = (String, Option<String>, Option<String>);
+
+#[derive(Debug, PartialEq, Eq)]
+enum Change {
+    Open {
+        started_at: String,
+        msg: Option<String>,
+    },
+    Resolve {
+        resolved_at: String,
+    },
+}
+
+const SELECT_INCIDENT: &str = r#"
+    SELECT i.id, i.healthcheck_id, h.name AS healthcheck_name,
+           i.application_id, a.name AS application_name,
+           i.service_id, s.name AS service_name,
+           i.started_at, i.resolved_at,
+           CAST(strftime('%s', COALESCE(i.resolved_at, datetime('now'))) - strftime('%s', i.started_at) AS INTEGER) AS duration_secs,
+           i.msg,
+           (SELECT json_group_array(fqdn) FROM (
+                SELECT d.fqdn FROM domain d
+                WHERE d.deleted_at IS NULL
+                  AND (d.id = h.domain_id
+                       OR d.target_application_id = i.application_id
+                       OR d.target_service_id = i.service_id
+                       OR d.id IN (SELECT domain_id FROM application_domain WHERE application_id = i.application_id))
+                ORDER BY d.fqdn
+           )) AS domains
+    FROM incident i
+    JOIN healthcheck h ON h.id = i.healthcheck_id
+    LEFT JOIN application a ON a.id = i.application_id
+    LEFT JOIN service s ON s.id = i.service_id
+"#;
+
+const FILTER_INCIDENTS: &str = r#"
+    WHERE (?1 IS NULL OR (i.resolved_at IS NULL) = ?1)
+      AND (?2 IS NULL OR i.healthcheck_id = ?2)
+      AND (?3 IS NULL OR i.application_id = ?3)
+      AND (?4 IS NULL OR i.service_id = ?4)
+      AND (?5 IS NULL OR i.started_at >= ?5)
+      AND (?6 IS NULL OR i.started_at < ?6)
+"#;
+
+pub async fn list(
+    pool: &SqlitePool,
+    filters: &IncidentFilters,
+) -> Result<PaginatedResponse<Incident>> {
+    let params = PaginationParams {
+        page: filters.page,
+        per_page: filters.per_page,
+        search: None,
+    };
+    let open = filters.status.map(|s| s == IncidentStatus::Open);
+
+    let incidents = sqlx::query_as::<_, Incident>(&format!(
+        "{SELECT_INCIDENT} {FILTER_INCIDENTS} ORDER BY i.started_at DESC LIMIT ?7 OFFSET ?8"
+    ))
+    .bind(open)
+    .bind(&filters.healthcheck_id)
+    .bind(&filters.application_id)
+    .bind(&filters.service_id)
+    .bind(&filters.since)
+    .bind(&filters.until)
+    .bind(params.limit() as i32)
+    .bind(params.offset() as i32)
+    .fetch_all(pool)
+    .await?;
+
+    let (total,) = sqlx::query_as::<_, (i64,)>(&format!(
+        "SELECT COUNT(*) FROM incident i {FILTER_INCIDENTS}"
+    ))
+    .bind(open)
+    .bind(&filters.healthcheck_id)
+    .bind(&filters.application_id)
+    .bind(&filters.service_id)
+    .bind(&filters.since)
+    .bind(&filters.until)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(PaginatedResponse::new(incidents, total, &params))
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<Incident> {
+    sqlx::query_as::<_, Incident>(&format!("{SELECT_INCIDENT} WHERE i.id = ?1"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| Error::NotFound(format!("Incident with id '{id}' not found")))
+}
+
+/// Open and resolve incidents of the healthchecks of a Kuma monitor from
+/// their stored heartbeats. Call this after storing new heartbeats. Kuma
+/// sends pending heartbeats while it retries, so the first down one opens an
+/// incident.
+///
+/// Returns the incidents that were opened or resolved.
+pub async fn detect(pool: &SqlitePool, kuma_id: i32) -> Result<Vec<Incident>> {
+    let healthchecks = sqlx::query_as::<_, Checked>(
+        r#"
+        SELECT id, application_id, service_id
+        FROM healthcheck
+        WHERE kuma_id = ?1 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(kuma_id)
+    .fetch_all(pool)
+    .await?;
+
+    detect_for(pool, healthchecks).await
+}
+
+/// Like [`detect`], for a healthcheck run by the native scheduler. It too
+/// only reports down once all retries failed.
+pub async fn detect_native(pool: &SqlitePool, healthcheck_id: &str) -> Result<Vec<Incident>> {
+    let healthchecks = sqlx::query_as::<_, Checked>(
+        r#"
+        SELECT id, application_id, service_id
+        FROM healthcheck
+        WHERE id = ?1 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(healthcheck_id)
+    .fetch_all(pool)
+    .await?;
+
+    detect_for(pool, healthchecks).await
+}
+
+async fn detect_for(pool: &SqlitePool, healthchecks: Vec<Checked>) -> Result<Vec<Incident>> {
+    let mut changed = Vec::new();
+    for (healthcheck_id, application_id, service_id) in healthchecks {
+        let open = sqlx::query_scalar::<_, String>(
+            "SELECT started_at FROM incident WHERE healthcheck_id = ?1 AND resolved_at IS NULL",
+        )
+        .bind(&healthcheck_id)
+        .fetch_optional(pool)
+        .await?;
+
+        // Everything since an open incident started, or since the last up
+        // heartbeat: earlier down heartbeats have been dealt with
+        let since = match &open {
+            Some(started_at) => Some(started_at.clone()),
+            None => {
+                sqlx::query_scalar::<_, Option<String>>(
+                    "SELECT MAX(time) FROM heartbeat WHERE healthcheck_id = ?1 AND status = 1",
+                )
+                .bind(&healthcheck_id)
+                .fetch_one(pool)
+                .await?
+            }
+        };
+
+        let heartbeats = sqlx::query_as::<_, Beat>(
+            r#"
+            SELECT time, status, msg
+            FROM heartbeat
+            WHERE healthcheck_id = ?1 AND time > COALESCE(?2, '')
+            ORDER BY time
+            "#,
+        )
+        .bind(&healthcheck_id)
+        .bind(&since)
+        .fetch_all(pool)
+        .await?;
+
+        let changes = changes(open.is_some(), &heartbeats);
+        if changes.is_empty() {
+            continue;
+        }
+
+        let mut tx = pool.begin().await?;
+        for change in changes {
+            match change {
+                Change::Open { started_at, msg } => {
+                    info!("Healthcheck {healthcheck_id} is down since {started_at}");
+                    let id = sqlx::query_scalar::<_, String>(
+                        r#"
+                        INSERT INTO incident (id, healthcheck_id, application_id, service_id, started_at, msg)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                        ON CONFLICT DO NOTHING
+                        RETURNING id
+                        "#,
+                    )
+                    .bind(new_id())
+                    .bind(&healthcheck_id)
+                    .bind(&application_id)
+                    .bind(&service_id)
+                    .bind(&started_at)
+                    .bind(&msg)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                    changed.extend(id);
+                }
+                Change::Resolve { resolved_at } => {
+                    info!("Healthcheck {healthcheck_id} is up again since {resolved_at}");
+                    let id = sqlx::query_scalar::<_, String>(
+                        r#"
+                        UPDATE incident SET resolved_at = ?2
+                        WHERE healthcheck_id = ?1 AND resolved_at IS NULL
+                        RETURNING id
+                        "#,
+                    )
+                    .bind(&healthcheck_id)
+                    .bind(&resolved_at)
+                    .fetch_optional(&mut *tx)
+                    .await?;
+                    changed.extend(id);
+                }
+            }
+        }
+        tx.commit().await?;
+    }
+
+    let mut incidents = Vec::new();
+    for id in changed {
+        incidents.push(get(pool, &id).await?);
+    }
+    Ok(incidents)
+}
+
+/// Incidents opened and resolved by a run of heartbeats. An incident opens
+/// at a down heartbeat, the retries were spent before it, and is resolved by
+/// the next up heartbeat. Pending and maintenance heartbeats are ignored.
+fn changes(mut open: bool, heartbeats: &[Beat]) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    for beat in heartbeats {
+        match beat.1 {
+            1 if open => {
+                changes.push(Change::Resolve {
+                    resolved_at: beat.0.clone(),
+                });
+                open = false;
+            }
+            0 if !open => {
+                changes.push(Change::Open {
+                    started_at: beat.0.clone(),
+                    msg: beat.2.clone(),
+                });
+                open = true;
+            }
+            _ => {}
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beat(minute: u32, status: i32) -> Beat {
+        (
+            format!("2026-03-01 10:{minute:02}:00"),
+            status,
+            Some(format!("at {minute}")),
+        )
+    }
+
+    #[test]
+    fn changes_open_at_down_and_ignore_pending() {
+        let heartbeats = [
+            beat(0, 0),
+            beat(1, 1),
+            beat(2, 0),
+            beat(3, 2),
+            beat(4, 0),
+            beat(5, 0),
+            beat(6, 1),
+            beat(7, 0),
+        ];
+
+        assert_eq!(
+            changes(false, &heartbeats[2..7]),
+            vec![
+                Change::Open {
+                    started_at: "2026-03-01 10:02:00".into(),
+                    msg: Some("at 2".into()),
+                },
+                Change::Resolve {
+                    resolved_at: "2026-03-01 10:06:00".into(),
+                },
+            ]
+        );
+        assert_eq!(changes(false, &heartbeats).len(), 5);
+        assert_eq!(
+            changes(true, &heartbeats[..1]),
+            vec![],
+            "an open incident stays open while down"
+        );
+    }
+}
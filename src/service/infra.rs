@@ -1,9 +1,10 @@
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 
 use crate::models::{
-    ApplicationInfraRelation, CreateInfra, Infra, InfraWithRelations, PaginatedResponse,
-    PaginationParams, ServiceInfraRelation, UpdateInfra, new_id,
+    ApplicationInfraRelation, AuditAction, CreateInfra, Infra, InfraWithRelations,
+    PaginatedResponse, PaginationParams, ServiceInfraRelation, UpdateInfra, new_id,
 };
+use crate::service::{audit, revision, trash, version};
 use crate::{Error, Result};
 
 pub async fn list(
@@ -17,9 +18,10 @@ pub async fn list(
 
     let items = sqlx::query_as::<_, Infra>(
         r#"
-        SELECT id, name, description, type, created_at, updated_at, created_by
+        SELECT id, name, description, type, created_at, updated_at, version, created_by
         FROM infra
-        WHERE (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
           AND (?2 IS NULL OR type = ?2)
         ORDER BY name COLLATE NOCASE ASC
         LIMIT ?3 OFFSET ?4
@@ -36,7 +38,8 @@ pub async fn list(
         r#"
         SELECT COUNT(*)
         FROM infra
-        WHERE (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
           AND (?2 IS NULL OR type = ?2)
         "#,
     )
@@ -49,15 +52,20 @@ pub async fn list(
 }
 
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<Infra> {
+    get_in(&mut *pool.acquire().await?, id).await
+}
+
+/// [`get`] on a connection, e.g. in a transaction
+pub async fn get_in(conn: &mut SqliteConnection, id: &str) -> Result<Infra> {
     sqlx::query_as::<_, Infra>(
         r#"
-        SELECT id, name, description, type, created_at, updated_at, created_by
+        SELECT id, name, description, type, created_at, updated_at, version, created_by
         FROM infra
-        WHERE id = ?1
+        WHERE id = ?1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or_else(|| Error::NotFound(format!("Infra with id '{}' not found", id)))
 }
@@ -71,6 +79,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<InfraWith
         FROM application a
         JOIN application_infra ai ON a.id = ai.application_id
         WHERE ai.infra_id = ?1
+          AND a.deleted_at IS NULL
         ORDER BY a.name COLLATE NOCASE
         "#,
     )
@@ -84,6 +93,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<InfraWith
         FROM service s
         JOIN service_infra si ON s.id = si.service_id
         WHERE si.infra_id = ?1
+          AND s.deleted_at IS NULL
         ORDER BY s.name COLLATE NOCASE
         "#,
     )
@@ -98,58 +108,152 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<InfraWith
     })
 }
 
-pub async fn create(pool: &SqlitePool, input: CreateInfra) -> Result<Infra> {
+pub async fn create(pool: &SqlitePool, actor: Option<&str>, input: CreateInfra) -> Result<Infra> {
     let id = new_id();
 
     sqlx::query(
         r#"
-        INSERT INTO infra (id, name, description, type)
-        VALUES (?1, ?2, ?3, ?4)
+        INSERT INTO infra (id, name, description, type, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5)
         "#,
     )
     .bind(&id)
     .bind(&input.name)
     .bind(&input.description)
     .bind(&input.infra_type)
+    .bind(actor)
     .execute(pool)
     .await?;
 
-    get(pool, &id).await
+    let infra = get(pool, &id).await?;
+    audit::record(
+        pool,
+        actor,
+        "infra",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&infra),
+    )
+    .await?;
+    revision::record(pool, actor, "infra", &id, None, &infra).await?;
+
+    Ok(infra)
 }
 
-pub async fn update(pool: &SqlitePool, id: &str, input: UpdateInfra) -> Result<Infra> {
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdateInfra,
+) -> Result<Infra> {
     let existing = get(pool, id).await?;
+    version::check("Infra", id, input.version, existing.version)?;
 
-    let name = input.name.unwrap_or(existing.name);
-    let description = input.description.or(existing.description);
-    let infra_type = input.infra_type.unwrap_or(existing.infra_type);
+    let values = Infra {
+        name: input.name.unwrap_or_else(|| existing.name.clone()),
+        description: input.description.or_else(|| existing.description.clone()),
+        infra_type: input
+            .infra_type
+            .unwrap_or_else(|| existing.infra_type.clone()),
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
 
-    sqlx::query(
+/// Roll back to a revision. Unlike [`update`], fields without a value in
+/// the revision are cleared instead of kept.
+pub async fn rollback(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    rev: i64,
+    expected_version: Option<i64>,
+) -> Result<Infra> {
+    let existing = get(pool, id).await?;
+    version::check("Infra", id, expected_version, existing.version)?;
+    let snapshot = revision::snapshot::<UpdateInfra>(pool, "infra", id, rev).await?;
+
+    let values = Infra {
+        name: snapshot.name.unwrap_or_else(|| existing.name.clone()),
+        description: snapshot.description,
+        infra_type: snapshot
+            .infra_type
+            .unwrap_or_else(|| existing.infra_type.clone()),
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
+
+/// Store `values` as the next version of `previous`
+async fn write(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    previous: Infra,
+    values: Infra,
+) -> Result<Infra> {
+    let id = previous.id.as_str();
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
         r#"
         UPDATE infra
-        SET name = ?1, description = ?2, type = ?3, updated_at = datetime('now')
-        WHERE id = ?4
+        SET name = ?1, description = ?2, type = ?3, updated_at = datetime('now'), version = version + 1
+        WHERE id = ?4 AND version = ?5
         "#,
     )
-    .bind(&name)
-    .bind(&description)
-    .bind(&infra_type)
+    .bind(&values.name)
+    .bind(&values.description)
+    .bind(&values.infra_type)
     .bind(id)
-    .execute(pool)
+    .bind(previous.version)
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Infra", id));
+    }
+
+    let infra = get_in(&mut tx, id).await?;
+    audit::record_in(
+        &mut tx,
+        actor,
+        "infra",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&infra),
+    )
     .await?;
+    revision::record_in(&mut tx, actor, "infra", id, Some(&previous), &infra).await?;
 
-    get(pool, id).await
+    tx.commit().await?;
+    Ok(infra)
 }
 
-pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
-    let result = sqlx::query("DELETE FROM infra WHERE id = ?1")
-        .bind(id)
-        .execute(pool)
-        .await?;
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Infra", id, expected_version, existing.version)?;
+
+    let result = trash::soft_delete(pool, actor, "infra", id).await?;
 
     if result.rows_affected() == 0 {
         return Err(Error::NotFound(format!("Infra with id '{}' not found", id)));
     }
 
+    audit::record(
+        pool,
+        actor,
+        "infra",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
     Ok(())
 }
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use sqlx::{FromRow, SqlitePool, types::Json};
+
+use crate::Result;
+use crate::models::{Healthcheck, KumaSyncItem};
+
+/// First retry of a failed item, doubled on every next failure.
+const RETRY_BASE_SECS: i64 = 30;
+
+/// Longest wait between retries.
+const RETRY_MAX_SECS: i64 = 3600;
+
+/// Queue the healthchecks flagged `kuma_dirty`: a push for healthchecks,
+/// a pause for those in the trash. Without `new_monitors` only healthchecks
+/// that already have a Kuma monitor are queued.
+///
+/// Queued items keep their attempts and backoff, unless the operation
+/// changed, and get the `kuma_version` of a healthcheck changed meanwhile.
+pub async fn enqueue_dirty(pool: &SqlitePool, new_monitors: bool) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO kuma_sync_queue (healthcheck_id, operation, kuma_id, name, kuma_version)
+        SELECT id, CASE WHEN deleted_at IS NULL THEN 'push' ELSE 'pause' END, kuma_id, name,
+               kuma_version
+        FROM healthcheck
+        WHERE kuma_dirty = 1
+          AND (kuma_id IS NOT NULL OR (deleted_at IS NULL AND ?1))
+        ON CONFLICT (healthcheck_id) DO UPDATE SET
+            operation = excluded.operation,
+            kuma_id = excluded.kuma_id,
+            name = excluded.name,
+            kuma_version = excluded.kuma_version,
+            attempts = CASE WHEN operation = excluded.operation THEN attempts ELSE 0 END,
+            last_error = CASE WHEN operation = excluded.operation THEN last_error END,
+            next_attempt_at = CASE WHEN operation = excluded.operation
+                                   THEN next_attempt_at ELSE datetime('now') END,
+            updated_at = datetime('now')
+        WHERE kuma_sync_queue.operation <> excluded.operation
+           OR kuma_sync_queue.kuma_version <> excluded.kuma_version
+        "#,
+    )
+    .bind(new_monitors)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Queue a push of one healthcheck, or of all of them, to be done right
+/// away.
+pub async fn enqueue_push(pool: &SqlitePool, healthcheck_id: Option<&str>) -> Result<u64> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO kuma_sync_queue (healthcheck_id, operation, kuma_id, name, kuma_version)
+        SELECT id, 'push', kuma_id, name, kuma_version
+        FROM healthcheck
+        WHERE deleted_at IS NULL AND (?1 IS NULL OR id = ?1)
+        ON CONFLICT (healthcheck_id) DO UPDATE SET
+            operation = 'push',
+            kuma_id = excluded.kuma_id,
+            name = excluded.name,
+            kuma_version = excluded.kuma_version,
+            attempts = 0,
+            last_error = NULL,
+            next_attempt_at = datetime('now'),
+            updated_at = datetime('now')
+        "#,
+    )
+    .bind(healthcheck_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Queued items, oldest first, optionally of one healthcheck
+pub async fn list(pool: &SqlitePool, healthcheck_id: Option<&str>) -> Result<Vec<KumaSyncItem>> {
+    sqlx::query_as::<_, KumaSyncItem>(
+        r#"
+        SELECT healthcheck_id, operation, kuma_id, name, attempts, last_error,
+               next_attempt_at, created_at, updated_at, kuma_version
+        FROM kuma_sync_queue
+        WHERE ?1 IS NULL OR healthcheck_id = ?1
+        ORDER BY created_at, name COLLATE NOCASE
+        "#,
+    )
+    .bind(healthcheck_id)
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Queued items whose (next) attempt is due, oldest first
+pub async fn due(pool: &SqlitePool) -> Result<Vec<KumaSyncItem>> {
+    sqlx::query_as::<_, KumaSyncItem>(
+        r#"
+        SELECT healthcheck_id, operation, kuma_id, name, attempts, last_error,
+               next_attempt_at, created_at, updated_at, kuma_version
+        FROM kuma_sync_queue
+        WHERE next_attempt_at <= datetime('now')
+        ORDER BY created_at, name COLLATE NOCASE
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Remove an item Kuma accepted, unless it was queued again for a newer
+/// change meanwhile
+pub async fn done(pool: &SqlitePool, item: &KumaSyncItem) -> Result<()> {
+    sqlx::query(
+        "DELETE FROM kuma_sync_queue WHERE healthcheck_id = ?1 AND operation = ?2 AND kuma_version = ?3",
+    )
+    .bind(&item.healthcheck_id)
+    .bind(&item.operation)
+    .bind(item.kuma_version)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a failed attempt and when to retry
+pub async fn failed(pool: &SqlitePool, item: &KumaSyncItem, error: &str) -> Result<()> {
+    let delay = format!("+{} seconds", retry_delay_secs(item.attempts + 1));
+    sqlx::query(
+        r#"
+        UPDATE kuma_sync_queue
+        SET attempts = attempts + 1,
+            last_error = ?2,
+            next_attempt_at = datetime('now', ?3)
+        WHERE healthcheck_id = ?1
+        "#,
+    )
+    .bind(&item.healthcheck_id)
+    .bind(error)
+    .bind(delay)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Application or service a healthcheck belongs to, as grouped and tagged
+/// in Kuma
+#[derive(Debug, Clone, FromRow)]
+pub struct KumaTarget {
+    pub target_type: String,
+    pub target_id: String,
+    pub name: String,
+    pub environment: String,
+    /// Kuma group monitor, if one was added
+    pub group_kuma_id: Option<i32>,
+    /// Names of the stacks of an application, as a JSON array
+    pub stacks: Json<Vec<String>>,
+}
+
+/// The application or service of a healthcheck
+pub async fn target(pool: &SqlitePool, hc: &Healthcheck) -> Result<Option<KumaTarget>> {
+    sqlx::query_as::<_, KumaTarget>(
+        r#"
+        SELECT 'application' AS target_type, a.id AS target_id, a.name, a.environment,
+               g.kuma_id AS group_kuma_id,
+               (SELECT json_group_array(name) FROM (
+                    SELECT s.name FROM application_stack x
+                    JOIN stack s ON s.id = x.stack_id
+                    WHERE x.application_id = a.id AND s.deleted_at IS NULL
+                    ORDER BY s.name COLLATE NOCASE)) AS stacks
+        FROM application a
+        LEFT JOIN kuma_group g ON g.target_id = a.id
+        WHERE a.id = ?1
+        UNION ALL
+        SELECT 'service', s.id, s.name, s.environment, g.kuma_id, '[]'
+        FROM service s
+        LEFT JOIN kuma_group g ON g.target_id = s.id
+        WHERE s.id = ?2
+        "#,
+    )
+    .bind(&hc.application_id)
+    .bind(&hc.service_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Kuma group monitors, by application or service id
+pub async fn groups(pool: &SqlitePool) -> Result<HashMap<String, i32>> {
+    let groups = sqlx::query_as::<_, (String, i32)>("SELECT target_id, kuma_id FROM kuma_group")
+        .fetch_all(pool)
+        .await?;
+    Ok(groups.into_iter().collect())
+}
+
+/// Remember the Kuma group monitor of an application or service
+pub async fn set_group(pool: &SqlitePool, target: &KumaTarget, kuma_id: i32) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO kuma_group (target_id, target_type, kuma_id)
+        VALUES (?1, ?2, ?3)
+        ON CONFLICT (target_id) DO UPDATE SET kuma_id = excluded.kuma_id
+        "#,
+    )
+    .bind(&target.target_id)
+    .bind(&target.target_type)
+    .bind(kuma_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Seconds to wait after the given number of failed attempts
+pub(crate) fn retry_delay_secs(attempts: i32) -> i64 {
+    let doublings = attempts.clamp(1, 16) as u32 - 1;
+    (RETRY_BASE_SECS << doublings).min(RETRY_MAX_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_up_to_an_hour() {
+        let delays: Vec<_> = (1..=8).map(retry_delay_secs).collect();
+        assert_eq!(delays, [30, 60, 120, 240, 480, 960, 1920, 3600]);
+        assert_eq!(retry_delay_secs(1000), 3600);
+    }
+
+    #[tokio::test]
+    async fn change_during_push_stays_dirty_and_queued() {
+        let pool = crate::testing::pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO application (id, name) VALUES ('a', 'web');
+            INSERT INTO domain (id, fqdn, target_application_id) VALUES ('d', 'web.example.org', 'a');
+            INSERT INTO healthcheck (id, name, application_id, domain_id, kuma_id, kuma_dirty)
+            VALUES ('h', 'web', 'a', 'd', 7, 1);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let dirty = || {
+            sqlx::query_scalar::<_, bool>("SELECT kuma_dirty FROM healthcheck WHERE id = 'h'")
+                .fetch_one(&pool)
+        };
+
+        enqueue_dirty(&pool, false).await.unwrap();
+        let pushed = due(&pool).await.unwrap().remove(0);
+        // Edited while the worker pushes what it read
+        crate::service::healthcheck::update(
+            &pool,
+            None,
+            "h",
+            crate::models::UpdateHealthcheck {
+                name: Some("www".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        enqueue_dirty(&pool, false).await.unwrap();
+        crate::service::healthcheck::clear_kuma_dirty(&pool, "h", pushed.kuma_version)
+            .await
+            .unwrap();
+        done(&pool, &pushed).await.unwrap();
+        assert!(dirty().await.unwrap());
+
+        let queued = due(&pool).await.unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(
+            (queued[0].name.as_str(), queued[0].kuma_version),
+            ("www", pushed.kuma_version + 1)
+        );
+        crate::service::healthcheck::clear_kuma_dirty(&pool, "h", queued[0].kuma_version)
+            .await
+            .unwrap();
+        done(&pool, &queued[0]).await.unwrap();
+        assert!(!dirty().await.unwrap());
+        assert!(due(&pool).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn marking_for_sync_keeps_the_version() {
+        let pool = crate::testing::pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO application (id, name) VALUES ('a', 'web');
+            INSERT INTO domain (id, fqdn, target_application_id) VALUES ('d', 'web.example.org', 'a');
+            INSERT INTO healthcheck (id, name, application_id, domain_id, kuma_id)
+            VALUES ('h', 'web', 'a', 'd', 7);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let versions = || {
+            sqlx::query_as::<_, (i64, i64)>(
+                "SELECT version, kuma_version FROM healthcheck WHERE id = 'h'",
+            )
+            .fetch_one(&pool)
+        };
+        let (version, kuma_version) = versions().await.unwrap();
+
+        // The application was renamed, its group changes in Kuma
+        crate::service::healthcheck::mark_kuma_dirty_of(&pool, "a")
+            .await
+            .unwrap();
+        assert_eq!(versions().await.unwrap(), (version, kuma_version + 1));
+        enqueue_dirty(&pool, false).await.unwrap();
+        assert_eq!(due(&pool).await.unwrap()[0].kuma_version, kuma_version + 1);
+    }
+}
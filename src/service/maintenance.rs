@@ -0,0 +1,332 @@
+use serde_json::json;
+use sqlx::{SqlitePool, types::Json};
+
+use crate::models::{
+    AuditAction, CreateMaintenance, Maintenance, MaintenanceFilters, UpdateMaintenance, new_id,
+};
+use crate::service::{audit, impact, infra, kuma_sync, version};
+use crate::{Error, Result};
+
+const SELECT: &str = r#"
+    SELECT m.id, m.infra_id, i.name AS infra_name, m.title, m.description,
+           m.starts_at, m.ends_at, m.kuma_id, m.kuma_monitors, m.kuma_dirty,
+           m.attempts, m.last_error, m.next_attempt_at, m.deleted_at,
+           m.created_at, m.updated_at, m.version, m.created_by
+    FROM maintenance m
+    LEFT JOIN infra i ON i.id = m.infra_id
+"#;
+
+pub async fn list(pool: &SqlitePool, filters: &MaintenanceFilters) -> Result<Vec<Maintenance>> {
+    sqlx::query_as::<_, Maintenance>(&format!(
+        r#"{SELECT}
+        WHERE m.deleted_at IS NULL
+          AND (?1 IS NULL OR m.infra_id = ?1)
+          AND (NOT ?2 OR m.ends_at > datetime('now'))
+        ORDER BY m.starts_at DESC, m.title COLLATE NOCASE
+        "#
+    ))
+    .bind(&filters.infra_id)
+    .bind(filters.upcoming)
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<Maintenance> {
+    sqlx::query_as::<_, Maintenance>(&format!(
+        "{SELECT} WHERE m.id = ?1 AND m.deleted_at IS NULL"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound(format!("Maintenance with id '{}' not found", id)))
+}
+
+pub async fn create(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    input: CreateMaintenance,
+) -> Result<Maintenance> {
+    infra::get(pool, &input.infra_id).await?;
+    let (starts_at, ends_at) = period(pool, &input.starts_at, &input.ends_at).await?;
+    let id = new_id();
+
+    sqlx::query(
+        r#"
+        INSERT INTO maintenance (id, infra_id, title, description, starts_at, ends_at, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+        "#,
+    )
+    .bind(&id)
+    .bind(&input.infra_id)
+    .bind(&input.title)
+    .bind(&input.description)
+    .bind(&starts_at)
+    .bind(&ends_at)
+    .bind(actor)
+    .execute(pool)
+    .await?;
+
+    let maintenance = get(pool, &id).await?;
+    audit::record(
+        pool,
+        actor,
+        "maintenance",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&maintenance),
+    )
+    .await?;
+
+    Ok(maintenance)
+}
+
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdateMaintenance,
+) -> Result<Maintenance> {
+    let existing = get(pool, id).await?;
+    version::check("Maintenance", id, input.version, existing.version)?;
+    let previous = existing.clone();
+
+    let title = input.title.unwrap_or(existing.title);
+    let description = input.description.or(existing.description);
+    let (starts_at, ends_at) = period(
+        pool,
+        input.starts_at.as_deref().unwrap_or(&existing.starts_at),
+        input.ends_at.as_deref().unwrap_or(&existing.ends_at),
+    )
+    .await?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE maintenance
+        SET title = ?1, description = ?2, starts_at = ?3, ends_at = ?4, kuma_dirty = 1,
+            attempts = 0, last_error = NULL, next_attempt_at = datetime('now'),
+            updated_at = datetime('now'), version = version + 1
+        WHERE id = ?5 AND version = ?6
+        "#,
+    )
+    .bind(&title)
+    .bind(&description)
+    .bind(&starts_at)
+    .bind(&ends_at)
+    .bind(id)
+    .bind(existing.version)
+    .execute(pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Maintenance", id));
+    }
+
+    let maintenance = get(pool, id).await?;
+    audit::record(
+        pool,
+        actor,
+        "maintenance",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&maintenance),
+    )
+    .await?;
+
+    Ok(maintenance)
+}
+
+/// Deletes maintenance right away if it never made it to Kuma, otherwise
+/// flags it for the sync worker to delete it there first.
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Maintenance", id, expected_version, existing.version)?;
+
+    sqlx::query(
+        r#"
+        UPDATE maintenance
+        SET deleted_at = datetime('now'), kuma_dirty = 1, attempts = 0, last_error = NULL,
+            next_attempt_at = datetime('now'), updated_at = datetime('now')
+        WHERE id = ?1
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+    if existing.kuma_id.is_none() {
+        remove(pool, id).await?;
+    }
+
+    audit::record(
+        pool,
+        actor,
+        "maintenance",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Kuma monitors of the healthchecks of everything that depends on an infra
+/// item, sorted. Empty when the infra item is gone.
+pub async fn monitor_ids(pool: &SqlitePool, infra_id: &str) -> Result<Vec<i32>> {
+    let report = match impact::analyze(pool, "infra", infra_id).await {
+        Ok(report) => report,
+        Err(Error::NotFound(_)) => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let ids = json!(
+        report
+            .healthchecks
+            .iter()
+            .map(|h| &h.id)
+            .collect::<Vec<_>>()
+    );
+
+    sqlx::query_scalar::<_, i32>(
+        r#"
+        SELECT DISTINCT kuma_id FROM healthcheck
+        WHERE id IN (SELECT value FROM json_each(?1)) AND kuma_id IS NOT NULL
+        ORDER BY kuma_id
+        "#,
+    )
+    .bind(ids.to_string())
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Flags maintenance that hasn't ended yet and whose dependent monitors
+/// changed since the last sync, e.g. after adding a healthcheck or linking
+/// an application to the infra item.
+pub async fn mark_changed_dirty(pool: &SqlitePool) -> Result<u64> {
+    let current = sqlx::query_as::<_, (String, String, Json<Vec<i32>>)>(
+        r#"
+        SELECT id, infra_id, kuma_monitors FROM maintenance
+        WHERE deleted_at IS NULL AND kuma_dirty = 0 AND ends_at > datetime('now')
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut marked = 0;
+    for (id, infra_id, Json(synced)) in current {
+        if monitor_ids(pool, &infra_id).await? != synced {
+            sqlx::query("UPDATE maintenance SET kuma_dirty = 1 WHERE id = ?1")
+                .bind(&id)
+                .execute(pool)
+                .await?;
+            marked += 1;
+        }
+    }
+    Ok(marked)
+}
+
+/// Flagged maintenance, including deleted, whose (next) sync is due
+pub async fn due(pool: &SqlitePool) -> Result<Vec<Maintenance>> {
+    sqlx::query_as::<_, Maintenance>(&format!(
+        r#"{SELECT}
+        WHERE m.kuma_dirty = 1 AND m.next_attempt_at <= datetime('now')
+        ORDER BY m.created_at
+        "#
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Records a sync to Kuma, unless the maintenance was changed meanwhile
+pub async fn synced(
+    pool: &SqlitePool,
+    maintenance: &Maintenance,
+    kuma_id: i32,
+    monitors: &[i32],
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE maintenance
+        SET kuma_id = ?2, kuma_monitors = ?3, attempts = 0, last_error = NULL,
+            kuma_dirty = CASE WHEN updated_at = ?4 THEN 0 ELSE kuma_dirty END
+        WHERE id = ?1
+        "#,
+    )
+    .bind(&maintenance.id)
+    .bind(kuma_id)
+    .bind(json!(monitors).to_string())
+    .bind(&maintenance.updated_at)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Remember the Kuma maintenance window added for maintenance
+pub async fn set_kuma_id(pool: &SqlitePool, id: &str, kuma_id: i32) -> Result<()> {
+    sqlx::query("UPDATE maintenance SET kuma_id = ?2 WHERE id = ?1")
+        .bind(id)
+        .bind(kuma_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Record a failed sync and when to retry
+pub async fn failed(pool: &SqlitePool, maintenance: &Maintenance, error: &str) -> Result<()> {
+    let delay = format!(
+        "+{} seconds",
+        kuma_sync::retry_delay_secs(maintenance.attempts + 1)
+    );
+    sqlx::query(
+        r#"
+        UPDATE maintenance
+        SET attempts = attempts + 1, last_error = ?2, next_attempt_at = datetime('now', ?3)
+        WHERE id = ?1
+        "#,
+    )
+    .bind(&maintenance.id)
+    .bind(error)
+    .bind(delay)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Removes deleted maintenance for good, once it's gone from Kuma
+pub async fn remove(pool: &SqlitePool, id: &str) -> Result<()> {
+    sqlx::query("DELETE FROM maintenance WHERE id = ?1 AND deleted_at IS NOT NULL")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Normalizes a start and end to `YYYY-MM-DD HH:MM:SS`, the end after the
+/// start.
+async fn period(pool: &SqlitePool, starts_at: &str, ends_at: &str) -> Result<(String, String)> {
+    let (start, end) =
+        sqlx::query_as::<_, (Option<String>, Option<String>)>("SELECT datetime(?1), datetime(?2)")
+            .bind(starts_at)
+            .bind(ends_at)
+            .fetch_one(pool)
+            .await?;
+
+    let start = start
+        .ok_or_else(|| Error::ValidationError(format!("Invalid starts_at '{}'", starts_at)))?;
+    let end =
+        end.ok_or_else(|| Error::ValidationError(format!("Invalid ends_at '{}'", ends_at)))?;
+    if end <= start {
+        return Err(Error::ValidationError(
+            "ends_at must be after starts_at".into(),
+        ));
+    }
+    Ok((start, end))
+}
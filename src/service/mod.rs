@@ -1,12 +1,28 @@
 pub mod application;
+pub mod audit;
+pub mod auth;
+pub mod certificate;
+pub mod csv_import;
 pub mod dashboard;
 pub mod domain;
+pub mod domain_lookup;
+pub mod graph;
 pub mod healthcheck;
+pub mod heartbeat;
+pub mod impact;
+pub mod incident;
 pub mod infra;
+pub mod kuma_sync;
+pub mod maintenance;
 pub mod network_share;
 pub mod note;
+pub mod notification;
 pub mod person;
+pub mod revision;
 pub mod search;
 #[allow(clippy::module_inception)]
 pub mod service;
 pub mod stack;
+pub mod transfer;
+pub mod trash;
+pub mod version;
@@ -1,9 +1,10 @@
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 
 use crate::models::{
-    ApplicationNetworkShareRelation, CreateNetworkShare, NetworkShare, NetworkShareWithRelations,
-    PaginatedResponse, PaginationParams, UpdateNetworkShare, new_id,
+    ApplicationNetworkShareRelation, AuditAction, CreateNetworkShare, NetworkShare,
+    NetworkShareWithRelations, PaginatedResponse, PaginationParams, UpdateNetworkShare, new_id,
 };
+use crate::service::{audit, trash, version};
 use crate::{Error, Result};
 
 pub async fn list(
@@ -18,9 +19,10 @@ pub async fn list(
 
     let shares = sqlx::query_as::<_, NetworkShare>(
         r#"
-        SELECT id, name, path, share_type, server, purpose, status, notes, created_at, updated_at, created_by
+        SELECT id, name, path, share_type, server, purpose, status, notes, created_at, updated_at, version, created_by
         FROM network_share
-        WHERE (?1 IS NULL OR name LIKE ?1 OR path LIKE ?1 OR server LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR path LIKE ?1 OR server LIKE ?1)
           AND (?2 IS NULL OR status = ?2)
           AND (?3 IS NULL OR share_type = ?3)
         ORDER BY name COLLATE NOCASE ASC
@@ -39,7 +41,8 @@ pub async fn list(
         r#"
         SELECT COUNT(*)
         FROM network_share
-        WHERE (?1 IS NULL OR name LIKE ?1 OR path LIKE ?1 OR server LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR path LIKE ?1 OR server LIKE ?1)
           AND (?2 IS NULL OR status = ?2)
           AND (?3 IS NULL OR share_type = ?3)
         "#,
@@ -54,15 +57,20 @@ pub async fn list(
 }
 
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<NetworkShare> {
+    get_in(&mut *pool.acquire().await?, id).await
+}
+
+/// [`get`] on a connection, e.g. in a transaction
+pub async fn get_in(conn: &mut SqliteConnection, id: &str) -> Result<NetworkShare> {
     sqlx::query_as::<_, NetworkShare>(
         r#"
-        SELECT id, name, path, share_type, server, purpose, status, notes, created_at, updated_at, created_by
+        SELECT id, name, path, share_type, server, purpose, status, notes, created_at, updated_at, version, created_by
         FROM network_share
-        WHERE id = ?1
+        WHERE id = ?1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or_else(|| Error::NotFound(format!("Network share with id '{}' not found", id)))
 }
@@ -76,6 +84,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<NetworkSh
         FROM application a
         JOIN application_network_share ans ON a.id = ans.application_id
         WHERE ans.network_share_id = ?1
+          AND a.deleted_at IS NULL
         ORDER BY a.name COLLATE NOCASE
         "#,
     )
@@ -89,13 +98,17 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<NetworkSh
     })
 }
 
-pub async fn create(pool: &SqlitePool, input: CreateNetworkShare) -> Result<NetworkShare> {
+pub async fn create(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    input: CreateNetworkShare,
+) -> Result<NetworkShare> {
     let id = new_id();
 
     sqlx::query(
         r#"
-        INSERT INTO network_share (id, name, path, share_type, server, purpose, status, notes)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        INSERT INTO network_share (id, name, path, share_type, server, purpose, status, notes, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
         "#,
     )
     .bind(&id)
@@ -106,18 +119,34 @@ pub async fn create(pool: &SqlitePool, input: CreateNetworkShare) -> Result<Netw
     .bind(&input.purpose)
     .bind(&input.status)
     .bind(&input.notes)
+    .bind(actor)
     .execute(pool)
     .await?;
 
-    get(pool, &id).await
+    let network_share = get(pool, &id).await?;
+    audit::record(
+        pool,
+        actor,
+        "network_share",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&network_share),
+    )
+    .await?;
+
+    Ok(network_share)
 }
 
 pub async fn update(
     pool: &SqlitePool,
+    actor: Option<&str>,
     id: &str,
     input: UpdateNetworkShare,
 ) -> Result<NetworkShare> {
     let existing = get(pool, id).await?;
+    version::check("Network share", id, input.version, existing.version)?;
+    let previous = existing.clone();
 
     let name = input.name.unwrap_or(existing.name);
     let path = input.path.unwrap_or(existing.path);
@@ -127,11 +156,11 @@ pub async fn update(
     let status = input.status.unwrap_or(existing.status);
     let notes = input.notes.or(existing.notes);
 
-    sqlx::query(
+    let result = sqlx::query(
         r#"
         UPDATE network_share
-        SET name = ?1, path = ?2, share_type = ?3, server = ?4, purpose = ?5, status = ?6, notes = ?7, updated_at = datetime('now')
-        WHERE id = ?8
+        SET name = ?1, path = ?2, share_type = ?3, server = ?4, purpose = ?5, status = ?6, notes = ?7, updated_at = datetime('now'), version = version + 1
+        WHERE id = ?8 AND version = ?9
         "#,
     )
     .bind(&name)
@@ -142,17 +171,38 @@ pub async fn update(
     .bind(&status)
     .bind(&notes)
     .bind(id)
+    .bind(existing.version)
     .execute(pool)
     .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Network share", id));
+    }
 
-    get(pool, id).await
+    let network_share = get(pool, id).await?;
+    audit::record(
+        pool,
+        actor,
+        "network_share",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&network_share),
+    )
+    .await?;
+
+    Ok(network_share)
 }
 
-pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
-    let result = sqlx::query("DELETE FROM network_share WHERE id = ?1")
-        .bind(id)
-        .execute(pool)
-        .await?;
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Network share", id, expected_version, existing.version)?;
+
+    let result = trash::soft_delete(pool, actor, "network_share", id).await?;
 
     if result.rows_affected() == 0 {
         return Err(Error::NotFound(format!(
@@ -161,5 +211,16 @@ pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
         )));
     }
 
+    audit::record(
+        pool,
+        actor,
+        "network_share",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
     Ok(())
 }
@@ -1,6 +1,9 @@
 use sqlx::SqlitePool;
 
-use crate::models::{CreateNote, Note, PaginatedResponse, PaginationParams, UpdateNote, new_id};
+use crate::models::{
+    AuditAction, CreateNote, Note, PaginatedResponse, PaginationParams, UpdateNote, new_id,
+};
+use crate::service::{audit, version};
 use crate::{Error, Result};
 
 pub async fn list_for_entity(
@@ -14,7 +17,7 @@ pub async fn list_for_entity(
 
     let notes = sqlx::query_as::<_, Note>(
         r#"
-        SELECT id, entity_type, entity_id, title, content, note_type, url, is_pinned, created_at, updated_at, created_by
+        SELECT id, entity_type, entity_id, title, content, note_type, url, is_pinned, created_at, updated_at, version, created_by
         FROM note
         WHERE entity_type = ?1 AND entity_id = ?2
         ORDER BY is_pinned DESC, created_at DESC
@@ -41,7 +44,7 @@ pub async fn list_for_entity(
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<Note> {
     sqlx::query_as::<_, Note>(
         r#"
-        SELECT id, entity_type, entity_id, title, content, note_type, url, is_pinned, created_at, updated_at, created_by
+        SELECT id, entity_type, entity_id, title, content, note_type, url, is_pinned, created_at, updated_at, version, created_by
         FROM note
         WHERE id = ?1
         "#,
@@ -52,13 +55,13 @@ pub async fn get(pool: &SqlitePool, id: &str) -> Result<Note> {
     .ok_or_else(|| Error::NotFound(format!("Note with id '{}' not found", id)))
 }
 
-pub async fn create(pool: &SqlitePool, input: CreateNote) -> Result<Note> {
+pub async fn create(pool: &SqlitePool, actor: Option<&str>, input: CreateNote) -> Result<Note> {
     let id = new_id();
 
     sqlx::query(
         r#"
-        INSERT INTO note (id, entity_type, entity_id, title, content, note_type, url, is_pinned)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        INSERT INTO note (id, entity_type, entity_id, title, content, note_type, url, is_pinned, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
         "#,
     )
     .bind(&id)
@@ -69,14 +72,34 @@ pub async fn create(pool: &SqlitePool, input: CreateNote) -> Result<Note> {
     .bind(&input.note_type)
     .bind(&input.url)
     .bind(input.is_pinned)
+    .bind(actor)
     .execute(pool)
     .await?;
 
-    get(pool, &id).await
+    let note = get(pool, &id).await?;
+    audit::record(
+        pool,
+        actor,
+        "note",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&note),
+    )
+    .await?;
+
+    Ok(note)
 }
 
-pub async fn update(pool: &SqlitePool, id: &str, input: UpdateNote) -> Result<Note> {
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdateNote,
+) -> Result<Note> {
     let existing = get(pool, id).await?;
+    version::check("Note", id, input.version, existing.version)?;
+    let previous = existing.clone();
 
     let title = input.title.unwrap_or(existing.title);
     let content = input.content.or(existing.content);
@@ -84,11 +107,11 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdateNote) -> Result<No
     let url = input.url.or(existing.url);
     let is_pinned = input.is_pinned.unwrap_or(existing.is_pinned);
 
-    sqlx::query(
+    let result = sqlx::query(
         r#"
         UPDATE note
-        SET title = ?1, content = ?2, note_type = ?3, url = ?4, is_pinned = ?5, updated_at = datetime('now')
-        WHERE id = ?6
+        SET title = ?1, content = ?2, note_type = ?3, url = ?4, is_pinned = ?5, updated_at = datetime('now'), version = version + 1
+        WHERE id = ?6 AND version = ?7
         "#,
     )
     .bind(&title)
@@ -97,13 +120,37 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdateNote) -> Result<No
     .bind(&url)
     .bind(is_pinned)
     .bind(id)
+    .bind(existing.version)
     .execute(pool)
     .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Note", id));
+    }
+
+    let note = get(pool, id).await?;
+    audit::record(
+        pool,
+        actor,
+        "note",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&note),
+    )
+    .await?;
 
-    get(pool, id).await
+    Ok(note)
 }
 
-pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Note", id, expected_version, existing.version)?;
+
     let result = sqlx::query("DELETE FROM note WHERE id = ?1")
         .bind(id)
         .execute(pool)
@@ -113,5 +160,16 @@ pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
         return Err(Error::NotFound(format!("Note with id '{}' not found", id)));
     }
 
+    audit::record(
+        pool,
+        actor,
+        "note",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
     Ok(())
 }
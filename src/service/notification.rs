@@ -0,0 +1,591 @@
+use chrono::{NaiveDateTime, Utc};
+use serde_json::json;
+use sqlx::SqlitePool;
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::models::{
+    AuditAction, CreateNotificationChannel, Incident, NOTIFICATION_EVENTS, NOTIFICATION_KINDS,
+    Notification, NotificationChannel, NotificationFilters, PaginatedResponse, PaginationParams,
+    UpdateNotificationChannel, new_id,
+};
+use crate::notify::{self, Message};
+use crate::service::impact::NOTIFY_CONTRIBUTION_TYPES;
+use crate::service::{audit, version};
+use crate::{Config, Error, Result};
+
+/// Days before a domain expires to send a reminder.
+const EXPIRY_REMINDER_DAYS: &[i64] = &[30, 14, 7, 1];
+
+/// Transitions older than this are not notified about, e.g. those in the
+/// heartbeat history Kuma sends after a reconnect.
+const MAX_INCIDENT_AGE_SECS: i64 = 3600;
+
+pub async fn list(pool: &SqlitePool) -> Result<Vec<NotificationChannel>> {
+    sqlx::query_as::<_, NotificationChannel>(
+        r#"
+        SELECT id, name, kind, url, email, events, is_enabled, created_at, updated_at, version, created_by
+        FROM notification_channel
+        ORDER BY name COLLATE NOCASE
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(Into::into)
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<NotificationChannel> {
+    sqlx::query_as::<_, NotificationChannel>(
+        r#"
+        SELECT id, name, kind, url, email, events, is_enabled, created_at, updated_at, version, created_by
+        FROM notification_channel
+        WHERE id = ?1
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound(format!("Notification channel with id '{id}' not found")))
+}
+
+pub async fn create(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    input: CreateNotificationChannel,
+) -> Result<NotificationChannel> {
+    validate(&input.kind, input.url.as_deref(), &input.events)?;
+    let id = new_id();
+
+    sqlx::query(
+        r#"
+        INSERT INTO notification_channel (id, name, kind, url, email, events, is_enabled, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        "#,
+    )
+    .bind(&id)
+    .bind(&input.name)
+    .bind(&input.kind)
+    .bind(&input.url)
+    .bind(&input.email)
+    .bind(json!(input.events).to_string())
+    .bind(input.is_enabled)
+    .bind(actor)
+    .execute(pool)
+    .await?;
+
+    let channel = get(pool, &id).await?;
+    audit::record(
+        pool,
+        actor,
+        "notification_channel",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&audited(&channel)),
+    )
+    .await?;
+
+    Ok(channel)
+}
+
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdateNotificationChannel,
+) -> Result<NotificationChannel> {
+    let existing = get(pool, id).await?;
+    version::check("Notification channel", id, input.version, existing.version)?;
+    let previous = existing.clone();
+
+    let name = input.name.unwrap_or(existing.name);
+    let kind = input.kind.unwrap_or(existing.kind);
+    let url = input.url.or(existing.url);
+    let email = input.email.or(existing.email);
+    let events = input.events.unwrap_or(existing.events.0);
+    let is_enabled = input.is_enabled.unwrap_or(existing.is_enabled);
+    validate(&kind, url.as_deref(), &events)?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE notification_channel
+        SET name = ?1, kind = ?2, url = ?3, email = ?4, events = ?5, is_enabled = ?6, updated_at = datetime('now'), version = version + 1
+        WHERE id = ?7 AND version = ?8
+        "#,
+    )
+    .bind(&name)
+    .bind(&kind)
+    .bind(&url)
+    .bind(&email)
+    .bind(json!(events).to_string())
+    .bind(is_enabled)
+    .bind(id)
+    .bind(existing.version)
+    .execute(pool)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Notification channel", id));
+    }
+
+    let channel = get(pool, id).await?;
+    audit::record(
+        pool,
+        actor,
+        "notification_channel",
+        id,
+        AuditAction::Update,
+        Some(&audited(&previous)),
+        Some(&audited(&channel)),
+    )
+    .await?;
+
+    Ok(channel)
+}
+
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check(
+        "Notification channel",
+        id,
+        expected_version,
+        existing.version,
+    )?;
+
+    sqlx::query("DELETE FROM notification_channel WHERE id = ?1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+
+    audit::record(
+        pool,
+        actor,
+        "notification_channel",
+        id,
+        AuditAction::Delete,
+        Some(&audited(&existing)),
+        None,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// A channel as recorded in the audit log, which viewers can read. Webhook
+/// URLs hold their secret in the path, so only the scheme and host are kept.
+fn audited(channel: &NotificationChannel) -> NotificationChannel {
+    let mut channel = channel.clone();
+    channel.url = channel.url.map(|url| match Url::parse(&url) {
+        Ok(url) => format!("{}/[redacted]", url.origin().ascii_serialization()),
+        Err(_) => "[redacted]".to_string(),
+    });
+    channel
+}
+
+fn validate(kind: &str, url: Option<&str>, events: &[String]) -> Result<()> {
+    if !NOTIFICATION_KINDS.contains(&kind) {
+        return Err(Error::ValidationError(format!(
+            "Kind should be one of {}",
+            NOTIFICATION_KINDS.join(", ")
+        )));
+    }
+    if let Some(event) = events
+        .iter()
+        .find(|e| !NOTIFICATION_EVENTS.contains(&e.as_str()))
+    {
+        return Err(Error::ValidationError(format!(
+            "Unknown event '{event}', should be one of {}",
+            NOTIFICATION_EVENTS.join(", ")
+        )));
+    }
+    if kind != "email" {
+        let url =
+            url.ok_or_else(|| Error::ValidationError(format!("A {kind} channel needs a URL")))?;
+        Url::parse(url)
+            .ok()
+            .filter(|u| matches!(u.scheme(), "http" | "https"))
+            .ok_or_else(|| Error::ValidationError(format!("Invalid URL '{url}'")))?;
+    }
+    Ok(())
+}
+
+/// Sent notifications, newest first.
+pub async fn list_sent(
+    pool: &SqlitePool,
+    filters: &NotificationFilters,
+) -> Result<PaginatedResponse<Notification>> {
+    let params = PaginationParams {
+        page: filters.page,
+        per_page: filters.per_page,
+        search: None,
+    };
+
+    let notifications = sqlx::query_as::<_, Notification>(
+        r#"
+        SELECT n.id, n.channel_id, c.name AS channel_name, n.event, n.subject_key, n.title,
+               n.recipients, n.error, n.created_at
+        FROM notification n
+        JOIN notification_channel c ON c.id = n.channel_id
+        WHERE (?1 IS NULL OR n.channel_id = ?1)
+          AND (?2 IS NULL OR n.event = ?2)
+        ORDER BY n.id DESC
+        LIMIT ?3 OFFSET ?4
+        "#,
+    )
+    .bind(&filters.channel_id)
+    .bind(&filters.event)
+    .bind(params.limit() as i32)
+    .bind(params.offset() as i32)
+    .fetch_all(pool)
+    .await?;
+
+    let (total,) = sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT COUNT(*)
+        FROM notification
+        WHERE (?1 IS NULL OR channel_id = ?1)
+          AND (?2 IS NULL OR event = ?2)
+        "#,
+    )
+    .bind(&filters.channel_id)
+    .bind(&filters.event)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(PaginatedResponse::new(notifications, total, &params))
+}
+
+/// Send a test message through a channel. Only the channel's own address
+/// gets a test email.
+pub async fn test(pool: &SqlitePool, config: &Config, id: &str) -> Result<Notification> {
+    let channel = get(pool, id).await?;
+    let message = Message {
+        event: "test".to_string(),
+        subject_key: new_id(),
+        title: format!("Test notification for {}", channel.name),
+        text: "Notifications of this channel arrive here.".to_string(),
+        url: Some(config.base_url.clone()),
+        data: json!({}),
+    };
+
+    let id = deliver(pool, config, &channel, &message, Vec::new())
+        .await?
+        .ok_or_else(|| Error::InternalError("Test notification was not recorded".to_string()))?;
+    get_sent(pool, id).await
+}
+
+async fn get_sent(pool: &SqlitePool, id: i64) -> Result<Notification> {
+    sqlx::query_as::<_, Notification>(
+        r#"
+        SELECT n.id, n.channel_id, c.name AS channel_name, n.event, n.subject_key, n.title,
+               n.recipients, n.error, n.created_at
+        FROM notification n
+        JOIN notification_channel c ON c.id = n.channel_id
+        WHERE n.id = ?1
+        "#,
+    )
+    .bind(id)
+    .fetch_one(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Notify the channels subscribed to `down` or `up` about an incident that
+/// was just opened or resolved.
+pub async fn notify_incident(
+    pool: &SqlitePool,
+    config: &Config,
+    incident: &Incident,
+) -> Result<()> {
+    let (event, time) = match &incident.resolved_at {
+        Some(resolved_at) => ("up", resolved_at),
+        None => ("down", &incident.started_at),
+    };
+    if !is_recent(time, Utc::now().naive_utc()) {
+        debug!("Not notifying about incident {} at {time}", incident.id);
+        return Ok(());
+    }
+
+    let owner = incident
+        .application_name
+        .as_ref()
+        .or(incident.service_name.as_ref())
+        .map(|name| format!("{name}: "))
+        .unwrap_or_default();
+    let title = match event {
+        "up" => format!(
+            "{owner}{} is up again after {}",
+            incident.healthcheck_name,
+            duration(incident.duration_secs)
+        ),
+        _ => format!("{owner}{} is down", incident.healthcheck_name),
+    };
+    let mut text = format!("Down since {} UTC", incident.started_at);
+    if let Some(resolved_at) = &incident.resolved_at {
+        text.push_str(&format!(", up again at {resolved_at} UTC"));
+    }
+    if let Some(msg) = &incident.msg {
+        text.push_str(&format!("\nError: {msg}"));
+    }
+    if !incident.domains.is_empty() {
+        text.push_str(&format!("\nDomains: {}", incident.domains.join(", ")));
+    }
+
+    let application_ids = match (&incident.application_id, &incident.service_id) {
+        (Some(application_id), _) => vec![application_id.clone()],
+        (None, Some(service_id)) => {
+            sqlx::query_scalar::<_, String>(
+                "SELECT application_id FROM application_service WHERE service_id = ?1",
+            )
+            .bind(service_id)
+            .fetch_all(pool)
+            .await?
+        }
+        (None, None) => Vec::new(),
+    };
+
+    let message = Message {
+        event: event.to_string(),
+        subject_key: incident.id.clone(),
+        title,
+        text,
+        url: Some(format!(
+            "{}/healthchecks/{}",
+            config.base_url.trim_end_matches('/'),
+            incident.healthcheck_id
+        )),
+        data: json!(incident),
+    };
+    dispatch(pool, config, &message, &application_ids).await
+}
+
+/// Remind the channels subscribed to `domain_expiring` of domains that
+/// expire within 30, 14, 7 or 1 days. Each reminder is sent once.
+pub async fn scan_domain_expiry(pool: &SqlitePool, config: &Config) -> Result<()> {
+    let max_days = EXPIRY_REMINDER_DAYS
+        .iter()
+        .max()
+        .copied()
+        .unwrap_or_default();
+    let domains = sqlx::query_as::<_, (String, String, String, i64)>(
+        r#"
+        SELECT id, fqdn, expires_at,
+               CAST(julianday(date(expires_at)) - julianday(date('now')) AS INTEGER) AS days_left
+        FROM domain
+        WHERE deleted_at IS NULL
+          AND expires_at IS NOT NULL
+          AND date(expires_at) >= date('now')
+          AND date(expires_at) <= date('now', ?1)
+        ORDER BY expires_at
+        "#,
+    )
+    .bind(format!("+{max_days} days"))
+    .fetch_all(pool)
+    .await?;
+
+    for (id, fqdn, expires_at, days_left) in domains {
+        let Some(reminder) = reminder(days_left) else {
+            continue;
+        };
+
+        // Applications the domain belongs to or points at, directly or
+        // through a service
+        let application_ids = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT application_id FROM application_domain WHERE domain_id = ?1
+            UNION SELECT target_application_id FROM domain WHERE id = ?1 AND target_application_id IS NOT NULL
+            UNION SELECT s.application_id FROM application_service s
+                  JOIN domain d ON d.target_service_id = s.service_id
+                  WHERE d.id = ?1
+            "#,
+        )
+        .bind(&id)
+        .fetch_all(pool)
+        .await?;
+
+        let message = Message {
+            event: "domain_expiring".to_string(),
+            subject_key: format!("{id}:{expires_at}:{reminder}"),
+            title: match days_left {
+                0 => format!("{fqdn} expires today"),
+                1 => format!("{fqdn} expires tomorrow"),
+                days => format!("{fqdn} expires in {days} days"),
+            },
+            text: format!("The registration of {fqdn} expires on {expires_at}."),
+            url: Some(format!(
+                "{}/domains/{id}",
+                config.base_url.trim_end_matches('/')
+            )),
+            data: json!({
+                "id": id,
+                "fqdn": fqdn,
+                "expires_at": expires_at,
+                "days_left": days_left,
+            }),
+        };
+        dispatch(pool, config, &message, &application_ids).await?;
+    }
+
+    Ok(())
+}
+
+/// Send a message to every enabled channel subscribed to its event, with
+/// the owners and maintainers of the given applications as recipients.
+async fn dispatch(
+    pool: &SqlitePool,
+    config: &Config,
+    message: &Message,
+    application_ids: &[String],
+) -> Result<()> {
+    let channels = sqlx::query_as::<_, NotificationChannel>(
+        r#"
+        SELECT id, name, kind, url, email, events, is_enabled, created_at, updated_at, version, created_by
+        FROM notification_channel
+        WHERE is_enabled = 1
+          AND ?1 IN (SELECT value FROM json_each(events))
+        "#,
+    )
+    .bind(&message.event)
+    .fetch_all(pool)
+    .await?;
+    if channels.is_empty() {
+        return Ok(());
+    }
+
+    let people = sqlx::query_scalar::<_, String>(
+        r#"
+        SELECT DISTINCT p.email
+        FROM application_person ap
+        JOIN person p ON p.id = ap.person_id
+        WHERE ap.application_id IN (SELECT value FROM json_each(?1))
+          AND ap.contribution_type IN (SELECT value FROM json_each(?2))
+          AND p.deleted_at IS NULL
+          AND p.email IS NOT NULL
+        ORDER BY p.email
+        "#,
+    )
+    .bind(json!(application_ids).to_string())
+    .bind(json!(NOTIFY_CONTRIBUTION_TYPES).to_string())
+    .fetch_all(pool)
+    .await?;
+
+    for channel in &channels {
+        deliver(pool, config, channel, message, people.clone()).await?;
+    }
+    Ok(())
+}
+
+/// Send a message through one channel and record it. Returns the ID of the
+/// recorded notification, or `None` if the channel already got it.
+async fn deliver(
+    pool: &SqlitePool,
+    config: &Config,
+    channel: &NotificationChannel,
+    message: &Message,
+    mut recipients: Vec<String>,
+) -> Result<Option<i64>> {
+    if let Some(email) = &channel.email
+        && !recipients.contains(email)
+    {
+        recipients.push(email.clone());
+    }
+
+    // Claim the notification first, so it is sent once even if a scan or
+    // heartbeat handler runs twice
+    let id = sqlx::query_scalar::<_, i64>(
+        r#"
+        INSERT INTO notification (channel_id, event, subject_key, title, recipients)
+        VALUES (?1, ?2, ?3, ?4, ?5)
+        ON CONFLICT (channel_id, event, subject_key) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(&channel.id)
+    .bind(&message.event)
+    .bind(&message.subject_key)
+    .bind(&message.title)
+    .bind(json!(recipients).to_string())
+    .fetch_optional(pool)
+    .await?;
+    let Some(id) = id else {
+        return Ok(None);
+    };
+
+    match notify::send(
+        config,
+        &channel.kind,
+        channel.url.as_deref(),
+        &recipients,
+        message,
+    )
+    .await
+    {
+        Ok(()) => info!("Sent '{}' to {}", message.title, channel.name),
+        Err(e) => {
+            warn!(
+                "Failed to send '{}' to {}: {e}",
+                message.title, channel.name
+            );
+            sqlx::query("UPDATE notification SET error = ?2 WHERE id = ?1")
+                .bind(id)
+                .bind(e.to_string())
+                .execute(pool)
+                .await?;
+        }
+    }
+
+    Ok(Some(id))
+}
+
+/// The reminder a domain that expires in `days_left` days is due for: the
+/// smallest of `EXPIRY_REMINDER_DAYS` that isn't less than it.
+fn reminder(days_left: i64) -> Option<i64> {
+    EXPIRY_REMINDER_DAYS
+        .iter()
+        .filter(|&&days| days >= days_left)
+        .min()
+        .copied()
+}
+
+fn is_recent(time: &str, now: NaiveDateTime) -> bool {
+    NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+        .is_ok_and(|time| (now - time).num_seconds() <= MAX_INCIDENT_AGE_SECS)
+}
+
+/// A duration in seconds as e.g. `3m` or `2h 5m`.
+fn duration(secs: i64) -> String {
+    let (hours, minutes) = (secs / 3600, secs % 3600 / 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{secs}s"),
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reminders_are_due_at_thresholds() {
+        assert_eq!(reminder(30), Some(30));
+        assert_eq!(reminder(20), Some(30));
+        assert_eq!(reminder(14), Some(14));
+        assert_eq!(reminder(3), Some(7));
+        assert_eq!(reminder(0), Some(1));
+        assert_eq!(reminder(31), None);
+    }
+
+    #[test]
+    fn durations_are_short() {
+        assert_eq!(duration(42), "42s");
+        assert_eq!(duration(180), "3m");
+        assert_eq!(duration(7200), "2h");
+        assert_eq!(duration(7500), "2h 5m");
+    }
+}
@@ -1,9 +1,10 @@
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 
 use crate::models::{
-    ApplicationPersonRelation, CreatePerson, PaginatedResponse, PaginationParams, Person,
-    PersonWithRelations, UpdatePerson, new_id,
+    ApplicationPersonRelation, AuditAction, CreatePerson, PaginatedResponse, PaginationParams,
+    Person, PersonWithRelations, UpdatePerson, new_id,
 };
+use crate::service::{audit, trash, version};
 use crate::{Error, Result};
 
 pub async fn list(
@@ -17,9 +18,10 @@ pub async fn list(
 
     let people = sqlx::query_as::<_, Person>(
         r#"
-        SELECT id, name, email, role, department, phone, is_active, notes, created_at, updated_at, created_by
+        SELECT id, name, email, role, department, phone, is_active, notes, access_role, created_at, updated_at, version, created_by
         FROM person
-        WHERE (?1 IS NULL OR name LIKE ?1 OR email LIKE ?1 OR role LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR email LIKE ?1 OR role LIKE ?1)
           AND (?2 IS NULL OR is_active = ?2)
         ORDER BY name COLLATE NOCASE ASC
         LIMIT ?3 OFFSET ?4
@@ -36,7 +38,8 @@ pub async fn list(
         r#"
         SELECT COUNT(*)
         FROM person
-        WHERE (?1 IS NULL OR name LIKE ?1 OR email LIKE ?1 OR role LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR email LIKE ?1 OR role LIKE ?1)
           AND (?2 IS NULL OR is_active = ?2)
         "#,
     )
@@ -49,19 +52,92 @@ pub async fn list(
 }
 
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<Person> {
+    get_in(&mut *pool.acquire().await?, id).await
+}
+
+/// [`get`] on a connection, e.g. in a transaction
+pub async fn get_in(conn: &mut SqliteConnection, id: &str) -> Result<Person> {
     sqlx::query_as::<_, Person>(
         r#"
-        SELECT id, name, email, role, department, phone, is_active, notes, created_at, updated_at, created_by
+        SELECT id, name, email, role, department, phone, is_active, notes, access_role, created_at, updated_at, version, created_by
         FROM person
-        WHERE id = ?1
+        WHERE id = ?1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or_else(|| Error::NotFound(format!("Person with id '{}' not found", id)))
 }
 
+/// Find a person by email address, ignoring case.
+pub async fn find_by_email(pool: &SqlitePool, email: &str) -> Result<Option<Person>> {
+    let person = sqlx::query_as::<_, Person>(
+        r#"
+        SELECT id, name, email, role, department, phone, is_active, notes, access_role, created_at, updated_at, version, created_by
+        FROM person
+        WHERE email = ?1 COLLATE NOCASE AND deleted_at IS NULL
+        "#,
+    )
+    .bind(email)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(person)
+}
+
+/// Find the person who logs in with this OIDC subject identifier.
+pub async fn find_by_oidc_sub(pool: &SqlitePool, sub: &str) -> Result<Option<Person>> {
+    let person = sqlx::query_as::<_, Person>(
+        r#"
+        SELECT id, name, email, role, department, phone, is_active, notes, access_role, created_at, updated_at, version, created_by
+        FROM person
+        WHERE oidc_sub = ?1 AND deleted_at IS NULL
+        "#,
+    )
+    .bind(sub)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(person)
+}
+
+/// Link an OIDC subject identifier to the person with this email address,
+/// unless that person is already linked to one.
+pub async fn link_oidc_sub(pool: &SqlitePool, email: &str, sub: &str) -> Result<Option<Person>> {
+    let id = sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE person
+        SET oidc_sub = ?2
+        WHERE email = ?1 COLLATE NOCASE AND deleted_at IS NULL AND oidc_sub IS NULL
+        RETURNING id
+        "#,
+    )
+    .bind(email)
+    .bind(sub)
+    .fetch_optional(pool)
+    .await?;
+
+    match id {
+        Some(id) => Ok(Some(get(pool, &id).await?)),
+        None => Ok(None),
+    }
+}
+
+/// Whether a deleted person still holds this OIDC subject identifier or
+/// email address.
+pub async fn is_login_in_trash(pool: &SqlitePool, sub: &str, email: &str) -> Result<bool> {
+    let (exists,) = sqlx::query_as::<_, (bool,)>(
+        "SELECT EXISTS(SELECT 1 FROM person WHERE (oidc_sub = ?1 OR email = ?2 COLLATE NOCASE) AND deleted_at IS NOT NULL)",
+    )
+    .bind(sub)
+    .bind(email)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(exists)
+}
+
 pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<PersonWithRelations> {
     let person = get(pool, id).await?;
 
@@ -71,6 +147,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<PersonWit
         FROM application a
         JOIN application_person ap ON a.id = ap.application_id
         WHERE ap.person_id = ?1
+          AND a.deleted_at IS NULL
         ORDER BY a.name COLLATE NOCASE
         "#,
     )
@@ -84,13 +161,22 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<PersonWit
     })
 }
 
-pub async fn create(pool: &SqlitePool, input: CreatePerson) -> Result<Person> {
+pub async fn create(pool: &SqlitePool, actor: Option<&str>, input: CreatePerson) -> Result<Person> {
+    create_in(&mut *pool.acquire().await?, actor, input).await
+}
+
+/// [`create`] on a connection, e.g. in a transaction
+pub async fn create_in(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    input: CreatePerson,
+) -> Result<Person> {
     let id = new_id();
 
     sqlx::query(
         r#"
-        INSERT INTO person (id, name, email, role, department, phone, is_active, notes)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        INSERT INTO person (id, name, email, role, department, phone, is_active, notes, access_role, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
         "#,
     )
     .bind(&id)
@@ -101,14 +187,35 @@ pub async fn create(pool: &SqlitePool, input: CreatePerson) -> Result<Person> {
     .bind(&input.phone)
     .bind(input.is_active)
     .bind(&input.notes)
-    .execute(pool)
+    .bind(input.access_role.unwrap_or_default())
+    .bind(actor)
+    .execute(&mut *conn)
+    .await?;
+
+    let person = get_in(&mut *conn, &id).await?;
+    audit::record_in(
+        &mut *conn,
+        actor,
+        "person",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&person),
+    )
     .await?;
 
-    get(pool, &id).await
+    Ok(person)
 }
 
-pub async fn update(pool: &SqlitePool, id: &str, input: UpdatePerson) -> Result<Person> {
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdatePerson,
+) -> Result<Person> {
     let existing = get(pool, id).await?;
+    version::check("Person", id, input.version, existing.version)?;
+    let previous = existing.clone();
 
     let name = input.name.unwrap_or(existing.name);
     let email = input.email.or(existing.email);
@@ -117,12 +224,13 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdatePerson) -> Result<
     let phone = input.phone.or(existing.phone);
     let is_active = input.is_active.unwrap_or(existing.is_active);
     let notes = input.notes.or(existing.notes);
+    let access_role = input.access_role.unwrap_or(existing.access_role);
 
-    sqlx::query(
+    let result = sqlx::query(
         r#"
         UPDATE person
-        SET name = ?1, email = ?2, role = ?3, department = ?4, phone = ?5, is_active = ?6, notes = ?7, updated_at = datetime('now')
-        WHERE id = ?8
+        SET name = ?1, email = ?2, role = ?3, department = ?4, phone = ?5, is_active = ?6, notes = ?7, access_role = ?8, updated_at = datetime('now'), version = version + 1
+        WHERE id = ?9 AND version = ?10
         "#,
     )
     .bind(&name)
@@ -132,18 +240,40 @@ pub async fn update(pool: &SqlitePool, id: &str, input: UpdatePerson) -> Result<
     .bind(&phone)
     .bind(is_active)
     .bind(&notes)
+    .bind(access_role)
     .bind(id)
+    .bind(existing.version)
     .execute(pool)
     .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Person", id));
+    }
+
+    let person = get(pool, id).await?;
+    audit::record(
+        pool,
+        actor,
+        "person",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&person),
+    )
+    .await?;
 
-    get(pool, id).await
+    Ok(person)
 }
 
-pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
-    let result = sqlx::query("DELETE FROM person WHERE id = ?1")
-        .bind(id)
-        .execute(pool)
-        .await?;
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Person", id, expected_version, existing.version)?;
+
+    let result = trash::soft_delete(pool, actor, "person", id).await?;
 
     if result.rows_affected() == 0 {
         return Err(Error::NotFound(format!(
@@ -152,5 +282,16 @@ pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
         )));
     }
 
+    audit::record(
+        pool,
+        actor,
+        "person",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
     Ok(())
 }
@@ -0,0 +1,338 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::models::{FieldChange, PaginatedResponse, PaginationParams, Revision, RevisionDiff};
+use crate::{Error, Result};
+
+/// Fields that change on every write and would only add noise to a diff.
+const IGNORED_FIELDS: &[&str] = &["updated_at", "version"];
+
+/// Store a full snapshot of an entity after a create or update.
+///
+/// Entities that existed before revisions were introduced get their
+/// previous state stored first, so their first update can be rolled back.
+pub async fn record<T: Serialize>(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    entity_type: &str,
+    entity_id: &str,
+    before: Option<&T>,
+    after: &T,
+) -> Result<()> {
+    record_in(
+        &mut *pool.acquire().await?,
+        actor,
+        entity_type,
+        entity_id,
+        before,
+        after,
+    )
+    .await
+}
+
+/// [`record`] on a connection, e.g. in a transaction. Call it in the
+/// transaction of the write, so concurrent writes can't take the same
+/// revision number.
+pub async fn record_in<T: Serialize>(
+    conn: &mut SqliteConnection,
+    actor: Option<&str>,
+    entity_type: &str,
+    entity_id: &str,
+    before: Option<&T>,
+    after: &T,
+) -> Result<()> {
+    let latest = latest(&mut *conn, entity_type, entity_id).await?;
+
+    let mut revision = latest.unwrap_or(0) + 1;
+    if let (None, Some(before)) = (latest, before) {
+        let before = to_value(before)?;
+        let at = before.get("updated_at").and_then(Value::as_str);
+        insert(
+            &mut *conn,
+            (entity_type, entity_id),
+            revision,
+            None,
+            &before,
+            at,
+        )
+        .await?;
+        revision += 1;
+    }
+
+    let after = to_value(after)?;
+    insert(
+        &mut *conn,
+        (entity_type, entity_id),
+        revision,
+        actor,
+        &after,
+        None,
+    )
+    .await
+}
+
+async fn latest(
+    conn: &mut SqliteConnection,
+    entity_type: &str,
+    entity_id: &str,
+) -> Result<Option<i64>> {
+    Ok(sqlx::query_scalar::<_, Option<i64>>(
+        "SELECT MAX(revision) FROM revision WHERE entity_type = ?1 AND entity_id = ?2",
+    )
+    .bind(entity_type)
+    .bind(entity_id)
+    .fetch_one(conn)
+    .await?)
+}
+
+async fn insert(
+    conn: &mut SqliteConnection,
+    (entity_type, entity_id): (&str, &str),
+    revision: i64,
+    actor: Option<&str>,
+    snapshot: &Value,
+    created_at: Option<&str>,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO revision (entity_type, entity_id, revision, actor, snapshot, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, COALESCE(?6, datetime('now')))
+        "#,
+    )
+    .bind(entity_type)
+    .bind(entity_id)
+    .bind(revision)
+    .bind(actor)
+    .bind(snapshot.to_string())
+    .bind(created_at)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// All revisions of an entity, newest first.
+pub async fn list(
+    pool: &SqlitePool,
+    entity_type: &str,
+    entity_id: &str,
+    params: &PaginationParams,
+) -> Result<PaginatedResponse<Revision>> {
+    let limit = params.limit() as i32;
+    let offset = params.offset() as i32;
+
+    let revisions = sqlx::query_as::<_, Revision>(
+        r#"
+        SELECT entity_type, entity_id, revision, actor, snapshot, created_at
+        FROM revision
+        WHERE entity_type = ?1 AND entity_id = ?2
+        ORDER BY revision DESC
+        LIMIT ?3 OFFSET ?4
+        "#,
+    )
+    .bind(entity_type)
+    .bind(entity_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let (total,) = sqlx::query_as::<_, (i64,)>(
+        "SELECT COUNT(*) FROM revision WHERE entity_type = ?1 AND entity_id = ?2",
+    )
+    .bind(entity_type)
+    .bind(entity_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(PaginatedResponse::new(revisions, total, params))
+}
+
+pub async fn get(
+    pool: &SqlitePool,
+    entity_type: &str,
+    entity_id: &str,
+    revision: i64,
+) -> Result<Revision> {
+    sqlx::query_as::<_, Revision>(
+        r#"
+        SELECT entity_type, entity_id, revision, actor, snapshot, created_at
+        FROM revision
+        WHERE entity_type = ?1 AND entity_id = ?2 AND revision = ?3
+        "#,
+    )
+    .bind(entity_type)
+    .bind(entity_id)
+    .bind(revision)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| {
+        Error::NotFound(format!(
+            "Revision {revision} of {entity_type} '{entity_id}' not found"
+        ))
+    })
+}
+
+/// The changes made in a revision, compared to the one before it or to
+/// `against`.
+pub async fn diff(
+    pool: &SqlitePool,
+    entity_type: &str,
+    entity_id: &str,
+    revision: i64,
+    against: Option<i64>,
+) -> Result<RevisionDiff> {
+    let to = get(pool, entity_type, entity_id, revision).await?;
+    let from = match against.or((revision > 1).then_some(revision - 1)) {
+        Some(against) => Some(get(pool, entity_type, entity_id, against).await?),
+        None => None,
+    };
+
+    let empty = Value::Object(Default::default());
+    let before = from.as_ref().map_or(&empty, |from| &from.snapshot.0);
+
+    Ok(RevisionDiff {
+        entity_type: entity_type.to_string(),
+        entity_id: entity_id.to_string(),
+        from: from.as_ref().map(|from| from.revision),
+        to: revision,
+        changes: changes(before, &to.snapshot.0),
+    })
+}
+
+/// A revision's snapshot as an `Update*` DTO, to roll back to it. Fields
+/// the entity didn't have back then are `None`.
+pub async fn snapshot<T: DeserializeOwned>(
+    pool: &SqlitePool,
+    entity_type: &str,
+    entity_id: &str,
+    revision: i64,
+) -> Result<T> {
+    let revision = get(pool, entity_type, entity_id, revision).await?;
+    let mut snapshot = revision.snapshot.0;
+    // A rollback is a new version, not a change based on the old one
+    if let Some(fields) = snapshot.as_object_mut() {
+        fields.remove("version");
+    }
+    serde_json::from_value(snapshot).map_err(|e| {
+        Error::InternalError(format!(
+            "Revision {} can't be applied: {e}",
+            revision.revision
+        ))
+    })
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value> {
+    serde_json::to_value(value)
+        .map_err(|e| Error::InternalError(format!("Failed to serialize revision: {e}")))
+}
+
+/// Fields whose values differ between two snapshots, in field order.
+fn changes(before: &Value, after: &Value) -> Vec<FieldChange> {
+    let empty = Default::default();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let removed = before.keys().filter(|key| !after.contains_key(*key));
+    after
+        .keys()
+        .chain(removed)
+        .filter(|key| !IGNORED_FIELDS.contains(&key.as_str()))
+        .filter_map(|key| {
+            let before = before.get(key).unwrap_or(&Value::Null);
+            let after = after.get(key).unwrap_or(&Value::Null);
+            (before != after).then(|| FieldChange {
+                field: key.clone(),
+                before: before.clone(),
+                after: after.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service;
+    use serde_json::json;
+
+    #[test]
+    fn changes_lists_changed_added_and_removed_fields() {
+        let before = json!({"name": "a", "status": "active", "old": 1, "updated_at": "1"});
+        let after = json!({"name": "b", "status": "active", "new": true, "updated_at": "2"});
+
+        let changes = changes(&before, &after)
+            .into_iter()
+            .map(|c| (c.field, c.before, c.after))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            changes,
+            vec![
+                ("name".to_string(), json!("a"), json!("b")),
+                ("new".to_string(), Value::Null, json!(true)),
+                ("old".to_string(), json!(1), Value::Null),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rollback_clears_fields_that_were_null() {
+        let pool = crate::testing::pool().await;
+        fn input<T: DeserializeOwned>(value: Value) -> T {
+            serde_json::from_value(value).unwrap()
+        }
+        let application = service::application::create(&pool, None, input(json!({"name": "web"})))
+            .await
+            .unwrap();
+        let domain = service::domain::create(
+            &pool,
+            None,
+            input(json!({"fqdn": "web.example.org", "target_application_id": application.id})),
+        )
+        .await
+        .unwrap();
+        let healthcheck = service::healthcheck::create(
+            &pool,
+            None,
+            input(json!({"name": "web", "application_id": application.id, "domain_id": domain.id})),
+        )
+        .await
+        .unwrap();
+
+        service::application::update(
+            &pool,
+            None,
+            &application.id,
+            input(json!({"description": "Website", "url": "https://web.example.org"})),
+        )
+        .await
+        .unwrap();
+        // Not on top of a change it didn't see
+        let stale = Some(application.version);
+        let outdated = service::application::rollback(&pool, None, &application.id, 1, stale).await;
+        assert!(matches!(outdated, Err(Error::PreconditionFailed(_))));
+        let rolled_back = service::application::rollback(&pool, None, &application.id, 1, None)
+            .await
+            .unwrap();
+        assert_eq!((rolled_back.description, rolled_back.url), (None, None));
+        assert_eq!(rolled_back.version, application.version + 2);
+
+        service::healthcheck::update(
+            &pool,
+            None,
+            &healthcheck.id,
+            input(json!({"notes": "Flaky", "port": 8443, "kuma_id": 7})),
+        )
+        .await
+        .unwrap();
+        let rolled_back = service::healthcheck::rollback(&pool, None, &healthcheck.id, 1, None)
+            .await
+            .unwrap();
+        assert_eq!((rolled_back.notes, rolled_back.port), (None, None));
+        // The monitor stays, it's not part of what's rolled back
+        assert_eq!(rolled_back.kuma_id, Some(7));
+    }
+}
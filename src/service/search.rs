@@ -17,14 +17,14 @@ pub async fn resolve_id(pool: &SqlitePool, id: &str) -> Result<ResolvedEntity> {
     let pattern = format!("{id}%");
     let result = sqlx::query_as::<_, ResolvedEntity>(
         r#"
-        SELECT id, name, 'application' as entity_type FROM application WHERE id LIKE ?1
-        UNION ALL SELECT id, name, 'service' FROM service WHERE id LIKE ?1
-        UNION ALL SELECT id, name, 'infra' FROM infra WHERE id LIKE ?1
-        UNION ALL SELECT id, fqdn as name, 'domain' FROM domain WHERE id LIKE ?1
-        UNION ALL SELECT id, name, 'person' FROM person WHERE id LIKE ?1
-        UNION ALL SELECT id, name, 'network_share' FROM network_share WHERE id LIKE ?1
-        UNION ALL SELECT id, name, 'stack' FROM stack WHERE id LIKE ?1
-        UNION ALL SELECT id, name, 'healthcheck' FROM healthcheck WHERE id LIKE ?1
+        SELECT id, name, 'application' as entity_type FROM application WHERE id LIKE ?1 AND deleted_at IS NULL
+        UNION ALL SELECT id, name, 'service' FROM service WHERE id LIKE ?1 AND deleted_at IS NULL
+        UNION ALL SELECT id, name, 'infra' FROM infra WHERE id LIKE ?1 AND deleted_at IS NULL
+        UNION ALL SELECT id, fqdn as name, 'domain' FROM domain WHERE id LIKE ?1 AND deleted_at IS NULL
+        UNION ALL SELECT id, name, 'person' FROM person WHERE id LIKE ?1 AND deleted_at IS NULL
+        UNION ALL SELECT id, name, 'network_share' FROM network_share WHERE id LIKE ?1 AND deleted_at IS NULL
+        UNION ALL SELECT id, name, 'stack' FROM stack WHERE id LIKE ?1 AND deleted_at IS NULL
+        UNION ALL SELECT id, name, 'healthcheck' FROM healthcheck WHERE id LIKE ?1 AND deleted_at IS NULL
         LIMIT 1
         "#,
     )
@@ -92,7 +92,7 @@ async fn search_applications(
         r#"
         SELECT id, name, description, 'application' as entity_type
         FROM application
-        WHERE name LIKE ?1 OR description LIKE ?1
+        WHERE (name LIKE ?1 OR description LIKE ?1) AND deleted_at IS NULL
         ORDER BY CASE WHEN name LIKE ?2 THEN 0 ELSE 1 END, name COLLATE NOCASE ASC
         LIMIT 20
         "#,
@@ -112,7 +112,7 @@ async fn search_services(
         r#"
         SELECT id, name, description, 'service' as entity_type
         FROM service
-        WHERE name LIKE ?1 OR description LIKE ?1
+        WHERE (name LIKE ?1 OR description LIKE ?1) AND deleted_at IS NULL
         ORDER BY CASE WHEN name LIKE ?2 THEN 0 ELSE 1 END, name COLLATE NOCASE ASC
         LIMIT 20
         "#,
@@ -128,7 +128,7 @@ async fn search_infra(pool: &SqlitePool, pattern: &str, prefix: &str) -> Result<
         r#"
         SELECT id, name, description, 'infra' as entity_type
         FROM infra
-        WHERE name LIKE ?1 OR description LIKE ?1
+        WHERE (name LIKE ?1 OR description LIKE ?1) AND deleted_at IS NULL
         ORDER BY CASE WHEN name LIKE ?2 THEN 0 ELSE 1 END, name COLLATE NOCASE ASC
         LIMIT 20
         "#,
@@ -153,12 +153,13 @@ async fn search_domains(
         FROM domain d
         LEFT JOIN application a ON d.target_application_id = a.id
         LEFT JOIN service s ON d.target_service_id = s.id
-        WHERE d.fqdn LIKE ?1
+        WHERE d.deleted_at IS NULL
+          AND (d.fqdn LIKE ?1
            OR d.registrar LIKE ?1
            OR d.dns_provider LIKE ?1
            OR d.notes LIKE ?1
            OR a.name LIKE ?1
-           OR s.name LIKE ?1
+           OR s.name LIKE ?1)
         ORDER BY CASE WHEN d.fqdn LIKE ?2 THEN 0 ELSE 1 END, d.fqdn COLLATE NOCASE ASC
         LIMIT 20
         "#,
@@ -178,7 +179,7 @@ async fn search_people(
         r#"
         SELECT id, name, email as description, 'person' as entity_type
         FROM person
-        WHERE name LIKE ?1 OR email LIKE ?1 OR role LIKE ?1
+        WHERE (name LIKE ?1 OR email LIKE ?1 OR role LIKE ?1) AND deleted_at IS NULL
         ORDER BY CASE WHEN name LIKE ?2 THEN 0 ELSE 1 END, name COLLATE NOCASE ASC
         LIMIT 20
         "#,
@@ -198,7 +199,7 @@ async fn search_network_shares(
         r#"
         SELECT id, name, path as description, 'network_share' as entity_type
         FROM network_share
-        WHERE name LIKE ?1 OR path LIKE ?1 OR server LIKE ?1
+        WHERE (name LIKE ?1 OR path LIKE ?1 OR server LIKE ?1) AND deleted_at IS NULL
         ORDER BY CASE WHEN name LIKE ?2 THEN 0 ELSE 1 END, name COLLATE NOCASE ASC
         LIMIT 20
         "#,
@@ -218,7 +219,7 @@ async fn search_stacks(
         r#"
         SELECT id, name, notes as description, 'stack' as entity_type
         FROM stack
-        WHERE name LIKE ?1 OR notes LIKE ?1
+        WHERE (name LIKE ?1 OR notes LIKE ?1) AND deleted_at IS NULL
         ORDER BY CASE WHEN name LIKE ?2 THEN 0 ELSE 1 END, name COLLATE NOCASE ASC
         LIMIT 20
         "#,
@@ -243,12 +244,13 @@ async fn search_healthchecks(
         JOIN domain d ON h.domain_id = d.id
         LEFT JOIN application a ON h.application_id = a.id
         LEFT JOIN service s ON h.service_id = s.id
-        WHERE h.name LIKE ?1
+        WHERE h.deleted_at IS NULL
+          AND (h.name LIKE ?1
            OR d.fqdn LIKE ?1
            OR h.path LIKE ?1
            OR h.notes LIKE ?1
            OR a.name LIKE ?1
-           OR s.name LIKE ?1
+           OR s.name LIKE ?1)
         ORDER BY CASE WHEN h.name LIKE ?2 THEN 0 ELSE 1 END, h.name COLLATE NOCASE ASC
         LIMIT 20
         "#,
@@ -1,9 +1,12 @@
-use sqlx::SqlitePool;
+use serde_json::{Value, json};
+use sqlx::types::Json;
+use sqlx::{SqliteConnection, SqlitePool};
 
 use crate::models::{
-    ApplicationServiceRelation, CreateService, InfraRelation, PaginatedResponse, PaginationParams,
-    Service, ServiceWithRelations, UpdateService, new_id,
+    ApplicationServiceRelation, AuditAction, CreateService, InfraRelation, PaginatedResponse,
+    PaginationParams, Service, ServiceWithRelations, UpdateService, new_id,
 };
+use crate::service::{audit, revision, trash, version};
 use crate::{Error, Result, service};
 
 pub async fn list(
@@ -18,9 +21,10 @@ pub async fn list(
 
     let services = sqlx::query_as::<_, Service>(
         r#"
-        SELECT id, name, description, repository_url, environment, status, image_refs, outline_url, created_at, updated_at, created_by
+        SELECT id, name, description, repository_url, environment, status, image_refs, outline_url, created_at, updated_at, version, created_by
         FROM service
-        WHERE (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
           AND (?2 IS NULL OR status = ?2)
           AND (?3 IS NULL OR environment = ?3)
         ORDER BY name COLLATE NOCASE ASC
@@ -39,7 +43,8 @@ pub async fn list(
         r#"
         SELECT COUNT(*)
         FROM service
-        WHERE (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
+        WHERE deleted_at IS NULL
+          AND (?1 IS NULL OR name LIKE ?1 OR description LIKE ?1)
           AND (?2 IS NULL OR status = ?2)
           AND (?3 IS NULL OR environment = ?3)
         "#,
@@ -54,15 +59,20 @@ pub async fn list(
 }
 
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<Service> {
+    get_in(&mut *pool.acquire().await?, id).await
+}
+
+/// [`get`] on a connection, e.g. in a transaction
+pub async fn get_in(conn: &mut SqliteConnection, id: &str) -> Result<Service> {
     sqlx::query_as::<_, Service>(
         r#"
-        SELECT id, name, description, repository_url, environment, status, image_refs, outline_url, created_at, updated_at, created_by
+        SELECT id, name, description, repository_url, environment, status, image_refs, outline_url, created_at, updated_at, version, created_by
         FROM service
-        WHERE id = ?1
+        WHERE id = ?1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or_else(|| Error::NotFound(format!("Service with id '{}' not found", id)))
 }
@@ -76,6 +86,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<ServiceWi
         FROM application a
         JOIN application_service asvc ON a.id = asvc.application_id
         WHERE asvc.service_id = ?1
+          AND a.deleted_at IS NULL
         ORDER BY a.name COLLATE NOCASE
         "#,
     )
@@ -89,6 +100,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<ServiceWi
         FROM infra i
         JOIN service_infra si ON i.id = si.infra_id
         WHERE si.service_id = ?1
+          AND i.deleted_at IS NULL
         ORDER BY i.name COLLATE NOCASE
         "#,
     )
@@ -106,13 +118,17 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<ServiceWi
     })
 }
 
-pub async fn create(pool: &SqlitePool, input: CreateService) -> Result<Service> {
+pub async fn create(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    input: CreateService,
+) -> Result<Service> {
     let id = new_id();
 
     sqlx::query(
         r#"
-        INSERT INTO service (id, name, description, repository_url, environment, status, image_refs, outline_url)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+        INSERT INTO service (id, name, description, repository_url, environment, status, image_refs, outline_url, created_by)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
         "#,
     )
     .bind(&id)
@@ -123,49 +139,141 @@ pub async fn create(pool: &SqlitePool, input: CreateService) -> Result<Service>
     .bind(&input.status)
     .bind(&input.image_refs)
     .bind(&input.outline_url)
+    .bind(actor)
     .execute(pool)
     .await?;
 
-    get(pool, &id).await
+    let service = get(pool, &id).await?;
+    audit::record(
+        pool,
+        actor,
+        "service",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&service),
+    )
+    .await?;
+    revision::record(pool, actor, "service", &id, None, &service).await?;
+
+    Ok(service)
 }
 
-pub async fn update(pool: &SqlitePool, id: &str, input: UpdateService) -> Result<Service> {
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdateService,
+) -> Result<Service> {
     let existing = get(pool, id).await?;
+    version::check("Service", id, input.version, existing.version)?;
 
-    let name = input.name.unwrap_or(existing.name);
-    let description = input.description.or(existing.description);
-    let repository_url = input.repository_url.or(existing.repository_url);
-    let environment = input.environment.unwrap_or(existing.environment);
-    let status = input.status.unwrap_or(existing.status);
-    let image_refs = input.image_refs.or(existing.image_refs);
-    let outline_url = input.outline_url.or(existing.outline_url);
+    let values = Service {
+        name: input.name.unwrap_or_else(|| existing.name.clone()),
+        description: input.description.or_else(|| existing.description.clone()),
+        repository_url: input
+            .repository_url
+            .or_else(|| existing.repository_url.clone()),
+        environment: input
+            .environment
+            .unwrap_or_else(|| existing.environment.clone()),
+        status: input.status.unwrap_or_else(|| existing.status.clone()),
+        image_refs: input.image_refs.or_else(|| existing.image_refs.clone()),
+        outline_url: input.outline_url.or_else(|| existing.outline_url.clone()),
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
 
-    sqlx::query(
+/// Roll back to a revision. Unlike [`update`], fields without a value in
+/// the revision are cleared instead of kept.
+pub async fn rollback(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    rev: i64,
+    expected_version: Option<i64>,
+) -> Result<Service> {
+    let existing = get(pool, id).await?;
+    version::check("Service", id, expected_version, existing.version)?;
+    let snapshot = revision::snapshot::<UpdateService>(pool, "service", id, rev).await?;
+
+    let values = Service {
+        name: snapshot.name.unwrap_or_else(|| existing.name.clone()),
+        description: snapshot.description,
+        repository_url: snapshot.repository_url,
+        environment: snapshot
+            .environment
+            .unwrap_or_else(|| existing.environment.clone()),
+        status: snapshot.status.unwrap_or_else(|| existing.status.clone()),
+        image_refs: snapshot.image_refs,
+        outline_url: snapshot.outline_url,
+        ..existing.clone()
+    };
+    write(pool, actor, existing, values).await
+}
+
+/// Store `values` as the next version of `previous`
+async fn write(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    previous: Service,
+    values: Service,
+) -> Result<Service> {
+    let id = previous.id.as_str();
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
         r#"
         UPDATE service
-        SET name = ?1, description = ?2, repository_url = ?3, environment = ?4, status = ?5, image_refs = ?6, outline_url = ?7, updated_at = datetime('now')
-        WHERE id = ?8
+        SET name = ?1, description = ?2, repository_url = ?3, environment = ?4, status = ?5, image_refs = ?6, outline_url = ?7, updated_at = datetime('now'), version = version + 1
+        WHERE id = ?8 AND version = ?9
         "#,
     )
-    .bind(&name)
-    .bind(&description)
-    .bind(&repository_url)
-    .bind(&environment)
-    .bind(&status)
-    .bind(&image_refs)
-    .bind(&outline_url)
+    .bind(&values.name)
+    .bind(&values.description)
+    .bind(&values.repository_url)
+    .bind(&values.environment)
+    .bind(&values.status)
+    .bind(&values.image_refs)
+    .bind(&values.outline_url)
     .bind(id)
-    .execute(pool)
+    .bind(previous.version)
+    .execute(&mut *tx)
+    .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Service", id));
+    }
+
+    let service = get_in(&mut tx, id).await?;
+    audit::record_in(
+        &mut tx,
+        actor,
+        "service",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&service),
+    )
     .await?;
+    revision::record_in(&mut tx, actor, "service", id, Some(&previous), &service).await?;
+    if service.name != previous.name || service.environment != previous.environment {
+        service::healthcheck::mark_kuma_dirty_of_in(&mut tx, id).await?;
+    }
 
-    get(pool, id).await
+    tx.commit().await?;
+    Ok(service)
 }
 
-pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
-    let result = sqlx::query("DELETE FROM service WHERE id = ?1")
-        .bind(id)
-        .execute(pool)
-        .await?;
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Service", id, expected_version, existing.version)?;
+
+    let result = trash::soft_delete(pool, actor, "service", id).await?;
 
     if result.rows_affected() == 0 {
         return Err(Error::NotFound(format!(
@@ -174,6 +282,17 @@ pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
         )));
     }
 
+    audit::record(
+        pool,
+        actor,
+        "service",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
     Ok(())
 }
 
@@ -181,6 +300,7 @@ pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
 
 pub async fn link_infra(
     pool: &SqlitePool,
+    actor: Option<&str>,
     service_id: &str,
     infra_id: &str,
     notes: Option<&str>,
@@ -202,19 +322,46 @@ pub async fn link_infra(
     .execute(pool)
     .await?;
 
+    audit::record_link(
+        pool,
+        actor,
+        AuditAction::Link,
+        ("service", service_id),
+        ("infra", infra_id),
+        Some(json!({ "notes": notes })),
+    )
+    .await?;
+
     Ok(())
 }
 
-pub async fn unlink_infra(pool: &SqlitePool, service_id: &str, infra_id: &str) -> Result<()> {
-    let result = sqlx::query("DELETE FROM service_infra WHERE service_id = ?1 AND infra_id = ?2")
-        .bind(service_id)
-        .bind(infra_id)
-        .execute(pool)
-        .await?;
+pub async fn unlink_infra(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    service_id: &str,
+    infra_id: &str,
+) -> Result<()> {
+    let removed = sqlx::query_scalar::<_, Json<Value>>(
+        r#"
+        DELETE FROM service_infra WHERE service_id = ?1 AND infra_id = ?2
+        RETURNING json_object('notes', notes)
+        "#,
+    )
+    .bind(service_id)
+    .bind(infra_id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound("Relationship not found".to_string()))?;
 
-    if result.rows_affected() == 0 {
-        return Err(Error::NotFound("Relationship not found".to_string()));
-    }
+    audit::record_link(
+        pool,
+        actor,
+        AuditAction::Unlink,
+        ("service", service_id),
+        ("infra", infra_id),
+        Some(removed.0),
+    )
+    .await?;
 
     Ok(())
 }
@@ -1,10 +1,11 @@
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 
 use crate::models::{
-    ApplicationStackRelation, CreateStack, PaginatedResponse, PaginationParams, Stack,
+    ApplicationStackRelation, AuditAction, CreateStack, PaginatedResponse, PaginationParams, Stack,
     StackWithRelations, UpdateStack, new_id,
 };
-use crate::{Error, Result};
+use crate::service::{audit, trash, version};
+use crate::{Error, Result, service};
 
 pub async fn list(
     pool: &SqlitePool,
@@ -17,9 +18,9 @@ pub async fn list(
         let search_pattern = format!("%{}%", search);
         let items = sqlx::query_as::<_, Stack>(
             r#"
-            SELECT id, name, notes, created_at, updated_at
+            SELECT id, name, notes, created_at, updated_at, version
             FROM stack
-            WHERE name LIKE ?1
+            WHERE name LIKE ?1 AND deleted_at IS NULL
             ORDER BY name COLLATE NOCASE ASC
             LIMIT ?2 OFFSET ?3
             "#,
@@ -30,17 +31,19 @@ pub async fn list(
         .fetch_all(pool)
         .await?;
 
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM stack WHERE name LIKE ?1")
-            .bind(&search_pattern)
-            .fetch_one(pool)
-            .await?;
+        let count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM stack WHERE name LIKE ?1 AND deleted_at IS NULL")
+                .bind(&search_pattern)
+                .fetch_one(pool)
+                .await?;
 
         (items, count.0)
     } else {
         let items = sqlx::query_as::<_, Stack>(
             r#"
-            SELECT id, name, notes, created_at, updated_at
+            SELECT id, name, notes, created_at, updated_at, version
             FROM stack
+            WHERE deleted_at IS NULL
             ORDER BY name COLLATE NOCASE ASC
             LIMIT ?1 OFFSET ?2
             "#,
@@ -50,7 +53,7 @@ pub async fn list(
         .fetch_all(pool)
         .await?;
 
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM stack")
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM stack WHERE deleted_at IS NULL")
             .fetch_one(pool)
             .await?;
 
@@ -61,15 +64,20 @@ pub async fn list(
 }
 
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<Stack> {
+    get_in(&mut *pool.acquire().await?, id).await
+}
+
+/// [`get`] on a connection, e.g. in a transaction
+pub async fn get_in(conn: &mut SqliteConnection, id: &str) -> Result<Stack> {
     sqlx::query_as::<_, Stack>(
         r#"
-        SELECT id, name, notes, created_at, updated_at
+        SELECT id, name, notes, created_at, updated_at, version
         FROM stack
-        WHERE id = ?1
+        WHERE id = ?1 AND deleted_at IS NULL
         "#,
     )
     .bind(id)
-    .fetch_optional(pool)
+    .fetch_optional(&mut *conn)
     .await?
     .ok_or_else(|| Error::NotFound(format!("Stack with id '{}' not found", id)))
 }
@@ -83,6 +91,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<StackWith
         FROM application a
         JOIN application_stack ast ON a.id = ast.application_id
         WHERE ast.stack_id = ?1
+          AND a.deleted_at IS NULL
         ORDER BY a.name COLLATE NOCASE
         "#,
     )
@@ -96,7 +105,7 @@ pub async fn get_with_relations(pool: &SqlitePool, id: &str) -> Result<StackWith
     })
 }
 
-pub async fn create(pool: &SqlitePool, input: CreateStack) -> Result<Stack> {
+pub async fn create(pool: &SqlitePool, actor: Option<&str>, input: CreateStack) -> Result<Stack> {
     let id = new_id();
 
     sqlx::query(
@@ -111,40 +120,94 @@ pub async fn create(pool: &SqlitePool, input: CreateStack) -> Result<Stack> {
     .execute(pool)
     .await?;
 
-    get(pool, &id).await
+    let stack = get(pool, &id).await?;
+    audit::record(
+        pool,
+        actor,
+        "stack",
+        &id,
+        AuditAction::Create,
+        None,
+        Some(&stack),
+    )
+    .await?;
+
+    Ok(stack)
 }
 
-pub async fn update(pool: &SqlitePool, id: &str, input: UpdateStack) -> Result<Stack> {
+pub async fn update(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    input: UpdateStack,
+) -> Result<Stack> {
     let existing = get(pool, id).await?;
+    version::check("Stack", id, input.version, existing.version)?;
+    let previous = existing.clone();
 
     let name = input.name.unwrap_or(existing.name);
     let notes = input.notes.or(existing.notes);
 
-    sqlx::query(
+    let result = sqlx::query(
         r#"
         UPDATE stack
-        SET name = ?1, notes = ?2, updated_at = datetime('now')
-        WHERE id = ?3
+        SET name = ?1, notes = ?2, updated_at = datetime('now'), version = version + 1
+        WHERE id = ?3 AND version = ?4
         "#,
     )
     .bind(&name)
     .bind(&notes)
     .bind(id)
+    .bind(existing.version)
     .execute(pool)
     .await?;
+    if result.rows_affected() == 0 {
+        return Err(version::changed("Stack", id));
+    }
 
-    get(pool, id).await
+    let stack = get(pool, id).await?;
+    audit::record(
+        pool,
+        actor,
+        "stack",
+        id,
+        AuditAction::Update,
+        Some(&previous),
+        Some(&stack),
+    )
+    .await?;
+    if stack.name != previous.name {
+        service::healthcheck::mark_kuma_dirty_of(pool, id).await?;
+    }
+
+    Ok(stack)
 }
 
-pub async fn delete(pool: &SqlitePool, id: &str) -> Result<()> {
-    let result = sqlx::query("DELETE FROM stack WHERE id = ?1")
-        .bind(id)
-        .execute(pool)
-        .await?;
+pub async fn delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    id: &str,
+    expected_version: Option<i64>,
+) -> Result<()> {
+    let existing = get(pool, id).await?;
+    version::check("Stack", id, expected_version, existing.version)?;
+
+    let result = trash::soft_delete(pool, actor, "stack", id).await?;
 
     if result.rows_affected() == 0 {
         return Err(Error::NotFound(format!("Stack with id '{}' not found", id)));
     }
 
+    audit::record(
+        pool,
+        actor,
+        "stack",
+        id,
+        AuditAction::Delete,
+        Some(&existing),
+        None,
+    )
+    .await?;
+
     Ok(())
 }
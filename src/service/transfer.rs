@@ -0,0 +1,520 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use serde_json::{Map, Value};
+use sqlx::query::Query;
+use sqlx::sqlite::{Sqlite, SqliteArguments};
+use sqlx::{Row, SqliteConnection, SqlitePool};
+
+use crate::models::{
+    AuditAction, EXPORT_FORMAT, EXPORT_VERSION, ExportDocument, ImportReport, TableImportReport,
+};
+use crate::service::audit;
+use crate::{Error, Result};
+
+/// Entity tables, ordered so every foreign key points to an earlier table.
+const ENTITY_TABLES: &[&str] = &[
+    "person",
+    "stack",
+    "infra",
+    "network_share",
+    "application",
+    "service",
+    "domain",
+    "healthcheck",
+    "note",
+];
+
+/// Junction tables with the entity type of both sides of the link, in the
+/// order of their primary key columns.
+const LINK_TABLES: &[(&str, &str, &str)] = &[
+    ("application_infra", "application", "infra"),
+    ("application_service", "application", "service"),
+    ("application_domain", "application", "domain"),
+    ("application_person", "application", "person"),
+    ("application_network_share", "application", "network_share"),
+    ("application_stack", "application", "stack"),
+    ("service_infra", "service", "infra"),
+];
+
+/// Columns an import keeps up to date itself on updated rows, rather than
+/// taking them from the document: the version is bumped, and healthchecks
+/// are flagged to be synced to Kuma.
+const MANAGED_COLUMNS: &[&str] = &["version", "kuma_dirty", "kuma_version"];
+
+type SqliteQuery<'q> = Query<'q, Sqlite, SqliteArguments<'q>>;
+
+/// Every exported table, in import order.
+fn table_names() -> impl Iterator<Item = &'static str> {
+    ENTITY_TABLES
+        .iter()
+        .copied()
+        .chain(LINK_TABLES.iter().map(|(table, _, _)| *table))
+}
+
+fn link_types(table: &str) -> Option<(&'static str, &'static str)> {
+    LINK_TABLES
+        .iter()
+        .find(|(name, _, _)| *name == table)
+        .map(|(_, left, right)| (*left, *right))
+}
+
+/// Column names of a table, and separately its primary key columns in order.
+async fn columns(conn: &mut SqliteConnection, table: &str) -> Result<(Vec<String>, Vec<String>)> {
+    let info = sqlx::query_as::<_, (String, i64)>(
+        "SELECT name, pk FROM pragma_table_info(?1) ORDER BY cid",
+    )
+    .bind(table)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let primary_key = info
+        .iter()
+        .filter(|(_, pk)| *pk > 0)
+        .sorted_by_key(|(_, pk)| *pk)
+        .map(|(name, _)| name.clone())
+        .collect();
+    let columns = info.into_iter().map(|(name, _)| name).collect();
+
+    Ok((columns, primary_key))
+}
+
+/// SQL expression turning the given columns of a row into a JSON object.
+fn json_object<'a>(columns: impl IntoIterator<Item = &'a String>) -> String {
+    let pairs = columns
+        .into_iter()
+        .map(|column| format!("'{column}', \"{column}\""))
+        .join(", ");
+    format!("json_object({pairs})")
+}
+
+fn parse_row(json: &str) -> Result<Map<String, Value>> {
+    serde_json::from_str(json)
+        .map_err(|e| Error::InternalError(format!("Failed to read exported row: {e}")))
+}
+
+/// Dump every entity and junction table.
+pub async fn export(pool: &SqlitePool) -> Result<ExportDocument> {
+    let mut conn = pool.acquire().await?;
+    let mut tables = BTreeMap::new();
+
+    for table in table_names() {
+        let (columns, primary_key) = columns(&mut conn, table).await?;
+        let order = primary_key.iter().map(|c| format!("\"{c}\"")).join(", ");
+        let sql = format!(
+            "SELECT {} FROM \"{table}\" ORDER BY {order}",
+            json_object(&columns)
+        );
+
+        let rows = sqlx::query_scalar::<_, String>(&sql)
+            .fetch_all(&mut *conn)
+            .await?
+            .iter()
+            .map(|row| parse_row(row))
+            .collect::<Result<Vec<_>>>()?;
+
+        tables.insert(table.to_string(), rows);
+    }
+
+    let exported_at = sqlx::query_scalar::<_, String>("SELECT datetime('now')")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(ExportDocument {
+        format: EXPORT_FORMAT.to_string(),
+        version: EXPORT_VERSION,
+        app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+        exported_at: Some(exported_at),
+        tables,
+    })
+}
+
+/// A row written by an import, kept to audit it once the import is committed.
+struct ImportedRow {
+    table: &'static str,
+    key: Vec<String>,
+    action: AuditAction,
+    before: Option<Value>,
+    after: Value,
+}
+
+enum RowOutcome {
+    Created,
+    Updated { before: Value, after: Value },
+    Unchanged,
+}
+
+/// Restore an export document, creating rows with unknown IDs and updating
+/// rows whose IDs already exist. Rows that aren't in the document are left
+/// alone. Updated rows get their next version rather than the exported one,
+/// see [`MANAGED_COLUMNS`].
+///
+/// Everything happens in one transaction: either the whole document is
+/// imported or nothing is. With `dry_run` the transaction is always rolled
+/// back, so the report shows what an import would change.
+pub async fn import(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    document: ExportDocument,
+    dry_run: bool,
+) -> Result<ImportReport> {
+    if document.format != EXPORT_FORMAT {
+        return Err(Error::ValidationError(format!(
+            "Unsupported document format '{}', expected '{EXPORT_FORMAT}'",
+            document.format
+        )));
+    }
+    if document.version != EXPORT_VERSION {
+        return Err(Error::ValidationError(format!(
+            "Unsupported export version {}, expected {EXPORT_VERSION}",
+            document.version
+        )));
+    }
+    if let Some(table) = document
+        .tables
+        .keys()
+        .find(|table| !table_names().any(|name| name == table.as_str()))
+    {
+        return Err(Error::ValidationError(format!("Unknown table '{table}'")));
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut reports = Vec::new();
+    let mut imported = Vec::new();
+
+    for table in table_names() {
+        let Some(rows) = document.tables.get(table) else {
+            continue;
+        };
+        let (columns, primary_key) = columns(&mut tx, table).await?;
+
+        let mut report = TableImportReport {
+            table: table.to_string(),
+            created: Vec::new(),
+            updated: Vec::new(),
+            unchanged: 0,
+        };
+
+        for (index, row) in rows.iter().enumerate() {
+            let row = normalize_row(table, index, row, &columns)?;
+            let key = primary_key
+                .iter()
+                .map(|column| match row.get(column) {
+                    Some(Value::String(value)) if !value.is_empty() => Ok(value.clone()),
+                    _ => Err(Error::ValidationError(format!(
+                        "Row {index} of table '{table}' has no '{column}'"
+                    ))),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let id = key.join("/");
+
+            let (action, before, after) =
+                match import_row(&mut tx, table, &columns, &primary_key, &row)
+                    .await
+                    .map_err(|e| row_error(table, &id, e))?
+                {
+                    RowOutcome::Created => {
+                        report.created.push(id);
+                        (AuditAction::Create, None, Value::Object(row))
+                    }
+                    RowOutcome::Updated { before, after } => {
+                        report.updated.push(id);
+                        (AuditAction::Update, Some(before), after)
+                    }
+                    RowOutcome::Unchanged => {
+                        report.unchanged += 1;
+                        continue;
+                    }
+                };
+
+            imported.push(ImportedRow {
+                table,
+                key,
+                action,
+                before,
+                after,
+            });
+        }
+
+        reports.push(report);
+    }
+
+    if dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+
+        for row in &imported {
+            match link_types(row.table) {
+                Some((left, right)) => {
+                    audit::record_link(
+                        pool,
+                        actor,
+                        AuditAction::Link,
+                        (left, &row.key[0]),
+                        (right, &row.key[1]),
+                        Some(row.after.clone()),
+                    )
+                    .await?
+                }
+                None => {
+                    audit::record(
+                        pool,
+                        actor,
+                        row.table,
+                        &row.key[0],
+                        row.action,
+                        row.before.as_ref(),
+                        Some(&row.after),
+                    )
+                    .await?
+                }
+            }
+        }
+    }
+
+    Ok(ImportReport {
+        dry_run,
+        created: reports.iter().map(|r| r.created.len()).sum(),
+        updated: reports.iter().map(|r| r.updated.len()).sum(),
+        unchanged: reports.iter().map(|r| r.unchanged).sum(),
+        tables: reports,
+    })
+}
+
+/// Check the columns of an imported row and convert its values to the form
+/// SQLite stores them in, so they compare equal to exported rows.
+fn normalize_row(
+    table: &str,
+    index: usize,
+    row: &Map<String, Value>,
+    columns: &[String],
+) -> Result<Map<String, Value>> {
+    row.iter()
+        .map(|(column, value)| {
+            if !columns.contains(column) {
+                return Err(Error::ValidationError(format!(
+                    "Row {index} of table '{table}' has unknown column '{column}'"
+                )));
+            }
+            let value = match value {
+                Value::Bool(value) => Value::from(*value as i64),
+                Value::Array(_) | Value::Object(_) => Value::String(value.to_string()),
+                value => value.clone(),
+            };
+            Ok((column.clone(), value))
+        })
+        .collect()
+}
+
+async fn import_row(
+    conn: &mut SqliteConnection,
+    table: &str,
+    columns: &[String],
+    primary_key: &[String],
+    row: &Map<String, Value>,
+) -> Result<RowOutcome> {
+    let filter = primary_key
+        .iter()
+        .enumerate()
+        .map(|(i, column)| format!("\"{column}\" = ?{}", i + 1))
+        .join(" AND ");
+    let key_values = primary_key.iter().map(|column| &row[column]);
+
+    let select = format!(
+        "SELECT {} FROM \"{table}\" WHERE {filter}",
+        json_object(row.keys())
+    );
+    let existing = bind_values(sqlx::query(&select), key_values.clone())
+        .fetch_optional(&mut *conn)
+        .await?
+        .map(|existing| parse_row(existing.get::<&str, _>(0)))
+        .transpose()?;
+    let is_managed = |column: &String| MANAGED_COLUMNS.contains(&column.as_str());
+
+    match existing {
+        None => {
+            let sql = format!(
+                "INSERT INTO \"{table}\" ({}) VALUES ({})",
+                row.keys().map(|c| format!("\"{c}\"")).join(", "),
+                (1..=row.len()).map(|i| format!("?{i}")).join(", ")
+            );
+            bind_values(sqlx::query(&sql), row.values())
+                .execute(&mut *conn)
+                .await?;
+
+            Ok(RowOutcome::Created)
+        }
+        Some(existing)
+            if row.iter().all(|(column, value)| {
+                is_managed(column) || existing.get(column) == Some(value)
+            }) =>
+        {
+            Ok(RowOutcome::Unchanged)
+        }
+        Some(existing) => {
+            // Key columns are bound first, so the filter can stay as it is
+            let changed = row
+                .iter()
+                .filter(|(column, _)| !primary_key.contains(column) && !is_managed(column))
+                .collect::<Vec<_>>();
+            let managed = columns
+                .iter()
+                .filter(|column| is_managed(column))
+                .map(|column| match column.as_str() {
+                    "kuma_dirty" => format!("\"{column}\" = 1"),
+                    _ => format!("\"{column}\" = \"{column}\" + 1"),
+                });
+            let assignments = changed
+                .iter()
+                .enumerate()
+                .map(|(i, (column, _))| format!("\"{column}\" = ?{}", primary_key.len() + i + 1))
+                .chain(managed)
+                .join(", ");
+
+            let sql = format!("UPDATE \"{table}\" SET {assignments} WHERE {filter}");
+            bind_values(
+                sqlx::query(&sql),
+                key_values
+                    .clone()
+                    .chain(changed.iter().map(|(_, value)| *value)),
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            let after = bind_values(sqlx::query(&select), key_values)
+                .fetch_one(&mut *conn)
+                .await?;
+            Ok(RowOutcome::Updated {
+                before: Value::Object(existing),
+                after: Value::Object(parse_row(after.get::<&str, _>(0))?),
+            })
+        }
+    }
+}
+
+fn bind_values<'q>(
+    query: SqliteQuery<'q>,
+    values: impl IntoIterator<Item = &'q Value>,
+) -> SqliteQuery<'q> {
+    values.into_iter().fold(query, |query, value| match value {
+        Value::Null => query.bind(None::<String>),
+        Value::Bool(value) => query.bind(*value),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => query.bind(value),
+            None => query.bind(number.as_f64()),
+        },
+        Value::String(value) => query.bind(value.as_str()),
+        value => query.bind(value.to_string()),
+    })
+}
+
+/// Point database errors at the row that caused them.
+fn row_error(table: &str, id: &str, error: Error) -> Error {
+    match error {
+        Error::SqlxError(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Error::Conflict(format!("Row '{id}' of table '{table}': {e}"))
+        }
+        Error::SqlxError(sqlx::Error::Database(e)) => {
+            Error::ValidationError(format!("Row '{id}' of table '{table}': {e}"))
+        }
+        error => error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::pool;
+
+    #[tokio::test]
+    async fn export_round_trips_through_import() {
+        let source = pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO application (id, name) VALUES ('a1', 'app');
+            INSERT INTO infra (id, name, type) VALUES ('i1', 'cluster', 'nomad');
+            INSERT INTO application_infra (application_id, infra_id, notes) VALUES ('a1', 'i1', 'x');
+            "#,
+        )
+        .execute(&source)
+        .await
+        .unwrap();
+        let document = export(&source).await.unwrap();
+
+        let target = pool().await;
+        let report = import(&target, None, document.clone(), true).await.unwrap();
+        assert_eq!(report.created, 3);
+        let empty = export(&target).await.unwrap();
+        assert!(empty.tables.values().all(Vec::is_empty));
+
+        let report = import(&target, None, document.clone(), false)
+            .await
+            .unwrap();
+        assert_eq!(report.created, 3);
+        assert_eq!(export(&target).await.unwrap().tables, document.tables);
+
+        let mut changed = document.clone();
+        changed.tables.get_mut("application").unwrap()[0]
+            .insert("name".to_string(), Value::from("renamed"));
+        let report = import(&target, None, changed, true).await.unwrap();
+        assert_eq!(
+            (report.created, report.updated, report.unchanged),
+            (0, 1, 2)
+        );
+        let application = report.tables.iter().find(|t| t.table == "application");
+        assert_eq!(application.unwrap().updated, vec!["a1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn import_bumps_the_version_and_flags_healthchecks() {
+        let pool = pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO application (id, name) VALUES ('a', 'web');
+            INSERT INTO domain (id, fqdn, target_application_id) VALUES ('d', 'web.example.org', 'a');
+            INSERT INTO healthcheck (id, name, application_id, domain_id, kuma_id, kuma_dirty)
+            VALUES ('h', 'web', 'a', 'd', 7, 0);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let state = || {
+            sqlx::query_as::<_, (String, i64, bool, i64)>(
+                "SELECT name, version, kuma_dirty, kuma_version FROM healthcheck WHERE id = 'h'",
+            )
+            .fetch_one(&pool)
+        };
+        let (_, version, _, kuma_version) = state().await.unwrap();
+
+        // Only another version is no change
+        let mut document = export(&pool).await.unwrap();
+        let healthcheck = &mut document.tables.get_mut("healthcheck").unwrap()[0];
+        healthcheck.insert("version".to_string(), Value::from(version + 5));
+        let report = import(&pool, None, document.clone(), false).await.unwrap();
+        assert_eq!(report.updated, 0);
+
+        let healthcheck = &mut document.tables.get_mut("healthcheck").unwrap()[0];
+        healthcheck.insert("name".to_string(), Value::from("www"));
+        let report = import(&pool, None, document, false).await.unwrap();
+        assert_eq!(report.updated, 1);
+        assert_eq!(
+            state().await.unwrap(),
+            ("www".to_string(), version + 1, true, kuma_version + 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn import_rejects_unknown_columns() {
+        let pool = pool().await;
+        let mut document = export(&pool).await.unwrap();
+        let mut row = Map::new();
+        row.insert("id".to_string(), Value::from("s1"));
+        row.insert("colour".to_string(), Value::from("red"));
+        document.tables.insert("stack".to_string(), vec![row]);
+
+        let result = import(&pool, None, document, false).await;
+        assert!(matches!(result, Err(Error::ValidationError(_))));
+    }
+}
@@ -0,0 +1,595 @@
+use itertools::Itertools;
+use sqlx::sqlite::SqliteQueryResult;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::models::{
+    AuditAction, PaginatedResponse, PaginationParams, TrashFilters, TrashItem, TrashRelation,
+};
+use crate::service::audit;
+use crate::{Error, Result};
+
+/// Soft deleted entity types: table, name column and whether the table
+/// has a `deleted_with` column.
+const ENTITY_TYPES: &[(&str, &str, bool)] = &[
+    ("application", "name", false),
+    ("service", "name", false),
+    ("infra", "name", false),
+    ("domain", "fqdn", true),
+    ("person", "name", false),
+    ("network_share", "name", false),
+    ("stack", "name", false),
+    ("healthcheck", "name", true),
+];
+
+/// Junction tables with the entity types on both sides; the columns are
+/// named after the types.
+const LINKS: &[(&str, &str, &str)] = &[
+    ("application_infra", "application", "infra"),
+    ("application_service", "application", "service"),
+    ("application_domain", "application", "domain"),
+    ("application_person", "application", "person"),
+    ("application_network_share", "application", "network_share"),
+    ("application_stack", "application", "stack"),
+    ("service_infra", "service", "infra"),
+];
+
+/// Columns pointing at an entity that a trashed entity can't live without:
+/// (entity type, column, parent type).
+const PARENTS: &[(&str, &str, &str)] = &[
+    ("domain", "target_application_id", "application"),
+    ("domain", "target_service_id", "service"),
+    ("healthcheck", "application_id", "application"),
+    ("healthcheck", "service_id", "service"),
+    ("healthcheck", "domain_id", "domain"),
+];
+
+/// Columns that have to be unique outside the trash, per entity type.
+const UNIQUE_KEYS: &[(&str, &[&str])] = &[
+    ("application", &["name", "environment"]),
+    ("service", &["name", "environment"]),
+    ("infra", &["name"]),
+    ("domain", &["fqdn"]),
+    ("person", &["email"]),
+    ("network_share", &["name"]),
+    ("stack", &["name"]),
+];
+
+/// Unique key columns compared ignoring case, as DNS names and email
+/// addresses are.
+const NOCASE_COLUMNS: &[&str] = &["fqdn", "email"];
+
+/// Look up an entity type, returning its static table and name column.
+fn entity_type(entity_type: &str) -> Result<(&'static str, &'static str, bool)> {
+    ENTITY_TYPES
+        .iter()
+        .find(|(table, _, _)| *table == entity_type)
+        .copied()
+        .ok_or_else(|| Error::NotFound(format!("Unknown entity type '{entity_type}'")))
+}
+
+fn name_column(entity_type: &str) -> &'static str {
+    ENTITY_TYPES
+        .iter()
+        .find(|(table, _, _)| *table == entity_type)
+        .map_or("name", |(_, name, _)| *name)
+}
+
+/// Everything in the trash that was deleted on its own, as one query over
+/// all entity tables.
+fn trash_query() -> String {
+    ENTITY_TYPES
+        .iter()
+        .map(|(table, name, has_deleted_with)| {
+            let top_level = if *has_deleted_with {
+                " AND deleted_with IS NULL"
+            } else {
+                ""
+            };
+            format!(
+                "SELECT '{table}' AS entity_type, id, {name} AS name, deleted_at, deleted_by \
+                 FROM {table} WHERE deleted_at IS NOT NULL{top_level}"
+            )
+        })
+        .join(" UNION ALL ")
+}
+
+/// Move an entity to the trash, together with the domains and healthchecks
+/// that used to be removed by `ON DELETE CASCADE`. Links are left alone, so
+/// a restore brings everything back as it was.
+pub async fn soft_delete(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    entity_type: &str,
+    id: &str,
+) -> Result<SqliteQueryResult> {
+    let (table, _, _) = self::entity_type(entity_type)?;
+
+    if table == "domain" {
+        let (in_use,) = sqlx::query_as::<_, (i64,)>(
+            "SELECT COUNT(*) FROM healthcheck WHERE domain_id = ?1 AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+        if in_use > 0 {
+            return Err(Error::Conflict(format!(
+                "Domain is used by {in_use} healthcheck(s), delete those first"
+            )));
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let deleted_at = sqlx::query_scalar::<_, String>("SELECT datetime('now')")
+        .fetch_one(&mut *tx)
+        .await?;
+
+    let result = sqlx::query(&format!(
+        "UPDATE {table} SET deleted_at = ?1, deleted_by = ?2 WHERE id = ?3 AND deleted_at IS NULL"
+    ))
+    .bind(&deleted_at)
+    .bind(actor)
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    let dependents = match table {
+        "application" => Some(("target_application_id", "application_id")),
+        "service" => Some(("target_service_id", "service_id")),
+        _ => None,
+    };
+    if let Some((domain_column, healthcheck_column)) = dependents {
+        sqlx::query(&format!(
+            r#"
+            UPDATE domain SET deleted_at = ?1, deleted_by = ?2, deleted_with = ?3
+            WHERE {domain_column} = ?3 AND deleted_at IS NULL
+            "#
+        ))
+        .bind(&deleted_at)
+        .bind(actor)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(&format!(
+            r#"
+            UPDATE healthcheck SET deleted_at = ?1, deleted_by = ?2, deleted_with = ?3
+            WHERE ({healthcheck_column} = ?3 OR domain_id IN (SELECT id FROM domain WHERE deleted_with = ?3))
+              AND deleted_at IS NULL
+            "#
+        ))
+        .bind(&deleted_at)
+        .bind(actor)
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // Their Kuma monitors are paused on the next sync
+    mark_kuma_dirty(&mut tx, id).await?;
+
+    tx.commit().await?;
+
+    Ok(result)
+}
+
+pub async fn list(
+    pool: &SqlitePool,
+    filters: &TrashFilters,
+) -> Result<PaginatedResponse<TrashItem>> {
+    let params = PaginationParams {
+        page: filters.page,
+        per_page: filters.per_page,
+        search: None,
+    };
+    let limit = params.limit() as i32;
+    let offset = params.offset() as i32;
+
+    let mut items = sqlx::query_as::<_, TrashItem>(&format!(
+        r#"
+        SELECT entity_type, id, name, deleted_at, deleted_by
+        FROM ({})
+        WHERE (?1 IS NULL OR entity_type = ?1)
+        ORDER BY deleted_at DESC, name COLLATE NOCASE
+        LIMIT ?2 OFFSET ?3
+        "#,
+        trash_query()
+    ))
+    .bind(&filters.entity_type)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    let (total,) = sqlx::query_as::<_, (i64,)>(&format!(
+        "SELECT COUNT(*) FROM ({}) WHERE (?1 IS NULL OR entity_type = ?1)",
+        trash_query()
+    ))
+    .bind(&filters.entity_type)
+    .fetch_one(pool)
+    .await?;
+
+    for item in &mut items {
+        item.relations = relations(pool, &item.entity_type, &item.id).await?;
+    }
+
+    Ok(PaginatedResponse::new(items, total, &params))
+}
+
+/// A trashed entity that was deleted on its own.
+async fn get(pool: &SqlitePool, entity_type: &str, id: &str) -> Result<TrashItem> {
+    let (table, name, has_deleted_with) = self::entity_type(entity_type)?;
+    let deleted_with = if has_deleted_with {
+        "deleted_with"
+    } else {
+        "NULL"
+    };
+
+    let row = sqlx::query_as::<_, (String, String, Option<String>, Option<String>)>(&format!(
+        "SELECT {name}, deleted_at, deleted_by, {deleted_with} FROM {table} WHERE id = ?1 AND deleted_at IS NOT NULL"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| Error::NotFound(format!("No {table} with id '{id}' in the trash")))?;
+
+    let (name, deleted_at, deleted_by, deleted_with) = row;
+    if let Some(parent) = deleted_with {
+        let (parent_type, parent_name) = sqlx::query_as::<_, (String, String)>(&format!(
+            "SELECT entity_type, name FROM ({}) WHERE id = ?1",
+            trash_query()
+        ))
+        .bind(&parent)
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or_else(|| ("item".to_string(), parent));
+        return Err(Error::Conflict(format!(
+            "'{name}' was deleted along with the {parent_type} '{parent_name}', restore or purge that instead"
+        )));
+    }
+
+    Ok(TrashItem {
+        entity_type: table.to_string(),
+        id: id.to_string(),
+        name,
+        deleted_at,
+        deleted_by,
+        relations: relations(pool, table, id).await?,
+    })
+}
+
+/// Linked entities and cascaded dependents of a trashed entity.
+async fn relations(pool: &SqlitePool, entity_type: &str, id: &str) -> Result<Vec<TrashRelation>> {
+    let mut relations = sqlx::query_as::<_, TrashRelation>(
+        r#"
+        SELECT 'domain' AS entity_type, id, fqdn AS name, 1 AS cascaded
+        FROM domain WHERE deleted_with = ?1
+        UNION ALL
+        SELECT 'healthcheck', id, name, 1
+        FROM healthcheck WHERE deleted_with = ?1
+        "#,
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+
+    for (junction, left, right) in LINKS {
+        for (own_type, other_type) in [(left, right), (right, left)] {
+            if *own_type != entity_type {
+                continue;
+            }
+            let name = name_column(other_type);
+            let linked = sqlx::query_as::<_, TrashRelation>(&format!(
+                r#"
+                SELECT '{other_type}' AS entity_type, o.id, o.{name} AS name, 0 AS cascaded
+                FROM {junction} j
+                JOIN {other_type} o ON o.id = j.{other_type}_id
+                WHERE j.{own_type}_id = ?1
+                ORDER BY o.{name} COLLATE NOCASE
+                "#
+            ))
+            .bind(id)
+            .fetch_all(pool)
+            .await?;
+            relations.extend(linked);
+        }
+    }
+
+    Ok(relations)
+}
+
+/// Take an entity out of the trash, together with everything deleted along
+/// with it.
+pub async fn restore(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    entity_type: &str,
+    id: &str,
+) -> Result<TrashItem> {
+    let item = get(pool, entity_type, id).await?;
+    let table = self::entity_type(entity_type)?.0;
+
+    for (child, column, parent) in PARENTS.iter().filter(|(child, _, _)| *child == table) {
+        let name = name_column(parent);
+        let deleted_parent = sqlx::query_scalar::<_, String>(&format!(
+            r#"
+            SELECT p.{name}
+            FROM {parent} p
+            JOIN {child} c ON c.{column} = p.id
+            WHERE c.id = ?1 AND p.deleted_at IS NOT NULL
+            "#
+        ))
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        if let Some(name) = deleted_parent {
+            return Err(Error::Conflict(format!(
+                "The {parent} '{name}' is in the trash, restore it first"
+            )));
+        }
+    }
+
+    // The entity itself, and the domains deleted with it
+    for (table, column) in [(table, "id"), ("domain", "deleted_with")] {
+        if let Some(conflict) = taken_key(pool, table, column, id).await? {
+            return Err(conflict);
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(&format!(
+        "UPDATE {table} SET deleted_at = NULL, deleted_by = NULL WHERE id = ?1"
+    ))
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    // Their Kuma monitors are resumed on the next sync
+    mark_kuma_dirty(&mut tx, id).await?;
+
+    for dependent in ["domain", "healthcheck"] {
+        sqlx::query(&format!(
+            r#"
+            UPDATE {dependent} SET deleted_at = NULL, deleted_by = NULL, deleted_with = NULL
+            WHERE deleted_with = ?1
+            "#
+        ))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    audit::record(
+        pool,
+        actor,
+        table,
+        id,
+        AuditAction::Restore,
+        None,
+        Some(&item),
+    )
+    .await?;
+
+    Ok(item)
+}
+
+/// A conflict if an entity in the trash (by `column`) has the unique key of
+/// one outside of it.
+async fn taken_key(
+    pool: &SqlitePool,
+    table: &str,
+    column: &str,
+    id: &str,
+) -> Result<Option<Error>> {
+    let Some((_, key)) = UNIQUE_KEYS.iter().find(|(t, _)| *t == table) else {
+        return Ok(None);
+    };
+    let name = name_column(table);
+    let same_key = key
+        .iter()
+        .map(|c| {
+            if NOCASE_COLUMNS.contains(c) {
+                format!("o.{c} = t.{c} COLLATE NOCASE")
+            } else {
+                format!("o.{c} = t.{c}")
+            }
+        })
+        .join(" AND ");
+    let taken = sqlx::query_scalar::<_, String>(&format!(
+        r#"
+        SELECT t.{name}
+        FROM {table} t
+        JOIN {table} o ON {same_key} AND o.deleted_at IS NULL
+        WHERE t.{column} = ?1 AND t.deleted_at IS NOT NULL
+        LIMIT 1
+        "#
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(taken.map(|name| {
+        Error::Conflict(format!(
+            "Another {table} with the {} of '{name}' exists, rename or delete it first",
+            key.join(" and ")
+        ))
+    }))
+}
+
+/// Flag the healthchecks trashed or restored with an entity (or the
+/// healthcheck itself) to be synced to Kuma, bumping their `kuma_version`
+/// like [`crate::service::healthcheck::mark_kuma_dirty_of`] does.
+async fn mark_kuma_dirty(conn: &mut SqliteConnection, id: &str) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE healthcheck SET kuma_dirty = 1, kuma_version = kuma_version + 1
+        WHERE kuma_id IS NOT NULL AND (id = ?1 OR deleted_with = ?1)
+        "#,
+    )
+    .bind(id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Permanently delete an entity from the trash. Its links are removed by
+/// the `ON DELETE CASCADE` of the junction tables. Purged healthchecks
+/// leave a tombstone so their Kuma monitor gets deleted.
+pub async fn purge(
+    pool: &SqlitePool,
+    actor: Option<&str>,
+    entity_type: &str,
+    id: &str,
+) -> Result<()> {
+    let item = get(pool, entity_type, id).await?;
+    let table = self::entity_type(entity_type)?.0;
+
+    let mut tx = pool.begin().await?;
+
+    // Healthchecks block the deletion of their domain, so they go first:
+    // the ones deleted along with the item, and trashed ones on a domain
+    // that is about to be purged.
+    sqlx::query(
+        r#"
+        DELETE FROM healthcheck
+        WHERE deleted_with = ?1
+           OR (deleted_at IS NOT NULL
+               AND (domain_id = ?1
+                    OR domain_id IN (SELECT id FROM domain
+                                     WHERE deleted_with = ?1
+                                        OR target_application_id = ?1
+                                        OR target_service_id = ?1)))
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM domain WHERE deleted_with = ?1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(&format!("DELETE FROM {table} WHERE id = ?1"))
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    audit::record(
+        pool,
+        actor,
+        table,
+        id,
+        AuditAction::Purge,
+        Some(&item),
+        None,
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// An application with a domain and a healthcheck on it that has a
+    /// Kuma monitor
+    async fn application_with_healthcheck() -> SqlitePool {
+        let pool = testing::pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO application (id, name) VALUES ('a', 'web');
+            INSERT INTO domain (id, fqdn, target_application_id) VALUES ('d', 'web.example.org', 'a');
+            INSERT INTO healthcheck (id, name, application_id, domain_id, kuma_id)
+            VALUES ('h', 'web', 'a', 'd', 7);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    /// `deleted_at IS NOT NULL` and `deleted_with` of a row, if it exists
+    async fn trashed(pool: &SqlitePool, table: &str, id: &str) -> Option<(bool, Option<String>)> {
+        let deleted_with = if entity_type(table).unwrap().2 {
+            "deleted_with"
+        } else {
+            "NULL"
+        };
+        sqlx::query_as(&format!(
+            "SELECT deleted_at IS NOT NULL, {deleted_with} FROM {table} WHERE id = ?1"
+        ))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn domain_and_healthcheck_go_and_come_back_with_their_application() {
+        let pool = application_with_healthcheck().await;
+
+        soft_delete(&pool, None, "application", "a").await.unwrap();
+        let with_application = Some((true, Some("a".to_string())));
+        assert_eq!(trashed(&pool, "domain", "d").await, with_application);
+        assert_eq!(trashed(&pool, "healthcheck", "h").await, with_application);
+        let dirty = sqlx::query_scalar::<_, bool>("SELECT kuma_dirty FROM healthcheck")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(dirty);
+
+        // Not on their own
+        let domain = restore(&pool, None, "domain", "d").await;
+        assert!(matches!(domain, Err(Error::Conflict(_))));
+
+        restore(&pool, None, "application", "a").await.unwrap();
+        for (table, id) in [("application", "a"), ("domain", "d"), ("healthcheck", "h")] {
+            assert_eq!(trashed(&pool, table, id).await, Some((false, None)));
+        }
+    }
+
+    #[tokio::test]
+    async fn purge_removes_the_dependents_and_queues_the_monitor_delete() {
+        let pool = application_with_healthcheck().await;
+
+        soft_delete(&pool, None, "application", "a").await.unwrap();
+        purge(&pool, None, "application", "a").await.unwrap();
+        for (table, id) in [("application", "a"), ("domain", "d"), ("healthcheck", "h")] {
+            assert_eq!(trashed(&pool, table, id).await, None);
+        }
+        let queued = sqlx::query_as::<_, (String, String, i32)>(
+            "SELECT healthcheck_id, operation, kuma_id FROM kuma_sync_queue",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(queued, [("h".to_string(), "delete".to_string(), 7)]);
+    }
+
+    #[tokio::test]
+    async fn restore_refuses_an_email_taken_in_another_case() {
+        let pool = testing::pool().await;
+        sqlx::query(
+            r#"
+            INSERT INTO person (id, name, email, deleted_at) VALUES ('old', 'Old', 'Jo@example.com', datetime('now'));
+            INSERT INTO person (id, name, email) VALUES ('new', 'New', 'jo@example.com');
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let restored = restore(&pool, None, "person", "old").await;
+        assert!(matches!(restored, Err(Error::Conflict(_))));
+    }
+}
@@ -0,0 +1,37 @@
+//! Optimistic concurrency: editable entities carry a `version` that every
+//! update bumps. Clients send the version their change is based on, and
+//! get a 412 when someone else changed the entity meanwhile.
+
+use crate::{Error, Result};
+
+/// Fails when a change is based on another version than the `current` one.
+/// Without an expected version anything goes.
+pub fn check(entity: &str, id: &str, expected: Option<i64>, current: i64) -> Result<()> {
+    match expected {
+        Some(expected) if expected != current => Err(Error::PreconditionFailed(format!(
+            "{entity} '{id}' was changed meanwhile, version {expected} is now version {current}"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// For an update that matched no row because the version changed between
+/// reading and writing the entity.
+pub fn changed(entity: &str, id: &str) -> Error {
+    Error::PreconditionFailed(format!("{entity} '{id}' was changed meanwhile"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_only_fails_on_another_version() {
+        assert!(check("Application", "a1", None, 3).is_ok());
+        assert!(check("Application", "a1", Some(3), 3).is_ok());
+        assert!(matches!(
+            check("Application", "a1", Some(2), 3),
+            Err(Error::PreconditionFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+//! Helpers for tests that need a database or a running server.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use sqlx::SqlitePool;
+use sqlx::sqlite::SqlitePoolOptions;
+use tokio::sync::{RwLock, broadcast, watch};
+use url::Url;
+
+use crate::kuma::UptimeTx;
+use crate::models::{CreateApiToken, Role, UptimeRetention, new_id};
+use crate::service::auth;
+use crate::{AppState, Config, UptimeSource};
+
+/// Token of the bootstrap admin in [`state`]
+pub const ADMIN_TOKEN: &str = "test-admin-token";
+
+/// An empty, migrated in-memory database
+pub async fn pool() -> SqlitePool {
+    // A single connection, as every connection gets its own in-memory database
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    crate::migrate(&pool).await.unwrap();
+    pool
+}
+
+/// State on an in-memory database, without Kuma, Outline, SSO or mail
+pub async fn state() -> AppState {
+    let config = Config {
+        host: "127.0.0.1:0".to_string(),
+        base_url: "http://localhost".to_string(),
+        database_url: "sqlite::memory:".to_string(),
+        kuma_url: Url::parse("http://127.0.0.1:1").unwrap(),
+        kuma_username: String::new(),
+        kuma_password: String::new(),
+        uptime_source: UptimeSource::Native,
+        uptime_retention: UptimeRetention {
+            raw_days: 7,
+            hourly_days: 90,
+            daily_days: 730,
+        },
+        outline_url: None,
+        outline_api_key: None,
+        auth_admin_token: Some(ADMIN_TOKEN.to_string()),
+        oidc: None,
+        smtp: None,
+        rdap_url: Url::parse("http://127.0.0.1:1").unwrap(),
+    };
+    let (change_tx, _) = broadcast::channel(256);
+    let (kuma_refresh_tx, _) = watch::channel(());
+    let (kuma_sync_tx, _) = watch::channel(());
+
+    AppState {
+        pool: pool().await,
+        config,
+        uptime_state: Arc::new(RwLock::new(HashMap::new())),
+        uptime_tx: UptimeTx::new(64),
+        change_tx,
+        kuma_refresh_tx,
+        kuma_sync_tx,
+    }
+}
+
+/// Serves the app on a free port, returns its base URL
+pub async fn serve(state: AppState) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, crate::router(state)).await.unwrap();
+    });
+    format!("http://{address}")
+}
+
+/// Adds a person with this access role, returns their id and an API token
+pub async fn person_with_token(pool: &SqlitePool, name: &str, role: Role) -> (String, String) {
+    let id = new_id();
+    sqlx::query("INSERT INTO person (id, name, access_role) VALUES (?1, ?2, ?3)")
+        .bind(&id)
+        .bind(name)
+        .bind(role)
+        .execute(pool)
+        .await
+        .unwrap();
+    let token = auth::create_token(
+        pool,
+        None,
+        &id,
+        CreateApiToken {
+            name: "test".to_string(),
+            person_id: None,
+            expires_at: None,
+        },
+    )
+    .await
+    .unwrap();
+    (id, token.secret)
+}